@@ -1,3 +1,4 @@
+use alloc::vec::Vec;
 use core::ops::{Add, Mul, Sub};
 
 use p3_field::{Algebra, ExtensionField, Field, PrimeCharacteristicRing};
@@ -13,6 +14,101 @@ pub trait BaseAir<F>: Sync {
     fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
         None
     }
+
+    /// The number of consecutive rows the constraints can read, starting with the current row.
+    /// This must be at least 2.
+    ///
+    /// The matrices returned by [`AirBuilder::main`], [`PairBuilder::preprocessed`] and
+    /// [`MultiPhaseAirBuilder::phase`] have this many rows, row `j` of the window on row `i`
+    /// being row `i + j` of the trace, wrapping around at the end. A constraint needing an
+    /// earlier row, e.g. row `i - 1`, is written relative to that row instead, guarded by
+    /// [`AirBuilder::is_transition_window`] so it does not wrap around.
+    ///
+    /// Every extra row of the window is an extra point at which the prover opens the trace.
+    fn window_size(&self) -> usize {
+        2
+    }
+
+    /// The challenge-dependent trace phases committed to after the main trace, in order.
+    ///
+    /// Before each phase is committed to, the prover samples the phase's challenges, so the phase
+    /// trace may depend on the challenges of every phase up to and including itself.
+    fn trace_phases(&self) -> Vec<TracePhase> {
+        Vec::new()
+    }
+
+    /// How the prover pads a trace whose height is not a power of two.
+    ///
+    /// If this is `None`, the trace height must be a power of two. Otherwise the prover accepts
+    /// a trace of any non-zero height, pads it, records its real height in the proof, and exposes
+    /// the padding rows to the constraints through [`AirBuilder::is_padding`].
+    fn trace_padding(&self) -> Option<TracePadding<F>> {
+        None
+    }
+}
+
+/// A trace committed to after the main trace, once some challenges have been sampled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TracePhase {
+    /// The number of extension field columns in this phase.
+    pub width: usize,
+    /// The number of extension field challenges sampled right before this phase is committed to.
+    pub num_challenges: usize,
+}
+
+/// How a trace is padded to a power of two height, see [`BaseAir::trace_padding`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TracePadding<F> {
+    /// Repeat the last row of the trace.
+    RepeatLastRow,
+    /// Append rows of zeros.
+    ZeroRow,
+    /// Append copies of the given row.
+    FillerRow(Vec<F>),
+}
+
+/// The reason [`TracePadding::pad`] cannot pad a trace.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TracePaddingError {
+    /// The trace has no rows.
+    EmptyTrace,
+    /// The filler row does not have the width of the trace.
+    FillerRowWidthMismatch { expected: usize, actual: usize },
+}
+
+impl<F: PrimeCharacteristicRing + Send + Sync> TracePadding<F> {
+    /// Pad `trace` to the next power of two height.
+    ///
+    /// Returns an error, leaving the trace untouched, if the trace is empty or if a filler row
+    /// does not have the width of the trace.
+    pub fn pad(&self, trace: &mut RowMajorMatrix<F>) -> Result<(), TracePaddingError> {
+        let height = trace.height();
+        if height == 0 {
+            return Err(TracePaddingError::EmptyTrace);
+        }
+        let padded_height = height.next_power_of_two();
+        match self {
+            Self::RepeatLastRow => {
+                let last_row = trace.row_slice(height - 1).unwrap().to_vec();
+                for _ in height..padded_height {
+                    trace.values.extend_from_slice(&last_row);
+                }
+            }
+            Self::ZeroRow => trace.pad_to_height(padded_height, F::ZERO),
+            Self::FillerRow(row) => {
+                if row.len() != trace.width() {
+                    return Err(TracePaddingError::FillerRowWidthMismatch {
+                        expected: trace.width(),
+                        actual: row.len(),
+                    });
+                }
+                for _ in height..padded_height {
+                    trace.values.extend_from_slice(row);
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 /// An extension of `BaseAir` that includes support for public values.
@@ -90,6 +186,10 @@ pub trait AirBuilder: Sized {
     }
 
     /// Expression evaluating to 1 on rows except the last `size - 1` rows, 0 otherwise.
+    ///
+    /// This guards constraints reading the first `size` rows of the window, see
+    /// [`BaseAir::window_size`], so that they do not wrap around. Builders may panic if `size`
+    /// exceeds the window size of the AIR.
     fn is_transition_window(&self, size: usize) -> Self::Expr;
 
     /// Returns a sub-builder whose constraints are enforced only when `condition` is nonzero.
@@ -133,11 +233,16 @@ pub trait AirBuilder: Sized {
     ///
     /// Where possible, batching multiple assert_zero calls
     /// into a single assert_zeros call will improve performance.
+    ///
+    /// This and the other assertion methods track their caller, so that builders which report
+    /// failing constraints can point at the line of the AIR asserting them.
+    #[track_caller]
     fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I);
 
     /// Assert that every element of a given array is 0.
     ///
     /// This should be preferred over calling `assert_zero` multiple times.
+    #[track_caller]
     fn assert_zeros<const N: usize, I: Into<Self::Expr>>(&mut self, array: [I; N]) {
         for elem in array {
             self.assert_zero(elem);
@@ -145,17 +250,20 @@ pub trait AirBuilder: Sized {
     }
 
     /// Assert that a given array consists of only boolean values.
+    #[track_caller]
     fn assert_bools<const N: usize, I: Into<Self::Expr>>(&mut self, array: [I; N]) {
         let zero_array = array.map(|x| x.into().bool_check());
         self.assert_zeros(zero_array);
     }
 
     /// Assert that `x` element is equal to `1`.
+    #[track_caller]
     fn assert_one<I: Into<Self::Expr>>(&mut self, x: I) {
         self.assert_zero(x.into() - Self::Expr::ONE);
     }
 
     /// Assert that the given elements are equal.
+    #[track_caller]
     fn assert_eq<I1: Into<Self::Expr>, I2: Into<Self::Expr>>(&mut self, x: I1, y: I2) {
         self.assert_zero(x.into() - y.into());
     }
@@ -164,9 +272,26 @@ pub trait AirBuilder: Sized {
     ///
     /// Where possible, batching multiple assert_bool calls
     /// into a single assert_bools call will improve performance.
+    #[track_caller]
     fn assert_bool<I: Into<Self::Expr>>(&mut self, x: I) {
         self.assert_zero(x.into().bool_check());
     }
+
+    /// Label every constraint asserted from now on, until the next call to this method.
+    ///
+    /// Labels have no effect on the constraints themselves. Builders which report failing
+    /// constraints use them, along with the source location of this call, to describe the
+    /// failures. Other builders simply ignore them.
+    #[track_caller]
+    fn label_constraints(&mut self, _label: &'static str) {}
+
+    /// Returns an expression which is `1` on the rows added by padding the trace and `0` on the
+    /// rows of the original trace, see [`BaseAir::trace_padding`].
+    ///
+    /// This is `0` on every row unless the AIR pads its trace.
+    fn is_padding(&self) -> Self::Expr {
+        Self::Expr::ZERO
+    }
 }
 
 /// Extension trait for `AirBuilder` providing access to public values.
@@ -196,11 +321,13 @@ pub trait ExtensionBuilder: AirBuilder<F: Field> {
     type VarEF: Into<Self::ExprEF> + Copy + Send + Sync;
 
     /// Assert that an extension field expression is zero.
+    #[track_caller]
     fn assert_zero_ext<I>(&mut self, x: I)
     where
         I: Into<Self::ExprEF>;
 
     /// Assert that two extension field expressions are equal.
+    #[track_caller]
     fn assert_eq_ext<I1, I2>(&mut self, x: I1, y: I2)
     where
         I1: Into<Self::ExprEF>,
@@ -210,6 +337,7 @@ pub trait ExtensionBuilder: AirBuilder<F: Field> {
     }
 
     /// Assert that an extension field expression is equal to one.
+    #[track_caller]
     fn assert_one_ext<I>(&mut self, x: I)
     where
         I: Into<Self::ExprEF>,
@@ -233,6 +361,22 @@ pub trait PermutationAirBuilder: ExtensionBuilder {
     fn permutation_randomness(&self) -> &[Self::RandomVar];
 }
 
+/// Trait for builders supporting AIRs with challenge-dependent trace phases,
+/// see [`BaseAir::trace_phases`].
+pub trait MultiPhaseAirBuilder: ExtensionBuilder {
+    /// Matrix type over extension field variables representing a phase trace.
+    type MPhase: Matrix<Self::VarEF>;
+
+    /// Challenge variable type available to phase traces.
+    type PhaseChallengeVar: Into<Self::ExprEF> + Copy;
+
+    /// Return the matrix of the given phase, where phase `0` is the first phase after the main trace.
+    fn phase(&self, phase: usize) -> Self::MPhase;
+
+    /// Return the challenges sampled right before the given phase was committed to.
+    fn phase_challenges(&self, phase: usize) -> &[Self::PhaseChallengeVar];
+}
+
 /// A wrapper around an [`AirBuilder`] that enforces constraints only when a specified condition is met.
 ///
 /// This struct allows selectively applying constraints to certain rows or under certain conditions in the AIR,
@@ -280,6 +424,14 @@ impl<AB: AirBuilder> AirBuilder for FilteredAirBuilder<'_, AB> {
     fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
         self.inner.assert_zero(self.condition() * x.into());
     }
+
+    fn label_constraints(&mut self, label: &'static str) {
+        self.inner.label_constraints(label);
+    }
+
+    fn is_padding(&self) -> Self::Expr {
+        self.inner.is_padding()
+    }
 }
 
 impl<AB: ExtensionBuilder> ExtensionBuilder for FilteredAirBuilder<'_, AB> {
@@ -308,3 +460,17 @@ impl<AB: PermutationAirBuilder> PermutationAirBuilder for FilteredAirBuilder<'_,
         self.inner.permutation_randomness()
     }
 }
+
+impl<AB: MultiPhaseAirBuilder> MultiPhaseAirBuilder for FilteredAirBuilder<'_, AB> {
+    type MPhase = AB::MPhase;
+
+    type PhaseChallengeVar = AB::PhaseChallengeVar;
+
+    fn phase(&self, phase: usize) -> Self::MPhase {
+        self.inner.phase(phase)
+    }
+
+    fn phase_challenges(&self, phase: usize) -> &[Self::PhaseChallengeVar] {
+        self.inner.phase_challenges(phase)
+    }
+}
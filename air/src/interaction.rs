@@ -0,0 +1,67 @@
+use alloc::vec::Vec;
+
+use crate::{AirBuilder, FilteredAirBuilder};
+
+/// A single message sent to, or received from, a bus shared between AIRs.
+///
+/// An interaction with a positive multiplicity sends `fields` to the bus `multiplicity` times,
+/// while a negative multiplicity receives them. A lookup or permutation argument then proves that,
+/// summed over every row of every AIR, the multiplicities of each message on each bus cancel out.
+#[derive(Clone, Debug)]
+pub struct Interaction<Expr> {
+    /// The values making up the message.
+    pub fields: Vec<Expr>,
+    /// The signed number of times the message is sent on this row.
+    pub multiplicity: Expr,
+    /// The identifier of the bus the message is sent on.
+    pub bus: usize,
+}
+
+/// Trait for `AirBuilder` variants which support cross-AIR interactions, such as lookups or
+/// memory/bus arguments.
+///
+/// The AIR only declares which messages are sent and received on each row. Proving that every
+/// bus balances is the responsibility of the proof system.
+pub trait InteractionBuilder: AirBuilder {
+    /// Record an interaction with the given signed multiplicity.
+    fn push_interaction<E: Into<Self::Expr>>(
+        &mut self,
+        bus: usize,
+        fields: impl IntoIterator<Item = E>,
+        multiplicity: impl Into<Self::Expr>,
+    );
+
+    /// Send `fields` on `bus`, `multiplicity` times.
+    fn send<E: Into<Self::Expr>>(
+        &mut self,
+        bus: usize,
+        fields: impl IntoIterator<Item = E>,
+        multiplicity: impl Into<Self::Expr>,
+    ) {
+        self.push_interaction(bus, fields, multiplicity);
+    }
+
+    /// Receive `fields` from `bus`, `multiplicity` times.
+    fn receive<E: Into<Self::Expr>>(
+        &mut self,
+        bus: usize,
+        fields: impl IntoIterator<Item = E>,
+        multiplicity: impl Into<Self::Expr>,
+    ) {
+        self.push_interaction(bus, fields, -multiplicity.into());
+    }
+}
+
+impl<AB: InteractionBuilder> InteractionBuilder for FilteredAirBuilder<'_, AB> {
+    /// Interactions pushed through a filtered builder only happen when the condition is nonzero,
+    /// so the multiplicity is scaled by the condition.
+    fn push_interaction<E: Into<Self::Expr>>(
+        &mut self,
+        bus: usize,
+        fields: impl IntoIterator<Item = E>,
+        multiplicity: impl Into<Self::Expr>,
+    ) {
+        let multiplicity = self.condition() * multiplicity.into();
+        self.inner.push_interaction(bus, fields, multiplicity);
+    }
+}
@@ -3,7 +3,7 @@ use core::marker::PhantomData;
 
 use p3_field::{BasedVectorSpace, PrimeField32, PrimeField64};
 use p3_maybe_rayon::prelude::*;
-use p3_symmetric::{CryptographicHasher, Hash};
+use p3_symmetric::{CryptographicHasher, Hash, MerkleCap};
 use p3_util::log2_ceil_u64;
 use tracing::instrument;
 
@@ -87,6 +87,18 @@ impl<F: PrimeField32, const N: usize, Inner: CanObserve<u8>> CanObserve<Hash<F,
     }
 }
 
+impl<F: PrimeField32, const N: usize, W, Inner: CanObserve<u8>> CanObserve<MerkleCap<F, W, N>>
+    for SerializingChallenger32<F, Inner>
+where
+    Self: CanObserve<Hash<F, W, N>>,
+{
+    fn observe(&mut self, values: MerkleCap<F, W, N>) {
+        for digest in values {
+            self.observe(digest);
+        }
+    }
+}
+
 impl<F, EF, Inner> CanSample<EF> for SerializingChallenger32<F, Inner>
 where
     F: PrimeField32,
@@ -204,6 +216,18 @@ impl<F: PrimeField64, const N: usize, Inner: CanObserve<u8>> CanObserve<Hash<F,
     }
 }
 
+impl<F: PrimeField64, const N: usize, W, Inner: CanObserve<u8>> CanObserve<MerkleCap<F, W, N>>
+    for SerializingChallenger64<F, Inner>
+where
+    Self: CanObserve<Hash<F, W, N>>,
+{
+    fn observe(&mut self, values: MerkleCap<F, W, N>) {
+        for digest in values {
+            self.observe(digest);
+        }
+    }
+}
+
 impl<F, EF, Inner> CanSample<EF> for SerializingChallenger64<F, Inner>
 where
     F: PrimeField64,
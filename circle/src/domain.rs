@@ -164,6 +164,16 @@ impl<F: ComplexExtendable> PolynomialSpace for CircleDomain<F> {
             .collect()
     }
 
+    fn split_index(&self, num_chunks: usize, index: usize) -> (usize, usize) {
+        // Every run of `2 * num_chunks` rows sends one row to each chunk on the way forward and
+        // one on the way back.
+        let offset = index % (2 * num_chunks);
+        (
+            forward_backward_index(index, num_chunks),
+            2 * (index / (2 * num_chunks)) + (offset >= num_chunks) as usize,
+        )
+    }
+
     fn vanishing_poly_at_point<Ext: ExtensionField<Self::Val>>(&self, point: Ext) -> Ext {
         self.vanishing_poly(Point::from_projective_line(point))
     }
@@ -299,6 +309,14 @@ mod tests {
             assert_eq!(sds.len(), num_chunks);
             let ses = d.split_evals(num_chunks, evals.clone());
             assert_eq!(ses.len(), num_chunks);
+            // split_index locates every row of the evaluations in the split evaluations
+            for i in 0..n {
+                let (chunk, row) = d.split_index(num_chunks, i);
+                assert_eq!(
+                    *ses[chunk].row_slice(row).unwrap(),
+                    *evals.row_slice(i).unwrap()
+                );
+            }
             for (sd, se) in izip!(sds, ses) {
                 // Split domains are twin cosets
                 assert_is_twin_coset(sd);
@@ -6,13 +6,23 @@ use itertools::Itertools;
 use p3_commit::Mmcs;
 use p3_field::extension::ComplexExtendable;
 use p3_field::{ExtensionField, batch_multiplicative_inverse};
-use p3_fri::FriFoldingStrategy;
+use p3_fri::{FriFoldingStrategy, FriParameters};
 use p3_matrix::Matrix;
 use p3_util::{log2_strict_usize, reverse_bits_len};
 
 use crate::domain::CircleDomain;
 use crate::{CircleInputProof, InputError};
 
+/// Whether `params` fold by 2 in every round of the commit phase, the only arity Circle FRI
+/// supports.
+pub(crate) fn folds_by_two<M>(params: &FriParameters<M>) -> bool {
+    params.max_log_arity == 1
+        && params
+            .log_arity_schedule
+            .iter()
+            .all(|&log_arity| log_arity == 1)
+}
+
 pub(crate) struct CircleFriFolding<F, InputProof, InputError>(
     pub(crate) PhantomData<(F, InputProof, InputError)>,
 );
@@ -108,6 +118,14 @@ pub(crate) fn fold_x<F: ComplexExtendable, EF: ExtensionField<F>>(
     )
 }
 
+/// The x-coordinate of the point at which the entry at `index` of evaluations of height
+/// `2^log_height` is taken, when these are folded by `fold_x`.
+pub(crate) fn fold_x_point<F: ComplexExtendable>(index: usize, log_height: usize) -> F {
+    let x = CircleDomain::<F>::standard(log_height + 1)
+        .nth_x_twiddle(reverse_bits_len(index >> 1, log_height - 1));
+    if index & 1 == 0 { x } else { -x }
+}
+
 pub(crate) fn fold_x_row<F: ComplexExtendable, EF: ExtensionField<F>>(
     index: usize,
     log_folded_height: usize,
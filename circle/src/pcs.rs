@@ -5,11 +5,14 @@ use core::marker::PhantomData;
 
 use itertools::{Itertools, izip};
 use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
-use p3_commit::{BatchOpening, BatchOpeningRef, Mmcs, OpenedValues, Pcs, PolynomialSpace};
+use p3_commit::{
+    BatchOpening, BatchOpeningRef, Mmcs, OpenedValues, OpeningProofSize, Pcs, PolynomialSpace,
+    SerializedSize, SizeBreakdown,
+};
 use p3_field::extension::ComplexExtendable;
 use p3_field::{ExtensionField, Field};
 use p3_fri::FriParameters;
-use p3_fri::verifier::FriError;
+use p3_fri::verifier::{FriError, check_optional_witness};
 use p3_matrix::dense::{RowMajorMatrix, RowMajorMatrixCow};
 use p3_matrix::row_index_mapped::RowIndexMappedView;
 use p3_matrix::{Dimensions, Matrix};
@@ -35,6 +38,8 @@ pub struct CirclePcs<Val: Field, InputMmcs, FriMmcs> {
 }
 
 impl<Val: Field, InputMmcs, FriMmcs> CirclePcs<Val, InputMmcs, FriMmcs> {
+    /// Creates a `CirclePcs`, whose FRI folds by 2 in every round: opening panics, and verifying
+    /// fails with [`FriError::UnsupportedArity`], if `fri_params` fold by any other arity.
     pub const fn new(mmcs: InputMmcs, fri_params: FriParameters<FriMmcs>) -> Self {
         Self {
             mmcs,
@@ -198,7 +203,9 @@ where
             })
             .collect();
 
-        // Batch combination challenge
+        // Batch combination challenge, optionally ground for.
+        let batch_pow_witness = (self.fri_params.batch_proof_of_work_bits > 0)
+            .then(|| challenger.grind(self.fri_params.batch_proof_of_work_bits));
         let alpha: Challenge = challenger.sample_algebra_element();
 
         /*
@@ -298,7 +305,7 @@ where
         let folding: CircleFriFoldingForMmcs<Val, Challenge, InputMmcs, FriMmcs> =
             CircleFriFolding(PhantomData);
 
-        let fri_proof = prove(&folding, &self.fri_params, fri_input, challenger, |index| {
+        let open_input = |index| {
             // CircleFriFolder asks for an extra query index bit, so we use that here to index
             // the first layer fold.
 
@@ -331,7 +338,15 @@ where
                 first_layer_siblings,
                 first_layer_proof,
             }
-        });
+        };
+        let fri_proof = prove(
+            &folding,
+            &self.fri_params,
+            fri_input,
+            challenger,
+            open_input,
+            batch_pow_witness,
+        );
 
         (
             values,
@@ -375,14 +390,21 @@ where
             }
         }
 
-        // Batch combination challenge
+        // Batch combination challenge, with the proof of work witness ground before it.
+        check_optional_witness(
+            challenger,
+            self.fri_params.batch_proof_of_work_bits,
+            proof.fri_proof.batch_pow_witness,
+        )?;
         let alpha: Challenge = challenger.sample_algebra_element();
         challenger.observe(proof.first_layer_commitment.clone());
         let bivariate_beta: Challenge = challenger.sample_algebra_element();
 
         // +1 to account for first layer
-        let log_global_max_height =
-            proof.fri_proof.commit_phase_commits.len() + self.fri_params.log_blowup + 1;
+        let log_global_max_height = proof.fri_proof.commit_phase_commits.len()
+            + self.fri_params.log_blowup
+            + self.fri_params.log_final_poly_len
+            + 1;
 
         let folding: CircleFriFoldingForMmcs<Val, Challenge, InputMmcs, FriMmcs> =
             CircleFriFolding(PhantomData);
@@ -523,6 +545,14 @@ where
             },
         )
     }
+
+    fn opening_proof_size(proof: &Self::Proof, sizer: &impl SerializedSize) -> OpeningProofSize {
+        // The first layer is committed to like the other layers of the commit phase, while its
+        // openings are part of the input proof of each query. The lambdas are counted as `other`.
+        let mut size = proof.fri_proof.size(sizer);
+        size.commit_phase_commitments += sizer.serialized_size(&proof.first_layer_commitment);
+        size.with_total(sizer.serialized_size(proof))
+    }
 }
 
 #[cfg(test)]
@@ -540,35 +570,38 @@ mod tests {
 
     use super::*;
 
-    #[test]
-    fn circle_pcs() {
-        // Very simple pcs test. More rigorous tests in p3_fri/tests/pcs.
-
+    type Val = Mersenne31;
+    type Challenge = BinomialExtensionField<Mersenne31, 3>;
+    type ByteHash = Keccak256Hash;
+    type FieldHash = SerializingHasher<ByteHash>;
+    type MyCompress = CompressionFunctionFromHasher<ByteHash, 2, 32>;
+    type ValMmcs = MerkleTreeMmcs<Val, u8, FieldHash, MyCompress, 32>;
+    type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+    type Challenger = SerializingChallenger32<Val, HashChallenger<u8, ByteHash, 32>>;
+    type MyPcs = CirclePcs<Val, ValMmcs, ChallengeMmcs>;
+
+    /// Opens a random polynomial and verifies the opening, with the verifier folding by
+    /// `2^verifier_max_log_arity` past the first round.
+    fn open_and_verify(
+        log_final_poly_len: usize,
+        proof_of_work_bits: usize,
+        verifier_max_log_arity: usize,
+    ) -> Result<(), <MyPcs as p3_commit::Pcs<Challenge, Challenger>>::Error> {
         let mut rng = SmallRng::seed_from_u64(0);
 
-        type Val = Mersenne31;
-        type Challenge = BinomialExtensionField<Mersenne31, 3>;
-
-        type ByteHash = Keccak256Hash;
-        type FieldHash = SerializingHasher<ByteHash>;
         let byte_hash = ByteHash {};
         let field_hash = FieldHash::new(byte_hash);
-
-        type MyCompress = CompressionFunctionFromHasher<ByteHash, 2, 32>;
         let compress = MyCompress::new(byte_hash);
-
-        type ValMmcs = MerkleTreeMmcs<Val, u8, FieldHash, MyCompress, 32>;
         let val_mmcs = ValMmcs::new(field_hash, compress);
-
-        type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
         let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
 
-        type Challenger = SerializingChallenger32<Val, HashChallenger<u8, ByteHash, 32>>;
-
-        let fri_params = create_test_fri_params(challenge_mmcs, 0);
+        let fri_params = FriParameters {
+            commit_proof_of_work_bits: proof_of_work_bits,
+            batch_proof_of_work_bits: proof_of_work_bits,
+            ..create_test_fri_params(challenge_mmcs, log_final_poly_len)
+        };
 
-        type Pcs = CirclePcs<Val, ValMmcs, ChallengeMmcs>;
-        let pcs = Pcs {
+        let mut pcs = MyPcs {
             mmcs: val_mmcs,
             fri_params,
             _phantom: PhantomData,
@@ -576,7 +609,7 @@ mod tests {
 
         let log_n = 10;
 
-        let d = <Pcs as p3_commit::Pcs<Challenge, Challenger>>::natural_domain_for_degree(
+        let d = <MyPcs as p3_commit::Pcs<Challenge, Challenger>>::natural_domain_for_degree(
             &pcs,
             1 << log_n,
         );
@@ -584,19 +617,50 @@ mod tests {
         let evals = RowMajorMatrix::rand(&mut rng, 1 << log_n, 1);
 
         let (comm, data) =
-            <Pcs as p3_commit::Pcs<Challenge, Challenger>>::commit(&pcs, [(d, evals)]);
+            <MyPcs as p3_commit::Pcs<Challenge, Challenger>>::commit(&pcs, [(d, evals)]);
 
         let zeta: Challenge = rng.random();
 
         let mut chal = Challenger::from_hasher(vec![], byte_hash);
         let (values, proof) = pcs.open(vec![(&data, vec![vec![zeta]])], &mut chal);
 
+        pcs.fri_params.max_log_arity = verifier_max_log_arity;
         let mut chal = Challenger::from_hasher(vec![], byte_hash);
         pcs.verify(
             vec![(comm, vec![(d, vec![(zeta, values[0][0][0].clone())])])],
             &proof,
             &mut chal,
         )
-        .expect("verify err");
+    }
+
+    fn do_test_circle_pcs(log_final_poly_len: usize, proof_of_work_bits: usize) {
+        open_and_verify(log_final_poly_len, proof_of_work_bits, 1).expect("verify err");
+    }
+
+    #[test]
+    fn circle_pcs() {
+        // Very simple pcs test. More rigorous tests in p3_fri/tests/pcs.
+        do_test_circle_pcs(0, 0);
+    }
+
+    #[test]
+    fn circle_pcs_final_poly() {
+        for log_final_poly_len in 1..5 {
+            do_test_circle_pcs(log_final_poly_len, 0);
+        }
+    }
+
+    #[test]
+    fn circle_pcs_grinding() {
+        do_test_circle_pcs(0, 4);
+        do_test_circle_pcs(2, 4);
+    }
+
+    #[test]
+    fn circle_pcs_rejects_arity() {
+        assert!(matches!(
+            open_and_verify(0, 0, 2),
+            Err(FriError::UnsupportedArity)
+        ));
     }
 }
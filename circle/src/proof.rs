@@ -1,6 +1,6 @@
 use alloc::vec::Vec;
 
-use p3_commit::Mmcs;
+use p3_commit::{Mmcs, OpeningProofSize, SerializedSize, SizeBreakdown};
 use p3_field::Field;
 use serde::{Deserialize, Serialize};
 
@@ -12,10 +12,36 @@ use serde::{Deserialize, Serialize};
 pub struct CircleFriProof<F: Field, M: Mmcs<F>, Witness, InputProof> {
     pub commit_phase_commits: Vec<M::Commitment>,
     pub query_proofs: Vec<CircleQueryProof<F, M, InputProof>>,
-    // This could become Vec<FC::Challenge> if this library was generalized to support non-constant
-    // final polynomials.
-    pub final_poly: F,
+    /// The coefficients of the final polynomial, in the monomial basis of the x-coordinate.
+    pub final_poly: Vec<F>,
     pub pow_witness: Witness,
+    /// The proof of work witness ground before each folding challenge, empty if
+    /// `commit_proof_of_work_bits` is `0`.
+    pub commit_pow_witnesses: Vec<Witness>,
+    /// The proof of work witness ground before the batching challenge, if
+    /// `batch_proof_of_work_bits` is not `0`.
+    pub batch_pow_witness: Option<Witness>,
+}
+
+impl<F: Field, M: Mmcs<F>, Witness: Serialize, InputProof: Serialize>
+    CircleFriProof<F, M, Witness, InputProof>
+{
+    /// The serialized size of each part of this proof, as measured by `sizer`.
+    pub fn size(&self, sizer: &impl SerializedSize) -> OpeningProofSize {
+        let mut size = OpeningProofSize {
+            commit_phase_commitments: sizer.serialized_size(&self.commit_phase_commits),
+            final_poly: sizer.serialized_size(&self.final_poly),
+            pow_witness: sizer.serialized_size(&self.pow_witness)
+                + sizer.serialized_size(&self.commit_pow_witnesses)
+                + sizer.serialized_size(&self.batch_pow_witness),
+            ..OpeningProofSize::default()
+        };
+        for query_proof in &self.query_proofs {
+            size.query_openings += sizer.serialized_size(&query_proof.input_proof);
+            size.commit_phase_openings += sizer.serialized_size(&query_proof.commit_phase_openings);
+        }
+        size.with_total(sizer.serialized_size(self))
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -5,14 +5,18 @@ use core::iter;
 use itertools::{Itertools, izip};
 use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
 use p3_commit::Mmcs;
+use p3_field::extension::ComplexExtendable;
 use p3_field::{ExtensionField, Field};
 use p3_fri::{FriFoldingStrategy, FriParameters};
 use p3_matrix::dense::RowMajorMatrix;
 use p3_util::log2_strict_usize;
 use tracing::{info_span, instrument};
 
+use crate::folding::{fold_x_point, folds_by_two};
 use crate::{CircleCommitPhaseProofStep, CircleFriProof, CircleQueryProof};
 
+/// Proves that `inputs` are evaluations of low degree polynomials, with `batch_pow_witness` the
+/// proof of work witness the caller ground before sampling the challenge batching them, if any.
 #[instrument(name = "FRI prover", skip_all)]
 pub fn prove<Folding, Val, Challenge, M, Challenger>(
     folding: &Folding,
@@ -20,9 +24,10 @@ pub fn prove<Folding, Val, Challenge, M, Challenger>(
     inputs: Vec<Vec<Challenge>>,
     challenger: &mut Challenger,
     open_input: impl Fn(usize) -> Folding::InputProof,
+    batch_pow_witness: Option<Challenger::Witness>,
 ) -> CircleFriProof<Challenge, M, Challenger::Witness, Folding::InputProof>
 where
-    Val: Field,
+    Val: ComplexExtendable,
     Challenge: ExtensionField<Val>,
     M: Mmcs<Challenge>,
     Challenger: FieldChallenger<Val> + GrindingChallenger + CanObserve<M::Commitment>,
@@ -36,7 +41,14 @@ where
             .all(|(l, r)| l.len() >= r.len())
     );
 
+    assert!(folds_by_two(params), "Circle FRI only folds by 2");
+
     let log_max_height = log2_strict_usize(inputs[0].len());
+    let log_min_height = log2_strict_usize(inputs.last().unwrap().len());
+    if params.log_final_poly_len > 0 {
+        // The final polynomial must not be longer than the smallest input polynomial.
+        assert!(log_min_height > params.log_final_poly_len + params.log_blowup);
+    }
 
     let commit_phase_result = commit_phase(folding, params, inputs, challenger);
 
@@ -65,13 +77,16 @@ where
         query_proofs,
         final_poly: commit_phase_result.final_poly,
         pow_witness,
+        commit_pow_witnesses: commit_phase_result.pow_witnesses,
+        batch_pow_witness,
     }
 }
 
-struct CommitPhaseResult<F: Field, M: Mmcs<F>> {
+struct CommitPhaseResult<F: Field, M: Mmcs<F>, Witness> {
     commits: Vec<M::Commitment>,
     data: Vec<M::ProverData<RowMajorMatrix<F>>>,
-    final_poly: F,
+    final_poly: Vec<F>,
+    pow_witnesses: Vec<Witness>,
 }
 
 #[instrument(name = "commit phase", skip_all)]
@@ -80,24 +95,30 @@ fn commit_phase<Folding, Val, Challenge, M, Challenger>(
     params: &FriParameters<M>,
     inputs: Vec<Vec<Challenge>>,
     challenger: &mut Challenger,
-) -> CommitPhaseResult<Challenge, M>
+) -> CommitPhaseResult<Challenge, M, Challenger::Witness>
 where
-    Val: Field,
+    Val: ComplexExtendable,
     Challenge: ExtensionField<Val>,
     M: Mmcs<Challenge>,
-    Challenger: FieldChallenger<Val> + CanObserve<M::Commitment>,
+    Challenger: FieldChallenger<Val> + GrindingChallenger + CanObserve<M::Commitment>,
     Folding: FriFoldingStrategy<Val, Challenge>,
 {
     let mut inputs_iter = inputs.into_iter().peekable();
     let mut folded = inputs_iter.next().unwrap();
     let mut commits = vec![];
     let mut data = vec![];
+    let mut pow_witnesses = vec![];
 
-    while folded.len() > params.blowup() {
+    while folded.len() > params.blowup() * params.final_poly_len() {
         let leaves = RowMajorMatrix::new(folded, 2);
         let (commit, prover_data) = params.mmcs.commit_matrix(leaves);
         challenger.observe(commit.clone());
 
+        // Optionally grind before the challenge, which makes it costlier to search for a lucky one.
+        if params.commit_proof_of_work_bits > 0 {
+            pow_witnesses.push(challenger.grind(params.commit_proof_of_work_bits));
+        }
+
         let beta: Challenge = challenger.sample_algebra_element();
         // We passed ownership of `current` to the MMCS, so get a reference to it
         let leaves = params.mmcs.get_matrices(&prover_data).pop().unwrap();
@@ -111,19 +132,74 @@ where
         }
     }
 
-    // We should be left with `blowup` evaluations of a constant polynomial.
-    assert_eq!(folded.len(), params.blowup());
-    let final_poly = folded[0];
-    for x in folded {
-        assert_eq!(x, final_poly);
+    // We should be left with `blowup * final_poly_len` evaluations of a polynomial in the
+    // x-coordinate of degree less than `final_poly_len`.
+    assert_eq!(folded.len(), params.blowup() * params.final_poly_len());
+    let log_final_height = params.log_blowup + params.log_final_poly_len;
+    let final_poly = if params.log_final_poly_len == 0 {
+        vec![folded[0]]
+    } else {
+        // The first `final_poly_len` evaluations are at distinct x-coordinates, so they determine
+        // the final polynomial.
+        let xs = (0..params.final_poly_len())
+            .map(|i| fold_x_point::<Val>(i, log_final_height))
+            .collect_vec();
+        interpolate(&xs, &folded[..params.final_poly_len()])
+    };
+    for (i, &eval) in folded.iter().enumerate() {
+        let x = if final_poly.len() == 1 {
+            Val::ZERO
+        } else {
+            fold_x_point(i, log_final_height)
+        };
+        assert_eq!(horner(&final_poly, x), eval);
+    }
+    for &coeff in &final_poly {
+        challenger.observe_algebra_element(coeff);
     }
-    challenger.observe_algebra_element(final_poly);
 
     CommitPhaseResult {
         commits,
         data,
         final_poly,
+        pow_witnesses,
+    }
+}
+
+/// The coefficients of the polynomial of degree less than `xs.len()` taking the values `ys` at the
+/// distinct points `xs`, by Lagrange interpolation.
+fn interpolate<F: Field, EF: ExtensionField<F>>(xs: &[F], ys: &[EF]) -> Vec<EF> {
+    // The coefficients of `prod_j (X - x_j)`.
+    let mut vanishing = vec![F::ONE];
+    for &x in xs {
+        vanishing.insert(0, F::ZERO);
+        for i in 0..vanishing.len() - 1 {
+            let next = vanishing[i + 1];
+            vanishing[i] -= x * next;
+        }
+    }
+
+    let mut coeffs = vec![EF::ZERO; xs.len()];
+    for (&x, &y) in izip!(xs, ys) {
+        // Divide the vanishing polynomial by `X - x`, highest coefficient first.
+        let mut quotient = vec![F::ZERO; xs.len()];
+        let mut carry = F::ZERO;
+        for i in (0..xs.len()).rev() {
+            carry = vanishing[i + 1] + carry * x;
+            quotient[i] = carry;
+        }
+        let weight = y * horner(&quotient, x).inverse();
+        izip!(&mut coeffs, quotient).for_each(|(c, q)| *c += weight * q);
     }
+    coeffs
+}
+
+/// Evaluates the polynomial with the given coefficients at `x`.
+fn horner<F: Field, EF: ExtensionField<F>>(coeffs: &[EF], x: F) -> EF {
+    coeffs
+        .iter()
+        .rev()
+        .fold(EF::ZERO, |acc, &coeff| acc * x + coeff)
 }
 
 fn answer_query<F, M>(
@@ -4,12 +4,14 @@ use alloc::vec::Vec;
 use itertools::Itertools;
 use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
 use p3_commit::{BatchOpeningRef, Mmcs};
+use p3_field::extension::ComplexExtendable;
 use p3_field::{ExtensionField, Field};
-use p3_fri::verifier::FriError;
+use p3_fri::verifier::{FriError, check_optional_witness};
 use p3_fri::{FriFoldingStrategy, FriParameters};
 use p3_matrix::Dimensions;
 use p3_util::zip_eq::zip_eq;
 
+use crate::folding::{fold_x_point, folds_by_two};
 use crate::{CircleCommitPhaseProofStep, CircleFriProof};
 
 pub fn verify<Folding, Val, Challenge, M, Challenger>(
@@ -23,23 +25,51 @@ pub fn verify<Folding, Val, Challenge, M, Challenger>(
     ) -> Result<Vec<(usize, Challenge)>, Folding::InputError>,
 ) -> Result<(), FriError<M::Error, Folding::InputError>>
 where
-    Val: Field,
+    Val: ComplexExtendable,
     Challenge: ExtensionField<Val>,
     M: Mmcs<Challenge>,
     Challenger: FieldChallenger<Val> + GrindingChallenger + CanObserve<M::Commitment>,
     Folding: FriFoldingStrategy<Val, Challenge>,
 {
+    if !folds_by_two(params) {
+        return Err(FriError::UnsupportedArity);
+    }
+
+    // There is a proof of work witness for each round, unless the prover does not grind.
+    let num_commit_pow_witnesses = if params.commit_proof_of_work_bits > 0 {
+        proof.commit_phase_commits.len()
+    } else {
+        0
+    };
+    if proof.commit_pow_witnesses.len() != num_commit_pow_witnesses {
+        return Err(FriError::InvalidProofShape);
+    }
+    let mut commit_pow_witnesses = proof.commit_pow_witnesses.iter().copied();
+
     let betas: Vec<Challenge> = proof
         .commit_phase_commits
         .iter()
         .map(|comm| {
             challenger.observe(comm.clone());
-            challenger.sample_algebra_element()
+            check_optional_witness(
+                challenger,
+                params.commit_proof_of_work_bits,
+                commit_pow_witnesses.next(),
+            )?;
+            Ok(challenger.sample_algebra_element())
         })
-        .collect();
+        .collect::<Result<_, _>>()?;
 
-    // Observe the claimed final polynomial.
-    challenger.observe_algebra_element(proof.final_poly);
+    // Ensure that the final polynomial has the expected degree.
+    if proof.final_poly.len() != params.final_poly_len() {
+        return Err(FriError::InvalidProofShape);
+    }
+
+    // Observe all coefficients of the final polynomial.
+    proof
+        .final_poly
+        .iter()
+        .for_each(|x| challenger.observe_algebra_element(*x));
 
     if proof.query_proofs.len() != params.num_queries {
         return Err(FriError::InvalidProofShape);
@@ -50,8 +80,9 @@ where
         return Err(FriError::InvalidPowWitness);
     }
 
-    // The log of the maximum domain size.
-    let log_max_height = proof.commit_phase_commits.len() + params.log_blowup;
+    // The log of the maximum domain size, and of the domain of the final polynomial.
+    let log_final_height = params.log_blowup + params.log_final_poly_len;
+    let log_max_height = proof.commit_phase_commits.len() + log_final_height;
 
     for qp in &proof.query_proofs {
         let index = challenger.sample_bits(log_max_height + folding.extra_query_index_bits());
@@ -66,10 +97,11 @@ where
         // perform fri folds until the domain size reaches the final domain size.
         // Check after each fold that the pair of sibling evaluations at the current
         // node match the commitment.
+        let domain_index = index >> folding.extra_query_index_bits();
         let folded_eval = verify_query(
             folding,
             params,
-            index >> folding.extra_query_index_bits(),
+            domain_index,
             zip_eq(
                 zip_eq(
                     &betas,
@@ -81,11 +113,23 @@ where
             )?,
             ro,
             log_max_height,
+            log_final_height,
         )?;
 
-        // As we fold until the polynomial is constant, proof.final_poly should be a constant value and
-        // we do not need to do any polynomial evaluations.
-        if folded_eval != proof.final_poly {
+        // The chain of folds ends at the entry at `final_index` of the final evaluations, which is
+        // taken at the x-coordinate `fold_x_point(final_index, log_final_height)`. A constant final
+        // polynomial needs no evaluation point.
+        let final_index = domain_index >> proof.commit_phase_commits.len();
+        let x = if params.log_final_poly_len == 0 {
+            Val::ZERO
+        } else {
+            fold_x_point(final_index, log_final_height)
+        };
+        let mut eval = Challenge::ZERO;
+        for &coeff in proof.final_poly.iter().rev() {
+            eval = eval * x + coeff;
+        }
+        if eval != folded_eval {
             return Err(FriError::FinalPolyMismatch);
         }
     }
@@ -115,6 +159,7 @@ fn verify_query<'a, Folding, F, EF, M>(
     steps: impl ExactSizeIterator<Item = CommitStep<'a, EF, M>>,
     reduced_openings: Vec<(usize, EF)>,
     log_max_height: usize,
+    log_final_height: usize,
 ) -> Result<EF, FriError<M::Error, Folding::InputError>>
 where
     F: Field,
@@ -126,10 +171,9 @@ where
     let mut ro_iter = reduced_openings.into_iter().peekable();
 
     // We start with evaluations over a domain of size (1 << log_max_height). We fold
-    // using FRI until the domain size reaches (1 << log_final_height). This is equal to 1 << log_blowup
-    // currently as we have not yet implemented early stopping.
+    // using FRI until the domain size reaches (1 << log_final_height).
     for (log_folded_height, ((&beta, comm), opening)) in zip_eq(
-        (params.log_blowup..log_max_height).rev(),
+        (log_final_height..log_max_height).rev(),
         steps,
         FriError::InvalidProofShape,
     )? {
@@ -6,7 +6,7 @@ use p3_field::{ExtensionField, Field};
 use p3_matrix::extension::FlatMatrixView;
 use p3_matrix::{Dimensions, Matrix};
 
-use crate::{BatchOpening, BatchOpeningRef, Mmcs};
+use crate::{BatchOpening, BatchOpeningRef, Mmcs, MultiBatchOpening, MultiBatchOpeningRef};
 
 /// A wrapper to lift an MMCS from a base field `F` to an extension field `EF`.
 ///
@@ -39,6 +39,7 @@ where
     type ProverData<M> = InnerMmcs::ProverData<FlatMatrixView<F, EF, M>>;
     type Commitment = InnerMmcs::Commitment;
     type Proof = InnerMmcs::Proof;
+    type MultiProof = InnerMmcs::MultiProof;
     type Error = InnerMmcs::Error;
 
     fn commit<M: Matrix<EF>>(&self, inputs: Vec<M>) -> (Self::Commitment, Self::ProverData<M>) {
@@ -94,4 +95,45 @@ where
             BatchOpeningRef::new(&opened_base_values, batch_opening.opening_proof),
         )
     }
+
+    fn open_multi_batch<M: Matrix<EF>>(
+        &self,
+        indices: &[usize],
+        prover_data: &Self::ProverData<M>,
+    ) -> MultiBatchOpening<EF, Self> {
+        let (inner_opened_values, inner_proof) =
+            self.inner.open_multi_batch(indices, prover_data).unpack();
+        let opened_ext_values = inner_opened_values
+            .into_iter()
+            .map(|rows| rows.into_iter().map(EF::reconstitute_from_base).collect())
+            .collect();
+        MultiBatchOpening::new(opened_ext_values, inner_proof)
+    }
+
+    fn verify_multi_batch(
+        &self,
+        commit: &Self::Commitment,
+        dimensions: &[Dimensions],
+        indices: &[usize],
+        batch_opening: MultiBatchOpeningRef<EF, Self>,
+    ) -> Result<(), Self::Error> {
+        let opened_base_values: Vec<Vec<Vec<F>>> = batch_opening
+            .opened_values
+            .iter()
+            .map(|rows| rows.iter().cloned().map(EF::flatten_to_base).collect())
+            .collect();
+        let base_dimensions = dimensions
+            .iter()
+            .map(|dim| Dimensions {
+                width: dim.width * EF::DIMENSION,
+                height: dim.height,
+            })
+            .collect::<Vec<_>>();
+        self.inner.verify_multi_batch(
+            commit,
+            &base_dimensions,
+            indices,
+            MultiBatchOpeningRef::new(&opened_base_values, batch_opening.opening_proof),
+        )
+    }
 }
@@ -95,6 +95,18 @@ pub trait PolynomialSpace: Copy {
         evals: RowMajorMatrix<Self::Val>,
     ) -> Vec<RowMajorMatrix<Self::Val>>;
 
+    /// The chunk, and the row within it, to which [`split_evals`](Self::split_evals) sends row
+    /// `index` of the evaluations.
+    ///
+    /// This allows filling in the chunks without first building the full evaluation matrix.
+    ///
+    /// The default sends every `num_chunks`-th row to the same chunk, starting with row `i` for
+    /// chunk `i`, as splitting a coset `gH` into the cosets of a subgroup of `H` does. A space
+    /// whose `split_evals` orders its rows differently must override this.
+    fn split_index(&self, num_chunks: usize, index: usize) -> (usize, usize) {
+        (index % num_chunks, index / num_chunks)
+    }
+
     /// Compute the vanishing polynomial of the space, evaluated at the given point.
     ///
     /// This is a polynomial which evaluates to `0` on every point of the
@@ -8,6 +8,7 @@ mod adapters;
 mod domain;
 mod mmcs;
 mod pcs;
+mod proof_size;
 
 #[cfg(any(test, feature = "test-utils"))]
 pub mod testing;
@@ -16,3 +17,4 @@ pub use adapters::*;
 pub use domain::*;
 pub use mmcs::*;
 pub use pcs::*;
+pub use proof_size::*;
@@ -20,6 +20,7 @@ pub trait Mmcs<T: Send + Sync + Clone>: Clone {
     type ProverData<M>;
     type Commitment: Clone + Serialize + DeserializeOwned;
     type Proof: Clone + Serialize + DeserializeOwned;
+    type MultiProof: Clone + Serialize + DeserializeOwned;
     type Error: Debug;
 
     /// Commits to a batch of matrices at once and returns both the commitment and associated prover data.
@@ -149,6 +150,45 @@ pub trait Mmcs<T: Send + Sync + Clone>: Clone {
         index: usize,
         batch_opening: BatchOpeningRef<T, Self>,
     ) -> Result<(), Self::Error>;
+
+    /// Opens the rows at each of several indices from each matrix in the batch, with one proof
+    /// for all of them.
+    ///
+    /// Each index is interpreted as in [`open_batch`]. The proof can be much smaller than one
+    /// proof per index, as the openings share the parts of their proofs which they have in common.
+    ///
+    /// # Parameters
+    /// - `indices`: The global row indices (relative to max height). They may repeat.
+    /// - `prover_data`: Prover data returned from [`commit`] or related methods.
+    ///
+    /// # Returns
+    /// A [`MultiBatchOpening`] containing the opened rows for each index, in the order of
+    /// `indices`, and the proof of their correctness.
+    fn open_multi_batch<M: Matrix<T>>(
+        &self,
+        indices: &[usize],
+        prover_data: &Self::ProverData<M>,
+    ) -> MultiBatchOpening<T, Self>;
+
+    /// Verifies an opening of several row indices at once against the original commitment.
+    ///
+    /// This is the verifier-side analogue of [`open_multi_batch`].
+    ///
+    /// # Parameters
+    /// - `commit`: The original commitment.
+    /// - `dimensions`: Dimensions of the committed matrices, in order.
+    /// - `indices`: The global row indices that were opened, in the order they were opened.
+    /// - `batch_opening`: A reference to the values and proof to verify.
+    ///
+    /// # Returns
+    /// `Ok(())` if the opening is valid; otherwise returns a verification error.
+    fn verify_multi_batch(
+        &self,
+        commit: &Self::Commitment,
+        dimensions: &[Dimensions],
+        indices: &[usize],
+        batch_opening: MultiBatchOpeningRef<T, Self>,
+    ) -> Result<(), Self::Error>;
 }
 
 /// A Batched opening proof.
@@ -223,3 +263,70 @@ impl<'a, T: Send + Sync + Clone, InputMmcs: Mmcs<T>> From<&'a BatchOpening<T, In
         Self::new(&batch_opening.opened_values, &batch_opening.opening_proof)
     }
 }
+
+/// An opening of several rows of a batch, with one proof for all of them.
+///
+/// Primarily used by the prover.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(bound(serialize = "T: Serialize"))]
+#[serde(bound(deserialize = "T: DeserializeOwned"))]
+pub struct MultiBatchOpening<T: Send + Sync + Clone, InputMmcs: Mmcs<T>> {
+    /// For each opened index, the opened row values from each matrix in the batch.
+    pub opened_values: Vec<Vec<Vec<T>>>,
+    /// The proof showing the values are valid openings.
+    pub opening_proof: InputMmcs::MultiProof,
+}
+
+impl<T: Send + Sync + Clone, InputMmcs: Mmcs<T>> MultiBatchOpening<T, InputMmcs> {
+    /// Creates a new multi batch opening proof.
+    #[inline]
+    pub fn new(opened_values: Vec<Vec<Vec<T>>>, opening_proof: InputMmcs::MultiProof) -> Self {
+        Self {
+            opened_values,
+            opening_proof,
+        }
+    }
+
+    /// Unpacks the multi batch opening proof into its components.
+    #[inline]
+    pub fn unpack(self) -> (Vec<Vec<Vec<T>>>, InputMmcs::MultiProof) {
+        (self.opened_values, self.opening_proof)
+    }
+}
+
+/// A reference to an opening of several rows of a batch.
+///
+/// Primarily used by the verifier.
+#[derive(Copy, Clone)]
+pub struct MultiBatchOpeningRef<'a, T: Send + Sync + Clone, InputMmcs: Mmcs<T>> {
+    /// Reference to the opened row values for each index.
+    pub opened_values: &'a [Vec<Vec<T>>],
+    /// Reference to the proof object used for verification.
+    pub opening_proof: &'a InputMmcs::MultiProof,
+}
+
+impl<'a, T: Send + Sync + Clone, InputMmcs: Mmcs<T>> MultiBatchOpeningRef<'a, T, InputMmcs> {
+    /// Creates a new reference to a multi batch opening proof.
+    #[inline]
+    pub fn new(opened_values: &'a [Vec<Vec<T>>], opening_proof: &'a InputMmcs::MultiProof) -> Self {
+        Self {
+            opened_values,
+            opening_proof,
+        }
+    }
+
+    /// Unpacks the multi batch opening proof into its components.
+    #[inline]
+    pub fn unpack(&self) -> (&'a [Vec<Vec<T>>], &'a InputMmcs::MultiProof) {
+        (self.opened_values, self.opening_proof)
+    }
+}
+
+impl<'a, T: Send + Sync + Clone, InputMmcs: Mmcs<T>> From<&'a MultiBatchOpening<T, InputMmcs>>
+    for MultiBatchOpeningRef<'a, T, InputMmcs>
+{
+    #[inline]
+    fn from(batch_opening: &'a MultiBatchOpening<T, InputMmcs>) -> Self {
+        Self::new(&batch_opening.opened_values, &batch_opening.opening_proof)
+    }
+}
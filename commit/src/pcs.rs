@@ -9,7 +9,7 @@ use p3_matrix::dense::RowMajorMatrix;
 use serde::Serialize;
 use serde::de::DeserializeOwned;
 
-use crate::PolynomialSpace;
+use crate::{OpeningProofSize, PolynomialSpace, SerializedSize};
 
 pub type Val<D> = <D as PolynomialSpace>::Val;
 
@@ -29,6 +29,9 @@ where
     type Commitment: Clone + Serialize + DeserializeOwned;
 
     /// Data that the prover stores for committed polynomials, to help the prover with opening.
+    ///
+    /// This is not required to be serializable, but implementations backed by a Merkle tree
+    /// generally are, so it can be checkpointed between committing and opening.
     type ProverData;
 
     /// Type of the output of `get_evaluations_on_domain`.
@@ -88,13 +91,23 @@ where
         //       We may be able to avoid this copy making use of bit-reversals.
         let quotient_sub_evaluations =
             quotient_domain.split_evals(num_chunks, quotient_evaluations);
-        let quotient_sub_domains = quotient_domain.split_domains(num_chunks);
+        self.commit_quotient_chunks(quotient_domain, quotient_sub_evaluations)
+    }
 
-        self.commit(
-            quotient_sub_domains
-                .into_iter()
-                .zip(quotient_sub_evaluations),
-        )
+    /// Commit to the quotient polynomial, given its evaluations already split into chunks as by
+    /// [`PolynomialSpace::split_evals`].
+    ///
+    /// This is the same as [`commit_quotient`](Self::commit_quotient), for a prover which builds
+    /// the chunks directly instead of first computing the evaluations over the whole domain. It
+    /// saves the copy made by `split_evals`, but every chunk is still held in memory in full.
+    #[allow(clippy::type_complexity)]
+    fn commit_quotient_chunks(
+        &self,
+        quotient_domain: Self::Domain,
+        quotient_chunks: Vec<RowMajorMatrix<Val<Self::Domain>>>,
+    ) -> (Self::Commitment, Self::ProverData) {
+        let quotient_sub_domains = quotient_domain.split_domains(quotient_chunks.len());
+        self.commit(quotient_sub_domains.into_iter().zip(quotient_chunks))
     }
 
     /// Given prover data corresponding to a commitment to a collection of evaluation matrices,
@@ -175,6 +188,14 @@ where
         fiat_shamir_challenger: &mut Challenger,
     ) -> Result<(), Self::Error>;
 
+    /// The serialized size of each part of an opening proof, as measured by `sizer`.
+    ///
+    /// By default the proof is not broken down, and all of it is counted as
+    /// [`other`](OpeningProofSize::other).
+    fn opening_proof_size(proof: &Self::Proof, sizer: &impl SerializedSize) -> OpeningProofSize {
+        OpeningProofSize::opaque(sizer.serialized_size(proof))
+    }
+
     fn get_opt_randomization_poly_commitment(
         &self,
         _domain: Self::Domain,
@@ -0,0 +1,70 @@
+//! Measuring the size of proofs and of their parts.
+
+use serde::{Deserialize, Serialize};
+
+/// Measures the size of a value in some serialization format, such as the one proofs are sent in.
+pub trait SerializedSize {
+    /// The number of bytes `value` is serialized to.
+    fn serialized_size<T: Serialize>(&self, value: &T) -> usize;
+}
+
+/// A breakdown of the serialized size of a proof into parts, where everything not counted in its
+/// own part is counted in an `other` part.
+pub trait SizeBreakdown: Sized {
+    /// The size of the whole proof.
+    fn total(&self) -> usize;
+
+    /// The size of everything not counted in its own part.
+    fn other_mut(&mut self) -> &mut usize;
+
+    /// Set `other` so that the parts add up to the size `total` of the whole proof.
+    #[must_use]
+    fn with_total(mut self, total: usize) -> Self {
+        *self.other_mut() = 0;
+        let counted = self.total();
+        *self.other_mut() = total.saturating_sub(counted);
+        self
+    }
+}
+
+/// The serialized size, in bytes, of each part of a PCS opening proof.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpeningProofSize {
+    /// The commitments sent during the FRI commit phase.
+    pub commit_phase_commitments: usize,
+    /// The sibling values and Merkle paths opened in the FRI commit phase, over all queries.
+    pub commit_phase_openings: usize,
+    /// The openings of the committed input matrices, rows and Merkle paths, over all queries.
+    pub query_openings: usize,
+    /// The final polynomial of FRI.
+    pub final_poly: usize,
+    /// The proof of work witness.
+    pub pow_witness: usize,
+    /// Everything not counted above, e.g. length prefixes and values specific to the PCS.
+    pub other: usize,
+}
+
+impl OpeningProofSize {
+    /// The size of a proof which is not broken down into parts, counted entirely as `other`.
+    pub fn opaque(total: usize) -> Self {
+        Self {
+            other: total,
+            ..Self::default()
+        }
+    }
+}
+
+impl SizeBreakdown for OpeningProofSize {
+    fn total(&self) -> usize {
+        self.commit_phase_commitments
+            + self.commit_phase_openings
+            + self.query_openings
+            + self.final_poly
+            + self.pow_witness
+            + self.other
+    }
+
+    fn other_mut(&mut self) -> &mut usize {
+        &mut self.other
+    }
+}
@@ -46,15 +46,31 @@ impl<F: TwoAdicField> TwoAdicSubgroupDft<F> for Radix2Dit<F> {
             root.powers().collect_n(1 << log_h)
         });
 
-        // DIT butterfly
-        reverse_matrix_index_bits(&mut mat);
-        for layer in 0..log_h {
-            dit_layer(&mut mat.as_view_mut(), layer, twiddles);
-        }
+        dit(&mut mat.as_view_mut(), twiddles);
         mat
     }
 }
 
+/// Compute the DFT of each column of `mat` in place, without memoizing the twiddle factors.
+///
+/// This lets a DFT run on any storage which can be borrowed as a mutable slice, e.g. a
+/// memory-mapped file.
+pub(crate) fn dit_in_place<F: TwoAdicField>(mat: &mut RowMajorMatrixViewMut<'_, F>) {
+    let log_h = log2_strict_usize(mat.height());
+    let twiddles = F::two_adic_generator(log_h).powers().collect_n(1 << log_h);
+    dit(mat, &twiddles);
+}
+
+/// Compute the DFT of each column of `mat` in place, given the powers of the generator of the
+/// subgroup of order `mat.height()`.
+fn dit<F: Field>(mat: &mut RowMajorMatrixViewMut<'_, F>, twiddles: &[F]) {
+    // DIT butterfly
+    reverse_matrix_index_bits(mat);
+    for layer in 0..log2_strict_usize(mat.height()) {
+        dit_layer(mat, layer, twiddles);
+    }
+}
+
 /// Applies one layer of the Radix-2 DIT FFT butterfly network.
 ///
 /// Splits the matrix into blocks of rows and performs in-place butterfly operations
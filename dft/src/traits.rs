@@ -1,11 +1,13 @@
 use alloc::vec::Vec;
+use core::borrow::BorrowMut;
 
 use p3_field::{BasedVectorSpace, TwoAdicField};
-use p3_matrix::Matrix;
 use p3_matrix::bitrev::BitReversibleMatrix;
-use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::dense::{DenseMatrix, DenseStorage, RowMajorMatrix, RowMajorMatrixViewMut};
 use p3_matrix::util::swap_rows;
+use p3_matrix::{Dimensions, Matrix};
 
+use crate::radix_2_dit::dit_in_place;
 use crate::util::{coset_shift_cols, divide_by_height};
 
 /// This trait gives an interface for computing discrete fourier transforms (DFT's) and their inverses over
@@ -248,6 +250,57 @@ pub trait TwoAdicSubgroupDft<F: TwoAdicField>: Clone + Default {
         self.coset_dft_batch(coeffs, shift)
     }
 
+    /// Compute the low-degree extension of each column in `mat` onto a coset of a larger subgroup,
+    /// writing the evaluations into `out`.
+    ///
+    /// This computes the same evaluations as [`coset_lde_batch`](Self::coset_lde_batch), in their
+    /// natural order, but reads `mat` and writes `out` in place. Both may use any [`DenseStorage`],
+    /// e.g. the `MmapStorage` of the `mmap` feature of `p3-matrix`, so an LDE too large for memory
+    /// can be computed from a trace which is not in memory either. Apart from the twiddle factors,
+    /// nothing is allocated.
+    ///
+    /// # Panics
+    /// Panics if `out` does not have the width of `mat` and `mat.height() << added_bits` rows.
+    fn coset_lde_batch_into<S, T>(
+        &self,
+        mat: &DenseMatrix<F, S>,
+        added_bits: usize,
+        shift: F,
+        out: &mut DenseMatrix<F, T>,
+    ) where
+        S: DenseStorage<F>,
+        T: DenseStorage<F> + BorrowMut<[F]>,
+    {
+        let (w, h) = (mat.width(), mat.height());
+        assert_eq!(
+            out.dimensions(),
+            Dimensions {
+                width: w,
+                height: h << added_bits,
+            }
+        );
+        if w == 0 {
+            return;
+        }
+
+        // The inverse DFT runs in the top `h` rows of `out`, and the DFT over all of them once the
+        // coefficients are padded with zeros, as in `coset_lde_batch`.
+        let (top, bottom) = out.values.borrow_mut().split_at_mut(w * h);
+        top.copy_from_slice(mat.values.borrow());
+        bottom.fill(F::ZERO);
+
+        // The inverse DFT is the DFT with all but the first row reversed, divided by the height.
+        let mut coeffs = RowMajorMatrixViewMut::new(top, w);
+        dit_in_place(&mut coeffs);
+        for row in 1..h / 2 {
+            swap_rows(&mut coeffs, row, h - row);
+        }
+        divide_by_height(&mut coeffs);
+        coset_shift_cols(&mut coeffs, shift);
+
+        dit_in_place(&mut out.as_view_mut());
+    }
+
     /// Compute the discrete Fourier transform (DFT) of `vec`.
     ///
     /// #### Mathematical Description
@@ -496,3 +549,46 @@ pub trait TwoAdicSubgroupDft<F: TwoAdicField>: Clone + Default {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use alloc::format;
+
+    use p3_baby_bear::BabyBear;
+    use p3_field::Field;
+    use p3_matrix::mmap::{MmapMatrix, MmapStorage};
+    use rand::SeedableRng;
+    use rand::rngs::SmallRng;
+
+    use super::*;
+    use crate::Radix2Dit;
+
+    type F = BabyBear;
+
+    #[test]
+    fn coset_lde_batch_into_mmap() {
+        let dft = Radix2Dit::<F>::default();
+        let mut rng = SmallRng::seed_from_u64(1);
+        let mat = RowMajorMatrix::<F>::rand(&mut rng, 16, 5);
+        let shift = F::GENERATOR;
+
+        let dir = std::env::temp_dir();
+        let trace_path = dir.join(format!("p3-dft-mmap-trace-{}", std::process::id()));
+        let lde_path = dir.join(format!("p3-dft-mmap-lde-{}", std::process::id()));
+        let mut trace =
+            MmapMatrix::new(unsafe { MmapStorage::create(&trace_path, 80).unwrap() }, 5);
+        trace.copy_from(&mat);
+        let mut lde = MmapMatrix::new(unsafe { MmapStorage::create(&lde_path, 320).unwrap() }, 5);
+
+        dft.coset_lde_batch_into(&trace, 2, shift, &mut lde);
+        assert_eq!(
+            lde.to_row_major_matrix(),
+            dft.coset_lde_batch(mat, 2, shift)
+        );
+
+        std::fs::remove_file(&trace_path).unwrap();
+        std::fs::remove_file(&lde_path).unwrap();
+    }
+}
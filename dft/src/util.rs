@@ -2,7 +2,7 @@ use core::borrow::BorrowMut;
 
 use p3_field::{Field, PrimeCharacteristicRing};
 use p3_matrix::Matrix;
-use p3_matrix::dense::{DenseMatrix, DenseStorage, RowMajorMatrix};
+use p3_matrix::dense::{DenseMatrix, DenseStorage};
 use p3_util::log2_strict_usize;
 use tracing::instrument;
 
@@ -25,7 +25,10 @@ pub fn divide_by_height<F: Field, S: DenseStorage<F> + BorrowMut<[F]>>(
 }
 
 /// Multiply each element of row `i` of `mat` by `shift**i`.
-pub(crate) fn coset_shift_cols<F: Field>(mat: &mut RowMajorMatrix<F>, shift: F) {
+pub(crate) fn coset_shift_cols<F: Field, S: DenseStorage<F> + BorrowMut<[F]>>(
+    mat: &mut DenseMatrix<F, S>,
+    shift: F,
+) {
     mat.rows_mut()
         .zip(shift.powers())
         .for_each(|(row, weight)| {
@@ -9,6 +9,7 @@ use p3_examples::proofs::{
     prove_m31_keccak, prove_m31_poseidon2, prove_monty31_keccak, prove_monty31_poseidon2,
     report_result,
 };
+use p3_examples::report::PhaseTimingLayer;
 use p3_field::extension::BinomialExtensionField;
 use p3_keccak_air::KeccakAir;
 use p3_koala_bear::{GenericPoseidon2LinearLayersKoalaBear, KoalaBear, Poseidon2KoalaBear};
@@ -58,9 +59,11 @@ fn main() {
         .with_default_directive(LevelFilter::INFO.into())
         .from_env_lossy();
 
+    let timing_layer = PhaseTimingLayer::default();
     Registry::default()
         .with(env_filter)
         .with(ForestLayer::default())
+        .with(timing_layer.clone())
         .init();
 
     let args = Args::parse();
@@ -131,14 +134,24 @@ fn main() {
 
             match args.merkle_hash {
                 MerkleHashOptions::KeccakF => {
-                    let result = prove_monty31_keccak::<_, EF, _, _>(proof_goal, dft, num_hashes);
+                    let result = prove_monty31_keccak::<_, EF, _, _>(
+                        proof_goal,
+                        dft,
+                        num_hashes,
+                        timing_layer,
+                    );
                     report_result(result);
                 }
                 MerkleHashOptions::Poseidon2 => {
                     let perm16 = Poseidon2KoalaBear::<16>::new_from_rng_128(&mut rng);
                     let perm24 = Poseidon2KoalaBear::<24>::new_from_rng_128(&mut rng);
                     let result = prove_monty31_poseidon2::<_, EF, _, _, _, _>(
-                        proof_goal, dft, num_hashes, perm16, perm24,
+                        proof_goal,
+                        dft,
+                        num_hashes,
+                        perm16,
+                        perm24,
+                        timing_layer,
                     );
                     report_result(result);
                 }
@@ -184,14 +197,24 @@ fn main() {
 
             match args.merkle_hash {
                 MerkleHashOptions::KeccakF => {
-                    let result = prove_monty31_keccak::<_, EF, _, _>(proof_goal, dft, num_hashes);
+                    let result = prove_monty31_keccak::<_, EF, _, _>(
+                        proof_goal,
+                        dft,
+                        num_hashes,
+                        timing_layer,
+                    );
                     report_result(result);
                 }
                 MerkleHashOptions::Poseidon2 => {
                     let perm16 = Poseidon2BabyBear::<16>::new_from_rng_128(&mut rng);
                     let perm24 = Poseidon2BabyBear::<24>::new_from_rng_128(&mut rng);
                     let result = prove_monty31_poseidon2::<_, EF, _, _, _, _>(
-                        proof_goal, dft, num_hashes, perm16, perm24,
+                        proof_goal,
+                        dft,
+                        num_hashes,
+                        perm16,
+                        perm24,
+                        timing_layer,
                     );
                     report_result(result);
                 }
@@ -234,14 +257,18 @@ fn main() {
 
             match args.merkle_hash {
                 MerkleHashOptions::KeccakF => {
-                    let result = prove_m31_keccak(proof_goal, num_hashes);
+                    let result = prove_m31_keccak(proof_goal, num_hashes, timing_layer);
                     report_result(result);
                 }
                 MerkleHashOptions::Poseidon2 => {
                     let perm16 = Poseidon2Mersenne31::<16>::new_from_rng_128(&mut rng);
                     let perm24 = Poseidon2Mersenne31::<24>::new_from_rng_128(&mut rng);
                     let result = prove_m31_poseidon2::<_, EF, _, _, _>(
-                        proof_goal, num_hashes, perm16, perm24,
+                        proof_goal,
+                        num_hashes,
+                        perm16,
+                        perm24,
+                        timing_layer,
                     );
                     report_result(result);
                 }
@@ -2,6 +2,7 @@ pub mod airs;
 pub mod dfts;
 pub mod parsers;
 pub mod proofs;
+pub mod report;
 pub mod types;
 
 #[cfg(test)]
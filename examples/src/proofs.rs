@@ -15,6 +15,7 @@ use rand::distr::StandardUniform;
 use rand::prelude::Distribution;
 
 use crate::airs::ExampleHashAir;
+use crate::report::{PhaseTimingLayer, ProofReport};
 use crate::types::{
     KeccakCircleStarkConfig, KeccakCompressionFunction, KeccakMerkleMmcs, KeccakStarkConfig,
     Poseidon2CircleStarkConfig, Poseidon2Compression, Poseidon2MerkleMmcs, Poseidon2Sponge,
@@ -68,6 +69,7 @@ pub fn prove_monty31_keccak<
     proof_goal: PG,
     dft: DFT,
     num_hashes: usize,
+    timing_layer: PhaseTimingLayer,
 ) -> Result<(), impl Debug>
 where
     StandardUniform: Distribution<F>,
@@ -84,8 +86,8 @@ where
 
     let config = KeccakStarkConfig::new(pcs, challenger);
 
-    let proof = prove(&config, &proof_goal, trace, &vec![]);
-    report_proof_size(&proof);
+    let proof = prove(&config, &proof_goal, trace, &vec![]).expect("proving failed");
+    report_proof_size(&proof, &timing_layer);
 
     verify(&config, &proof_goal, &proof, &vec![])
 }
@@ -110,6 +112,7 @@ pub fn prove_monty31_poseidon2<
     num_hashes: usize,
     perm16: Perm16,
     perm24: Perm24,
+    timing_layer: PhaseTimingLayer,
 ) -> Result<(), impl Debug>
 where
     StandardUniform: Distribution<F>,
@@ -126,8 +129,8 @@ where
 
     let config = Poseidon2StarkConfig::new(pcs, challenger);
 
-    let proof = prove(&config, &proof_goal, trace, &vec![]);
-    report_proof_size(&proof);
+    let proof = prove(&config, &proof_goal, trace, &vec![]).expect("proving failed");
+    report_proof_size(&proof, &timing_layer);
 
     verify(&config, &proof_goal, &proof, &vec![])
 }
@@ -147,6 +150,7 @@ pub fn prove_m31_keccak<
 >(
     proof_goal: PG,
     num_hashes: usize,
+    timing_layer: PhaseTimingLayer,
 ) -> Result<(), impl Debug> {
     type F = Mersenne31;
     type EF = BinomialExtensionField<Mersenne31, 3>;
@@ -162,8 +166,8 @@ pub fn prove_m31_keccak<
 
     let config = KeccakCircleStarkConfig::new(pcs, challenger);
 
-    let proof = prove(&config, &proof_goal, trace, &vec![]);
-    report_proof_size(&proof);
+    let proof = prove(&config, &proof_goal, trace, &vec![]).expect("proving failed");
+    report_proof_size(&proof, &timing_layer);
 
     verify(&config, &proof_goal, &proof, &vec![])
 }
@@ -186,6 +190,7 @@ pub fn prove_m31_poseidon2<
     num_hashes: usize,
     perm16: Perm16,
     perm24: Perm24,
+    timing_layer: PhaseTimingLayer,
 ) -> Result<(), impl Debug>
 where
     StandardUniform: Distribution<F>,
@@ -202,8 +207,8 @@ where
 
     let config = Poseidon2CircleStarkConfig::new(pcs, challenger);
 
-    let proof = prove(&config, &proof_goal, trace, &vec![]);
-    report_proof_size(&proof);
+    let proof = prove(&config, &proof_goal, trace, &vec![]).expect("proving failed");
+    report_proof_size(&proof, &timing_layer);
 
     verify(&config, &proof_goal, &proof, &vec![])
 }
@@ -222,17 +227,14 @@ pub fn report_result(result: Result<(), impl Debug>) {
 
 /// Report the size of the serialized proof.
 ///
-/// Serializes the given proof instance using bincode and prints the size in bytes.
+/// Serializes the given proof instance using bincode and prints its size in bytes, broken down
+/// into the parts of the proof, along with the prover timings collected by `timing_layer`, which
+/// are empty unless it is installed.
 /// Panics if serialization fails.
 #[inline]
-pub fn report_proof_size<SC>(proof: &Proof<SC>)
+pub fn report_proof_size<SC>(proof: &Proof<SC>, timing_layer: &PhaseTimingLayer)
 where
     SC: StarkGenericConfig,
 {
-    let config = bincode::config::standard()
-        .with_little_endian()
-        .with_fixed_int_encoding();
-    let proof_bytes =
-        bincode::serde::encode_to_vec(proof, config).expect("Failed to serialize proof");
-    println!("Proof size: {} bytes", proof_bytes.len());
+    println!("{}", ProofReport::new(proof, timing_layer.take_timings()));
 }
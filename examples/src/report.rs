@@ -0,0 +1,132 @@
+use core::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use p3_commit::SerializedSize;
+use p3_uni_stark::{Proof, ProofSizeReport, StarkGenericConfig};
+use serde::{Deserialize, Serialize};
+use tracing::Subscriber;
+use tracing::span::{Attributes, Id};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+/// Measures sizes in the bincode encoding the examples report proof sizes in.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BincodeSize;
+
+impl SerializedSize for BincodeSize {
+    fn serialized_size<T: Serialize>(&self, value: &T) -> usize {
+        let config = bincode::config::standard()
+            .with_little_endian()
+            .with_fixed_int_encoding();
+        bincode::serde::encode_to_vec(value, config)
+            .expect("Failed to serialize value")
+            .len()
+    }
+}
+
+/// The total time spent in all spans of the same name.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhaseTiming {
+    /// The name of the spans, e.g. `commit to trace data`.
+    pub name: String,
+    /// The number of spans of this name which were closed.
+    pub count: usize,
+    /// The time from the creation to the closing of each span, summed over all of them.
+    pub duration: Duration,
+}
+
+/// A [`Layer`] which times the `tracing` spans of the prover, grouped by span name.
+///
+/// The timings are collected in the order the spans were first created, and shared between the
+/// clones of the layer, so a clone kept after installing the layer can take them with
+/// [`Self::take_timings`].
+#[derive(Clone, Debug, Default)]
+pub struct PhaseTimingLayer {
+    /// The timings collected since they were last taken.
+    timings: Arc<Mutex<Vec<PhaseTiming>>>,
+}
+
+impl PhaseTimingLayer {
+    /// Take the timings collected so far, leaving none behind.
+    ///
+    /// This is empty unless the layer, or a clone of it, was installed in the `tracing` subscriber.
+    pub fn take_timings(&self) -> Vec<PhaseTiming> {
+        core::mem::take(&mut *self.timings.lock().unwrap())
+    }
+}
+
+/// The time a span was created at.
+struct SpanStart(Instant);
+
+impl<S> Layer<S> for PhaseTimingLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, _attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        span.extensions_mut().insert(SpanStart(Instant::now()));
+
+        let mut timings = self.timings.lock().unwrap();
+        if !timings.iter().any(|timing| timing.name == span.name()) {
+            timings.push(PhaseTiming {
+                name: span.name().to_string(),
+                count: 0,
+                duration: Duration::ZERO,
+            });
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(SpanStart(start)) = span.extensions_mut().remove::<SpanStart>() else {
+            return;
+        };
+
+        let mut timings = self.timings.lock().unwrap();
+        if let Some(timing) = timings.iter_mut().find(|timing| timing.name == span.name()) {
+            timing.count += 1;
+            timing.duration += start.elapsed();
+        }
+    }
+}
+
+/// The size of a proof, broken down into its parts, and the time the prover spent in each phase.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofReport {
+    pub size: ProofSizeReport,
+    /// The timings collected by a [`PhaseTimingLayer`], or empty if there were none.
+    pub timings: Vec<PhaseTiming>,
+}
+
+impl ProofReport {
+    /// Measure `proof` in bincode, alongside the `timings` collected while proving it, e.g. by
+    /// [`PhaseTimingLayer::take_timings`].
+    pub fn new<SC: StarkGenericConfig>(proof: &Proof<SC>, timings: Vec<PhaseTiming>) -> Self {
+        Self {
+            size: proof.size_report(&BincodeSize),
+            timings,
+        }
+    }
+}
+
+impl fmt::Display for ProofReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.size)?;
+        if !self.timings.is_empty() {
+            write!(f, "\nProver timings:")?;
+        }
+        for timing in &self.timings {
+            write!(
+                f,
+                "\n  {}: {:?} ({} spans)",
+                timing.name, timing.duration, timing.count
+            )?;
+        }
+        Ok(())
+    }
+}
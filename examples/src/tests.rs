@@ -19,6 +19,7 @@ use crate::dfts::DftChoice;
 use crate::proofs::{
     prove_m31_keccak, prove_m31_poseidon2, prove_monty31_keccak, prove_monty31_poseidon2,
 };
+use crate::report::PhaseTimingLayer;
 
 // 128 rows for the Generic Poseidon2 AIR.
 // Wider traces will be make shorter.
@@ -59,7 +60,14 @@ fn test_end_to_end_koalabear_vectorized_poseidon2_hashes_recursive_dft_poseidon2
     let perm16 = Poseidon2KoalaBear::<16>::new_from_rng_128(&mut rng);
     let perm24 = Poseidon2KoalaBear::<24>::new_from_rng_128(&mut rng);
 
-    prove_monty31_poseidon2::<_, EF, _, _, _, _>(proof_goal, dft, TRACE_SIZE, perm16, perm24)
+    prove_monty31_poseidon2::<_, EF, _, _, _, _>(
+        proof_goal,
+        dft,
+        TRACE_SIZE,
+        perm16,
+        perm24,
+        PhaseTimingLayer::default(),
+    )
 }
 
 #[test]
@@ -89,7 +97,7 @@ fn test_end_to_end_koalabear_poseidon2_hashes_recursive_dft_keccak_merkle_tree()
 
     let dft = DftChoice::Recursive(RecursiveDft::new(TRACE_SIZE << 1));
 
-    prove_monty31_keccak::<_, EF, _, _>(proof_goal, dft, num_hashes)
+    prove_monty31_keccak::<_, EF, _, _>(proof_goal, dft, num_hashes, PhaseTimingLayer::default())
 }
 
 #[test]
@@ -103,7 +111,7 @@ fn test_end_to_end_koalabear_keccak_hashes_parallel_dft_keccak_merkle_tree()
 
     let dft = DftChoice::Parallel(Radix2DitParallel::default());
 
-    prove_monty31_keccak::<_, EF, _, _>(proof_goal, dft, num_hashes)
+    prove_monty31_keccak::<_, EF, _, _>(proof_goal, dft, num_hashes, PhaseTimingLayer::default())
 }
 
 #[test]
@@ -134,7 +142,14 @@ fn test_end_to_end_babybear_vectorized_poseidon2_hashes_recursive_dft_poseidon2_
     let perm16 = Poseidon2BabyBear::<16>::new_from_rng_128(&mut rng);
     let perm24 = Poseidon2BabyBear::<24>::new_from_rng_128(&mut rng);
 
-    prove_monty31_poseidon2::<_, EF, _, _, _, _>(proof_goal, dft, TRACE_SIZE, perm16, perm24)
+    prove_monty31_poseidon2::<_, EF, _, _, _, _>(
+        proof_goal,
+        dft,
+        TRACE_SIZE,
+        perm16,
+        perm24,
+        PhaseTimingLayer::default(),
+    )
 }
 
 #[test]
@@ -165,7 +180,14 @@ fn test_end_to_end_babybear_poseidon2_hashes_parallel_dft_poseidon2_merkle_tree(
     let perm16 = Poseidon2BabyBear::<16>::new_from_rng_128(&mut rng);
     let perm24 = Poseidon2BabyBear::<24>::new_from_rng_128(&mut rng);
 
-    prove_monty31_poseidon2::<_, EF, _, _, _, _>(proof_goal, dft, TRACE_SIZE, perm16, perm24)
+    prove_monty31_poseidon2::<_, EF, _, _, _, _>(
+        proof_goal,
+        dft,
+        TRACE_SIZE,
+        perm16,
+        perm24,
+        PhaseTimingLayer::default(),
+    )
 }
 
 #[test]
@@ -185,7 +207,14 @@ fn test_end_to_end_babybear_blake3_hashes_parallel_dft_poseidon2_merkle_tree()
     let perm16 = Poseidon2BabyBear::<16>::new_from_rng_128(&mut rng);
     let perm24 = Poseidon2BabyBear::<24>::new_from_rng_128(&mut rng);
 
-    prove_monty31_poseidon2::<_, EF, _, _, _, _>(proof_goal, dft, num_hashes, perm16, perm24)
+    prove_monty31_poseidon2::<_, EF, _, _, _, _>(
+        proof_goal,
+        dft,
+        num_hashes,
+        perm16,
+        perm24,
+        PhaseTimingLayer::default(),
+    )
 }
 
 #[test]
@@ -193,7 +222,7 @@ fn test_end_to_end_mersenne_31_keccak_hashes_keccak_merkle_tree() -> Result<(),
     let num_hashes = TRACE_SIZE / 24;
     let proof_goal = KeccakAir {};
 
-    prove_m31_keccak(proof_goal, num_hashes)
+    prove_m31_keccak(proof_goal, num_hashes, PhaseTimingLayer::default())
 }
 
 #[test]
@@ -201,7 +230,7 @@ fn test_end_to_end_mersenne31_blake3_hashes_keccak_merkle_tree() -> Result<(), i
     let num_hashes = TRACE_SIZE >> 4;
     let proof_goal = Blake3Air {};
 
-    prove_m31_keccak(proof_goal, num_hashes)
+    prove_m31_keccak(proof_goal, num_hashes, PhaseTimingLayer::default())
 }
 
 #[test]
@@ -233,7 +262,13 @@ fn test_end_to_end_mersenne31_vectorized_poseidon2_hashes_poseidon2_merkle_tree(
     let perm16 = Poseidon2Mersenne31::<16>::new_from_rng_128(&mut rng);
     let perm24 = Poseidon2Mersenne31::<24>::new_from_rng_128(&mut rng);
 
-    prove_m31_poseidon2::<_, EF, _, _, _>(proof_goal, TRACE_SIZE, perm16, perm24)
+    prove_m31_poseidon2::<_, EF, _, _, _>(
+        proof_goal,
+        TRACE_SIZE,
+        perm16,
+        perm24,
+        PhaseTimingLayer::default(),
+    )
 }
 
 #[test]
@@ -258,5 +293,5 @@ fn test_end_to_end_mersenne31_poseidon2_hashes_keccak_merkle_tree() -> Result<()
         PARTIAL_ROUNDS,
     > = Poseidon2Air::new(constants);
 
-    prove_m31_keccak(proof_goal, TRACE_SIZE)
+    prove_m31_keccak(proof_goal, TRACE_SIZE, PhaseTimingLayer::default())
 }
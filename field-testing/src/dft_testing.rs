@@ -111,6 +111,27 @@ where
     }
 }
 
+pub fn test_coset_lde_into_matches_naive<F, Dft>()
+where
+    F: TwoAdicField,
+    StandardUniform: Distribution<F>,
+    Dft: TwoAdicSubgroupDft<F>,
+{
+    let dft = Dft::default();
+    let mut rng = SmallRng::seed_from_u64(1);
+    for log_h in 0..5 {
+        let h = 1 << log_h;
+        let mat = RowMajorMatrix::<F>::rand(&mut rng, h, 3);
+        let shift = F::GENERATOR;
+        for added_bits in 0..3 {
+            let coset_lde_naive = NaiveDft.coset_lde_batch(mat.clone(), added_bits, shift);
+            let mut coset_lde_result = RowMajorMatrix::new(F::zero_vec(3 * (h << added_bits)), 3);
+            dft.coset_lde_batch_into(&mat.as_view(), added_bits, shift, &mut coset_lde_result);
+            assert_eq!(coset_lde_naive, coset_lde_result);
+        }
+    }
+}
+
 pub fn test_dft_idft_consistency<F, Dft>()
 where
     F: TwoAdicField,
@@ -337,6 +358,11 @@ macro_rules! test_field_dft {
                 $crate::test_coset_lde_matches_naive::<$field, $dft>();
             }
 
+            #[test]
+            fn coset_lde_into_matches_naive() {
+                $crate::test_coset_lde_into_matches_naive::<$field, $dft>();
+            }
+
             #[test]
             fn dft_idft_consistency() {
                 $crate::test_dft_idft_consistency::<$field, $dft>();
@@ -1,17 +1,36 @@
+use alloc::collections::BTreeSet;
 use alloc::vec::Vec;
 use core::fmt::Debug;
 
 use p3_field::{ExtensionField, Field};
 use p3_matrix::Matrix;
 
+use crate::{BatchingStrategy, SoundnessCalculator};
+
 /// A set of parameters defining a specific instance of the FRI protocol.
 #[derive(Debug)]
 pub struct FriParameters<M> {
     pub log_blowup: usize,
-    // TODO: This parameter and FRI early stopping are not yet implemented in `CirclePcs`.
     pub log_final_poly_len: usize,
     pub num_queries: usize,
     pub proof_of_work_bits: usize,
+    /// The proof of work bits ground before sampling each folding challenge `beta`, or `0` to
+    /// not grind.
+    pub commit_proof_of_work_bits: usize,
+    /// The proof of work bits ground before sampling the challenge `alpha` batching the inputs, or
+    /// `0` to not grind.
+    pub batch_proof_of_work_bits: usize,
+    /// The `log_2` of the arity the rounds of the commit phase fold by, at least `1`, for rounds
+    /// past the end of `log_arity_schedule`.
+    ///
+    /// Each commit phase leaf holds a whole coset of the folding, so folding by `2^k` replaces `k`
+    /// binary rounds by one, with `k` times fewer commitments and Merkle paths per query.
+    ///
+    /// `CirclePcs` only folds by 2, and rejects any other arity.
+    pub max_log_arity: usize,
+    /// The `log_2` of the arity of each of the first rounds of the commit phase, e.g. `[3, 3]` with
+    /// a `max_log_arity` of `1` folds by 8 in the first two rounds and by 2 after that.
+    pub log_arity_schedule: Vec<usize>,
     pub mmcs: M,
 }
 
@@ -27,11 +46,78 @@ impl<M> FriParameters<M> {
     /// Returns the soundness bits of this FRI instance based on the
     /// [ethSTARK](https://eprint.iacr.org/2021/582) conjecture.
     ///
-    /// Certain users may instead want to look at proven soundness, a more complex calculation which
-    /// isn't currently supported by this crate.
+    /// Certain users may instead want to look at proven soundness, which `soundness_calculator`
+    /// estimates along with the soundness of the rest of the STARK.
     pub const fn conjectured_soundness_bits(&self) -> usize {
         self.log_blowup * self.num_queries + self.proof_of_work_bits
     }
+
+    /// Returns a `SoundnessCalculator` for a STARK using this FRI instance, over a field of size
+    /// `2^log_field_size` with challenges from its extension of degree `extension_degree`.
+    ///
+    /// The commit phase is assumed to fold the trace with the arities of [`Self::log_arities`],
+    /// without any shorter inputs to roll in.
+    pub fn soundness_calculator(
+        &self,
+        log_field_size: f64,
+        extension_degree: usize,
+        log_trace_height: usize,
+        num_constraints: usize,
+        batching: BatchingStrategy,
+    ) -> SoundnessCalculator {
+        SoundnessCalculator {
+            log_field_size,
+            extension_degree,
+            log_trace_height,
+            log_blowup: self.log_blowup,
+            log_arities: self.log_arities(log_trace_height + self.log_blowup, []),
+            log_final_poly_len: self.log_final_poly_len,
+            num_queries: self.num_queries,
+            proof_of_work_bits: self.proof_of_work_bits,
+            commit_proof_of_work_bits: self.commit_proof_of_work_bits,
+            batch_proof_of_work_bits: self.batch_proof_of_work_bits,
+            num_constraints,
+            batching,
+        }
+    }
+
+    /// The `log_2` of the arity of each round of the commit phase, when folding down from
+    /// evaluations of height `2^log_max_height`.
+    ///
+    /// Round `i` folds by `2^log_arity_schedule[i]`, or by `2^max_log_arity` past the end of the
+    /// schedule, except that it stops early at the next height in `log_input_heights`, so that
+    /// the inputs of that height can be rolled in, and at the height of the final polynomial.
+    pub fn log_arities(
+        &self,
+        log_max_height: usize,
+        log_input_heights: impl IntoIterator<Item = usize>,
+    ) -> Vec<usize> {
+        assert!(
+            self.max_log_arity > 0 && !self.log_arity_schedule.contains(&0),
+            "FRI must fold by at least 2"
+        );
+        let log_final_height = self.log_blowup + self.log_final_poly_len;
+        let log_input_heights: BTreeSet<usize> = log_input_heights.into_iter().collect();
+
+        let mut log_arities = Vec::new();
+        let mut log_height = log_max_height;
+        while log_height > log_final_height {
+            let log_next_height = log_input_heights
+                .range(log_final_height..log_height)
+                .next_back()
+                .copied()
+                .unwrap_or(log_final_height);
+            let log_arity = self
+                .log_arity_schedule
+                .get(log_arities.len())
+                .copied()
+                .unwrap_or(self.max_log_arity)
+                .min(log_height - log_next_height);
+            log_arities.push(log_arity);
+            log_height -= log_arity;
+        }
+        log_arities
+    }
 }
 
 /// Whereas `FriParameters` encompasses parameters the end user can set, `FriFoldingStrategy` is
@@ -45,8 +131,8 @@ pub trait FriFoldingStrategy<F: Field, EF: ExtensionField<F>> {
     fn extra_query_index_bits(&self) -> usize;
 
     /// Fold a row, returning a single column.
-    /// Right now the input row will always be 2 columns wide,
-    /// but we may support higher folding arity in the future.
+    /// The width of the row is the arity of the fold, a power of two which is at most
+    /// `2^max_log_arity`. Strategies which only fold by 2 may panic on wider rows.
     fn fold_row(
         &self,
         index: usize,
@@ -70,6 +156,10 @@ pub const fn create_test_fri_params<Mmcs>(
         log_final_poly_len,
         num_queries: 2,
         proof_of_work_bits: 1,
+        commit_proof_of_work_bits: 0,
+        batch_proof_of_work_bits: 0,
+        max_log_arity: 1,
+        log_arity_schedule: Vec::new(),
         mmcs,
     }
 }
@@ -82,6 +172,10 @@ pub const fn create_test_fri_params_zk<Mmcs>(mmcs: Mmcs) -> FriParameters<Mmcs>
         log_final_poly_len: 0,
         num_queries: 2,
         proof_of_work_bits: 1,
+        commit_proof_of_work_bits: 0,
+        batch_proof_of_work_bits: 0,
+        max_log_arity: 1,
+        log_arity_schedule: Vec::new(),
         mmcs,
     }
 }
@@ -94,6 +188,10 @@ pub const fn create_benchmark_fri_params<Mmcs>(mmcs: Mmcs) -> FriParameters<Mmcs
         log_final_poly_len: 0,
         num_queries: 100,
         proof_of_work_bits: 16,
+        commit_proof_of_work_bits: 0,
+        batch_proof_of_work_bits: 0,
+        max_log_arity: 1,
+        log_arity_schedule: Vec::new(),
         mmcs,
     }
 }
@@ -106,6 +204,10 @@ pub fn create_benchmark_fri_params_zk<Mmcs>(mmcs: Mmcs) -> FriParameters<Mmcs> {
         log_final_poly_len: 0,
         num_queries: 100,
         proof_of_work_bits: 16,
+        commit_proof_of_work_bits: 0,
+        batch_proof_of_work_bits: 0,
+        max_log_arity: 1,
+        log_arity_schedule: Vec::new(),
         mmcs,
     }
 }
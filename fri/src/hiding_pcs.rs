@@ -4,7 +4,10 @@ use core::fmt::Debug;
 
 use itertools::Itertools;
 use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
-use p3_commit::{BatchOpening, Mmcs, OpenedValues, Pcs, PolynomialSpace};
+use p3_commit::{
+    Mmcs, MultiBatchOpening, OpenedValues, OpeningProofSize, Pcs, PolynomialSpace, SerializedSize,
+    SizeBreakdown,
+};
 use p3_dft::TwoAdicSubgroupDft;
 use p3_field::coset::TwoAdicMultiplicativeCoset;
 use p3_field::{ExtensionField, Field, TwoAdicField, batch_multiplicative_inverse};
@@ -71,7 +74,7 @@ where
     /// The second item is the usual FRI proof.
     type Proof = (
         OpenedValues<Challenge>,
-        FriProof<Challenge, FriMmcs, Val, Vec<BatchOpening<Val, InputMmcs>>>,
+        FriProof<Challenge, FriMmcs, Val, Vec<MultiBatchOpening<Val, InputMmcs>>>,
     );
     type Error = FriError<FriMmcs::Error, InputMmcs::Error>;
 
@@ -136,6 +139,21 @@ where
         // Given the evaluation vector of `Q_i(x)` over a domain, split it into evaluation vectors
         // of `q_{i0}(x), ...` over subdomains.
         let evaluations = quotient_domain.split_evals(num_chunks, quotient_evaluations);
+        Pcs::<Challenge, Challenger>::commit_quotient_chunks(self, quotient_domain, evaluations)
+    }
+
+    /// Commit to the chunks of the quotient polynomial, randomizing them as in
+    /// [`commit_quotient`](Self::commit_quotient).
+    ///
+    /// # Panics
+    /// This function panics if there are fewer than two chunks.
+    fn commit_quotient_chunks(
+        &self,
+        quotient_domain: Self::Domain,
+        evaluations: Vec<RowMajorMatrix<Val>>,
+    ) -> (Self::Commitment, Self::ProverData) {
+        let num_chunks = evaluations.len();
+        assert!(num_chunks > 1);
         let domains = quotient_domain.split_domains(num_chunks);
 
         let cis = get_zp_cis(&domains);
@@ -277,6 +295,14 @@ where
         (inner_opened_values, (opened_values_rand, inner_proof))
     }
 
+    fn opening_proof_size(proof: &Self::Proof, sizer: &impl SerializedSize) -> OpeningProofSize {
+        // The opened values of the random polynomials are counted as `other`.
+        let (_, fri_proof) = proof;
+        fri_proof
+            .size(sizer)
+            .with_total(sizer.serialized_size(proof))
+    }
+
     fn verify(
         &self,
         // For each round:
@@ -8,10 +8,12 @@ mod config;
 mod hiding_pcs;
 mod proof;
 pub mod prover;
+mod soundness;
 mod two_adic_pcs;
 pub mod verifier;
 
 pub use config::*;
 pub use hiding_pcs::*;
 pub use proof::*;
+pub use soundness::*;
 pub use two_adic_pcs::*;
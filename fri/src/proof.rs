@@ -1,6 +1,6 @@
 use alloc::vec::Vec;
 
-use p3_commit::Mmcs;
+use p3_commit::{Mmcs, OpeningProofSize, SerializedSize, SizeBreakdown};
 use p3_field::Field;
 use serde::{Deserialize, Serialize};
 
@@ -11,30 +11,50 @@ use serde::{Deserialize, Serialize};
 ))]
 pub struct FriProof<F: Field, M: Mmcs<F>, Witness, InputProof> {
     pub commit_phase_commits: Vec<M::Commitment>,
-    pub query_proofs: Vec<QueryProof<F, M, InputProof>>,
+    /// The `log_2` of the arity of each round of the commit phase, see
+    /// [`FriParameters::log_arities`](crate::FriParameters::log_arities).
+    pub log_arities: Vec<usize>,
+    /// The openings of the inputs at all the queried indices.
+    pub input_proof: InputProof,
+    /// For each commit phase commitment, the openings of its codeword at the cosets of all the
+    /// queried indices, with one opening proof for all of them.
+    pub commit_phase_openings: Vec<CommitPhaseOpening<F, M>>,
     pub final_poly: Vec<F>,
     pub pow_witness: Witness,
+    /// The proof of work witness ground before each folding challenge, empty if
+    /// `commit_proof_of_work_bits` is `0`.
+    pub commit_pow_witnesses: Vec<Witness>,
+    /// The proof of work witness ground before the batching challenge, if
+    /// `batch_proof_of_work_bits` is not `0`.
+    pub batch_pow_witness: Option<Witness>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
-#[serde(bound(
-    serialize = "InputProof: Serialize",
-    deserialize = "InputProof: Deserialize<'de>",
-))]
-pub struct QueryProof<F: Field, M: Mmcs<F>, InputProof> {
-    pub input_proof: InputProof,
-    /// For each commit phase commitment, this contains openings of a commit phase codeword at the
-    /// queried location, along with an opening proof.
-    pub commit_phase_openings: Vec<CommitPhaseProofStep<F, M>>,
+impl<F: Field, M: Mmcs<F>, Witness: Serialize, InputProof: Serialize>
+    FriProof<F, M, Witness, InputProof>
+{
+    /// The serialized size of each part of this proof, as measured by `sizer`.
+    pub fn size(&self, sizer: &impl SerializedSize) -> OpeningProofSize {
+        OpeningProofSize {
+            commit_phase_commitments: sizer.serialized_size(&self.commit_phase_commits),
+            query_openings: sizer.serialized_size(&self.input_proof),
+            commit_phase_openings: sizer.serialized_size(&self.commit_phase_openings),
+            final_poly: sizer.serialized_size(&self.final_poly),
+            pow_witness: sizer.serialized_size(&self.pow_witness)
+                + sizer.serialized_size(&self.commit_pow_witnesses)
+                + sizer.serialized_size(&self.batch_pow_witness),
+            ..OpeningProofSize::default()
+        }
+        .with_total(sizer.serialized_size(self))
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(bound = "")]
-pub struct CommitPhaseProofStep<F: Field, M: Mmcs<F>> {
-    /// The opening of the commit phase codeword at the sibling location.
-    // This may change to Vec<FC::Challenge> if the library is generalized to support other FRI
-    // folding arities besides 2, meaning that there can be multiple siblings.
-    pub sibling_value: F,
+pub struct CommitPhaseOpening<F: Field, M: Mmcs<F>> {
+    /// For each query, the openings of the commit phase codeword at the other locations of its
+    /// folded coset, in order, i.e. the opened row without the value at the queried location.
+    pub sibling_values: Vec<Vec<F>>,
 
-    pub opening_proof: M::Proof,
+    /// A single proof of the rows opened by all the queries.
+    pub opening_proof: M::MultiProof,
 }
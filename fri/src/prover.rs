@@ -4,7 +4,7 @@ use core::iter;
 
 use itertools::{Itertools, izip};
 use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
-use p3_commit::{BatchOpening, Mmcs};
+use p3_commit::{Mmcs, MultiBatchOpening};
 use p3_dft::{Radix2DFTSmallBatch, TwoAdicSubgroupDft};
 use p3_field::{ExtensionField, Field, TwoAdicField};
 use p3_matrix::dense::RowMajorMatrix;
@@ -12,8 +12,7 @@ use p3_util::{log2_strict_usize, reverse_slice_index_bits};
 use tracing::{debug_span, info_span, instrument};
 
 use crate::{
-    CommitPhaseProofStep, FriFoldingStrategy, FriParameters, FriProof, ProverDataWithOpeningPoints,
-    QueryProof,
+    CommitPhaseOpening, FriFoldingStrategy, FriParameters, FriProof, ProverDataWithOpeningPoints,
 };
 
 /// Create a proof that an opening `f(zeta)` is correct by proving that the
@@ -39,7 +38,11 @@ use crate::{
 /// - `log_global_max_height`: The log of the maximum height of the input matrices.
 /// - `prover_data_with_opening_points`: A list of pairs of a batch commitment to a collection
 ///   of matrices and a list of points to open those matrices at.
+/// - `input_mmcs`: The MMCS the inputs were committed with.
+/// - `batch_pow_witness`: The proof of work witness the caller ground before sampling the challenge
+///   batching the inputs, if any.
 #[instrument(name = "FRI prover", skip_all)]
+#[allow(clippy::too_many_arguments)]
 pub fn prove_fri<Folding, Val, Challenge, InputMmcs, FriMmcs, Challenger>(
     folding: &Folding,
     params: &FriParameters<FriMmcs>,
@@ -51,6 +54,7 @@ pub fn prove_fri<Folding, Val, Challenge, InputMmcs, FriMmcs, Challenger>(
         InputMmcs::ProverData<RowMajorMatrix<Val>>,
     >],
     input_mmcs: &InputMmcs,
+    batch_pow_witness: Option<Challenger::Witness>,
 ) -> FriProof<Challenge, FriMmcs, Challenger::Witness, Folding::InputProof>
 where
     Val: TwoAdicField,
@@ -58,7 +62,8 @@ where
     InputMmcs: Mmcs<Val>,
     FriMmcs: Mmcs<Challenge>,
     Challenger: FieldChallenger<Val> + GrindingChallenger + CanObserve<FriMmcs::Commitment>,
-    Folding: FriFoldingStrategy<Val, Challenge, InputProof = Vec<BatchOpening<Val, InputMmcs>>>,
+    Folding:
+        FriFoldingStrategy<Val, Challenge, InputProof = Vec<MultiBatchOpening<Val, InputMmcs>>>,
 {
     assert!(!inputs.is_empty());
     assert!(
@@ -76,18 +81,25 @@ where
         assert!(log_min_height > params.log_final_poly_len + params.log_blowup);
     }
 
+    // The arity of each folding round, which the verifier derives in the same way from the heights
+    // of the inputs.
+    let log_arities = params.log_arities(
+        log_max_height,
+        inputs.iter().map(|input| log2_strict_usize(input.len())),
+    );
+
     // Continually fold the inputs down until the polynomial degree reaches final_poly_degree.
     // Returns a vector of commitments to the intermediate stage polynomials, the intermediate stage polynomials
     // themselves and the final polynomial.
     // Note that the challenger observes the commitments and the final polynomial inside this function so we don't
     // need to observe the output of this function here.
-    let commit_phase_result = commit_phase(folding, params, inputs, challenger);
+    let commit_phase_result = commit_phase(folding, params, inputs, &log_arities, challenger);
 
     // Produce a proof of work witness before receiving any query challenges.
     // This helps to prevent grinding attacks.
     let pow_witness = challenger.grind(params.proof_of_work_bits);
 
-    let query_proofs = info_span!("query phase").in_scope(|| {
+    let (input_proof, commit_phase_openings) = info_span!("query phase").in_scope(|| {
         // Sample num_queries indexes to check.
         // The probability that no two FRI indices are equal (ignoring extra query index bits) is:
         // (Grabbed this from wikipedia page on the birthday problem)
@@ -97,40 +109,51 @@ where
         // With num_queries = 100, N = 2^20, this is 0.995 so there is a .5% chance of a collision.
         // Due to this, security conscious users may want to set num_queries a little higher than the
         // theoretical minimum.
-        iter::repeat_with(|| {
-            let index = challenger.sample_bits(log_max_height + folding.extra_query_index_bits());
-            // For each index, create a proof that the folding operations along the chain:
-            // round 0: index, round 1: index >> 1, round 2: index >> 2, ... are correct.
-            QueryProof {
-                input_proof: open_input(
-                    log_global_max_height,
-                    index,
-                    prover_data_with_opening_points,
-                    input_mmcs,
-                ),
-                commit_phase_openings: answer_query(
-                    params,
-                    &commit_phase_result.data,
-                    index >> folding.extra_query_index_bits(),
-                ),
-            }
+        let indices = iter::repeat_with(|| {
+            challenger.sample_bits(log_max_height + folding.extra_query_index_bits())
         })
         .take(params.num_queries)
-        .collect()
+        .collect_vec();
+
+        // Open the inputs at all the indices, and create a proof that the folding operations
+        // along the chain: round 0: index, round 1: index >> log_arity_0, ... are correct for
+        // each of them. Each round is opened at all the indices at once, so that the queries
+        // share the parts of their opening proofs which they have in common.
+        let input_proof = open_input(
+            log_global_max_height,
+            &indices,
+            prover_data_with_opening_points,
+            input_mmcs,
+        );
+        let commit_phase_openings = answer_queries(
+            params,
+            &commit_phase_result.data,
+            &log_arities,
+            indices
+                .iter()
+                .map(|index| index >> folding.extra_query_index_bits())
+                .collect(),
+        );
+        (input_proof, commit_phase_openings)
     });
 
     FriProof {
         commit_phase_commits: commit_phase_result.commits,
-        query_proofs,
+        log_arities,
+        input_proof,
+        commit_phase_openings,
         final_poly: commit_phase_result.final_poly,
         pow_witness,
+        commit_pow_witnesses: commit_phase_result.pow_witnesses,
+        batch_pow_witness,
     }
 }
 
-struct CommitPhaseResult<F: Field, M: Mmcs<F>> {
+struct CommitPhaseResult<F: Field, M: Mmcs<F>, Witness> {
     commits: Vec<M::Commitment>,
     data: Vec<M::ProverData<RowMajorMatrix<F>>>,
     final_poly: Vec<F>,
+    pow_witnesses: Vec<Witness>,
 }
 
 /// Perform the commit phase of the FRI protocol.
@@ -141,6 +164,9 @@ struct CommitPhaseResult<F: Field, M: Mmcs<F>> {
 /// ```
 /// We then commit to the evaluation vector of `f_{i + 1}` over `H^2`.
 ///
+/// A round of arity `2^k` instead reduces our evaluations over `H` to evaluations over `H^{2^k}`,
+/// as `k` rounds of arity 2 would with the challenges `beta_i, beta_i^2, ..., beta_i^{2^{k - 1}}`.
+///
 /// Once the degree of our polynomial falls below `final_poly_degree`, we compute the coefficients of our
 /// polynomial and return them along with all intermediate evaluations and corresponding commitments.
 ///
@@ -150,43 +176,53 @@ struct CommitPhaseResult<F: Field, M: Mmcs<F>> {
 /// - `inputs`: The evaluation vectors of the polynomials. These must be sorted in descending order of length and each
 ///   evaluation vector must be in bit reversed order. This function assumes that commitments to these vectors
 ///   have already been produced and observed by the challenger.
+/// - `log_arities`: The `log_2` of the arity of each round, see [`FriParameters::log_arities`].
 /// - `challenger`: The Fiat-Shamir challenger to use for sampling challenges.
 #[instrument(name = "commit phase", skip_all)]
 fn commit_phase<Folding, Val, Challenge, M, Challenger>(
     folding: &Folding,
     params: &FriParameters<M>,
     inputs: Vec<Vec<Challenge>>,
+    log_arities: &[usize],
     challenger: &mut Challenger,
-) -> CommitPhaseResult<Challenge, M>
+) -> CommitPhaseResult<Challenge, M, Challenger::Witness>
 where
     Val: TwoAdicField,
     Challenge: ExtensionField<Val>,
     M: Mmcs<Challenge>,
-    Challenger: FieldChallenger<Val> + CanObserve<M::Commitment>,
+    Challenger: FieldChallenger<Val> + GrindingChallenger + CanObserve<M::Commitment>,
     Folding: FriFoldingStrategy<Val, Challenge>,
 {
     let mut inputs_iter = inputs.into_iter().peekable();
     let mut folded = inputs_iter.next().unwrap();
     let mut commits = vec![];
     let mut data = vec![];
+    let mut pow_witnesses = vec![];
 
-    while folded.len() > params.blowup() * params.final_poly_len() {
+    for &log_arity in log_arities {
         // As folded is in bit reversed order, it looks like:
         //      `[f_i(h^0), f_i(h^{N/2}), f_i(h^{N/4}), f_i(h^{3N/4}), ...] = [f_i(1), f_i(-1), f_i(h^{N/4}), f_i(-h^{N/4}), ...]`
         // so the relevant evaluations are adjacent and we can just reinterpret the vector as a matrix of width 2.
-        let leaves = RowMajorMatrix::new(folded, 2);
+        // More generally, each run of `2^log_arity` adjacent evaluations is a coset of the subgroup
+        // of order `2^log_arity`, which is what a round of this arity folds together.
+        let leaves = RowMajorMatrix::new(folded, 1 << log_arity);
 
         // Commit to these evaluations and observe the commitment.
         let (commit, prover_data) = params.mmcs.commit_matrix(leaves);
         challenger.observe(commit.clone());
         commits.push(commit);
 
+        // Optionally grind before the challenge, which makes it costlier to search for a lucky one.
+        if params.commit_proof_of_work_bits > 0 {
+            pow_witnesses.push(challenger.grind(params.commit_proof_of_work_bits));
+        }
+
         // Get the Fiat-Shamir challenge for this round.
         let beta: Challenge = challenger.sample_algebra_element();
 
         // We passed ownership of `leaves` to the MMCS, so get a reference to it
         let leaves = params.mmcs.get_matrices(&prover_data).pop().unwrap();
-        // Do the folding operation:
+        // Do the folding operation, which for arity 2 is:
         //      `f_{i + 1}'(x^2) = (f_i(x) + f_i(-x))/2 + beta_i (f_i(x) - f_i(-x))/2x`
         folded = folding.fold_matrix(beta, leaves.as_view());
 
@@ -197,9 +233,12 @@ where
             // Each element of `inputs_iter` is a reduced opening polynomial, which is itself a
             // random linear combination `f_{i, 0} + alpha f_{i, 1} + ...`, when we add it
             // to the current folded polynomial, we need to multiply by a random factor.
-            izip!(&mut folded, v).for_each(|(c, x)| *c += beta.square() * x);
+            // The fold used the powers of `beta` up to `beta^{arity - 1}`, so we use the next one.
+            let roll_in_factor = beta.exp_power_of_2(log_arity);
+            izip!(&mut folded, v).for_each(|(c, x)| *c += roll_in_factor * x);
         }
     }
+    debug_assert_eq!(folded.len(), params.blowup() * params.final_poly_len());
 
     // Now we need to get the coefficients of the final polynomial. As we know that the degree
     // is `<= params.final_poly_len()` and the evaluations are stored in bit-reversed order,
@@ -218,18 +257,21 @@ where
         commits,
         data,
         final_poly,
+        pow_witnesses,
     }
 }
 
-/// Given an `index` produce a proof that the chain of folds at `index, index >> 1, ... ` are correct.
-/// This is the prover's complement to the verifier's [`verify_query`] function.
+/// Given some `indices` produce a proof that the chain of folds at `index, index >> log_arity_0, ... ` are correct
+/// for each `index` among them. This is the prover's complement to the verifier's [`verify_queries`] function.
 ///
 /// In addition to the output of this function, the prover must also supply the verifier with the input values
 /// (with associated opening proofs). These are produced by the `open_input` function passed into `prove_fri`.
 ///
-/// For each `i` in `[0, ..., num_folds)` this returns the value at `(index >> i) ^ 1` in round `i` along with
-/// an opening proof. The verifier can then use the values in round `i` at `index >> i` and `(index >> i) ^ 1`
-/// along with possibly an input value to compute the value at `index >> (i + 1)` in round `i + 1`.
+/// For each round `i` this returns, for each `index`, the values at the siblings of `index_i` in its coset, where
+/// `index_i` is `index` shifted right by the `log_arity` of each earlier round, along with one opening proof for
+/// the cosets of all the indices. For arity 2 the only sibling is `index_i ^ 1`. The verifier can then use the
+/// values in round `i` at `index_i` and its siblings along with possibly an input value to compute the value at
+/// `index_{i + 1}` in round `i + 1`.
 ///
 /// We repeat until we reach the final round where the verifier can check the value against the
 /// polynomial they were sent.
@@ -237,48 +279,58 @@ where
 /// Arguments:
 /// - `params`: The parameters for the specific FRI protocol instance.
 /// - `folded_polynomial_commits`: A slice of commitments to the intermediate stage polynomials.
-/// - `start_index`: The opening index for the unfolded polynomial. For folded polynomials,
-///   we use this index right shifted by the number of folds.
+/// - `log_arities`: The `log_2` of the arity of each round.
+/// - `start_indices`: The opening indices for the unfolded polynomial. For folded polynomials,
+///   we use these indices right shifted by the `log_arity` of each earlier fold.
 #[inline]
-fn answer_query<F, M>(
+fn answer_queries<F, M>(
     config: &FriParameters<M>,
     folded_polynomial_commits: &[M::ProverData<RowMajorMatrix<F>>],
-    start_index: usize,
-) -> Vec<CommitPhaseProofStep<F, M>>
+    log_arities: &[usize],
+    start_indices: Vec<usize>,
+) -> Vec<CommitPhaseOpening<F, M>>
 where
     F: Field,
     M: Mmcs<F>,
 {
-    folded_polynomial_commits
-        .iter()
-        .enumerate()
-        .map(|(i, commit)| {
-            // After i folding rounds, the current index we are looking at is `index >> i`.
-            let index_i = start_index >> i;
-            let index_i_sibling = index_i ^ 1;
-            let index_pair = index_i >> 1;
+    let mut indices_i = start_indices;
+    izip!(folded_polynomial_commits, log_arities)
+        .map(|(commit, &log_arity)| {
+            // The coset of each `index_i` is a single row of the committed matrix.
+            let index_cosets = indices_i.iter().map(|&i| i >> log_arity).collect_vec();
+
+            // Get a proof that the values of all the cosets are correct.
+            let (opened_rows, opening_proof) =
+                config.mmcs.open_multi_batch(&index_cosets, commit).unpack();
 
-            // Get a proof that the pair of indices are correct.
-            let (mut opened_rows, opening_proof) =
-                config.mmcs.open_batch(index_pair, commit).unpack();
+            let sibling_values = izip!(opened_rows, &indices_i)
+                .map(|(mut opened_rows, &index_i)| {
+                    // opened_rows should contain just the values of the coset of index_i.
+                    // We just need to get the siblings.
+                    assert_eq!(opened_rows.len(), 1);
+                    let mut sibling_values = opened_rows.pop().unwrap();
+                    assert_eq!(
+                        sibling_values.len(),
+                        1 << log_arity,
+                        "Committed data should be in cosets of the arity"
+                    );
+                    sibling_values.remove(index_i % (1 << log_arity));
+                    sibling_values
+                })
+                .collect();
 
-            // opened_rows should contain just the value at index_i and its sibling.
-            // We just need to get the sibling.
-            assert_eq!(opened_rows.len(), 1);
-            let opened_row = &opened_rows.pop().unwrap();
-            assert_eq!(opened_row.len(), 2, "Committed data should be in pairs");
-            let sibling_value = opened_row[index_i_sibling % 2];
+            // In the next round, the current indices we are looking at are those of the cosets.
+            indices_i = index_cosets;
 
-            // Add the sibling and the proof to the vector.
-            CommitPhaseProofStep {
-                sibling_value,
+            CommitPhaseOpening {
+                sibling_values,
                 opening_proof,
             }
         })
         .collect()
 }
 
-/// Given an index, produce batch opening proofs for each collection of matrices
+/// Given some indices, produce an opening of all of them for each collection of matrices
 /// combined into a single mmcs commitment.
 ///
 /// In cases where the maximum height of a batch of matrices is smaller than the
@@ -286,20 +338,20 @@ where
 ///
 /// Arguments:
 /// - `log_global_max_height`: The log of the maximum height of the input matrices.
-/// - `index`: The index to open the matrices at.
+/// - `indices`: The indices to open the matrices at.
 /// - `prover_data_with_opening_points`: A list of pairs of a batch commitment to a collection
 ///   of matrices and a list of points to open those matrices at.
 /// - `mmcs`: The mixed matrix commitment scheme used to produce the batch commitments.
 #[inline]
 fn open_input<Val, Challenge, InputMmcs>(
     log_global_max_height: usize,
-    index: usize,
+    indices: &[usize],
     prover_data_with_opening_points: &[ProverDataWithOpeningPoints<
         Challenge,
         InputMmcs::ProverData<RowMajorMatrix<Val>>,
     >],
     mmcs: &InputMmcs,
-) -> Vec<BatchOpening<Val, InputMmcs>>
+) -> Vec<MultiBatchOpening<Val, InputMmcs>>
 where
     Val: TwoAdicField,
     Challenge: ExtensionField<Val>,
@@ -315,8 +367,11 @@ where
             let bits_reduced = log_global_max_height - log_max_height;
             // If a matrix is smaller than global max height, we roll it into
             // fri in a later round.
-            let reduced_index = index >> bits_reduced;
-            mmcs.open_batch(reduced_index, data)
+            let reduced_indices = indices
+                .iter()
+                .map(|index| index >> bits_reduced)
+                .collect_vec();
+            mmcs.open_multi_batch(&reduced_indices, data)
         })
         .collect()
 }
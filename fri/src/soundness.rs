@@ -0,0 +1,271 @@
+//! Estimates of the soundness of a STARK proven with FRI.
+//!
+//! The conjectured soundness follows the [ethSTARK](https://eprint.iacr.org/2021/582) conjecture.
+//! The proven soundness follows the ethSTARK analysis in the Johnson bound regime, based on the
+//! proximity gaps of [BCIKS20](https://eprint.iacr.org/2020/654).
+
+use alloc::vec::Vec;
+
+/// How a prover combines several constraints, or polynomials, into one with random challenges.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BatchingStrategy {
+    /// Combine them with the successive powers of a single challenge, as this crate does.
+    Powers,
+    /// Combine them with an independent challenge for each.
+    Independent,
+}
+
+/// The security model in which to measure soundness.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SoundnessRegime {
+    /// The ethSTARK conjecture, which assumes that FRI is as sound as the unique decoding of its
+    /// queries would suggest up to capacity.
+    Conjectured,
+    /// The bound proven for list decoding up to the Johnson radius.
+    Johnson,
+}
+
+/// The conjectured and proven soundness of a STARK, in bits.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SoundnessBits {
+    pub conjectured: f64,
+    pub johnson: f64,
+}
+
+/// The parameters of a STARK which its soundness depends on.
+///
+/// This assumes that the trace is opened at two points, one row and the next.
+#[derive(Clone, Debug)]
+pub struct SoundnessCalculator {
+    /// The `log_2` of the size of the base field, e.g. `30.9` for BabyBear.
+    pub log_field_size: f64,
+    /// The degree of the extension field the challenges are sampled from.
+    pub extension_degree: usize,
+    /// The `log_2` of the height of the trace, i.e. of the degree bound of its columns.
+    pub log_trace_height: usize,
+    pub log_blowup: usize,
+    /// The `log_2` of the arity of each round of the commit phase, as returned by
+    /// [`FriParameters::log_arities`](crate::FriParameters::log_arities).
+    pub log_arities: Vec<usize>,
+    /// The `log_2` of the length of the final polynomial the commit phase folds down to.
+    pub log_final_poly_len: usize,
+    pub num_queries: usize,
+    pub proof_of_work_bits: usize,
+    /// The proof of work bits ground before each folding challenge.
+    pub commit_proof_of_work_bits: usize,
+    /// The proof of work bits ground before the challenge batching the constraints.
+    pub batch_proof_of_work_bits: usize,
+    /// The number of constraints combined into the quotient polynomial.
+    pub num_constraints: usize,
+    /// How the constraints are combined into the quotient polynomial.
+    pub batching: BatchingStrategy,
+}
+
+impl SoundnessCalculator {
+    /// The largest proximity parameter `m` to try when optimizing the Johnson bound.
+    const MAX_PROXIMITY_PARAMETER: usize = 256;
+
+    /// The `log_2` of the size of the extension field the challenges are sampled from.
+    fn log_extension_field_size(&self) -> f64 {
+        self.log_field_size * self.extension_degree as f64
+    }
+
+    /// The `log_2` of the number of terms of the batching polynomial in the challenge, which
+    /// bounds the chance that a random challenge hides an unsatisfied constraint.
+    fn log_batching_degree(&self) -> f64 {
+        match self.batching {
+            BatchingStrategy::Powers => log2(self.num_constraints.max(1) as f64),
+            BatchingStrategy::Independent => 0.0,
+        }
+    }
+
+    /// Returns the soundness bits under the ethSTARK conjecture.
+    ///
+    /// This is the lesser of the bits of the queries with the proof of work, which is
+    /// `FriParameters::conjectured_soundness_bits`, and the bits of the random challenges, which
+    /// are sampled from an extension field that must be much larger than the evaluation domain,
+    /// with the least proof of work ground before any of them.
+    pub fn conjectured_soundness_bits(&self) -> f64 {
+        let query_bits = (self.log_blowup * self.num_queries + self.proof_of_work_bits) as f64;
+        let log_lde_height = (self.log_trace_height + self.log_blowup) as f64;
+        let challenge_pow_bits = self
+            .commit_proof_of_work_bits
+            .min(self.batch_proof_of_work_bits) as f64;
+        let field_bits =
+            self.log_extension_field_size() - log_lde_height - self.log_batching_degree()
+                + challenge_pow_bits;
+        query_bits.min(field_bits)
+    }
+
+    /// Returns the soundness bits proven for list decoding up to the Johnson radius.
+    ///
+    /// The error of the protocol is the sum of the errors of batching the constraints, of the
+    /// DEEP quotient, of the commit phase of FRI and of its query phase. Each depends on a
+    /// proximity parameter `m >= 3`, for which we take the value giving the best bound.
+    ///
+    /// # Panics
+    /// If `log_arities` do not fold a trace of height `2^log_trace_height` down to the final
+    /// polynomial.
+    pub fn johnson_soundness_bits(&self) -> f64 {
+        assert_eq!(
+            self.log_arities.iter().sum::<usize>(),
+            self.log_trace_height
+                .saturating_sub(self.log_final_poly_len),
+            "the arities must fold the trace down to the final polynomial"
+        );
+        (3..=Self::MAX_PROXIMITY_PARAMETER)
+            .map(|m| self.johnson_soundness_bits_for(m as f64))
+            .fold(f64::NEG_INFINITY, f64::max)
+    }
+
+    fn johnson_soundness_bits_for(&self, m: f64) -> f64 {
+        let log_field = self.log_extension_field_size();
+        let log_rate = -(self.log_blowup as f64);
+        let log_trace_height = self.log_trace_height as f64;
+        let log_lde_height = log_trace_height + self.log_blowup as f64;
+
+        // The size of the list of codewords within the Johnson radius of any word.
+        let log_list_size = log2(m + 0.5) - log_rate / 2.0;
+
+        // Batching the constraints, then opening the trace at two points out of the domain.
+        let log_batching_error = log_list_size + self.log_batching_degree()
+            - log_field
+            - self.batch_proof_of_work_bits as f64;
+        let log_deep_error = log_list_size + 1.0 + log_trace_height - log_field;
+
+        // The commit phase, from the proximity gaps for correlated agreement of the folds. A round
+        // folding by `2^k` combines `2^k` words with the powers of its challenge, which errs like
+        // `2^k - 1` folds by 2.
+        let num_binary_folds: usize = self.log_arities.iter().map(|&k| (1 << k) - 1).sum();
+        let log_commit_field = log_field + self.commit_proof_of_work_bits as f64;
+        let log_proximity_gap_error = 7.0 * log2(m + 0.5) + 2.0 * log_lde_height
+            - log2(3.0)
+            - 1.5 * log_rate
+            - log_commit_field;
+        let log_folding_error = if num_binary_folds == 0 {
+            f64::NEG_INFINITY
+        } else {
+            log2(2.0 * m + 1.0) + log2(exp2(log_lde_height) + 1.0) + log2(num_binary_folds as f64)
+                - log_rate / 2.0
+                - log_commit_field
+        };
+
+        // Each query fails to catch a word beyond the Johnson radius with probability at most
+        // `sqrt(rate) * (1 + 1 / 2m)`.
+        let log_query_error = self.num_queries as f64 * (log_rate / 2.0 + log2(1.0 + 0.5 / m))
+            - self.proof_of_work_bits as f64;
+
+        let error = exp2(log_batching_error)
+            + exp2(log_deep_error)
+            + exp2(log_proximity_gap_error)
+            + exp2(log_folding_error)
+            + exp2(log_query_error);
+        -log2(error)
+    }
+
+    /// Returns both the conjectured and the proven soundness bits.
+    pub fn soundness_bits(&self) -> SoundnessBits {
+        SoundnessBits {
+            conjectured: self.conjectured_soundness_bits(),
+            johnson: self.johnson_soundness_bits(),
+        }
+    }
+
+    /// Returns the smallest number of queries reaching `target_bits` of soundness in `regime`, or
+    /// `None` if no number of queries does, as the field is too small.
+    pub fn min_num_queries(&self, target_bits: f64, regime: SoundnessRegime) -> Option<usize> {
+        let soundness_bits = |num_queries| {
+            let calculator = Self {
+                num_queries,
+                ..self.clone()
+            };
+            match regime {
+                SoundnessRegime::Conjectured => calculator.conjectured_soundness_bits(),
+                SoundnessRegime::Johnson => calculator.johnson_soundness_bits(),
+            }
+        };
+
+        // Each query adds at least `log_blowup / 4` bits in either regime, so this many queries
+        // reach the target unless the other errors prevent it.
+        let max_num_queries = 4 * (target_bits.max(0.0) as usize + 1) / self.log_blowup.max(1) + 4;
+        if soundness_bits(max_num_queries) < target_bits {
+            return None;
+        }
+
+        // The soundness grows with the number of queries, so we search for the smallest number.
+        let (mut lo, mut hi) = (0, max_num_queries);
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            if soundness_bits(mid) >= target_bits {
+                hi = mid;
+            } else {
+                lo = mid + 1;
+            }
+        }
+        Some(lo)
+    }
+}
+
+/// Returns `2^x`.
+///
+/// This is a replacement for `f64::exp2()`, which is not available without `std`.
+fn exp2(x: f64) -> f64 {
+    if x < f64::from(f64::MIN_EXP - 1) {
+        return 0.0;
+    }
+    if x > f64::from(f64::MAX_EXP) {
+        return f64::INFINITY;
+    }
+    // Split `x` into an integer part, applied to the exponent, and a fractional part in `[0, 1)`,
+    // whose power of 2 we compute from its Taylor series.
+    let mut int = x as i32;
+    if f64::from(int) > x {
+        int -= 1;
+    }
+    let y = (x - f64::from(int)) * core::f64::consts::LN_2;
+    let mut term = 1.0;
+    let mut frac = 1.0;
+    for i in 1..30 {
+        term *= y / f64::from(i);
+        frac += term;
+    }
+    let mut result = frac;
+    while int > 0 {
+        let step = int.min(512);
+        result *= f64::from_bits(((1023 + step) as u64) << 52);
+        int -= step;
+    }
+    while int < 0 {
+        let step = int.max(-512);
+        result *= f64::from_bits(((1023 + step) as u64) << 52);
+        int -= step;
+    }
+    result
+}
+
+/// Returns `log_2(x)` for a positive `x`.
+///
+/// This is a replacement for `f64::log2()`, which is not available without `std`.
+fn log2(x: f64) -> f64 {
+    assert!(x > 0.0 && x.is_finite(), "log2 of {x}");
+    // Write `x = 2^e * s` with `s` in `[1, 2)`, using the representation of normal floats.
+    let (x, bias) = if x < f64::MIN_POSITIVE {
+        (x * exp2(64.0), -64.0)
+    } else {
+        (x, 0.0)
+    };
+    let bits = x.to_bits();
+    let e = ((bits >> 52) & 0x7ff) as i64 - 1023;
+    let s = f64::from_bits((bits & ((1 << 52) - 1)) | (1023 << 52));
+
+    // `ln(s) = 2 atanh(z)` with `z = (s - 1) / (s + 1)` in `[0, 1/3)`, whose series converges fast.
+    let z = (s - 1.0) / (s + 1.0);
+    let z2 = z * z;
+    let mut power = z;
+    let mut atanh = 0.0;
+    for i in 0..30 {
+        atanh += power / f64::from(2 * i + 1);
+        power *= z2;
+    }
+    e as f64 + bias + 2.0 * atanh * core::f64::consts::LOG2_E
+}
@@ -20,7 +20,7 @@ use core::marker::PhantomData;
 
 use itertools::{Itertools, izip};
 use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
-use p3_commit::{BatchOpening, Mmcs, OpenedValues, Pcs};
+use p3_commit::{Mmcs, MultiBatchOpening, OpenedValues, OpeningProofSize, Pcs, SerializedSize};
 use p3_dft::TwoAdicSubgroupDft;
 use p3_field::coset::TwoAdicMultiplicativeCoset;
 use p3_field::{
@@ -91,7 +91,7 @@ pub type CommitmentWithOpeningPoints<Challenge, Commitment, Domain> = (
 pub struct TwoAdicFriFolding<InputProof, InputError>(pub PhantomData<(InputProof, InputError)>);
 
 pub type TwoAdicFriFoldingForMmcs<F, M> =
-    TwoAdicFriFolding<Vec<BatchOpening<F, M>>, <M as Mmcs<F>>::Error>;
+    TwoAdicFriFolding<Vec<MultiBatchOpening<F, M>>, <M as Mmcs<F>>::Error>;
 
 impl<F: TwoAdicField, InputProof, InputError: Debug, EF: ExtensionField<F>>
     FriFoldingStrategy<F, EF> for TwoAdicFriFolding<InputProof, InputError>
@@ -110,58 +110,103 @@ impl<F: TwoAdicField, InputProof, InputError: Debug, EF: ExtensionField<F>>
         beta: EF,
         evals: impl Iterator<Item = EF>,
     ) -> EF {
-        let arity = 2;
-        let log_arity = 1;
-        let (e0, e1) = evals
-            .collect_tuple()
-            .expect("TwoAdicFriFolder only supports arity=2");
-        // If performance critical, make this API stateful to avoid this
-        // This is a bit more math than is necessary, but leaving it here
-        // in case we want higher arity in the future.
-        let subgroup_start = F::two_adic_generator(log_height + log_arity)
-            .exp_u64(reverse_bits_len(index, log_height) as u64);
-        let mut xs = F::two_adic_generator(log_arity)
-            .shifted_powers(subgroup_start)
-            .collect_n(arity);
-        reverse_slice_index_bits(&mut xs);
-        assert_eq!(log_arity, 1, "can only interpolate two points for now");
-        // interpolate and evaluate at beta
-        e0 + (beta - xs[0]) * (e1 - e0) * (xs[1] - xs[0]).inverse()
-        // Currently Algebra<F> does not include division so we do it manually.
-        // Note we do not want to do an EF division as that is far more expensive.
+        // A fold of arity `2^k` is `k` folds of arity 2, with the challenges `beta, beta^2, ...`.
+        // The row at `index` holds the evaluations at indices `index * 2^k..(index + 1) * 2^k` of
+        // the bit reversed domain, so after `j` of these folds, its pairs are the rows at indices
+        // `index * 2^{k - j - 1}..(index + 1) * 2^{k - j - 1}` of height `2^{log_height + k - j - 1}`.
+        let mut evals = evals.collect_vec();
+        let log_arity = log2_strict_usize(evals.len());
+        let mut beta = beta;
+        for log_pairs in (0..log_arity).rev() {
+            evals = evals
+                .chunks_exact(2)
+                .enumerate()
+                .map(|(i, pair)| {
+                    fold_pair::<F, EF>((index << log_pairs) + i, log_height + log_pairs, beta, pair)
+                })
+                .collect();
+            beta = beta.square();
+        }
+        evals[0]
     }
 
     fn fold_matrix<M: Matrix<EF>>(&self, beta: EF, m: M) -> Vec<EF> {
-        // We use the fact that
-        //     p_e(x^2) = (p(x) + p(-x)) / 2
-        //     p_o(x^2) = (p(x) - p(-x)) / (2 x)
-        // that is,
-        //     p_e(g^(2i)) = (p(g^i) + p(g^(n/2 + i))) / 2
-        //     p_o(g^(2i)) = (p(g^i) - p(g^(n/2 + i))) / (2 g^i)
-        // so
-        //     result(g^(2i)) = p_e(g^(2i)) + beta p_o(g^(2i))
-        //
-        // As p_e, p_o will be in the extension field we want to find ways to avoid extension multiplications.
-        // We should only need a single one (namely multiplication by beta).
-        let g_inv = F::two_adic_generator(log2_strict_usize(m.height()) + 1).inverse();
-
-        // TODO: vectorize this (after we have packed extension fields)
-
-        // As beta is in the extension field, we want to avoid multiplying by it
-        // for as long as possible. Here we precompute the powers  `g_inv^i / 2` in the base field.
-        let mut halve_inv_powers = g_inv.shifted_powers(F::ONE.halve()).collect_n(m.height());
-        reverse_slice_index_bits(&mut halve_inv_powers);
-
-        m.par_rows()
-            .zip(halve_inv_powers)
-            .map(|(mut row, halve_inv_power)| {
-                let (lo, hi) = row.next_tuple().unwrap();
-                (lo + hi).halve() + (lo - hi) * beta * halve_inv_power
-            })
-            .collect()
+        // A fold of arity `2^k` is `k` folds of arity 2, with the challenges `beta, beta^2, ...`.
+        // As the rows are adjacent runs of the bit reversed evaluations, these folds are over the
+        // consecutive pairs of all evaluations.
+        let log_arity = log2_strict_usize(m.width());
+        if log_arity == 1 {
+            return fold_pairs::<F, EF>(beta, m);
+        }
+        let mut folded = m.to_row_major_matrix().values;
+        let mut beta = beta;
+        for _ in 0..log_arity {
+            folded = fold_pairs::<F, EF>(beta, RowMajorMatrix::new(folded, 2));
+            beta = beta.square();
+        }
+        folded
     }
 }
 
+/// Fold the pair of evaluations `evals` in the row `index` of a matrix of width 2 and height
+/// `2^log_height`, see [`FriFoldingStrategy::fold_row`].
+fn fold_pair<F: TwoAdicField, EF: ExtensionField<F>>(
+    index: usize,
+    log_height: usize,
+    beta: EF,
+    evals: &[EF],
+) -> EF {
+    let arity = 2;
+    let log_arity = 1;
+    let [e0, e1] = evals else {
+        panic!("can only interpolate two points");
+    };
+    // If performance critical, make this API stateful to avoid this
+    // This is a bit more math than is necessary, but it matches the arity 2 folds that
+    // `TwoAdicFriFolding` composes into higher arity ones.
+    let subgroup_start = F::two_adic_generator(log_height + log_arity)
+        .exp_u64(reverse_bits_len(index, log_height) as u64);
+    let mut xs = F::two_adic_generator(log_arity)
+        .shifted_powers(subgroup_start)
+        .collect_n(arity);
+    reverse_slice_index_bits(&mut xs);
+    // interpolate and evaluate at beta
+    *e0 + (beta - xs[0]) * (*e1 - *e0) * (xs[1] - xs[0]).inverse()
+    // Currently Algebra<F> does not include division so we do it manually.
+    // Note we do not want to do an EF division as that is far more expensive.
+}
+
+/// Fold every row of a matrix of width 2, see [`FriFoldingStrategy::fold_matrix`].
+fn fold_pairs<F: TwoAdicField, EF: ExtensionField<F>>(beta: EF, m: impl Matrix<EF>) -> Vec<EF> {
+    // We use the fact that
+    //     p_e(x^2) = (p(x) + p(-x)) / 2
+    //     p_o(x^2) = (p(x) - p(-x)) / (2 x)
+    // that is,
+    //     p_e(g^(2i)) = (p(g^i) + p(g^(n/2 + i))) / 2
+    //     p_o(g^(2i)) = (p(g^i) - p(g^(n/2 + i))) / (2 g^i)
+    // so
+    //     result(g^(2i)) = p_e(g^(2i)) + beta p_o(g^(2i))
+    //
+    // As p_e, p_o will be in the extension field we want to find ways to avoid extension multiplications.
+    // We should only need a single one (namely multiplication by beta).
+    let g_inv = F::two_adic_generator(log2_strict_usize(m.height()) + 1).inverse();
+
+    // TODO: vectorize this (after we have packed extension fields)
+
+    // As beta is in the extension field, we want to avoid multiplying by it
+    // for as long as possible. Here we precompute the powers  `g_inv^i / 2` in the base field.
+    let mut halve_inv_powers = g_inv.shifted_powers(F::ONE.halve()).collect_n(m.height());
+    reverse_slice_index_bits(&mut halve_inv_powers);
+
+    m.par_rows()
+        .zip(halve_inv_powers)
+        .map(|(mut row, halve_inv_power)| {
+            let (lo, hi) = row.next_tuple().unwrap();
+            (lo + hi).halve() + (lo - hi) * beta * halve_inv_power
+        })
+        .collect()
+}
+
 impl<Val, Dft, InputMmcs, FriMmcs, Challenge, Challenger> Pcs<Challenge, Challenger>
     for TwoAdicFriPcs<Val, Dft, InputMmcs, FriMmcs>
 where
@@ -177,7 +222,7 @@ where
     type Commitment = InputMmcs::Commitment;
     type ProverData = InputMmcs::ProverData<RowMajorMatrix<Val>>;
     type EvaluationsOnDomain<'a> = BitReversedMatrixView<RowMajorMatrixView<'a, Val>>;
-    type Proof = FriProof<Challenge, FriMmcs, Val, Vec<BatchOpening<Val, InputMmcs>>>;
+    type Proof = FriProof<Challenge, FriMmcs, Val, Vec<MultiBatchOpening<Val, InputMmcs>>>;
     type Error = FriError<FriMmcs::Error, InputMmcs::Error>;
     const ZK: bool = false;
 
@@ -409,6 +454,9 @@ where
         // points it needs to be opened at. This comes from the fact that we are takeing a large linear combination
         // of `(f(zeta) - f(x))/(zeta - x)` for each function `f` and all of `f`'s opening points.
         // In our setup, k is two times the trace width plus the number of quotient polynomials.
+        // The prover can optionally grind before sampling it, to make a lucky alpha costlier to find.
+        let batch_pow_witness = (self.fri.batch_proof_of_work_bits > 0)
+            .then(|| challenger.grind(self.fri.batch_proof_of_work_bits));
         let alpha: Challenge = challenger.sample_algebra_element();
 
         // We precompute powers of alpha as we need the same powers for each matrix.
@@ -516,6 +564,7 @@ where
             log_global_max_height,
             &commitment_data_with_opening_points,
             &self.mmcs,
+            batch_pow_witness,
         );
 
         (all_opened_values, fri_proof)
@@ -542,6 +591,14 @@ where
             }
         }
 
+        // Check the proof of work witness ground before sampling the batch combination challenge,
+        // which `verify_fri` samples first.
+        verifier::check_optional_witness(
+            challenger,
+            self.fri.batch_proof_of_work_bits,
+            proof.batch_pow_witness,
+        )?;
+
         let folding: TwoAdicFriFoldingForMmcs<Val, InputMmcs> = TwoAdicFriFolding(PhantomData);
 
         verifier::verify_fri(
@@ -555,6 +612,10 @@ where
 
         Ok(())
     }
+
+    fn opening_proof_size(proof: &Self::Proof, sizer: &impl SerializedSize) -> OpeningProofSize {
+        proof.size(sizer)
+    }
 }
 
 /// Compute vectors of inverse denominators for each unique opening point.
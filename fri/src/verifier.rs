@@ -2,9 +2,9 @@ use alloc::collections::btree_map::BTreeMap;
 use alloc::vec;
 use alloc::vec::Vec;
 
-use itertools::Itertools;
+use itertools::{Itertools, izip};
 use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
-use p3_commit::{BatchOpening, BatchOpeningRef, Mmcs};
+use p3_commit::{Mmcs, MultiBatchOpening, MultiBatchOpeningRef};
 use p3_field::coset::TwoAdicMultiplicativeCoset;
 use p3_field::{ExtensionField, Field, TwoAdicField};
 use p3_matrix::Dimensions;
@@ -12,8 +12,7 @@ use p3_util::zip_eq::zip_eq;
 use p3_util::{log2_strict_usize, reverse_bits_len};
 
 use crate::{
-    CommitPhaseProofStep, CommitmentWithOpeningPoints, FriFoldingStrategy, FriParameters, FriProof,
-    QueryProof,
+    CommitPhaseOpening, CommitmentWithOpeningPoints, FriFoldingStrategy, FriParameters, FriProof,
 };
 
 #[derive(Debug)]
@@ -24,6 +23,28 @@ pub enum FriError<CommitMmcsErr, InputError> {
     FinalPolyMismatch,
     InvalidPowWitness,
     MissingInput,
+    /// The parameters fold by an arity the FRI implementation does not support.
+    UnsupportedArity,
+}
+
+/// Checks the proof of work witness ground before sampling a challenge, which must be present
+/// exactly when `bits` is not `0`.
+pub fn check_optional_witness<Challenger: GrindingChallenger, CommitMmcsErr, InputError>(
+    challenger: &mut Challenger,
+    bits: usize,
+    witness: Option<Challenger::Witness>,
+) -> Result<(), FriError<CommitMmcsErr, InputError>> {
+    match witness {
+        None if bits == 0 => Ok(()),
+        Some(witness) if bits > 0 => {
+            if challenger.check_witness(bits, witness) {
+                Ok(())
+            } else {
+                Err(FriError::InvalidPowWitness)
+            }
+        }
+        _ => Err(FriError::InvalidProofShape),
+    }
 }
 
 /// A chain of FRI input openings allowing a verifier to check a sequence of
@@ -62,7 +83,7 @@ where
             Val,
             Challenge,
             InputError = InputMmcs::Error,
-            InputProof = Vec<BatchOpening<Val, InputMmcs>>,
+            InputProof = Vec<MultiBatchOpening<Val, InputMmcs>>,
         >,
 {
     // Generate the Batch combination challenge
@@ -70,13 +91,39 @@ where
     // `(f(zeta) - fi(x))/(zeta - x)` which need to be checked.
     // Explicitly, `|f|` is `commitments_with_opening_points.flatten().flatten().len()`
     // (i.e counting the number (point, claimed_evaluation) pairs).
+    // The caller checks the proof of work witness ground before sampling it, as it is the caller
+    // which grinds for it when proving.
     let alpha: Challenge = challenger.sample_algebra_element();
 
-    // `commit_phase_commits.len()` is the number of folding steps, so the maximum polynomial degree will be
-    // `commit_phase_commits.len() + self.fri.log_final_poly_len` and so, as the same blow-up is used for all
-    // polynomials, the maximum matrix height is:
-    let log_global_max_height =
-        proof.commit_phase_commits.len() + params.log_blowup + params.log_final_poly_len;
+    // The heights of the input matrices, as the same blow-up is used for all polynomials.
+    let log_input_heights = commitments_with_opening_points
+        .iter()
+        .flat_map(|(_, mats)| mats)
+        .map(|(domain, _)| log2_strict_usize(domain.size()) + params.log_blowup)
+        .collect_vec();
+    let log_global_max_height = log_input_heights
+        .iter()
+        .copied()
+        .max()
+        .ok_or(FriError::InvalidProofShape)?;
+
+    // The arity of each folding round, which the prover derives in the same way, so we know
+    // how many rounds the commit phase must have and what the proof must record.
+    let log_arities = params.log_arities(log_global_max_height, log_input_heights);
+    if proof.log_arities != log_arities || proof.commit_phase_commits.len() != log_arities.len() {
+        return Err(FriError::InvalidProofShape);
+    }
+
+    // There is a proof of work witness for each round, unless the prover does not grind.
+    let num_commit_pow_witnesses = if params.commit_proof_of_work_bits > 0 {
+        log_arities.len()
+    } else {
+        0
+    };
+    if proof.commit_pow_witnesses.len() != num_commit_pow_witnesses {
+        return Err(FriError::InvalidProofShape);
+    }
+    let mut commit_pow_witnesses = proof.commit_pow_witnesses.iter().copied();
 
     // Generate all of the random challenges for the FRI rounds.
     let betas: Vec<Challenge> = proof
@@ -86,9 +133,14 @@ where
             // To match with the prover (and for security purposes),
             // we observe the commitment before sampling the challenge.
             challenger.observe(comm.clone());
-            challenger.sample_algebra_element()
+            check_optional_witness(
+                challenger,
+                params.commit_proof_of_work_bits,
+                commit_pow_witnesses.next(),
+            )?;
+            Ok(challenger.sample_algebra_element())
         })
-        .collect();
+        .collect::<Result<_, _>>()?;
 
     // Ensure that the final polynomial has the expected degree.
     if proof.final_poly.len() != params.final_poly_len() {
@@ -101,77 +153,67 @@ where
         .iter()
         .for_each(|x| challenger.observe_algebra_element(*x));
 
-    // Ensure that we have the expected number of FRI query proofs.
-    if proof.query_proofs.len() != params.num_queries {
-        return Err(FriError::InvalidProofShape);
-    }
-
     // Check PoW.
     if !challenger.check_witness(params.proof_of_work_bits, proof.pow_witness) {
         return Err(FriError::InvalidPowWitness);
     }
 
-    // The log of the maximum domain size.
-    let log_max_height =
-        proof.commit_phase_commits.len() + params.log_blowup + params.log_final_poly_len;
+    // We start by generating the random index of each query.
+    let indices = (0..params.num_queries)
+        .map(|_| challenger.sample_bits(log_global_max_height + folding.extra_query_index_bits()))
+        .collect_vec();
+
+    // Next we open all polynomials `f` at the relevant indices and combine them into our FRI inputs.
+    let ros = open_input(
+        params,
+        log_global_max_height,
+        &indices,
+        &proof.input_proof,
+        alpha,
+        input_mmcs,
+        commitments_with_opening_points,
+    )?;
 
-    // The log of the final domain size.
-    let log_final_height = params.log_blowup + params.log_final_poly_len;
+    debug_assert!(
+        ros.iter()
+            .all(|ro| ro.iter().tuple_windows().all(|((l, _), (r, _))| l > r)),
+        "reduced openings sorted by height descending"
+    );
 
-    for QueryProof {
-        input_proof,
-        commit_phase_openings,
-    } in &proof.query_proofs
-    {
-        // For each query proof, we start by generating the random index.
-        let index = challenger.sample_bits(log_max_height + folding.extra_query_index_bits());
-
-        // Next we open all polynomials `f` at the relevant index and combine them into our FRI inputs.
-        let ro = open_input(
-            params,
-            log_global_max_height,
-            index,
-            input_proof,
-            alpha,
-            input_mmcs,
-            commitments_with_opening_points,
-        )?;
-
-        debug_assert!(
-            ro.iter().tuple_windows().all(|((l, _), (r, _))| l > r),
-            "reduced openings sorted by height descending"
-        );
-
-        // If we queried extra bits, shift them off now.
-        let mut domain_index = index >> folding.extra_query_index_bits();
-
-        // Starting at the evaluation at `index` of the initial domain,
-        // perform FRI folds until the domain size reaches the final domain size.
-        // Check after each fold that the pair of sibling evaluations at the current
-        // node match the commitment.
-        let folded_eval = verify_query(
-            folding,
-            params,
-            &mut domain_index,
+    // If we queried extra bits, shift them off now.
+    let mut domain_indices = indices
+        .iter()
+        .map(|index| index >> folding.extra_query_index_bits())
+        .collect_vec();
+
+    // Starting at the evaluation at each index of the initial domain,
+    // perform FRI folds until the domain size reaches the final domain size.
+    // Check after each fold that the cosets of sibling evaluations at the current
+    // nodes match the commitment.
+    let folded_evals = verify_queries(
+        folding,
+        params,
+        &mut domain_indices,
+        zip_eq(
             zip_eq(
-                zip_eq(
-                    &betas,
-                    &proof.commit_phase_commits,
-                    FriError::InvalidProofShape,
-                )?,
-                commit_phase_openings,
+                &betas,
+                &proof.commit_phase_commits,
                 FriError::InvalidProofShape,
             )?,
-            ro,
-            log_max_height,
-            log_final_height,
-        )?;
+            &proof.commit_phase_openings,
+            FriError::InvalidProofShape,
+        )?,
+        ros,
+        log_global_max_height,
+        &log_arities,
+    )?;
 
+    for (domain_index, folded_eval) in domain_indices.into_iter().zip(folded_evals) {
         // We open the final polynomial at index `domain_index`, which corresponds to evaluating
         // the polynomial at x^k, where x is the 2-adic generator of order `max_height` and k is
-        // `reverse_bits_len(domain_index, log_max_height)`.
-        let x = Val::two_adic_generator(log_max_height)
-            .exp_u64(reverse_bits_len(domain_index, log_max_height) as u64);
+        // `reverse_bits_len(domain_index, log_global_max_height)`.
+        let x = Val::two_adic_generator(log_global_max_height)
+            .exp_u64(reverse_bits_len(domain_index, log_global_max_height) as u64);
 
         // Assuming all the checks passed, the final check is to ensure that the folded evaluation
         // matches the evaluation of the final polynomial sent by the prover.
@@ -195,123 +237,154 @@ type CommitStep<'a, F, M> = (
         &'a F, // The challenge point beta used for the next fold of FRI evaluations.
         &'a <M as Mmcs<F>>::Commitment, // A commitment to the FRI evaluations on the current domain.
     ),
-    &'a CommitPhaseProofStep<F, M>, // The sibling and opening proof for the current FRI node.
+    &'a CommitPhaseOpening<F, M>, // The siblings and opening proof for the current FRI nodes.
 );
 
-/// Verifies a single query chain in the FRI proof. This is the verifier complement
-/// to the prover's [`answer_query`] function.
+/// Verifies the query chains in the FRI proof. This is the verifier complement
+/// to the prover's [`answer_queries`] function.
 ///
-/// Given an initial `index` corresponding to a point in the initial domain
-/// and a series of `reduced_openings` corresponding to evaluations of
+/// Given initial indices corresponding to points in the initial domain
+/// and for each a series of `reduced_openings` corresponding to evaluations of
 /// polynomials to be added in at specific domain sizes, perform the standard
-/// sequence of FRI folds, checking at each step that the pair of sibling evaluations
-/// matches the commitment.
+/// sequence of FRI folds, checking at each step that the cosets of sibling evaluations
+/// match the commitment.
 ///
 /// Arguments:
 /// - `folding`: The FRI folding scheme used by the prover.
 /// - `params`: The parameters for the specific FRI protocol instance.
-/// - `start_index`: The opening index for the unfolded polynomial. For folded polynomials
-///   we use this this index right shifted by the number of folds.
+/// - `start_indices`: The opening indices for the unfolded polynomial. For folded polynomials
+///   we use these indices right shifted by the `log_arity` of each earlier fold.
 /// - `fold_data_iter`: An iterator containing, for each fold, the beta challenge, polynomial commitment
-///   and commitment opening at the appropriate index.
-/// - `reduced_openings`: A vector of pairs of a size and an opening. The opening is a linear combination
-///   of all input polynomials of that size opened at the appropriate index. Each opening is added into the
-///   the FRI folding chain once the domain size reaches the size specified in the pair.
+///   and commitment opening at the appropriate indices.
+/// - `reduced_openings`: For each index, a vector of pairs of a size and an opening. The opening is a linear
+///   combination of all input polynomials of that size opened at the appropriate index. Each opening is added
+///   into the the FRI folding chain once the domain size reaches the size specified in the pair.
 /// - `log_max_height`: The log of the maximum domain size.
-/// - `log_final_height`: The log of the final domain size.
+/// - `log_arities`: The `log_2` of the arity of each fold.
 #[inline]
-fn verify_query<'a, Folding, F, EF, M>(
+fn verify_queries<'a, Folding, F, EF, M>(
     folding: &Folding,
     params: &FriParameters<M>,
-    start_index: &mut usize,
+    start_indices: &mut [usize],
     fold_data_iter: impl ExactSizeIterator<Item = CommitStep<'a, EF, M>>,
-    reduced_openings: FriOpenings<EF>,
+    reduced_openings: Vec<FriOpenings<EF>>,
     log_max_height: usize,
-    log_final_height: usize,
-) -> Result<EF, FriError<M::Error, Folding::InputError>>
+    log_arities: &[usize],
+) -> Result<Vec<EF>, FriError<M::Error, Folding::InputError>>
 where
     F: Field,
     EF: ExtensionField<F>,
     M: Mmcs<EF> + 'a,
     Folding: FriFoldingStrategy<F, EF>,
 {
-    let mut ro_iter = reduced_openings.into_iter().peekable();
+    let mut ro_iters = reduced_openings
+        .into_iter()
+        .map(|ro| ro.into_iter().peekable())
+        .collect_vec();
 
     // These checks are not essential to security,
     // but they should be satisfied by any non malicious prover.
     // ro_iter being empty means that we have committed to no polynomials at all and
     // we need to roll in a polynomial initially otherwise we are just folding a zero polynomial.
-    if ro_iter.peek().is_none() || ro_iter.peek().unwrap().0 != log_max_height {
-        return Err(FriError::InvalidProofShape);
-    }
-    let mut folded_eval = ro_iter.next().unwrap().1;
+    let mut folded_evals = ro_iters
+        .iter_mut()
+        .map(|ro_iter| {
+            ro_iter
+                .next_if(|(lh, _)| *lh == log_max_height)
+                .map(|(_, ro)| ro)
+                .ok_or(FriError::InvalidProofShape)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
 
     // We start with evaluations over a domain of size (1 << log_max_height). We fold
-    // using FRI until the domain size reaches (1 << log_final_height).
-    for (log_folded_height, ((&beta, comm), opening)) in zip_eq(
+    // using FRI until the domain size reaches the final domain size.
+    let mut log_folded_height = log_max_height;
+    for (&log_arity, ((&beta, comm), opening)) in zip_eq(
         // zip_eq ensures that we have the right number of steps.
-        (log_final_height..log_max_height).rev(),
+        log_arities,
         fold_data_iter,
         FriError::InvalidProofShape,
     )? {
-        // Get the index of the other sibling of the current FRI node.
-        let index_sibling = *start_index ^ 1;
-
-        let mut evals = vec![folded_eval; 2];
-        evals[index_sibling % 2] = opening.sibling_value;
+        let arity = 1 << log_arity;
+        log_folded_height -= log_arity;
+
+        // Insert the evaluation at each queried FRI node among its siblings to get its coset,
+        // the single row opened from the commitment.
+        let cosets = zip_eq(
+            &opening.sibling_values,
+            izip!(&folded_evals, &*start_indices),
+            FriError::InvalidProofShape,
+        )?
+        .map(|(sibling_values, (&folded_eval, &index))| {
+            if sibling_values.len() != arity - 1 {
+                return Err(FriError::InvalidProofShape);
+            }
+            let mut evals = sibling_values.clone();
+            evals.insert(index % arity, folded_eval);
+            Ok(vec![evals])
+        })
+        .collect::<Result<Vec<_>, _>>()?;
 
         let dims = &[Dimensions {
-            width: 2,
+            width: arity,
             height: 1 << log_folded_height,
         }];
 
-        // Replace index with the index of the parent FRI node.
-        *start_index >>= 1;
+        // Replace each index with the index of its parent FRI node.
+        start_indices
+            .iter_mut()
+            .for_each(|index| *index >>= log_arity);
 
-        // Verify the commitment to the evaluations of the sibling nodes.
+        // Verify the commitment to the evaluations of the sibling nodes of all the queries.
         params
             .mmcs
-            .verify_batch(
+            .verify_multi_batch(
                 comm,
                 dims,
-                *start_index,
-                BatchOpeningRef::new(&[evals.clone()], &opening.opening_proof), // It's possible to remove the clone here but unnecessary as evals is tiny.
+                start_indices,
+                MultiBatchOpeningRef::new(&cosets, &opening.opening_proof),
             )
             .map_err(FriError::CommitPhaseMmcsError)?;
 
-        // Fold the pair of sibling nodes to get the evaluation of the parent FRI node.
-        folded_eval = folding.fold_row(*start_index, log_folded_height, beta, evals.into_iter());
-
-        // If there are new polynomials to roll in at the folded height, do so.
-        //
-        // Each element of `ro_iter` is the evaluation of a reduced opening polynomial, which is itself
-        // a random linear combination `f_{i, 0}(x) + alpha f_{i, 1}(x) + ...`, but when we add it
-        // to the current folded polynomial evaluation claim, we need to multiply by a new random factor
-        // since `f_{i, 0}` has no leading coefficient.
-        //
-        // We use `beta^2` as the random factor since `beta` is already used in the folding.
-        // This increases the query phase error probability by a negligible amount, and does not change
-        // the required number of FRI queries.
-        if let Some((_, ro)) = ro_iter.next_if(|(lh, _)| *lh == log_folded_height) {
-            folded_eval += beta.square() * ro;
+        for (folded_eval, &index, coset, ro_iter) in
+            izip!(&mut folded_evals, &*start_indices, cosets, &mut ro_iters)
+        {
+            // Fold the coset of sibling nodes to get the evaluation of the parent FRI node.
+            *folded_eval =
+                folding.fold_row(index, log_folded_height, beta, coset.into_iter().flatten());
+
+            // If there are new polynomials to roll in at the folded height, do so.
+            //
+            // Each element of `ro_iter` is the evaluation of a reduced opening polynomial, which is itself
+            // a random linear combination `f_{i, 0}(x) + alpha f_{i, 1}(x) + ...`, but when we add it
+            // to the current folded polynomial evaluation claim, we need to multiply by a new random factor
+            // since `f_{i, 0}` has no leading coefficient.
+            //
+            // We use `beta^arity` as the random factor since the powers of `beta` up to `beta^{arity - 1}`
+            // are already used in the folding.
+            // This increases the query phase error probability by a negligible amount, and does not change
+            // the required number of FRI queries.
+            if let Some((_, ro)) = ro_iter.next_if(|(lh, _)| *lh == log_folded_height) {
+                *folded_eval += beta.exp_power_of_2(log_arity) * ro;
+            }
         }
     }
 
-    // If ro_iter is not empty, we failed to fold in some polynomial evaluations.
-    if ro_iter.next().is_some() {
+    // If an ro_iter is not empty, we failed to fold in some polynomial evaluations.
+    if ro_iters.iter_mut().any(|ro_iter| ro_iter.next().is_some()) {
         return Err(FriError::InvalidProofShape);
     }
 
-    // If we reached this point, we have verified that, starting at the initial index,
-    // the chain of folds has produced folded_eval.
-    Ok(folded_eval)
+    // If we reached this point, we have verified that, starting at the initial indices,
+    // the chains of folds have produced folded_evals.
+    Ok(folded_evals)
 }
 
-/// Given an index and a collection of opening proofs, check all opening proofs and combine
-/// the opened values into the FRI inputs along the path specified by the index.
+/// Given some indices and a collection of opening proofs, check all opening proofs and combine
+/// the opened values into the FRI inputs along the path specified by each index.
 ///
 /// In cases where the maximum height of a batch of matrices is smaller than the
-/// global max height, shift the index down to compensate.
+/// global max height, shift the indices down to compensate.
 ///
 /// We combine the functions by mapping each function and opening point pair to `(f(z) - f(x))/(z - x)`
 /// and then combining functions of the same degree using the challenge alpha.
@@ -319,19 +392,20 @@ where
 /// ## Arguments:
 /// - `params`: The FRI parameters.
 /// - `log_global_max_height`: The log of the maximum height of the input matrices.
-/// - `index`: The index at which to open the functions.
-/// - `input_proof`: A vector of batch openings with each opening containing a
-///   list of opened values for a collection of matrices along with a batched opening proof.
+/// - `indices`: The indices at which to open the functions.
+/// - `input_proof`: A vector of openings with each opening containing, for each index, a
+///   list of opened values for a collection of matrices, along with one opening proof.
 /// - `alpha`: The challenge used to combine the functions.
 /// - `input_mmcs`: The input multi-matrix commitment scheme.
 /// - `commitments_with_opening_points`: A vector of joint commitments to collections of matrices
 ///   and openings of those matrices at a collection of points.
 #[inline]
+#[allow(clippy::type_complexity)]
 fn open_input<Val, Challenge, InputMmcs, FriMmcs>(
     params: &FriParameters<FriMmcs>,
     log_global_max_height: usize,
-    index: usize,
-    input_proof: &[BatchOpening<Val, InputMmcs>],
+    indices: &[usize],
+    input_proof: &[MultiBatchOpening<Val, InputMmcs>],
     alpha: Challenge,
     input_mmcs: &InputMmcs,
     commitments_with_opening_points: &[CommitmentWithOpeningPoints<
@@ -339,16 +413,17 @@ fn open_input<Val, Challenge, InputMmcs, FriMmcs>(
         InputMmcs::Commitment,
         TwoAdicMultiplicativeCoset<Val>,
     >],
-) -> Result<FriOpenings<Challenge>, FriError<FriMmcs::Error, InputMmcs::Error>>
+) -> Result<Vec<FriOpenings<Challenge>>, FriError<FriMmcs::Error, InputMmcs::Error>>
 where
     Val: TwoAdicField,
     Challenge: ExtensionField<Val>,
     InputMmcs: Mmcs<Val>,
     FriMmcs: Mmcs<Challenge>,
 {
-    // For each log_height, we store the alpha power and compute the reduced opening.
+    // For each index and log_height, we store the alpha power and compute the reduced opening.
     // log_height -> (alpha_pow, reduced_opening)
-    let mut reduced_openings = BTreeMap::<usize, (Challenge, Challenge)>::new();
+    let mut reduced_openings =
+        vec![BTreeMap::<usize, (Challenge, Challenge)>::new(); indices.len()];
 
     // For each batch commitment and opening proof
     for (batch_opening, (batch_commit, mats)) in zip_eq(
@@ -370,73 +445,91 @@ where
             .collect_vec();
 
         // If the maximum height of the batch is smaller than the global max height,
-        // we need to correct the index by right shifting it.
-        // If the batch is empty, we set the index to 0.
-        let reduced_index = batch_heights
+        // we need to correct the indices by right shifting them.
+        // If the batch is empty, we set the indices to 0.
+        let reduced_indices = indices
             .iter()
-            .max()
-            .map(|&h| index >> (log_global_max_height - log2_strict_usize(h)))
-            .unwrap_or(0);
+            .map(|&index| {
+                batch_heights
+                    .iter()
+                    .max()
+                    .map(|&h| index >> (log_global_max_height - log2_strict_usize(h)))
+                    .unwrap_or(0)
+            })
+            .collect_vec();
 
         input_mmcs
-            .verify_batch(
+            .verify_multi_batch(
                 batch_commit,
                 &batch_dims,
-                reduced_index,
+                &reduced_indices,
                 batch_opening.into(),
             )
             .map_err(FriError::InputError)?;
 
-        // For each matrix in the commitment
-        for (mat_opening, (mat_domain, mat_points_and_values)) in zip_eq(
-            &batch_opening.opened_values,
-            mats,
-            FriError::InvalidProofShape,
-        )? {
-            let log_height = log2_strict_usize(mat_domain.size()) + params.log_blowup;
-
-            let bits_reduced = log_global_max_height - log_height;
-            let rev_reduced_index = reverse_bits_len(index >> bits_reduced, log_height);
-
-            // TODO: this can be nicer with domain methods?
-
-            // Compute gh^i
-            let x = Val::GENERATOR
-                * Val::two_adic_generator(log_height).exp_u64(rev_reduced_index as u64);
-
-            let (alpha_pow, ro) = reduced_openings
-                .entry(log_height) // Get a mutable reference to the entry.
-                .or_insert((Challenge::ONE, Challenge::ZERO));
-
-            // For each polynomial `f` in our matrix, compute `(f(z) - f(x))/(z - x)`,
-            // scale by the appropriate alpha power and add to the reduced opening for this log_height.
-            for (z, ps_at_z) in mat_points_and_values {
-                let quotient = (*z - x).inverse();
-                for (&p_at_x, &p_at_z) in zip_eq(mat_opening, ps_at_z, FriError::InvalidProofShape)?
-                {
-                    // Note we just checked batch proofs to ensure p_at_x is correct.
-                    // x, z were sent by the verifier.
-                    // ps_at_z was sent to the verifier and we are using fri to prove it is correct.
-                    *ro += *alpha_pow * (p_at_z - p_at_x) * quotient;
-                    *alpha_pow *= alpha;
-                }
-            }
+        if batch_opening.opened_values.len() != indices.len() {
+            return Err(FriError::InvalidProofShape);
         }
 
-        // `reduced_openings` would have a log_height = log_blowup entry only if there was a
-        // trace matrix of height 1. In this case `f` is constant, so `f(zeta) - f(x))/(zeta - x)`
-        // must equal `0`.
-        if let Some((_, ro)) = reduced_openings.get(&params.log_blowup)
-            && !ro.is_zero()
+        // For each index
+        for (&index, opened_values, reduced_openings) in
+            izip!(indices, &batch_opening.opened_values, &mut reduced_openings)
         {
-            return Err(FriError::FinalPolyMismatch);
+            // For each matrix in the commitment
+            for (mat_opening, (mat_domain, mat_points_and_values)) in
+                zip_eq(opened_values, mats, FriError::InvalidProofShape)?
+            {
+                let log_height = log2_strict_usize(mat_domain.size()) + params.log_blowup;
+
+                let bits_reduced = log_global_max_height - log_height;
+                let rev_reduced_index = reverse_bits_len(index >> bits_reduced, log_height);
+
+                // TODO: this can be nicer with domain methods?
+
+                // Compute gh^i
+                let x = Val::GENERATOR
+                    * Val::two_adic_generator(log_height).exp_u64(rev_reduced_index as u64);
+
+                let (alpha_pow, ro) = reduced_openings
+                    .entry(log_height) // Get a mutable reference to the entry.
+                    .or_insert((Challenge::ONE, Challenge::ZERO));
+
+                // For each polynomial `f` in our matrix, compute `(f(z) - f(x))/(z - x)`,
+                // scale by the appropriate alpha power and add to the reduced opening for this log_height.
+                for (z, ps_at_z) in mat_points_and_values {
+                    let quotient = (*z - x).inverse();
+                    for (&p_at_x, &p_at_z) in
+                        zip_eq(mat_opening, ps_at_z, FriError::InvalidProofShape)?
+                    {
+                        // Note we just checked batch proofs to ensure p_at_x is correct.
+                        // x, z were sent by the verifier.
+                        // ps_at_z was sent to the verifier and we are using fri to prove it is correct.
+                        *ro += *alpha_pow * (p_at_z - p_at_x) * quotient;
+                        *alpha_pow *= alpha;
+                    }
+                }
+            }
+
+            // `reduced_openings` would have a log_height = log_blowup entry only if there was a
+            // trace matrix of height 1. In this case `f` is constant, so `f(zeta) - f(x))/(zeta - x)`
+            // must equal `0`.
+            if let Some((_, ro)) = reduced_openings.get(&params.log_blowup)
+                && !ro.is_zero()
+            {
+                return Err(FriError::FinalPolyMismatch);
+            }
         }
     }
 
     // Return reduced openings descending by log_height.
     Ok(reduced_openings
         .into_iter()
-        .rev()
-        .map(|(log_height, (_, ro))| (log_height, ro))
+        .map(|reduced_openings| {
+            reduced_openings
+                .into_iter()
+                .rev()
+                .map(|(log_height, (_, ro))| (log_height, ro))
+                .collect()
+        })
         .collect())
 }
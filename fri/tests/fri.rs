@@ -25,7 +25,12 @@ type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
 type MyPcs = TwoAdicFriPcs<BabyBear, Radix2Dit<BabyBear>, ValMmcs, ChallengeMmcs>;
 
 /// Returns a permutation and a FRI-pcs instance.
-fn get_ldt_for_testing<R: Rng>(rng: &mut R, log_final_poly_len: usize) -> (Perm, MyPcs) {
+fn get_ldt_for_testing<R: Rng>(
+    rng: &mut R,
+    log_final_poly_len: usize,
+    max_log_arity: usize,
+    log_arity_schedule: &[usize],
+) -> (Perm, MyPcs) {
     let perm = Perm::new_from_rng_128(rng);
     let hash = MyHash::new(perm.clone());
     let compress = MyCompress::new(perm.clone());
@@ -36,6 +41,10 @@ fn get_ldt_for_testing<R: Rng>(rng: &mut R, log_final_poly_len: usize) -> (Perm,
         log_final_poly_len,
         num_queries: 10,
         proof_of_work_bits: 8,
+        commit_proof_of_work_bits: 0,
+        batch_proof_of_work_bits: 0,
+        max_log_arity,
+        log_arity_schedule: log_arity_schedule.to_vec(),
         mmcs: fri_mmcs,
     };
     let dft = Radix2Dit::default();
@@ -49,9 +58,17 @@ fn get_ldt_for_testing<R: Rng>(rng: &mut R, log_final_poly_len: usize) -> (Perm,
 /// We then commit to these polynomials using a `log_blowup` of `1`.
 ///
 /// We open each polynomial at the same point `zeta` and run FRI to verify the openings, stopping
-/// FRI at `log_final_poly_len`.
-fn do_test_fri_ldt<R: Rng>(rng: &mut R, log_final_poly_len: usize, polynomial_log_sizes: &[u8]) {
-    let (perm, pcs) = get_ldt_for_testing(rng, log_final_poly_len);
+/// FRI at `log_final_poly_len` and folding by at most `2^log_arity_schedule[i]` in round `i`, or
+/// by at most `2^max_log_arity` past the end of the schedule.
+fn do_test_fri_ldt<R: Rng>(
+    rng: &mut R,
+    log_final_poly_len: usize,
+    max_log_arity: usize,
+    log_arity_schedule: &[usize],
+    polynomial_log_sizes: &[u8],
+) {
+    let (perm, pcs) =
+        get_ldt_for_testing(rng, log_final_poly_len, max_log_arity, log_arity_schedule);
 
     // Convert the polynomial_log_sizes into field elements so they can be observed.
     let val_sizes: Vec<Val> = polynomial_log_sizes
@@ -90,7 +107,7 @@ fn do_test_fri_ldt<R: Rng>(rng: &mut R, log_final_poly_len: usize, polynomial_lo
             >>::commit(&pcs, evaluations);
 
         // Observe the commitment.
-        challenger.observe(commitment);
+        challenger.observe(commitment.clone());
 
         // Sample the challenge point zeta which all polynomials
         // will be opened at.
@@ -116,7 +133,7 @@ fn do_test_fri_ldt<R: Rng>(rng: &mut R, log_final_poly_len: usize, polynomial_lo
         // as the prover.
         let mut challenger = Challenger::new(perm.clone());
         challenger.observe_slice(&val_sizes);
-        challenger.observe(commitment);
+        challenger.observe(commitment.clone());
 
         // Sample the opening point.
         let zeta = challenger.sample_algebra_element();
@@ -167,7 +184,42 @@ fn test_fri_ldt() {
     let polynomial_log_sizes = [5, 8, 10, 7, 5, 5, 7];
     for i in 0..5 {
         let mut rng = SmallRng::seed_from_u64(i as u64);
-        do_test_fri_ldt(&mut rng, i, &polynomial_log_sizes);
+        do_test_fri_ldt(&mut rng, i, 1, &[], &polynomial_log_sizes);
+    }
+}
+
+/// Test that the FRI commit, open and verify process work correctly
+/// when folding by more than 2 in each round.
+#[test]
+fn test_fri_ldt_higher_arity() {
+    // The rounds must stop early at each of these sizes to roll in the smaller polynomials.
+    let polynomial_log_sizes = [5, 8, 10, 7, 5, 5, 7];
+    for max_log_arity in 2..5 {
+        for i in 0..3 {
+            let mut rng = SmallRng::seed_from_u64((max_log_arity * 5 + i) as u64);
+            do_test_fri_ldt(&mut rng, i, max_log_arity, &[], &polynomial_log_sizes);
+        }
+    }
+}
+
+/// Test that the FRI commit, open and verify process work correctly
+/// when the arity of the first rounds differs from that of the later ones.
+#[test]
+fn test_fri_ldt_log_arity_schedule() {
+    let polynomial_log_sizes = [12, 9, 12, 6];
+    for (i, (max_log_arity, log_arity_schedule)) in
+        [(1, vec![3, 3]), (2, vec![1, 4]), (3, vec![1, 1, 2])]
+            .into_iter()
+            .enumerate()
+    {
+        let mut rng = SmallRng::seed_from_u64(i as u64);
+        do_test_fri_ldt(
+            &mut rng,
+            1,
+            max_log_arity,
+            &log_arity_schedule,
+            &polynomial_log_sizes,
+        );
     }
 }
 
@@ -180,5 +232,22 @@ fn test_fri_ldt_should_panic() {
     // of the same size and that the array is not ordered.
     let polynomial_log_sizes = [5, 8, 10, 7, 5, 5, 7];
     let mut rng = SmallRng::seed_from_u64(5);
-    do_test_fri_ldt(&mut rng, 5, &polynomial_log_sizes);
+    do_test_fri_ldt(&mut rng, 5, 1, &[], &polynomial_log_sizes);
+}
+
+#[test]
+fn test_log_arities() {
+    let params = FriParameters {
+        log_blowup: 1,
+        log_final_poly_len: 1,
+        num_queries: 10,
+        proof_of_work_bits: 8,
+        commit_proof_of_work_bits: 0,
+        batch_proof_of_work_bits: 0,
+        max_log_arity: 2,
+        log_arity_schedule: vec![3, 3, 3],
+        mmcs: (),
+    };
+    // The second round stops early at the inputs of height `2^8`, and the last at the final height.
+    assert_eq!(params.log_arities(13, [13, 10, 8]), vec![3, 2, 3, 2, 1]);
 }
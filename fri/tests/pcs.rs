@@ -168,12 +168,17 @@ mod babybear_fri_pcs {
     type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
     type MyPcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
 
-    fn get_pcs(log_blowup: usize) -> (MyPcs, Challenger) {
+    fn get_pcs(
+        log_blowup: usize,
+        commit_proof_of_work_bits: usize,
+        batch_proof_of_work_bits: usize,
+        cap_height: usize,
+    ) -> (MyPcs, Challenger) {
         let perm = Perm::new_from_rng_128(&mut seeded_rng());
         let hash = MyHash::new(perm.clone());
         let compress = MyCompress::new(perm.clone());
 
-        let val_mmcs = ValMmcs::new(hash, compress);
+        let val_mmcs = ValMmcs::new(hash, compress).with_cap_height(cap_height);
         let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
 
         let fri_params = FriParameters {
@@ -181,6 +186,10 @@ mod babybear_fri_pcs {
             log_final_poly_len: 0,
             num_queries: 10,
             proof_of_work_bits: 8,
+            commit_proof_of_work_bits,
+            batch_proof_of_work_bits,
+            max_log_arity: 1,
+            log_arity_schedule: vec![],
             mmcs: challenge_mmcs,
         };
 
@@ -189,10 +198,69 @@ mod babybear_fri_pcs {
     }
 
     mod blowup_1 {
-        make_tests_for_pcs!(super::get_pcs(1));
+        make_tests_for_pcs!(super::get_pcs(1, 0, 0, 0));
     }
     mod blowup_2 {
-        make_tests_for_pcs!(super::get_pcs(2));
+        make_tests_for_pcs!(super::get_pcs(2, 0, 0, 0));
+    }
+    mod grinding {
+        make_tests_for_pcs!(super::get_pcs(1, 4, 6, 0));
+    }
+    mod cap {
+        make_tests_for_pcs!(super::get_pcs(1, 0, 0, 2));
+    }
+
+    #[test]
+    fn open_from_checkpoint() {
+        let (pcs, challenger) = get_pcs(1, 0, 0, 1);
+        let mut rng = seeded_rng();
+
+        let domains_and_polys = [4, 3]
+            .map(|log_degree| {
+                let d = 1 << log_degree;
+                (
+                    Pcs::<Challenge, Challenger>::natural_domain_for_degree(&pcs, d),
+                    RowMajorMatrix::<Val>::rand(&mut rng, d, 7),
+                )
+            })
+            .to_vec();
+        let (commit, data) =
+            Pcs::<Challenge, Challenger>::commit(&pcs, domains_and_polys.iter().cloned());
+
+        // Checkpoint the prover data to a file and drop it, as if the prover had stopped.
+        let path = std::env::temp_dir().join(format!("p3-fri-checkpoint-{}", std::process::id()));
+        std::fs::write(&path, postcard::to_allocvec(&data).unwrap()).unwrap();
+
+        let mut p_challenger = challenger.clone();
+        p_challenger.observe(commit.clone());
+        let zeta: Challenge = p_challenger.sample_algebra_element();
+        let points = vec![vec![zeta]; domains_and_polys.len()];
+        let (expected_openings, expected_proof) =
+            pcs.open(vec![(&data, points.clone())], &mut p_challenger.clone());
+        drop(data);
+
+        // Resume from the checkpoint.
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let data: <MyPcs as Pcs<Challenge, Challenger>>::ProverData =
+            postcard::from_bytes(&bytes).unwrap();
+        let (openings, proof) = pcs.open(vec![(&data, points)], &mut p_challenger);
+        assert_eq!(openings, expected_openings);
+        assert_eq!(
+            postcard::to_allocvec(&proof).unwrap(),
+            postcard::to_allocvec(&expected_proof).unwrap()
+        );
+
+        let mut v_challenger = challenger;
+        v_challenger.observe(commit.clone());
+        assert_eq!(v_challenger.sample_algebra_element::<Challenge>(), zeta);
+        let claims = domains_and_polys
+            .iter()
+            .zip(&openings[0])
+            .map(|((domain, _), values)| (*domain, vec![(zeta, values[0].clone())]))
+            .collect_vec();
+        pcs.verify(vec![(commit, claims)], &proof, &mut v_challenger)
+            .unwrap();
     }
 }
 
@@ -234,6 +302,10 @@ mod m31_fri_pcs {
             log_final_poly_len: 0,
             num_queries: 10,
             proof_of_work_bits: 8,
+            commit_proof_of_work_bits: 0,
+            batch_proof_of_work_bits: 0,
+            max_log_arity: 1,
+            log_arity_schedule: vec![],
             mmcs: challenge_mmcs,
         };
         let pcs = Pcs {
@@ -0,0 +1,152 @@
+use p3_fri::{
+    BatchingStrategy, FriParameters, SoundnessCalculator, SoundnessRegime,
+    create_benchmark_fri_params,
+};
+
+fn calculator(num_queries: usize, batching: BatchingStrategy) -> SoundnessCalculator {
+    // BabyBear with a quartic extension, and a trace of height 2^20 with 100 constraints.
+    SoundnessCalculator {
+        log_field_size: 30.9,
+        extension_degree: 4,
+        log_trace_height: 20,
+        log_blowup: 2,
+        log_arities: vec![1; 20],
+        log_final_poly_len: 0,
+        num_queries,
+        proof_of_work_bits: 16,
+        commit_proof_of_work_bits: 0,
+        batch_proof_of_work_bits: 0,
+        num_constraints: 100,
+        batching,
+    }
+}
+
+#[test]
+fn test_conjectured_soundness_matches_fri_parameters() {
+    // Goldilocks with a cubic extension, which is large enough for the queries to limit soundness.
+    let params: FriParameters<()> = create_benchmark_fri_params(());
+    let calculator = params.soundness_calculator(64.0, 3, 16, 10, BatchingStrategy::Powers);
+    assert_eq!(
+        calculator.conjectured_soundness_bits(),
+        params.conjectured_soundness_bits() as f64
+    );
+}
+
+#[test]
+fn test_conjectured_soundness_limited_by_field() {
+    // The queries alone give 2 * 100 + 16 bits, but the field only `123.6 - 22 - log_2(100)`.
+    let bits = calculator(100, BatchingStrategy::Powers).conjectured_soundness_bits();
+    assert!(
+        (bits - (4.0 * 30.9 - 22.0 - 6.643_856)).abs() < 1e-4,
+        "{bits}"
+    );
+
+    let bits = calculator(100, BatchingStrategy::Independent).conjectured_soundness_bits();
+    assert!((bits - (4.0 * 30.9 - 22.0)).abs() < 1e-9, "{bits}");
+}
+
+#[test]
+fn test_johnson_soundness() {
+    let bits = calculator(30, BatchingStrategy::Powers).soundness_bits();
+    assert_eq!(bits.conjectured, 2.0 * 30.0 + 16.0);
+    // Each query gives a little less than `log_blowup / 2 = 1` bit of proven soundness.
+    assert!(bits.johnson < 30.0 + 16.0, "{bits:?}");
+    assert!(bits.johnson > 0.8 * 30.0 + 16.0, "{bits:?}");
+
+    // Past some number of queries, the proven soundness is limited by the field.
+    let bits = calculator(200, BatchingStrategy::Powers).soundness_bits();
+    assert!(bits.johnson < 70.0, "{bits:?}");
+}
+
+#[test]
+fn test_min_num_queries() {
+    let calculator = calculator(0, BatchingStrategy::Powers);
+    for (regime, target_bits) in [
+        (SoundnessRegime::Conjectured, 80.0),
+        (SoundnessRegime::Johnson, 60.0),
+    ] {
+        let num_queries = calculator.min_num_queries(target_bits, regime).unwrap();
+        let bits = |num_queries| {
+            let calculator = SoundnessCalculator {
+                num_queries,
+                ..calculator.clone()
+            };
+            match regime {
+                SoundnessRegime::Conjectured => calculator.conjectured_soundness_bits(),
+                SoundnessRegime::Johnson => calculator.johnson_soundness_bits(),
+            }
+        };
+        assert!(bits(num_queries) >= target_bits);
+        assert!(bits(num_queries - 1) < target_bits);
+    }
+    assert_eq!(
+        calculator.min_num_queries(80.0, SoundnessRegime::Conjectured),
+        Some(32)
+    );
+
+    // The field is too small for 80 bits of proven soundness, or 128 bits in either regime.
+    assert_eq!(
+        calculator.min_num_queries(80.0, SoundnessRegime::Johnson),
+        None
+    );
+    for regime in [SoundnessRegime::Conjectured, SoundnessRegime::Johnson] {
+        assert_eq!(calculator.min_num_queries(128.0, regime), None);
+    }
+}
+
+#[test]
+fn test_challenge_grinding() {
+    // Grinding before the challenges lifts the limit that the field puts on the soundness.
+    let calculator = calculator(200, BatchingStrategy::Powers);
+    let grinding = SoundnessCalculator {
+        commit_proof_of_work_bits: 20,
+        batch_proof_of_work_bits: 20,
+        ..calculator.clone()
+    };
+    let (bits, grinding_bits) = (calculator.soundness_bits(), grinding.soundness_bits());
+    assert!((grinding_bits.conjectured - bits.conjectured - 20.0).abs() < 1e-9);
+    assert!(
+        grinding_bits.johnson > bits.johnson + 10.0,
+        "{grinding_bits:?}"
+    );
+}
+
+#[test]
+fn test_arities() {
+    // A round folding by `2^k` errs like `2^k - 1` folds by 2, and folding down to a longer final
+    // polynomial takes fewer rounds.
+    let binary = calculator(200, BatchingStrategy::Powers);
+    let by_16 = SoundnessCalculator {
+        log_arities: vec![4; 5],
+        ..binary.clone()
+    };
+    let to_final_poly = SoundnessCalculator {
+        log_arities: vec![1; 17],
+        log_final_poly_len: 3,
+        ..binary.clone()
+    };
+    assert!(by_16.johnson_soundness_bits() < binary.johnson_soundness_bits());
+    assert!(to_final_poly.johnson_soundness_bits() > binary.johnson_soundness_bits());
+    assert_eq!(
+        by_16.conjectured_soundness_bits(),
+        binary.conjectured_soundness_bits()
+    );
+
+    // The calculator of some FRI parameters folds with their arities.
+    let params = FriParameters {
+        max_log_arity: 3,
+        ..create_benchmark_fri_params(())
+    };
+    let calculator = params.soundness_calculator(64.0, 3, 16, 10, BatchingStrategy::Powers);
+    assert_eq!(calculator.log_arities, [3, 3, 3, 3, 3, 1]);
+}
+
+#[test]
+#[should_panic(expected = "the arities must fold the trace down to the final polynomial")]
+fn test_arities_not_folding_to_final_poly() {
+    let calculator = SoundnessCalculator {
+        log_arities: vec![1; 19],
+        ..calculator(30, BatchingStrategy::Powers)
+    };
+    calculator.johnson_soundness_bits();
+}
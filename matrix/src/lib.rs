@@ -3,6 +3,8 @@
 #![no_std]
 
 extern crate alloc;
+#[cfg(feature = "mmap")]
+extern crate std;
 
 use alloc::vec::Vec;
 use core::fmt::{Debug, Display, Formatter};
@@ -22,6 +24,8 @@ pub mod bitrev;
 pub mod dense;
 pub mod extension;
 pub mod horizontally_truncated;
+#[cfg(feature = "mmap")]
+pub mod mmap;
 pub mod row_index_mapped;
 pub mod stack;
 pub mod strided;
@@ -408,6 +412,28 @@ pub trait Matrix<T: Send + Sync + Clone>: Send + Sync {
             .collect_vec()
     }
 
+    /// Pack together `size` collections of rows, `step` rows apart, from the matrix.
+    ///
+    /// Returns a vector corresponding to `size` packed rows. The i'th element of the j'th row
+    /// contains the packing of the i'th element of the rows r + j * step through
+    /// r + j * step + P::WIDTH - 1, wrapping around if we exceed the height of the matrix.
+    /// For `size = 2` this is the same as [`Self::vertically_packed_row_pair`].
+    #[inline]
+    fn vertically_packed_row_window<P>(&self, r: usize, step: usize, size: usize) -> Vec<P>
+    where
+        T: Copy,
+        P: PackedValue<Value = T>,
+    {
+        (0..size)
+            .flat_map(|j| {
+                let rows = self.wrapping_row_slices(r + j * step, P::WIDTH);
+                (0..self.width())
+                    .map(|c| P::from_fn(|i| rows[i][c]))
+                    .collect_vec()
+            })
+            .collect()
+    }
+
     /// Returns a view over a vertically strided submatrix.
     ///
     /// The view selects rows using `r = offset + i * stride` for each `i`.
@@ -0,0 +1,227 @@
+//! File-backed storage for dense matrices, for traces too large to comfortably keep in memory.
+//!
+//! An [`MmapMatrix`] is a [`DenseMatrix`] whose values live in a memory-mapped file, so the
+//! operating system pages rows in and out as they are accessed. It implements [`Matrix`]
+//! like any other dense matrix, so it can be hashed into a Merkle tree or viewed in place
+//! without being read into memory first.
+//!
+//! `TwoAdicSubgroupDft::coset_lde_batch_into` in `p3-dft` extends an `MmapMatrix` into another
+//! one in place, and [`reverse_matrix_index_bits`] puts the extension in the bit-reversed order
+//! in which an `Mmcs` commits to it, so neither has to be read into memory. The PCS of `p3-fri`
+//! still keeps the extensions it commits to in a `Vec`-backed [`RowMajorMatrix`].
+//!
+//! The values are stored in their in-memory representation, so a file is only meaningful on
+//! a machine with the same layout for `T` as the one which wrote it.
+//!
+//! [`Matrix`]: crate::Matrix
+//! [`RowMajorMatrix`]: crate::dense::RowMajorMatrix
+//! [`reverse_matrix_index_bits`]: crate::util::reverse_matrix_index_bits
+
+use alloc::vec::Vec;
+use core::borrow::{Borrow, BorrowMut};
+use core::marker::PhantomData;
+use core::mem::size_of;
+use core::slice;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::Path;
+
+use memmap2::MmapMut;
+
+use crate::dense::{DenseMatrix, DenseStorage};
+
+/// A dense matrix backed by a memory-mapped file.
+pub type MmapMatrix<T> = DenseMatrix<T, MmapStorage<T>>;
+
+/// A buffer of `T` values stored in a memory-mapped file.
+///
+/// Writes through [`BorrowMut`] go to the mapping and reach the file eventually, or on
+/// [`flush`](Self::flush).
+#[derive(Debug)]
+pub struct MmapStorage<T> {
+    mmap: MmapMut,
+    len: usize,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Copy + Default + Send + Sync> MmapStorage<T> {
+    /// Create a file at `path` holding `len` default values and map it, truncating any
+    /// existing file.
+    ///
+    /// # Safety
+    /// The file must not be modified, e.g. truncated, by anything else while it is mapped.
+    ///
+    /// # Errors
+    /// If the file cannot be created, resized or mapped.
+    ///
+    /// # Panics
+    /// If `T` is zero-sized.
+    pub unsafe fn create(path: impl AsRef<Path>, len: usize) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        file.set_len(byte_len::<T>(len)? as u64)?;
+
+        // SAFETY: Guaranteed by the caller. Every value is initialized below before it is read.
+        let mut storage = unsafe { Self::map(&file, len)? };
+        let ptr = storage.mmap.as_mut_ptr().cast::<T>();
+        for i in 0..len {
+            // SAFETY: The mapping is page-aligned and holds `len` values of `T`.
+            unsafe { ptr.add(i).write(T::default()) };
+        }
+        Ok(storage)
+    }
+}
+
+impl<T: Copy + Send + Sync> MmapStorage<T> {
+    /// Map the file at `path`, which must be readable and writable, as a buffer of `T` values.
+    ///
+    /// # Safety
+    /// The file must hold valid values of `T` in their in-memory representation, e.g. because
+    /// it was written through an `MmapStorage<T>` on the same machine. It must not be modified
+    /// by anything else while it is mapped.
+    ///
+    /// # Errors
+    /// If the file cannot be opened or mapped, or its length is not a multiple of the size of
+    /// `T`.
+    ///
+    /// # Panics
+    /// If `T` is zero-sized.
+    pub unsafe fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        let file_len = usize::try_from(file.metadata()?.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "file is too large"))?;
+        assert_ne!(size_of::<T>(), 0, "zero-sized values cannot be mapped");
+        if !file_len.is_multiple_of(size_of::<T>()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "file length is not a multiple of the value size",
+            ));
+        }
+        // SAFETY: Guaranteed by the caller.
+        unsafe { Self::map(&file, file_len / size_of::<T>()) }
+    }
+
+    /// Flush outstanding writes to the file.
+    pub fn flush(&self) -> io::Result<()> {
+        self.mmap.flush()
+    }
+
+    /// Map `len` values of `T` from the start of `file`.
+    ///
+    /// # Safety
+    /// The file must not be modified by anything else while it is mapped.
+    unsafe fn map(file: &File, len: usize) -> io::Result<Self> {
+        assert_ne!(size_of::<T>(), 0, "zero-sized values cannot be mapped");
+        // SAFETY: Guaranteed by the caller.
+        let mmap = unsafe { MmapMut::map_mut(file)? };
+        assert!(mmap.len() >= byte_len::<T>(len)?);
+        assert!(
+            mmap.as_ptr().cast::<T>().is_aligned(),
+            "mapping is not aligned for the value type"
+        );
+        Ok(Self {
+            mmap,
+            len,
+            _phantom: PhantomData,
+        })
+    }
+}
+
+impl<T: Copy + Send + Sync> MmapMatrix<T> {
+    /// Flush outstanding writes to the backing file.
+    pub fn flush(&self) -> io::Result<()> {
+        self.values.flush()
+    }
+}
+
+impl<T> Borrow<[T]> for MmapStorage<T> {
+    fn borrow(&self) -> &[T] {
+        if self.len == 0 {
+            return &[];
+        }
+        // SAFETY: The mapping is aligned for `T` and holds `len` initialized values.
+        unsafe { slice::from_raw_parts(self.mmap.as_ptr().cast(), self.len) }
+    }
+}
+
+impl<T> BorrowMut<[T]> for MmapStorage<T> {
+    fn borrow_mut(&mut self) -> &mut [T] {
+        if self.len == 0 {
+            return &mut [];
+        }
+        // SAFETY: The mapping is aligned for `T` and holds `len` initialized values.
+        unsafe { slice::from_raw_parts_mut(self.mmap.as_mut_ptr().cast(), self.len) }
+    }
+}
+
+impl<T: Copy + Send + Sync> DenseStorage<T> for MmapStorage<T> {
+    fn to_vec(self) -> Vec<T> {
+        <[T]>::to_vec(self.borrow())
+    }
+}
+
+/// The number of bytes taken by `len` values of `T`.
+fn byte_len<T>(len: usize) -> io::Result<usize> {
+    len.checked_mul(size_of::<T>())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "length overflows"))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+    use std::path::PathBuf;
+
+    use p3_baby_bear::BabyBear;
+    use rand::SeedableRng;
+    use rand::rngs::SmallRng;
+
+    use super::*;
+    use crate::Matrix;
+    use crate::dense::RowMajorMatrix;
+
+    type F = BabyBear;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("p3-matrix-{name}-{}", std::process::id()))
+    }
+
+    #[test]
+    fn write_and_reopen() {
+        let path = temp_path("reopen");
+        let mut rng = SmallRng::seed_from_u64(1);
+        let expected = RowMajorMatrix::<F>::rand(&mut rng, 10, 3);
+
+        let mut mat = MmapMatrix::new(unsafe { MmapStorage::create(&path, 30).unwrap() }, 3);
+        assert_eq!(mat.dimensions(), expected.dimensions());
+        assert!(mat.as_view().values.iter().all(|&x| x == F::default()));
+        mat.copy_from(&expected);
+        mat.flush().unwrap();
+        drop(mat);
+
+        let mat = MmapMatrix::<F>::new(unsafe { MmapStorage::open(&path).unwrap() }, 3);
+        assert_eq!(*mat.row_slice(7).unwrap(), *expected.row_slice(7).unwrap());
+        assert_eq!(mat.to_row_major_matrix(), expected);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn empty() {
+        let path = temp_path("empty");
+        let mut mat = MmapMatrix::<F>::new(unsafe { MmapStorage::create(&path, 0).unwrap() }, 2);
+        assert_eq!(mat.height(), 0);
+        assert!(mat.as_view_mut().values.is_empty());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn open_wrong_length() {
+        let path = temp_path("wrong-length");
+        std::fs::write(&path, [0u8; 7]).unwrap();
+        assert!(unsafe { MmapStorage::<F>::open(&path) }.is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -5,7 +5,7 @@ use p3_util::{log2_strict_usize, reverse_bits_len};
 use tracing::instrument;
 
 use crate::Matrix;
-use crate::dense::{DenseMatrix, DenseStorage, RowMajorMatrix};
+use crate::dense::{DenseMatrix, DenseStorage};
 
 /// Reverse the order of matrix rows based on the bit-reversal of their indices.
 ///
@@ -56,7 +56,7 @@ where
     });
 }
 
-/// Swap two rows `i` and `j` in a [`RowMajorMatrix`].
+/// Swap two rows `i` and `j` in a [`DenseMatrix`].
 ///
 /// # Panics
 /// Panics if the indices are out of bounds or not ordered as `i < j`.
@@ -65,9 +65,13 @@ where
 /// - `mat`: The matrix to modify.
 /// - `i`: The first row index (must be less than `j`).
 /// - `j`: The second row index.
-pub fn swap_rows<F: Clone + Send + Sync>(mat: &mut RowMajorMatrix<F>, i: usize, j: usize) {
+pub fn swap_rows<F, S>(mat: &mut DenseMatrix<F, S>, i: usize, j: usize)
+where
+    F: Clone + Send + Sync,
+    S: DenseStorage<F> + BorrowMut<[F]>,
+{
     let w = mat.width();
-    let (upper, lower) = mat.values.split_at_mut(j * w);
+    let (upper, lower) = mat.values.borrow_mut().split_at_mut(j * w);
     let row_i = &mut upper[i * w..(i + 1) * w];
     let row_j = &mut lower[..w];
     row_i.swap_with_slice(row_j);
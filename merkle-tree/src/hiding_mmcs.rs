@@ -2,12 +2,12 @@ use alloc::vec::Vec;
 use core::cell::RefCell;
 
 use itertools::Itertools;
-use p3_commit::{BatchOpening, BatchOpeningRef, Mmcs};
+use p3_commit::{BatchOpening, BatchOpeningRef, Mmcs, MultiBatchOpening, MultiBatchOpeningRef};
 use p3_field::PackedValue;
 use p3_matrix::dense::RowMajorMatrix;
 use p3_matrix::stack::HorizontalPair;
 use p3_matrix::{Dimensions, Matrix};
-use p3_symmetric::{CryptographicHasher, Hash, PseudoCompressionFunction};
+use p3_symmetric::{CryptographicHasher, MerkleCap, PseudoCompressionFunction};
 use p3_util::zip_eq::zip_eq;
 use rand::Rng;
 use rand::distr::{Distribution, StandardUniform};
@@ -35,15 +35,24 @@ use crate::{MerkleTree, MerkleTreeError, MerkleTreeMmcs};
 /// - `H`: the leaf hasher
 /// - `C`: the digest compression function
 /// - `R`: a random number generator for blinding leaves
+/// - `N`: the arity of the tree
 #[derive(Clone, Debug)]
-pub struct MerkleTreeHidingMmcs<P, PW, H, C, R, const DIGEST_ELEMS: usize, const SALT_ELEMS: usize>
-{
-    inner: MerkleTreeMmcs<P, PW, H, C, DIGEST_ELEMS>,
+pub struct MerkleTreeHidingMmcs<
+    P,
+    PW,
+    H,
+    C,
+    R,
+    const DIGEST_ELEMS: usize,
+    const SALT_ELEMS: usize,
+    const N: usize = 2,
+> {
+    inner: MerkleTreeMmcs<P, PW, H, C, DIGEST_ELEMS, N>,
     rng: RefCell<R>,
 }
 
-impl<P, PW, H, C, R, const DIGEST_ELEMS: usize, const SALT_ELEMS: usize>
-    MerkleTreeHidingMmcs<P, PW, H, C, R, DIGEST_ELEMS, SALT_ELEMS>
+impl<P, PW, H, C, R, const DIGEST_ELEMS: usize, const SALT_ELEMS: usize, const N: usize>
+    MerkleTreeHidingMmcs<P, PW, H, C, R, DIGEST_ELEMS, SALT_ELEMS, N>
 {
     pub const fn new(hash: H, compress: C, rng: R) -> Self {
         let inner = MerkleTreeMmcs::new(hash, compress);
@@ -52,10 +61,20 @@ impl<P, PW, H, C, R, const DIGEST_ELEMS: usize, const SALT_ELEMS: usize>
             rng: RefCell::new(rng),
         }
     }
+
+    /// Commit to the cap of height `cap_height` of each tree in place of its root, see
+    /// [`MerkleTreeMmcs::with_cap_height`].
+    #[must_use]
+    pub fn with_cap_height(self, cap_height: usize) -> Self {
+        Self {
+            inner: self.inner.with_cap_height(cap_height),
+            rng: self.rng,
+        }
+    }
 }
 
-impl<P, PW, H, C, R, const DIGEST_ELEMS: usize, const SALT_ELEMS: usize> Mmcs<P::Value>
-    for MerkleTreeHidingMmcs<P, PW, H, C, R, DIGEST_ELEMS, SALT_ELEMS>
+impl<P, PW, H, C, R, const DIGEST_ELEMS: usize, const SALT_ELEMS: usize, const N: usize>
+    Mmcs<P::Value> for MerkleTreeHidingMmcs<P, PW, H, C, R, DIGEST_ELEMS, SALT_ELEMS, N>
 where
     P: PackedValue,
     P::Value: Serialize + DeserializeOwned,
@@ -63,19 +82,26 @@ where
     H: CryptographicHasher<P::Value, [PW::Value; DIGEST_ELEMS]>
         + CryptographicHasher<P, [PW; DIGEST_ELEMS]>
         + Sync,
-    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], 2>
-        + PseudoCompressionFunction<[PW; DIGEST_ELEMS], 2>
+    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], N>
+        + PseudoCompressionFunction<[PW; DIGEST_ELEMS], N>
         + Sync,
     R: Rng + Clone,
     PW::Value: Eq,
     [PW::Value; DIGEST_ELEMS]: Serialize + for<'de> Deserialize<'de>,
     StandardUniform: Distribution<P::Value>,
 {
-    type ProverData<M> =
-        MerkleTree<P::Value, PW::Value, HorizontalPair<M, RowMajorMatrix<P::Value>>, DIGEST_ELEMS>;
-    type Commitment = Hash<P::Value, PW::Value, DIGEST_ELEMS>;
+    type ProverData<M> = MerkleTree<
+        P::Value,
+        PW::Value,
+        HorizontalPair<M, RowMajorMatrix<P::Value>>,
+        DIGEST_ELEMS,
+        N,
+    >;
+    type Commitment = MerkleCap<P::Value, PW::Value, DIGEST_ELEMS>;
     /// The first item is salts; the second is the usual Merkle proof (sibling digests).
     type Proof = (Vec<Vec<P::Value>>, Vec<[PW::Value; DIGEST_ELEMS]>);
+    /// The first item is the salts of each opened index; the second is the inner multi proof.
+    type MultiProof = (Vec<Vec<Vec<P::Value>>>, Vec<[PW::Value; DIGEST_ELEMS]>);
     type Error = MerkleTreeError;
 
     fn commit<M: Matrix<P::Value>>(
@@ -136,6 +162,52 @@ where
             BatchOpeningRef::new(&opened_salted_values, siblings),
         )
     }
+
+    fn open_multi_batch<M: Matrix<P::Value>>(
+        &self,
+        indices: &[usize],
+        prover_data: &Self::ProverData<M>,
+    ) -> MultiBatchOpening<P::Value, Self> {
+        let (salted_openings, siblings) =
+            self.inner.open_multi_batch(indices, prover_data).unpack();
+        let (openings, salts): (Vec<_>, Vec<_>) = salted_openings
+            .into_iter()
+            .map(|rows| {
+                rows.into_iter()
+                    .map(|row| {
+                        let (a, b) = row.split_at(row.len() - SALT_ELEMS);
+                        (a.to_vec(), b.to_vec())
+                    })
+                    .unzip::<_, _, Vec<_>, Vec<_>>()
+            })
+            .unzip();
+        MultiBatchOpening::new(openings, (salts, siblings))
+    }
+
+    fn verify_multi_batch(
+        &self,
+        commit: &Self::Commitment,
+        dimensions: &[Dimensions],
+        indices: &[usize],
+        batch_opening: MultiBatchOpeningRef<P::Value, Self>,
+    ) -> Result<(), Self::Error> {
+        let (opened_values, (salts, siblings)) = batch_opening.unpack();
+
+        let opened_salted_values = zip_eq(opened_values, salts, MerkleTreeError::WrongBatchSize)?
+            .map(|(rows, row_salts)| {
+                Ok(zip_eq(rows, row_salts, MerkleTreeError::WrongBatchSize)?
+                    .map(|(opened, salt)| opened.iter().chain(salt.iter()).copied().collect_vec())
+                    .collect_vec())
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.inner.verify_multi_batch(
+            commit,
+            dimensions,
+            indices,
+            MultiBatchOpeningRef::new(&opened_salted_values, siblings),
+        )
+    }
 }
 
 #[cfg(test)]
@@ -204,4 +276,30 @@ mod tests {
         let batch_proof = mmcs.open_batch(17, &prover_data);
         mmcs.verify_batch(&commit, &dims, 17, (&batch_proof).into())
     }
+
+    #[test]
+    fn multi_batch() -> Result<(), MerkleTreeError> {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let mats = vec![
+            RowMajorMatrix::<F>::rand(&mut rng, 32, 3),
+            RowMajorMatrix::<F>::rand(&mut rng, 4, 2),
+        ];
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash::new(perm.clone());
+        let compress = MyCompress::new(perm);
+        let mmcs = MyMmcs::new(hash, compress, rng);
+
+        let dims = mats.iter().map(|m| m.dimensions()).collect_vec();
+
+        let (commit, prover_data) = mmcs.commit(mats);
+        let indices = [17, 3, 16, 17];
+        let batch_proof = mmcs.open_multi_batch(&indices, &prover_data);
+        for (&index, opened_values) in indices.iter().zip(&batch_proof.opened_values) {
+            assert_eq!(
+                opened_values,
+                &mmcs.open_batch(index, &prover_data).opened_values
+            );
+        }
+        mmcs.verify_multi_batch(&commit, &dims, &indices, (&batch_proof).into())
+    }
 }
@@ -8,27 +8,38 @@ use itertools::Itertools;
 use p3_field::PackedValue;
 use p3_matrix::Matrix;
 use p3_maybe_rayon::prelude::*;
-use p3_symmetric::{CryptographicHasher, Hash, PseudoCompressionFunction};
+use p3_symmetric::{CryptographicHasher, Hash, MerkleCap, PseudoCompressionFunction};
+use p3_util::{log2_ceil_usize, log2_strict_usize};
 use serde::{Deserialize, Serialize};
 use tracing::instrument;
 
-/// A binary Merkle tree whose leaves are vectors of matrix rows.
+/// A Merkle tree of arity `N` whose leaves are vectors of matrix rows.
 ///
 /// * `F` – scalar element type inside each matrix row.
 /// * `W` – scalar element type of every digest word.
 /// * `M` – matrix type. Must implement [`Matrix<F>`].
 /// * `DIGEST_ELEMS` – number of `W` words in one digest.
+/// * `N` – number of children of each node, a power of two. Binary by default.
 ///
 /// The tree is **balanced only at the digest layer**.
 /// Leaf matrices may have arbitrary heights as long as any two heights
 /// that round **up** to the same power-of-two are equal.
 ///
-/// Use [`root`] to fetch the final digest once the tree is built.
+/// Each digest layer is `N` times shorter than the one below it, so the rows of a shorter
+/// matrix can only be injected if its padded height is the padded height of the tallest
+/// matrices divided by a power of `N`, or `1`.
+///
+/// Use [`root`] to fetch the final digest once the tree is built, or [`cap`] to fetch a layer
+/// of digests below it.
+///
+/// A tree can be checkpointed with `serde` whenever `M` is serializable. When the leaves are
+/// stored elsewhere, e.g. in a memory-mapped file, it suffices to save [`digest_layers`] and
+/// to reassemble the tree with [`from_parts`] later on.
 ///
 /// This generally shouldn't be used directly. If you're using a Merkle tree as an MMCS,
 /// see `MerkleTreeMmcs`.
 #[derive(Debug, Serialize, Deserialize)]
-pub struct MerkleTree<F, W, M, const DIGEST_ELEMS: usize> {
+pub struct MerkleTree<F, W, M, const DIGEST_ELEMS: usize, const N: usize = 2> {
     /// All leaf matrices in insertion order.
     ///
     /// Each matrix contributes rows to one or more digest layers, depending on its height.
@@ -44,7 +55,8 @@ pub struct MerkleTree<F, W, M, const DIGEST_ELEMS: usize> {
     /// the leaves and the last layer containing exactly one root digest.
     ///
     /// Every inner vector holds contiguous digests `[left₀, right₀, left₁,
-    /// right₁, …]`; higher layers refer to these by index.
+    /// right₁, …]`, or in general the `N` children of each node in turn; higher
+    /// layers refer to these by index.
     ///
     /// Serialization requires that `[W; DIGEST_ELEMS]` implements `Serialize` and
     /// `Deserialize`. This is automatically satisfied when `W` is a fixed-size type.
@@ -58,13 +70,13 @@ pub struct MerkleTree<F, W, M, const DIGEST_ELEMS: usize> {
     _phantom: PhantomData<F>,
 }
 
-impl<F: Clone + Send + Sync, W: Clone, M: Matrix<F>, const DIGEST_ELEMS: usize>
-    MerkleTree<F, W, M, DIGEST_ELEMS>
+impl<F: Clone + Send + Sync, W: Clone, M: Matrix<F>, const DIGEST_ELEMS: usize, const N: usize>
+    MerkleTree<F, W, M, DIGEST_ELEMS, N>
 {
     /// Build a tree from **one or more matrices**.
     ///
     /// * `h` – hashing function used on raw rows.
-    /// * `c` – 2-to-1 compression function used on digests.
+    /// * `c` – `N`-to-1 compression function used on digests.
     /// * `leaves` – matrices to commit to. Must be non-empty.
     ///
     /// Matrices do **not** need to have power-of-two heights. However, any two matrices
@@ -72,13 +84,17 @@ impl<F: Clone + Send + Sync, W: Clone, M: Matrix<F>, const DIGEST_ELEMS: usize>
     /// This ensures proper balancing when folding digests layer-by-layer.
     ///
     /// All matrices are hashed row-by-row with `h`. The resulting digests are
-    /// then folded upwards with `c` until a single root remains.
+    /// then folded upwards with `c` until a single root remains. The digest of the rows of a
+    /// shorter matrix is injected into a node by compressing the node, the digest and `N - 2`
+    /// default digests.
     ///
     /// # Panics
     /// * If `leaves` is empty.
+    /// * If `N` is not a power of two, at least `2`.
     /// * If the packing widths of `P` and `PW` differ.
     /// * If two leaf heights *round up* to the same power-of-two but are not
     ///   equal (violates balancing rule).
+    /// * If the padded height of a leaf is not that of a digest layer.
     #[instrument(name = "build merkle tree", level = "debug", skip_all,
                  fields(dimensions = alloc::format!("{:?}", leaves.iter().map(|l| l.dimensions()).collect::<Vec<_>>())))]
     pub fn new<P, PW, H, C>(h: &H, c: &C, leaves: Vec<M>) -> Self
@@ -88,11 +104,16 @@ impl<F: Clone + Send + Sync, W: Clone, M: Matrix<F>, const DIGEST_ELEMS: usize>
         H: CryptographicHasher<F, [W; DIGEST_ELEMS]>
             + CryptographicHasher<P, [PW; DIGEST_ELEMS]>
             + Sync,
-        C: PseudoCompressionFunction<[W; DIGEST_ELEMS], 2>
-            + PseudoCompressionFunction<[PW; DIGEST_ELEMS], 2>
+        C: PseudoCompressionFunction<[W; DIGEST_ELEMS], N>
+            + PseudoCompressionFunction<[PW; DIGEST_ELEMS], N>
             + Sync,
     {
         assert!(!leaves.is_empty(), "No matrices given?");
+        assert!(
+            N >= 2 && N.is_power_of_two(),
+            "arity must be a power of two, at least 2"
+        );
+        let log_arity = log2_strict_usize(N);
 
         assert_eq!(P::WIDTH, PW::WIDTH, "Packing widths must match");
 
@@ -117,7 +138,10 @@ impl<F: Clone + Send + Sync, W: Clone, M: Matrix<F>, const DIGEST_ELEMS: usize>
             .peeking_take_while(|m| m.height() == max_height)
             .collect_vec();
 
-        let mut digest_layers = vec![first_digest_layer::<P, _, _, _, DIGEST_ELEMS>(
+        // The log of the padded height of the current layer, i.e. its length before any padding
+        // to a multiple of `N`.
+        let mut log_layer_height = log2_ceil_usize(max_height);
+        let mut digest_layers = vec![first_digest_layer::<P, _, _, _, DIGEST_ELEMS, N>(
             h,
             tallest_matrices,
         )];
@@ -126,14 +150,14 @@ impl<F: Clone + Send + Sync, W: Clone, M: Matrix<F>, const DIGEST_ELEMS: usize>
             if prev_layer.len() == 1 {
                 break;
             }
-            let next_layer_len = (prev_layer.len() / 2).next_power_of_two();
+            log_layer_height = log_layer_height.saturating_sub(log_arity);
 
             // The matrices that get injected at this layer.
             let matrices_to_inject = leaves_largest_first
-                .peeking_take_while(|m| m.height().next_power_of_two() == next_layer_len)
+                .peeking_take_while(|m| log2_ceil_usize(m.height()) == log_layer_height)
                 .collect_vec();
 
-            let next_digests = compress_and_inject::<P, _, _, _, _, DIGEST_ELEMS>(
+            let next_digests = compress_and_inject::<P, _, _, _, _, DIGEST_ELEMS, N>(
                 prev_layer,
                 matrices_to_inject,
                 h,
@@ -141,6 +165,10 @@ impl<F: Clone + Send + Sync, W: Clone, M: Matrix<F>, const DIGEST_ELEMS: usize>
             );
             digest_layers.push(next_digests);
         }
+        assert!(
+            leaves_largest_first.peek().is_none(),
+            "matrix heights must be the height of the tallest one divided by a power of the arity"
+        );
 
         Self {
             leaves,
@@ -157,6 +185,67 @@ impl<F: Clone + Send + Sync, W: Clone, M: Matrix<F>, const DIGEST_ELEMS: usize>
     {
         self.digest_layers.last().unwrap()[0].into()
     }
+
+    /// Return the cap of height `cap_height` of the tree, the layer of digests `cap_height`
+    /// layers below the root.
+    ///
+    /// # Panics
+    /// If the tree has fewer than `cap_height` layers below the root.
+    #[must_use]
+    pub fn cap(&self, cap_height: usize) -> MerkleCap<F, W, DIGEST_ELEMS>
+    where
+        W: Copy,
+    {
+        let num_layers = self.digest_layers.len();
+        assert!(cap_height < num_layers, "cap is higher than the tree");
+        self.digest_layers[num_layers - 1 - cap_height]
+            .clone()
+            .into()
+    }
+
+    /// Return the digest layers of the tree, from the one above the leaves up to the root.
+    ///
+    /// Together with the leaves, these are all that is needed to reassemble the tree with
+    /// [`from_parts`](Self::from_parts), e.g. when the leaves are stored separately from a
+    /// checkpoint of the digests.
+    #[must_use]
+    pub fn digest_layers(&self) -> &[Vec<[W; DIGEST_ELEMS]>] {
+        &self.digest_layers
+    }
+
+    /// Reassemble a tree from its leaves and the digest layers previously built from them,
+    /// without hashing anything.
+    ///
+    /// The digests are trusted to be those [`new`](Self::new) would compute from `leaves`;
+    /// only the shape of the layers is checked. Openings of a tree with wrong digests will
+    /// simply fail to verify.
+    ///
+    /// # Panics
+    /// * If `leaves` is empty.
+    /// * If the number or lengths of the digest layers do not match the height of the
+    ///   tallest leaf.
+    pub fn from_parts(leaves: Vec<M>, digest_layers: Vec<Vec<[W; DIGEST_ELEMS]>>) -> Self {
+        assert!(!leaves.is_empty(), "No matrices given?");
+        assert!(
+            N >= 2 && N.is_power_of_two(),
+            "arity must be a power of two, at least 2"
+        );
+
+        let max_height = leaves.iter().map(|m| m.height()).max().unwrap();
+        assert!(
+            digest_layers
+                .iter()
+                .map(Vec::len)
+                .eq(digest_layer_lens::<N>(max_height)),
+            "digest layers do not match the leaf heights"
+        );
+
+        Self {
+            leaves,
+            digest_layers,
+            _phantom: PhantomData,
+        }
+    }
 }
 
 /// Hash every row of the tallest matrices and build the first digest layer.
@@ -167,8 +256,8 @@ impl<F: Clone + Send + Sync, W: Clone, M: Matrix<F>, const DIGEST_ELEMS: usize>
 /// as the base (leaf-level) nodes for the rest of the Merkle tree.
 ///
 /// # Details
-/// - We always return an *even number of digests* (except when height is 1), to
-///   ensure even pairing at higher layers.
+/// - We always return a *multiple of `N` digests* (except when height is 1), to
+///   ensure each node at the next layer has `N` children.
 /// - Matrices are "vertically packed" to allow SIMD-friendly parallel hashing,
 ///   meaning rows can be processed in batches.
 /// - If the total number of rows isn't a multiple of the SIMD packing width,
@@ -181,7 +270,7 @@ impl<F: Clone + Send + Sync, W: Clone, M: Matrix<F>, const DIGEST_ELEMS: usize>
 /// # Returns
 /// A vector of `[PW::Value; DIGEST_ELEMS]`, containing the digests of each row.
 #[instrument(name = "first digest layer", level = "debug", skip_all)]
-fn first_digest_layer<P, PW, H, M, const DIGEST_ELEMS: usize>(
+fn first_digest_layer<P, PW, H, M, const DIGEST_ELEMS: usize, const N: usize>(
     h: &H,
     tallest_matrices: Vec<&M>,
 ) -> Vec<[PW::Value; DIGEST_ELEMS]>
@@ -199,12 +288,12 @@ where
     // Get the height of the tallest matrices (they are guaranteed to be equal).
     let max_height = tallest_matrices[0].height();
 
-    // Compute the padded height to ensure we end up with an even number of digests.
+    // Compute the padded height to ensure we end up with a multiple of `N` digests.
     // **Exception:** if there's only 1 row, we keep it as 1.
     let max_height_padded = if max_height == 1 {
         1
     } else {
-        max_height + max_height % 2
+        max_height.next_multiple_of(N)
     };
 
     // Prepare a default digest value to fill unused slots or padding.
@@ -251,10 +340,10 @@ where
 }
 
 /// Fold one digest layer into the next and, when present, mix in rows
-/// taken from smaller matrices whose padded height equals `prev_layer.len()/2`.
+/// taken from smaller matrices whose padded height is at least `prev_layer.len()/N`.
 ///
-/// Pads the output so its length is even unless it becomes the root.
-fn compress_and_inject<P, PW, H, C, M, const DIGEST_ELEMS: usize>(
+/// Pads the output so its length is a multiple of `N` unless it becomes the root.
+fn compress_and_inject<P, PW, H, C, M, const DIGEST_ELEMS: usize, const N: usize>(
     prev_layer: &[[PW::Value; DIGEST_ELEMS]],
     matrices_to_inject: Vec<&M>,
     h: &H,
@@ -266,41 +355,41 @@ where
     H: CryptographicHasher<P::Value, [PW::Value; DIGEST_ELEMS]>
         + CryptographicHasher<P, [PW; DIGEST_ELEMS]>
         + Sync,
-    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], 2>
-        + PseudoCompressionFunction<[PW; DIGEST_ELEMS], 2>
+    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], N>
+        + PseudoCompressionFunction<[PW; DIGEST_ELEMS], N>
         + Sync,
     M: Matrix<P::Value>,
 {
     if matrices_to_inject.is_empty() {
-        return compress::<PW, _, DIGEST_ELEMS>(prev_layer, c);
+        return compress::<PW, _, DIGEST_ELEMS, N>(prev_layer, c);
     }
 
     let width = PW::WIDTH;
     let next_len = matrices_to_inject[0].height();
-    // We always want to return an even number of digests, except when it's the root.
-    let next_len_padded = if prev_layer.len() == 2 {
-        1
-    } else {
-        // Round prev_layer.len() / 2 up to the next even integer.
-        (prev_layer.len() / 2 + 1) & !1
-    };
+    let next_len_padded = next_layer_len_padded::<N>(prev_layer.len());
 
     let default_digest = [PW::Value::default(); DIGEST_ELEMS];
+    let packed_default_digest = [PW::from_fn(|_| PW::Value::default()); DIGEST_ELEMS];
     let mut next_digests = vec![default_digest; next_len_padded];
     next_digests[0..next_len]
         .par_chunks_exact_mut(width)
         .enumerate()
         .for_each(|(i, digests_chunk)| {
             let first_row = i * width;
-            let left = array::from_fn(|j| PW::from_fn(|k| prev_layer[2 * (first_row + k)][j]));
-            let right = array::from_fn(|j| PW::from_fn(|k| prev_layer[2 * (first_row + k) + 1][j]));
-            let mut packed_digest = c.compress([left, right]);
+            let children = array::from_fn(|child| {
+                array::from_fn(|j| PW::from_fn(|k| prev_layer[N * (first_row + k) + child][j]))
+            });
+            let packed_digest = c.compress(children);
             let tallest_digest = h.hash_iter(
                 matrices_to_inject
                     .iter()
                     .flat_map(|m| m.vertically_packed_row(first_row)),
             );
-            packed_digest = c.compress([packed_digest, tallest_digest]);
+            let packed_digest = c.compress(injection(
+                packed_digest,
+                tallest_digest,
+                packed_default_digest,
+            ));
             for (dst, src) in digests_chunk.iter_mut().zip(unpack_array(packed_digest)) {
                 *dst = src;
             }
@@ -309,24 +398,20 @@ where
     // If our packing width did not divide next_len, fall back to single-threaded scalar code
     // for the last bit.
     for i in (next_len / width * width)..next_len {
-        let left = prev_layer[2 * i];
-        let right = prev_layer[2 * i + 1];
-        let digest = c.compress([left, right]);
+        let digest = c.compress(array::from_fn(|child| prev_layer[N * i + child]));
         let rows_digest = unsafe {
             // Safety: Clearly i < next_len = m.height().
             h.hash_iter(matrices_to_inject.iter().flat_map(|m| m.row_unchecked(i)))
         };
-        next_digests[i] = c.compress([digest, rows_digest]);
+        next_digests[i] = c.compress(injection(digest, rows_digest, default_digest));
     }
 
     // At this point, we've exceeded the height of the matrices to inject, so we continue the
     // process above except with default_digest in place of an input digest.
-    // We only need go as far as half the length of the previous layer.
-    for i in next_len..(prev_layer.len() / 2) {
-        let left = prev_layer[2 * i];
-        let right = prev_layer[2 * i + 1];
-        let digest = c.compress([left, right]);
-        next_digests[i] = c.compress([digest, default_digest]);
+    // We only need go as far as `1/N` of the length of the previous layer.
+    for i in next_len..(prev_layer.len() / N) {
+        let digest = c.compress(array::from_fn(|child| prev_layer[N * i + child]));
+        next_digests[i] = c.compress(injection(digest, default_digest, default_digest));
     }
 
     next_digests
@@ -334,30 +419,24 @@ where
 
 /// Pure compression step used when no extra rows are injected.
 ///
-/// Takes pairs of digests from `prev_layer`, feeds them to `c`,
+/// Takes each run of `N` digests from `prev_layer`, feeds them to `c`,
 /// and writes the results in order.
 ///
-/// Pads with the zero digest so the caller always receives an even-sized
-/// slice, except when the tree has shrunk to its single root.
-fn compress<P, C, const DIGEST_ELEMS: usize>(
+/// Pads with the zero digest so the caller always receives a slice whose length
+/// is a multiple of `N`, except when the tree has shrunk to its single root.
+fn compress<P, C, const DIGEST_ELEMS: usize, const N: usize>(
     prev_layer: &[[P::Value; DIGEST_ELEMS]],
     c: &C,
 ) -> Vec<[P::Value; DIGEST_ELEMS]>
 where
     P: PackedValue,
-    C: PseudoCompressionFunction<[P::Value; DIGEST_ELEMS], 2>
-        + PseudoCompressionFunction<[P; DIGEST_ELEMS], 2>
+    C: PseudoCompressionFunction<[P::Value; DIGEST_ELEMS], N>
+        + PseudoCompressionFunction<[P; DIGEST_ELEMS], N>
         + Sync,
 {
     let width = P::WIDTH;
-    // Always return an even number of digests, except when it's the root.
-    let next_len_padded = if prev_layer.len() == 2 {
-        1
-    } else {
-        // Round prev_layer.len() / 2 up to the next even integer.
-        (prev_layer.len() / 2 + 1) & !1
-    };
-    let next_len = prev_layer.len() / 2;
+    let next_len_padded = next_layer_len_padded::<N>(prev_layer.len());
+    let next_len = prev_layer.len() / N;
 
     let default_digest = [P::Value::default(); DIGEST_ELEMS];
     let mut next_digests = vec![default_digest; next_len_padded];
@@ -367,9 +446,10 @@ where
         .enumerate()
         .for_each(|(i, digests_chunk)| {
             let first_row = i * width;
-            let left = array::from_fn(|j| P::from_fn(|k| prev_layer[2 * (first_row + k)][j]));
-            let right = array::from_fn(|j| P::from_fn(|k| prev_layer[2 * (first_row + k) + 1][j]));
-            let packed_digest = c.compress([left, right]);
+            let children = array::from_fn(|child| {
+                array::from_fn(|j| P::from_fn(|k| prev_layer[N * (first_row + k) + child][j]))
+            });
+            let packed_digest = c.compress(children);
             for (dst, src) in digests_chunk.iter_mut().zip(unpack_array(packed_digest)) {
                 *dst = src;
             }
@@ -378,15 +458,55 @@ where
     // If our packing width did not divide next_len, fall back to single-threaded scalar code
     // for the last bit.
     for i in (next_len / width * width)..next_len {
-        let left = prev_layer[2 * i];
-        let right = prev_layer[2 * i + 1];
-        next_digests[i] = c.compress([left, right]);
+        next_digests[i] = c.compress(array::from_fn(|child| prev_layer[N * i + child]));
     }
 
     // Everything has been initialized so we can safely cast.
     next_digests
 }
 
+/// The lengths of the digest layers of a tree whose tallest matrix has `max_height` rows, from the
+/// one above the leaves up to the root.
+pub(crate) fn digest_layer_lens<const N: usize>(max_height: usize) -> Vec<usize> {
+    let mut len = if max_height == 1 {
+        1
+    } else {
+        max_height.next_multiple_of(N)
+    };
+    let mut lens = vec![len];
+    while len != 1 {
+        len = next_layer_len_padded::<N>(len);
+        lens.push(len);
+    }
+    lens
+}
+
+/// The length of the digest layer above one of length `prev_len`, padded to a multiple of `N`
+/// unless it is the root.
+#[inline]
+const fn next_layer_len_padded<const N: usize>(prev_len: usize) -> usize {
+    if prev_len == N {
+        1
+    } else {
+        (prev_len / N).next_multiple_of(N)
+    }
+}
+
+/// The input to an `N`-to-1 compression which injects `rows_digest` into `node`, padded with
+/// `default_digest`.
+#[inline]
+pub(crate) fn injection<T: Copy, const N: usize>(
+    node: T,
+    rows_digest: T,
+    default_digest: T,
+) -> [T; N] {
+    array::from_fn(|i| match i {
+        0 => node,
+        1 => rows_digest,
+        _ => default_digest,
+    })
+}
+
 /// Converts a packed array `[P; N]` into its underlying `P::WIDTH` scalar arrays.
 ///
 /// Interprets `[P; N]` as the matrix `[[P::Value; P::WIDTH]; N]`, performs a transpose to
@@ -428,7 +548,7 @@ mod tests {
             [0x03; 32], // 0x01 ^ 0x02
             [0x07; 32], // 0x03 ^ 0x04
         ];
-        let result = compress::<u8, DummyCompressionFunction, 32>(&prev_layer, &compressor);
+        let result = compress::<u8, DummyCompressionFunction, 32, 2>(&prev_layer, &compressor);
         assert_eq!(result, expected);
     }
 
@@ -440,7 +560,7 @@ mod tests {
             [0x03; 32], // 0x05 ^ 0x06
             [0x00; 32],
         ];
-        let result = compress::<u8, DummyCompressionFunction, 32>(&prev_layer, &compressor);
+        let result = compress::<u8, DummyCompressionFunction, 32, 2>(&prev_layer, &compressor);
         assert_eq!(result, expected);
     }
 
@@ -459,7 +579,7 @@ mod tests {
                 result
             })
             .collect();
-        let result = compress::<u8, DummyCompressionFunction, 32>(&prev_layer, &compressor);
+        let result = compress::<u8, DummyCompressionFunction, 32, 2>(&prev_layer, &compressor);
         assert_eq!(result, expected);
     }
 
@@ -472,7 +592,7 @@ mod tests {
         let prev_layer = [[0xAA; 32], [0x55; 32]];
         let compressor = DummyCompressionFunction;
         let expected = vec![[0xFF; 32]];
-        let result = compress::<u8, DummyCompressionFunction, 32>(&prev_layer, &compressor);
+        let result = compress::<u8, DummyCompressionFunction, 32, 2>(&prev_layer, &compressor);
         assert_eq!(result, expected);
     }
 
@@ -495,7 +615,7 @@ mod tests {
         // extra padded digest filled with 0
         expected.push([0x00; 32]);
 
-        let result = compress::<u8, DummyCompressionFunction, 32>(&prev_layer, &compressor);
+        let result = compress::<u8, DummyCompressionFunction, 32, 2>(&prev_layer, &compressor);
         assert_eq!(result, expected);
         // also validate the padding branch explicitly
         assert_eq!(result.len(), 4);
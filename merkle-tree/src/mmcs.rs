@@ -18,29 +18,45 @@
 //! E.g. we start by making a standard MerkleTree commitment for each row of M and then add in the rows of N when we
 //! get to the correct level. A proof for the values of say `M[5]` and `N[1]` consists of the siblings `H(M[4]), c23, c10`.
 //!
+//! With a cap height of `1`, the commitment is instead the cap `[c10, c11]`, and the same proof consists of the
+//! siblings `H(M[4]), c23`.
+//!
+//! Several indices can also be opened at once, with a proof holding each sibling which can't be computed from the
+//! opened rows exactly once. A proof for the values at indices `4` and `5` consists of the siblings `c23, c10`.
+//!
+//! The tree may also have a higher arity `N`, with `C` compressing `N` digests into one. Each layer is then `N` times
+//! shorter than the one below, and the rows of N are injected by compressing the node, `H(N[i])` and `N - 2` default
+//! digests. A proof then consists of the `N - 1` siblings of the path at each layer.
+//!
 
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec;
 use alloc::vec::Vec;
-use core::cmp::Reverse;
+use core::array;
+use core::cmp::{Ordering, Reverse};
 use core::marker::PhantomData;
 
 use itertools::Itertools;
-use p3_commit::{BatchOpening, BatchOpeningRef, Mmcs};
+use p3_commit::{BatchOpening, BatchOpeningRef, Mmcs, MultiBatchOpening, MultiBatchOpeningRef};
 use p3_field::PackedValue;
 use p3_matrix::{Dimensions, Matrix};
-use p3_symmetric::{CryptographicHasher, Hash, PseudoCompressionFunction};
-use p3_util::{log2_ceil_usize, log2_strict_usize};
+use p3_symmetric::{CryptographicHasher, MerkleCap, PseudoCompressionFunction};
+use p3_util::log2_ceil_usize;
 use serde::{Deserialize, Serialize};
 
 use crate::MerkleTree;
 use crate::MerkleTreeError::{
-    EmptyBatch, IncompatibleHeights, RootMismatch, WrongBatchSize, WrongHeight,
+    ConflictingOpenings, EmptyBatch, IncompatibleHeights, RootMismatch, WrongBatchSize,
+    WrongCapSize, WrongHeight, WrongProofSize,
 };
+use crate::merkle_tree::{digest_layer_lens, injection};
 
 /// A Merkle Tree-based commitment scheme for multiple matrices of potentially differing heights.
 ///
 /// `MerkleTreeMmcs` generalizes a classical Merkle Tree to support committing to a list of
-/// matrices by arranging their rows into a unified binary tree. The tallest matrix defines
-/// the maximum height, and smaller matrices are integrated at appropriate depths.
+/// matrices by arranging their rows into a unified tree of arity `N`, binary by default. The
+/// tallest matrix defines the maximum height, and smaller matrices are integrated at appropriate
+/// depths.
 ///
 /// Type Parameters:
 /// - `P`: Packed leaf value (e.g. a field element or vector of elements)
@@ -48,14 +64,19 @@ use crate::MerkleTreeError::{
 /// - `H`: Cryptographic hash function (leaf hash)
 /// - `C`: Pseudo-compression function (internal node compression)
 /// - `DIGEST_ELEMS`: Number of elements in a single digest
+/// - `N`: Arity of the tree, a power of two
 #[derive(Copy, Clone, Debug)]
-pub struct MerkleTreeMmcs<P, PW, H, C, const DIGEST_ELEMS: usize> {
+pub struct MerkleTreeMmcs<P, PW, H, C, const DIGEST_ELEMS: usize, const N: usize = 2> {
     /// The hash function used to hash individual matrix rows (leaf level).
     hash: H,
 
     /// The compression function used to hash internal tree nodes.
     compress: C,
 
+    /// The height of the cap of the tree which is committed to in place of its root, see
+    /// [`MerkleTreeMmcs::with_cap_height`].
+    cap_height: usize,
+
     /// Phantom type to associate `P` and `PW` without storing values.
     _phantom: PhantomData<(P, PW)>,
 }
@@ -74,11 +95,14 @@ pub enum MerkleTreeError {
         /// Expected log2 of the maximum matrix height.
         log_max_height: usize,
 
+        /// Expected height of the cap, which the proof stops below.
+        cap_height: usize,
+
         /// Actual number of sibling hashes provided in the proof.
         num_siblings: usize,
     },
 
-    /// Matrix heights are incompatible; they cannot share a common binary Merkle tree.
+    /// Matrix heights are incompatible; they cannot share a common Merkle tree.
     IncompatibleHeights,
 
     /// The computed Merkle root does not match the provided commitment.
@@ -86,36 +110,141 @@ pub enum MerkleTreeError {
 
     /// Attempted to open an empty batch (no committed matrices).
     EmptyBatch,
+
+    /// A multi proof holds more or fewer sibling nodes than the opened indices need.
+    WrongProofSize,
+
+    /// Two opened indices share a row of some matrix, but their openings of it differ.
+    ConflictingOpenings,
+
+    /// The commitment does not hold as many digests as the cap of a tree of matrices of the
+    /// given dimensions.
+    WrongCapSize {
+        /// Expected number of digests in the cap.
+        expected: usize,
+
+        /// Actual number of digests in the commitment.
+        actual: usize,
+    },
 }
 
-impl<P, PW, H, C, const DIGEST_ELEMS: usize> MerkleTreeMmcs<P, PW, H, C, DIGEST_ELEMS> {
+impl<P, PW, H, C, const DIGEST_ELEMS: usize, const N: usize>
+    MerkleTreeMmcs<P, PW, H, C, DIGEST_ELEMS, N>
+{
+    /// The log of the arity of the tree.
+    const LOG_ARITY: usize = N.trailing_zeros() as usize;
+
     /// Create a new `MerkleTreeMmcs` with the given hash and compression functions.
+    ///
+    /// # Panics
+    /// If `N` is not a power of two, at least `2`.
     pub const fn new(hash: H, compress: C) -> Self {
+        assert!(
+            N >= 2 && N.is_power_of_two(),
+            "arity must be a power of two, at least 2"
+        );
         Self {
             hash,
             compress,
+            cap_height: 0,
             _phantom: PhantomData,
         }
     }
+
+    /// Commit to the cap of height `cap_height` of each tree, its layer of at most `N^cap_height`
+    /// digests, in place of its root, so that each opening proof has `cap_height` fewer layers of siblings.
+    ///
+    /// The cap of a tree is never higher than the layer into which the rows of its smallest
+    /// matrix are injected, so that the rows of every matrix are injected below the cap.
+    #[must_use]
+    pub const fn with_cap_height(mut self, cap_height: usize) -> Self {
+        self.cap_height = cap_height;
+        self
+    }
+
+    /// The height of the cap of a tree whose smallest matrix has padded height `2^log_min_height`.
+    fn tree_cap_height(&self, log_min_height: usize) -> usize {
+        self.cap_height
+            .min(log_min_height.div_ceil(Self::LOG_ARITY))
+    }
+
+    /// Checks that matrices of the given dimensions fit in one tree whose cap has `cap_len`
+    /// digests, and returns the log of its padded height, the height of its cap and, for each
+    /// layer from the leaves up to the cap, the indices of the matrices whose rows are hashed
+    /// into it.
+    fn layers(
+        &self,
+        dimensions: &[Dimensions],
+        cap_len: usize,
+    ) -> Result<(usize, usize, Vec<Vec<usize>>), MerkleTreeError> {
+        // Matrix heights that round up to the same power of two must be equal
+        if !dimensions
+            .iter()
+            .map(|dims| dims.height)
+            .sorted_by_key(|&height| Reverse(height))
+            .tuple_windows()
+            .all(|(curr, next)| {
+                curr == next || curr.next_power_of_two() != next.next_power_of_two()
+            })
+        {
+            return Err(IncompatibleHeights);
+        }
+
+        let (Some(max_height), Some(min_height)) = (
+            dimensions.iter().map(|dims| dims.height).max(),
+            dimensions.iter().map(|dims| dims.height).min(),
+        ) else {
+            return Err(EmptyBatch);
+        };
+        let log_max_height = log2_ceil_usize(max_height);
+        let cap_height = self.tree_cap_height(log2_ceil_usize(min_height));
+        let num_layers = log_max_height.div_ceil(Self::LOG_ARITY);
+
+        let layer_lens = digest_layer_lens::<N>(max_height);
+        let expected_cap_len = layer_lens[layer_lens.len() - 1 - cap_height];
+        if cap_len != expected_cap_len {
+            return Err(WrongCapSize {
+                expected: expected_cap_len,
+                actual: cap_len,
+            });
+        }
+
+        // The layer `i` has padded height `2^(log_max_height - i * LOG_ARITY)`, or `1` for the
+        // root, and the rows of each matrix must be injected into one of them.
+        let mut layer_matrices = vec![Vec::new(); num_layers - cap_height + 1];
+        for (i, dims) in dimensions.iter().enumerate() {
+            let log_height = log2_ceil_usize(dims.height);
+            let layer = if log_height == 0 {
+                num_layers
+            } else if (log_max_height - log_height).is_multiple_of(Self::LOG_ARITY) {
+                (log_max_height - log_height) / Self::LOG_ARITY
+            } else {
+                return Err(IncompatibleHeights);
+            };
+            layer_matrices[layer].push(i);
+        }
+        Ok((log_max_height, cap_height, layer_matrices))
+    }
 }
 
-impl<P, PW, H, C, const DIGEST_ELEMS: usize> Mmcs<P::Value>
-    for MerkleTreeMmcs<P, PW, H, C, DIGEST_ELEMS>
+impl<P, PW, H, C, const DIGEST_ELEMS: usize, const N: usize> Mmcs<P::Value>
+    for MerkleTreeMmcs<P, PW, H, C, DIGEST_ELEMS, N>
 where
     P: PackedValue,
     PW: PackedValue,
     H: CryptographicHasher<P::Value, [PW::Value; DIGEST_ELEMS]>
         + CryptographicHasher<P, [PW; DIGEST_ELEMS]>
         + Sync,
-    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], 2>
-        + PseudoCompressionFunction<[PW; DIGEST_ELEMS], 2>
+    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], N>
+        + PseudoCompressionFunction<[PW; DIGEST_ELEMS], N>
         + Sync,
     PW::Value: Eq,
     [PW::Value; DIGEST_ELEMS]: Serialize + for<'de> Deserialize<'de>,
 {
-    type ProverData<M> = MerkleTree<P::Value, PW::Value, M, DIGEST_ELEMS>;
-    type Commitment = Hash<P::Value, PW::Value, DIGEST_ELEMS>;
+    type ProverData<M> = MerkleTree<P::Value, PW::Value, M, DIGEST_ELEMS, N>;
+    type Commitment = MerkleCap<P::Value, PW::Value, DIGEST_ELEMS>;
     type Proof = Vec<[PW::Value; DIGEST_ELEMS]>;
+    type MultiProof = Vec<[PW::Value; DIGEST_ELEMS]>;
     type Error = MerkleTreeError;
 
     fn commit<M: Matrix<P::Value>>(
@@ -123,8 +252,8 @@ where
         inputs: Vec<M>,
     ) -> (Self::Commitment, Self::ProverData<M>) {
         let tree = MerkleTree::new::<P, PW, H, C>(&self.hash, &self.compress, inputs);
-        let root = tree.root();
-        (root, tree)
+        let cap = tree.cap(self.tree_cap_height(log_min_height(&tree.leaves)));
+        (cap, tree)
     }
 
     /// Opens a batch of rows from committed matrices.
@@ -133,30 +262,30 @@ where
     /// the `j`th row of the ith matrix `M[i]`, with
     ///     `j == index >> (log2_ceil(max_height) - log2_ceil(M[i].height))`
     /// and `proof` is the vector of sibling Merkle tree nodes allowing the verifier to
-    /// reconstruct the committed root.
+    /// reconstruct the committed node of the cap.
     fn open_batch<M: Matrix<P::Value>>(
         &self,
         index: usize,
-        prover_data: &MerkleTree<P::Value, PW::Value, M, DIGEST_ELEMS>,
+        prover_data: &MerkleTree<P::Value, PW::Value, M, DIGEST_ELEMS, N>,
     ) -> BatchOpening<P::Value, Self> {
         let max_height = self.get_max_height(prover_data);
         let log_max_height = log2_ceil_usize(max_height);
 
         // Get the matrix rows encountered along the path from the root to the given leaf index.
-        let openings = prover_data
-            .leaves
-            .iter()
-            .map(|matrix| {
-                let log2_height = log2_ceil_usize(matrix.height());
-                let bits_reduced = log_max_height - log2_height;
-                let reduced_index = index >> bits_reduced;
-                matrix.row(reduced_index).unwrap().into_iter().collect()
+        let openings = opened_rows(&prover_data.leaves, log_max_height, index);
+
+        // Get all the siblings nodes corresponding to the path from the cap to the given leaf index,
+        // the other `N - 1` children of its parent at each layer.
+        let cap_height = self.tree_cap_height(log_min_height(&prover_data.leaves));
+        let num_layers = prover_data.digest_layers.len() - 1;
+        let proof = (0..num_layers - cap_height)
+            .flat_map(|i| {
+                let node = index >> (i * Self::LOG_ARITY);
+                let first_sibling = node & !(N - 1);
+                (first_sibling..first_sibling + N)
+                    .filter(move |&j| j != node)
+                    .map(move |j| prover_data.digest_layers[i][j])
             })
-            .collect_vec();
-
-        // Get all the siblings nodes corresponding to the path from the root to the given leaf index.
-        let proof = (0..log_max_height)
-            .map(|i| prover_data.digest_layers[i][(index >> i) ^ 1])
             .collect();
 
         BatchOpening::new(openings, proof)
@@ -171,14 +300,15 @@ where
 
     /// Verifies an opened batch of rows with respect to a given commitment.
     ///
-    /// - `commit`: The merkle root of the tree.
+    /// - `commit`: The merkle cap of the tree, its root for a cap height of `0`.
     /// - `dimensions`: A vector of the dimensions of the matrices committed to.
     /// - `index`: The index of a leaf in the tree.
     /// - `opened_values`: A vector of matrix rows. Assume that the tallest matrix committed
     ///   to has height `2^n >= M_tall.height() > 2^{n - 1}` and the `j`th matrix has height
     ///   `2^m >= Mj.height() > 2^{m - 1}`. Then `j`'th value of opened values must be the row `Mj[index >> (m - n)]`.
-    /// - `proof`: A vector of sibling nodes. The `i`th element should be the node at level `i`
-    ///   with index `(index << i) ^ 1`.
+    /// - `proof`: A vector of sibling nodes. For each level `i`, up to the level below the cap, it
+    ///   holds the `N - 1` nodes at level `i` which share a parent with the node at index
+    ///   `index >> (i * log2(N))`, in order. For `N = 2` this is the node `(index >> i) ^ 1`.
     ///
     /// Returns nothing if the verification is successful, otherwise returns an error.
     fn verify_batch(
@@ -201,85 +331,182 @@ where
         //     }
         // }
 
-        let mut heights_tallest_first = dimensions
+        // Returns an error if either:
+        //              1. the matrix heights don't fit in one tree.
+        //              2. dimensions is empty.
+        //              3. the commitment is not a cap of the expected size.
+        //              4. proof.len() != (N - 1) * (num_layers - cap_height)
+        let (log_max_height, cap_height, layer_matrices) = self.layers(dimensions, commit.len())?;
+        if opening_proof.len() != (N - 1) * (layer_matrices.len() - 1) {
+            return Err(WrongHeight {
+                log_max_height,
+                cap_height,
+                num_siblings: opening_proof.len(),
+            });
+        }
+
+        // Hash all matrix openings of the given matrices.
+        let hash_openings = |matrices: &[usize]| {
+            self.hash
+                .hash_iter_slices(matrices.iter().map(|&i| opened_values[i].as_slice()))
+        };
+        let default_digest = [PW::Value::default(); DIGEST_ELEMS];
+
+        let mut root = hash_openings(&layer_matrices[0]);
+        for (siblings, matrices) in opening_proof.chunks_exact(N - 1).zip(&layer_matrices[1..]) {
+            // The last bits of index inform us of the position of the current node among its
+            // siblings. Combine them to get the parent node.
+            let position = index % N;
+            let children = array::from_fn(|j| match j.cmp(&position) {
+                Ordering::Less => siblings[j],
+                Ordering::Equal => root,
+                Ordering::Greater => siblings[j - 1],
+            });
+            root = self.compress.compress(children);
+            index >>= Self::LOG_ARITY;
+
+            // If there are new matrix rows, hash the rows together and then combine with the current root.
+            if !matrices.is_empty() {
+                root = self.compress.compress(injection(
+                    root,
+                    hash_openings(matrices),
+                    default_digest,
+                ));
+            }
+        }
+
+        // The computed node should equal the committed one in the cap.
+        if commit.as_ref().get(index) == Some(&root) {
+            Ok(())
+        } else {
+            Err(RootMismatch)
+        }
+    }
+
+    /// Opens the rows at several indices, as [`Self::open_batch`] does for each of them.
+    ///
+    /// The proof holds each sibling node which the verifier can't compute from the opened rows
+    /// exactly once, layer by layer from the leaves up to the cap, and by increasing index within
+    /// a layer.
+    fn open_multi_batch<M: Matrix<P::Value>>(
+        &self,
+        indices: &[usize],
+        prover_data: &MerkleTree<P::Value, PW::Value, M, DIGEST_ELEMS, N>,
+    ) -> MultiBatchOpening<P::Value, Self> {
+        let log_max_height = log2_ceil_usize(self.get_max_height(prover_data));
+
+        let openings = indices
             .iter()
-            .enumerate()
-            .sorted_by_key(|(_, dims)| Reverse(dims.height))
-            .peekable();
+            .map(|&index| opened_rows(&prover_data.leaves, log_max_height, index))
+            .collect();
 
-        // Matrix heights that round up to the same power of two must be equal
-        if !heights_tallest_first
-            .clone()
-            .map(|(_, dims)| dims.height)
-            .tuple_windows()
-            .all(|(curr, next)| {
-                curr == next || curr.next_power_of_two() != next.next_power_of_two()
-            })
+        // The nodes of the current layer which the verifier knows, starting with the opened leaves.
+        let mut known: BTreeSet<usize> = indices.iter().copied().collect();
+        let cap_height = self.tree_cap_height(log_min_height(&prover_data.leaves));
+        let num_layers = prover_data.digest_layers.len() - 1;
+        let mut proof = Vec::new();
+        for layer in &prover_data.digest_layers[..num_layers - cap_height] {
+            let parents: BTreeSet<usize> = known.iter().map(|&i| i >> Self::LOG_ARITY).collect();
+            proof.extend(
+                parents
+                    .iter()
+                    .flat_map(|&parent| parent * N..(parent + 1) * N)
+                    .filter(|i| !known.contains(i))
+                    .map(|i| layer[i]),
+            );
+            known = parents;
+        }
+
+        MultiBatchOpening::new(openings, proof)
+    }
+
+    /// Verifies the rows opened at several indices with respect to a given commitment.
+    ///
+    /// The arguments are as in [`Self::verify_batch`], with the opened values of each index in
+    /// the order of `indices`, and the proof as produced by [`Self::open_multi_batch`]. Indices
+    /// sharing a row of some matrix must open it to the same values.
+    fn verify_multi_batch(
+        &self,
+        commit: &Self::Commitment,
+        dimensions: &[Dimensions],
+        indices: &[usize],
+        batch_opening: MultiBatchOpeningRef<P::Value, Self>,
+    ) -> Result<(), Self::Error> {
+        let (opened_values, opening_proof) = batch_opening.unpack();
+        // Check that the openings have the correct shape.
+        if indices.len() != opened_values.len()
+            || opened_values
+                .iter()
+                .any(|rows| rows.len() != dimensions.len())
         {
-            return Err(IncompatibleHeights);
+            return Err(WrongBatchSize);
         }
 
-        // Get the initial height padded to a power of two. As heights_tallest_first is sorted,
-        // the initial height will be the maximum height.
-        // Returns an error if either:
-        //              1. proof.len() != log_max_height
-        //              2. heights_tallest_first is empty.
-        let mut curr_height_padded = match heights_tallest_first.peek() {
-            Some((_, dims)) => {
-                let max_height = dims.height.next_power_of_two();
-                let log_max_height = log2_strict_usize(max_height);
-                if opening_proof.len() != log_max_height {
-                    return Err(WrongHeight {
-                        log_max_height,
-                        num_siblings: opening_proof.len(),
-                    });
+        let (_, _, layer_matrices) = self.layers(dimensions, commit.len())?;
+        let default_digest = [PW::Value::default(); DIGEST_ELEMS];
+
+        // The known nodes of the current layer, by index.
+        let mut nodes = BTreeMap::new();
+        let mut siblings = opening_proof.iter();
+        for (layer, matrices) in layer_matrices.iter().enumerate() {
+            if layer > 0 {
+                // Combine the children of each parent of a known node, known or taken from the
+                // proof, to get the known nodes of the next layer.
+                let parents: BTreeSet<usize> =
+                    nodes.keys().map(|&i| i >> Self::LOG_ARITY).collect();
+                let mut parent_nodes = BTreeMap::new();
+                for parent in parents {
+                    let mut children = [default_digest; N];
+                    for (j, child) in children.iter_mut().enumerate() {
+                        *child = match nodes.get(&(parent * N + j)) {
+                            Some(&node) => node,
+                            None => *siblings.next().ok_or(WrongProofSize)?,
+                        };
+                    }
+                    parent_nodes.insert(parent, self.compress.compress(children));
                 }
-                max_height
+                nodes = parent_nodes;
             }
-            None => return Err(EmptyBatch),
-        };
 
-        // Hash all matrix openings at the current height.
-        let mut root = self.hash.hash_iter_slices(
-            heights_tallest_first
-                .peeking_take_while(|(_, dims)| {
-                    dims.height.next_power_of_two() == curr_height_padded
-                })
-                .map(|(i, _)| opened_values[i].as_slice()),
-        );
+            // Hash the opened rows of the matrices of this layer, checking that the indices
+            // sharing a node agree on them.
+            if matrices.is_empty() {
+                continue;
+            }
+            let mut digests = BTreeMap::new();
+            for (&index, rows) in indices.iter().zip(opened_values) {
+                let digest = self
+                    .hash
+                    .hash_iter_slices(matrices.iter().map(|&i| rows[i].as_slice()));
+                let i = index >> (layer * Self::LOG_ARITY);
+                if *digests.entry(i).or_insert(digest) != digest {
+                    return Err(ConflictingOpenings);
+                }
+            }
 
-        for &sibling in opening_proof {
-            // The last bit of index informs us whether the current node is on the left or right.
-            let (left, right) = if index & 1 == 0 {
-                (root, sibling)
+            nodes = if layer == 0 {
+                digests
             } else {
-                (sibling, root)
+                // If there are new matrix rows, combine their digests with the known nodes.
+                nodes
+                    .into_iter()
+                    .map(|(i, node)| {
+                        let digest = injection(node, digests[&i], default_digest);
+                        (i, self.compress.compress(digest))
+                    })
+                    .collect()
             };
+        }
 
-            // Combine the current node with the sibling node to get the parent node.
-            root = self.compress.compress([left, right]);
-            index >>= 1;
-            curr_height_padded >>= 1;
-
-            // Check if there are any new matrix rows to inject at the next height.
-            let next_height = heights_tallest_first
-                .peek()
-                .map(|(_, dims)| dims.height)
-                .filter(|h| h.next_power_of_two() == curr_height_padded);
-            if let Some(next_height) = next_height {
-                // If there are new matrix rows, hash the rows together and then combine with the current root.
-                let next_height_openings_digest = self.hash.hash_iter_slices(
-                    heights_tallest_first
-                        .peeking_take_while(|(_, dims)| dims.height == next_height)
-                        .map(|(i, _)| opened_values[i].as_slice()),
-                );
-
-                root = self.compress.compress([root, next_height_openings_digest]);
-            }
+        if siblings.next().is_some() {
+            return Err(WrongProofSize);
         }
 
-        // The computed root should equal the committed one.
-        if commit == &root {
+        // The computed nodes should equal the committed ones in the cap.
+        if nodes
+            .iter()
+            .all(|(&i, node)| commit.as_ref().get(i) == Some(node))
+        {
             Ok(())
         } else {
             Err(RootMismatch)
@@ -287,13 +514,35 @@ where
     }
 }
 
+/// The rows of `matrices` opened at `index`, for a tallest padded height of `2^log_max_height`.
+fn opened_rows<F: Clone + Send + Sync, M: Matrix<F>>(
+    matrices: &[M],
+    log_max_height: usize,
+    index: usize,
+) -> Vec<Vec<F>> {
+    matrices
+        .iter()
+        .map(|matrix| {
+            let log2_height = log2_ceil_usize(matrix.height());
+            let bits_reduced = log_max_height - log2_height;
+            let reduced_index = index >> bits_reduced;
+            matrix.row(reduced_index).unwrap().into_iter().collect()
+        })
+        .collect()
+}
+
+/// The log of the padded height of the smallest of `matrices`.
+fn log_min_height<F: Clone + Send + Sync, M: Matrix<F>>(matrices: &[M]) -> usize {
+    log2_ceil_usize(matrices.iter().map(|m| m.height()).min().unwrap())
+}
+
 #[cfg(test)]
 mod tests {
     use alloc::vec;
 
     use itertools::Itertools;
     use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
-    use p3_commit::Mmcs;
+    use p3_commit::{BatchOpeningRef, Mmcs};
     use p3_field::{Field, PrimeCharacteristicRing};
     use p3_matrix::dense::RowMajorMatrix;
     use p3_matrix::{Dimensions, Matrix};
@@ -304,6 +553,7 @@ mod tests {
     use rand::rngs::SmallRng;
 
     use super::MerkleTreeMmcs;
+    use crate::{MerkleTree, MerkleTreeError};
 
     type F = BabyBear;
 
@@ -313,6 +563,11 @@ mod tests {
     type MyMmcs =
         MerkleTreeMmcs<<F as Field>::Packing, <F as Field>::Packing, MyHash, MyCompress, 8>;
 
+    type MyHash4 = PaddingFreeSponge<Perm, 16, 8, 4>;
+    type MyCompress4 = TruncatedPermutation<Perm, 4, 4, 16>;
+    type MyMmcs4 =
+        MerkleTreeMmcs<<F as Field>::Packing, <F as Field>::Packing, MyHash4, MyCompress4, 4, 4>;
+
     #[test]
     fn commit_single_1x8() {
         let mut rng = SmallRng::seed_from_u64(1);
@@ -344,7 +599,7 @@ mod tests {
                 compress.compress([hash.hash_item(v[6]), hash.hash_item(v[7])]),
             ]),
         ]);
-        assert_eq!(commit, expected_result);
+        assert_eq!(commit.as_ref(), [expected_result]);
     }
 
     #[test]
@@ -359,7 +614,7 @@ mod tests {
         let (commit, _) = mmcs.commit(vec![mat.clone()]);
 
         let expected_result = hash.hash_iter(mat.vertically_packed_row(0));
-        assert_eq!(commit, expected_result);
+        assert_eq!(commit.as_ref(), [expected_result]);
     }
 
     #[test]
@@ -382,7 +637,7 @@ mod tests {
             hash.hash_slice(&[F::ZERO, F::ONE]),
             hash.hash_slice(&[F::TWO, F::ONE]),
         ]);
-        assert_eq!(commit, expected_result);
+        assert_eq!(commit.as_ref(), [expected_result]);
     }
 
     #[test]
@@ -410,7 +665,7 @@ mod tests {
             ]),
             compress.compress([hash.hash_slice(&[F::TWO, F::TWO]), default_digest]),
         ]);
-        assert_eq!(commit, expected_result);
+        assert_eq!(commit.as_ref(), [expected_result]);
     }
 
     #[test]
@@ -499,7 +754,7 @@ mod tests {
             ]),
         ]);
 
-        assert_eq!(commit, expected_result);
+        assert_eq!(commit.as_ref(), [expected_result]);
 
         let (opened_values, _) = mmcs.open_batch(2, &prover_data).unpack();
         assert_eq!(
@@ -649,4 +904,424 @@ mod tests {
         mmcs.verify_batch(&commit, &dims, 17, (&batch_opening).into())
             .expect("expected verification to succeed");
     }
+
+    #[test]
+    fn cap() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash::new(perm.clone());
+        let compress = MyCompress::new(perm);
+        let mmcs = MyMmcs::new(hash, compress);
+        let capped_mmcs = mmcs.clone().with_cap_height(2);
+
+        // A mat with 64 rows and one with 8 rows, injected below the cap.
+        let mats = vec![
+            RowMajorMatrix::<F>::rand(&mut rng, 64, 3),
+            RowMajorMatrix::<F>::rand(&mut rng, 8, 5),
+        ];
+        let dims = mats.iter().map(|m| m.dimensions()).collect_vec();
+
+        let (commit, prover_data) = mmcs.commit(mats.clone());
+        let (cap, capped_prover_data) = capped_mmcs.commit(mats);
+        assert_eq!(cap.len(), 4);
+        assert_eq!(cap.as_ref(), prover_data.cap(2).as_ref());
+        assert_eq!(commit, prover_data.root().into());
+
+        for index in [0, 37, 63] {
+            let batch_opening = mmcs.open_batch(index, &prover_data);
+            let capped_batch_opening = capped_mmcs.open_batch(index, &capped_prover_data);
+            assert_eq!(
+                batch_opening.opened_values,
+                capped_batch_opening.opened_values
+            );
+            assert_eq!(
+                capped_batch_opening.opening_proof,
+                batch_opening.opening_proof[..4]
+            );
+            capped_mmcs
+                .verify_batch(&cap, &dims, index, (&capped_batch_opening).into())
+                .expect("expected verification to succeed");
+
+            // The proof must stop below the cap.
+            mmcs.verify_batch(&cap, &dims, index, (&batch_opening).into())
+                .expect_err("expected verification to fail");
+        }
+
+        // A cap with a digest more or less than the tree's cap is rejected.
+        let mut digests = cap.as_ref().to_vec();
+        digests.push(digests[0]);
+        let long_cap = digests.clone().into();
+        digests.truncate(3);
+        let short_cap = digests.into();
+        let capped_batch_opening = capped_mmcs.open_batch(5, &capped_prover_data);
+        for (bad_cap, len) in [(long_cap, 5), (short_cap, 3)] {
+            assert!(matches!(
+                capped_mmcs.verify_batch(&bad_cap, &dims, 5, (&capped_batch_opening).into()),
+                Err(MerkleTreeError::WrongCapSize {
+                    expected: 4,
+                    actual
+                }) if actual == len
+            ));
+            let multi_opening = capped_mmcs.open_multi_batch(&[5, 40], &capped_prover_data);
+            assert!(matches!(
+                capped_mmcs.verify_multi_batch(&bad_cap, &dims, &[5, 40], (&multi_opening).into()),
+                Err(MerkleTreeError::WrongCapSize { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn cap_lowered_by_smallest_matrix() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash::new(perm.clone());
+        let compress = MyCompress::new(perm);
+        let mmcs = MyMmcs::new(hash, compress).with_cap_height(3);
+
+        // The rows of the mat with 2 rows are injected two layers below the root, so the cap
+        // can be at most 1 layer below it.
+        let mats = vec![
+            RowMajorMatrix::<F>::rand(&mut rng, 16, 3),
+            RowMajorMatrix::<F>::rand(&mut rng, 2, 5),
+        ];
+        let dims = mats.iter().map(|m| m.dimensions()).collect_vec();
+
+        let (cap, prover_data) = mmcs.commit(mats);
+        assert_eq!(cap.len(), 2);
+
+        let batch_opening = mmcs.open_batch(11, &prover_data);
+        assert_eq!(batch_opening.opening_proof.len(), 3);
+        mmcs.verify_batch(&cap, &dims, 11, (&batch_opening).into())
+            .expect("expected verification to succeed");
+    }
+
+    #[test]
+    fn multi_batch() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash::new(perm.clone());
+        let compress = MyCompress::new(perm);
+        let mmcs = MyMmcs::new(hash, compress);
+
+        // A mat with 8 rows and one with 2 rows, as in the module docs.
+        let mats = vec![
+            RowMajorMatrix::<F>::rand(&mut rng, 8, 3),
+            RowMajorMatrix::<F>::rand(&mut rng, 2, 5),
+        ];
+        let dims = mats.iter().map(|m| m.dimensions()).collect_vec();
+        let (commit, prover_data) = mmcs.commit(mats);
+
+        // The proof for indices 4 and 5 is `c23, c10`, and index 5 may be opened twice.
+        let indices = [5, 4, 5];
+        let opening = mmcs.open_multi_batch(&indices, &prover_data);
+        let proof_5 = mmcs.open_batch(5, &prover_data).opening_proof;
+        assert_eq!(opening.opening_proof, proof_5[1..]);
+        for (&index, opened_values) in indices.iter().zip(&opening.opened_values) {
+            let batch_opening = mmcs.open_batch(index, &prover_data);
+            assert_eq!(opened_values, &batch_opening.opened_values);
+        }
+        mmcs.verify_multi_batch(&commit, &dims, &indices, (&opening).into())
+            .expect("expected verification to succeed");
+
+        // No index at all needs no proof.
+        let opening = mmcs.open_multi_batch(&[], &prover_data);
+        assert!(opening.opening_proof.is_empty());
+        mmcs.verify_multi_batch(&commit, &dims, &[], (&opening).into())
+            .expect("expected verification to succeed");
+    }
+
+    #[test]
+    fn multi_batch_size_gaps_and_cap() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash::new(perm.clone());
+        let compress = MyCompress::new(perm);
+        let mmcs = MyMmcs::new(hash, compress).with_cap_height(2);
+
+        let mats = vec![
+            RowMajorMatrix::<F>::rand(&mut rng, 1000, 8),
+            RowMajorMatrix::<F>::rand(&mut rng, 70, 4),
+            RowMajorMatrix::<F>::rand(&mut rng, 1000, 2),
+            RowMajorMatrix::<F>::rand(&mut rng, 8, 1),
+        ];
+        let dims = mats.iter().map(|m| m.dimensions()).collect_vec();
+        let (cap, prover_data) = mmcs.commit(mats);
+
+        let indices = [6, 559, 7, 512, 300, 6, 0];
+        let opening = mmcs.open_multi_batch(&indices, &prover_data);
+        let num_siblings = indices
+            .iter()
+            .map(|&index| mmcs.open_batch(index, &prover_data).opening_proof.len())
+            .sum::<usize>();
+        assert!(opening.opening_proof.len() < num_siblings);
+        mmcs.verify_multi_batch(&cap, &dims, &indices, (&opening).into())
+            .expect("expected verification to succeed");
+    }
+
+    #[test]
+    fn multi_batch_tampered_fails() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash::new(perm.clone());
+        let compress = MyCompress::new(perm);
+        let mmcs = MyMmcs::new(hash, compress);
+
+        let mats = vec![
+            RowMajorMatrix::<F>::rand(&mut rng, 32, 3),
+            RowMajorMatrix::<F>::rand(&mut rng, 8, 2),
+        ];
+        let dims = mats.iter().map(|m| m.dimensions()).collect_vec();
+        let (commit, prover_data) = mmcs.commit(mats);
+
+        let indices = [3, 17, 16, 2];
+        let opening = mmcs.open_multi_batch(&indices, &prover_data);
+        mmcs.verify_multi_batch(&commit, &dims, &indices, (&opening).into())
+            .expect("expected verification to succeed");
+
+        let mut tampered = opening.clone();
+        tampered.opening_proof[0][0] += F::ONE;
+        mmcs.verify_multi_batch(&commit, &dims, &indices, (&tampered).into())
+            .expect_err("expected verification to fail");
+
+        let mut tampered = opening.clone();
+        tampered.opened_values[1][0][2] += F::ONE;
+        mmcs.verify_multi_batch(&commit, &dims, &indices, (&tampered).into())
+            .expect_err("expected verification to fail");
+
+        // Indices 3 and 2 share the row 0 of the small mat, so they must agree on it.
+        let mut tampered = opening.clone();
+        tampered.opened_values[0][1][0] += F::ONE;
+        assert!(matches!(
+            mmcs.verify_multi_batch(&commit, &dims, &indices, (&tampered).into()),
+            Err(MerkleTreeError::ConflictingOpenings)
+        ));
+
+        let mut tampered = opening;
+        tampered.opening_proof.push(tampered.opening_proof[0]);
+        assert!(matches!(
+            mmcs.verify_multi_batch(&commit, &dims, &indices, (&tampered).into()),
+            Err(MerkleTreeError::WrongProofSize)
+        ));
+
+        mmcs.verify_multi_batch(&commit, &dims, &[3, 17, 16, 1], (&tampered).into())
+            .expect_err("expected verification to fail");
+    }
+
+    #[test]
+    fn arity_4_commit_mixed() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash4::new(perm.clone());
+        let compress = MyCompress4::new(perm);
+        let mmcs = MyMmcs4::new(hash.clone(), compress.clone());
+
+        let large_mat = RowMajorMatrix::<F>::rand(&mut rng, 16, 3);
+        let small_mat = RowMajorMatrix::<F>::rand(&mut rng, 4, 2);
+        let (commit, _) = mmcs.commit(vec![large_mat.clone(), small_mat.clone()]);
+
+        // The rows of the small mat are injected into the layer of 4 nodes, compressed together
+        // with the node and 2 default digests.
+        let default_digest = [F::ZERO; 4];
+        let layer = (0..4)
+            .map(|i| {
+                let node = compress.compress(core::array::from_fn(|j| {
+                    hash.hash_iter(large_mat.row(4 * i + j).unwrap())
+                }));
+                let rows_digest = hash.hash_iter(small_mat.row(i).unwrap());
+                compress.compress([node, rows_digest, default_digest, default_digest])
+            })
+            .collect_vec();
+        let expected_result = compress.compress(layer.try_into().unwrap());
+        assert_eq!(commit.as_ref(), [expected_result]);
+    }
+
+    #[test]
+    fn arity_4_open_and_verify() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash4::new(perm.clone());
+        let compress = MyCompress4::new(perm);
+
+        // The padded heights are the tallest one divided by powers of 4, or 1.
+        let mats = [
+            RowMajorMatrix::<F>::rand(&mut rng, 30, 3),
+            RowMajorMatrix::<F>::rand(&mut rng, 8, 5),
+            RowMajorMatrix::<F>::rand(&mut rng, 2, 1),
+            RowMajorMatrix::<F>::rand(&mut rng, 1, 2),
+        ];
+
+        // The mat with 1 row is injected into the root, so the cap can't be any higher.
+        for (num_mats, cap_height) in [(3, 0), (3, 1), (4, 0), (4, 1)] {
+            let mats = mats[..num_mats].to_vec();
+            let dims = mats.iter().map(|m| m.dimensions()).collect_vec();
+            let mmcs = MyMmcs4::new(hash.clone(), compress.clone()).with_cap_height(cap_height);
+            let (commit, prover_data) = mmcs.commit(mats);
+            let cap_height = if num_mats == 4 { 0 } else { cap_height };
+            assert_eq!(commit.len(), [1, 4][cap_height]);
+
+            // The layers have 32, 8, 2 and 1 nodes, with 3 siblings per layer below the cap.
+            let indices = [0, 7, 29, 12, 6];
+            for &index in &indices {
+                let batch_opening = mmcs.open_batch(index, &prover_data);
+                assert_eq!(batch_opening.opening_proof.len(), 3 * (3 - cap_height));
+                mmcs.verify_batch(&commit, &dims, index, (&batch_opening).into())
+                    .expect("expected verification to succeed");
+            }
+
+            let opening = mmcs.open_multi_batch(&indices, &prover_data);
+            mmcs.verify_multi_batch(&commit, &dims, &indices, (&opening).into())
+                .expect("expected verification to succeed");
+
+            let mut tampered = mmcs.open_batch(12, &prover_data);
+            tampered.opening_proof[4][0] += F::ONE;
+            mmcs.verify_batch(&commit, &dims, 12, (&tampered).into())
+                .expect_err("expected verification to fail");
+        }
+    }
+
+    #[test]
+    fn arity_4_incompatible_heights() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash4::new(perm.clone());
+        let compress = MyCompress4::new(perm);
+        let mmcs = MyMmcs4::new(hash, compress);
+
+        // A mat with 8 rows can't be injected into a tree whose layers have 16, 4 and 1 nodes.
+        let dims = [16, 8].map(|height| Dimensions { width: 1, height });
+        let (commit, prover_data) = mmcs.commit(vec![RowMajorMatrix::<F>::rand(&mut rng, 16, 1)]);
+        let batch_opening = mmcs.open_batch(3, &prover_data);
+        let opened_values = vec![batch_opening.opened_values[0].clone(); 2];
+        assert!(matches!(
+            mmcs.verify_batch(
+                &commit,
+                &dims,
+                3,
+                BatchOpeningRef::new(&opened_values, &batch_opening.opening_proof)
+            ),
+            Err(MerkleTreeError::IncompatibleHeights)
+        ));
+    }
+
+    #[test]
+    #[should_panic]
+    fn arity_4_mismatched_heights() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash4::new(perm.clone());
+        let compress = MyCompress4::new(perm);
+        let mmcs = MyMmcs4::new(hash, compress);
+
+        let _ = mmcs.commit(vec![
+            RowMajorMatrix::<F>::rand(&mut rng, 16, 1),
+            RowMajorMatrix::<F>::rand(&mut rng, 8, 1),
+        ]);
+    }
+
+    #[test]
+    fn prover_data_serde_round_trip() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash::new(perm.clone());
+        let compress = MyCompress::new(perm);
+        let mmcs = MyMmcs::new(hash, compress).with_cap_height(1);
+
+        let mats = vec![
+            RowMajorMatrix::<F>::rand(&mut rng, 32, 3),
+            RowMajorMatrix::<F>::rand(&mut rng, 7, 2),
+        ];
+        let dims = mats.iter().map(|m| m.dimensions()).collect_vec();
+        let (commit, prover_data) = mmcs.commit(mats);
+
+        let bytes = postcard::to_allocvec(&prover_data).expect("unable to serialize");
+        drop(prover_data);
+        let prover_data: MerkleTree<F, F, RowMajorMatrix<F>, 8> =
+            postcard::from_bytes(&bytes).expect("unable to deserialize");
+
+        for index in [0, 13, 27] {
+            let opening = mmcs.open_batch(index, &prover_data);
+            mmcs.verify_batch(&commit, &dims, index, (&opening).into())
+                .expect("expected verification to succeed");
+        }
+    }
+
+    #[test]
+    fn prover_data_from_parts() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash4::new(perm.clone());
+        let compress = MyCompress4::new(perm);
+        let mmcs = MyMmcs4::new(hash, compress);
+
+        let mats = vec![
+            RowMajorMatrix::<F>::rand(&mut rng, 64, 3),
+            RowMajorMatrix::<F>::rand(&mut rng, 13, 2),
+        ];
+        let dims = mats.iter().map(|m| m.dimensions()).collect_vec();
+        let (commit, prover_data) = mmcs.commit(mats.clone());
+
+        let digest_layers = prover_data.digest_layers().to_vec();
+        let reassembled = MerkleTree::from_parts(mats, digest_layers);
+        assert_eq!(reassembled.root(), prover_data.root());
+
+        let indices = [5, 51, 6, 40];
+        let opening = mmcs.open_multi_batch(&indices, &reassembled);
+        assert_eq!(
+            opening.opening_proof,
+            mmcs.open_multi_batch(&indices, &prover_data).opening_proof
+        );
+        mmcs.verify_multi_batch(&commit, &dims, &indices, (&opening).into())
+            .expect("expected verification to succeed");
+    }
+
+    #[test]
+    #[should_panic]
+    fn prover_data_from_parts_wrong_leaves() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash::new(perm.clone());
+        let compress = MyCompress::new(perm);
+        let mmcs = MyMmcs::new(hash, compress);
+
+        let (_, prover_data) = mmcs.commit(vec![RowMajorMatrix::<F>::rand(&mut rng, 32, 3)]);
+        let _: MerkleTree<F, F, _, 8> = MerkleTree::from_parts(
+            vec![RowMajorMatrix::<F>::rand(&mut rng, 16, 3)],
+            prover_data.digest_layers().to_vec(),
+        );
+    }
+
+    #[test]
+    fn commit_mmap_matrix() {
+        extern crate std;
+
+        use alloc::format;
+
+        use p3_matrix::mmap::{MmapMatrix, MmapStorage};
+
+        let mut rng = SmallRng::seed_from_u64(1);
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash::new(perm.clone());
+        let compress = MyCompress::new(perm);
+        let mmcs = MyMmcs::new(hash, compress);
+
+        let mat = RowMajorMatrix::<F>::rand(&mut rng, 40, 5);
+        let path = std::env::temp_dir().join(format!("p3-merkle-mmap-{}", std::process::id()));
+        let mut mmap_mat = MmapMatrix::new(unsafe { MmapStorage::create(&path, 200).unwrap() }, 5);
+        mmap_mat.copy_from(&mat);
+
+        let (commit, _) = mmcs.commit_matrix(mat.clone());
+        let (mmap_commit, prover_data) = mmcs.commit_matrix(mmap_mat);
+        assert_eq!(mmap_commit, commit);
+
+        let opening = mmcs.open_batch(17, &prover_data);
+        assert_eq!(
+            opening.opened_values[0],
+            mat.row_slice(17).unwrap().to_vec()
+        );
+        mmcs.verify_batch(&commit, &[mat.dimensions()], 17, (&opening).into())
+            .expect("expected verification to succeed");
+
+        drop(prover_data);
+        std::fs::remove_file(&path).unwrap();
+    }
 }
@@ -113,7 +113,7 @@ fn prove_and_verify() -> Result<(), impl Debug> {
     type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
     let config = MyConfig::new(pcs, challenger);
 
-    let proof = prove(&config, &air, trace, &vec![]);
+    let proof = prove(&config, &air, trace, &vec![]).expect("proving failed");
 
     verify(&config, &air, &proof, &vec![])
 }
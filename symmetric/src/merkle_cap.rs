@@ -0,0 +1,68 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Hash;
+
+/// A layer of digests of a Merkle tree, which commits to the tree in place of its root.
+///
+/// A cap of height `k` of a tree of arity `N` holds the at most `N^k` digests `k` layers below
+/// the root, so that openings need `k` fewer layers of siblings. A cap of height `0` is the root
+/// itself.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(serialize = "[W; DIGEST_ELEMS]: Serialize"))]
+#[serde(bound(deserialize = "[W; DIGEST_ELEMS]: Deserialize<'de>"))]
+pub struct MerkleCap<F, W, const DIGEST_ELEMS: usize> {
+    digests: Vec<[W; DIGEST_ELEMS]>,
+    _marker: PhantomData<F>,
+}
+
+impl<F, W, const DIGEST_ELEMS: usize> MerkleCap<F, W, DIGEST_ELEMS> {
+    /// The number of digests in the cap.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.digests.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.digests.is_empty()
+    }
+}
+
+impl<F, W, const DIGEST_ELEMS: usize> From<Vec<[W; DIGEST_ELEMS]>>
+    for MerkleCap<F, W, DIGEST_ELEMS>
+{
+    fn from(digests: Vec<[W; DIGEST_ELEMS]>) -> Self {
+        Self {
+            digests,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, W, const DIGEST_ELEMS: usize> From<Hash<F, W, DIGEST_ELEMS>>
+    for MerkleCap<F, W, DIGEST_ELEMS>
+{
+    fn from(root: Hash<F, W, DIGEST_ELEMS>) -> Self {
+        vec![root.into()].into()
+    }
+}
+
+impl<F, W, const DIGEST_ELEMS: usize> IntoIterator for MerkleCap<F, W, DIGEST_ELEMS> {
+    type Item = Hash<F, W, DIGEST_ELEMS>;
+    type IntoIter =
+        core::iter::Map<vec::IntoIter<[W; DIGEST_ELEMS]>, fn([W; DIGEST_ELEMS]) -> Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.digests.into_iter().map(Hash::from)
+    }
+}
+
+impl<F, W, const DIGEST_ELEMS: usize> AsRef<[[W; DIGEST_ELEMS]]> for MerkleCap<F, W, DIGEST_ELEMS> {
+    fn as_ref(&self) -> &[[W; DIGEST_ELEMS]] {
+        &self.digests
+    }
+}
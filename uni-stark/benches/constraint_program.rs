@@ -0,0 +1,130 @@
+use core::array;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use p3_air::{Air, BaseAir};
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::DuplexChallenger;
+use p3_commit::ExtensionMmcs;
+use p3_dft::Radix2DitParallel;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{BasedVectorSpace, Field, PackedValue, PrimeCharacteristicRing};
+use p3_fri::TwoAdicFriPcs;
+use p3_keccak_air::KeccakAir;
+use p3_matrix::dense::RowMajorMatrixView;
+use p3_merkle_tree::MerkleTreeMmcs;
+use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+use p3_uni_stark::{
+    ConstraintInputs, ConstraintProgram, PackedChallenge, PackedVal, ProverConstraintFolder,
+    Registers, StarkConfig, get_symbolic_constraints,
+};
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+type Val = BabyBear;
+type Perm = Poseidon2BabyBear<16>;
+type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+type ValMmcs =
+    MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+type Challenge = BinomialExtensionField<Val, 4>;
+type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+type Dft = Radix2DitParallel<Val>;
+type Pcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+
+/// The number of packed row pairs on which the constraints are evaluated per iteration.
+const NUM_ROWS: usize = 64;
+
+/// The number of packed row pairs a `ConstraintProgram` is evaluated on at once.
+const ROWS_PER_EVAL: usize = 8;
+
+/// Compare evaluating the Keccak constraints through `Air::eval` with evaluating them through a
+/// compiled `ConstraintProgram`, as done when computing the quotient.
+fn bench_keccak_constraints(c: &mut Criterion) {
+    let air = KeccakAir {};
+    let width = <KeccakAir as BaseAir<Val>>::width(&air);
+    let mut rng = SmallRng::seed_from_u64(1);
+    let rows: Vec<Vec<PackedVal<MyConfig>>> = (0..NUM_ROWS)
+        .map(|_| {
+            (0..2 * width)
+                .map(|_| PackedVal::<MyConfig>::from_fn(|_| rng.random()))
+                .collect()
+        })
+        .collect();
+    let selectors = PackedVal::<MyConfig>::from_fn(|_| rng.random());
+
+    let constraints = get_symbolic_constraints::<Val, _>(&air, 0, 0);
+    let program = ConstraintProgram::new(&constraints);
+    let alpha: Challenge = rng.random();
+    let alpha_powers = alpha.powers().collect_n(constraints.len());
+    let decomposed_alpha_powers: Vec<Vec<Val>> = (0
+        ..<Challenge as BasedVectorSpace<Val>>::DIMENSION)
+        .map(|i| {
+            alpha_powers
+                .iter()
+                .map(|x| x.as_basis_coefficients_slice()[i])
+                .collect()
+        })
+        .collect();
+    let public_values = vec![];
+    let empty = RowMajorMatrixView::new(&[], 0);
+    let empty_ext = RowMajorMatrixView::new(&[], 0);
+
+    let mut group = c.benchmark_group("keccak constraints");
+    group.sample_size(10);
+    group.bench_function("Air::eval", |b| {
+        b.iter(|| {
+            rows.iter()
+                .map(|row| {
+                    let mut folder = ProverConstraintFolder::<MyConfig> {
+                        main: RowMajorMatrixView::new(row, width),
+                        preprocessed: empty,
+                        public_values: &public_values,
+                        is_first_row: selectors,
+                        is_last_row: selectors,
+                        is_transition: selectors,
+                        alpha_powers: &alpha_powers,
+                        decomposed_alpha_powers: &decomposed_alpha_powers,
+                        accumulator: PackedChallenge::<MyConfig>::ZERO,
+                        constraint_index: 0,
+                    };
+                    air.eval(&mut folder);
+                    folder.accumulator
+                })
+                .sum::<PackedChallenge<MyConfig>>()
+        })
+    });
+    group.bench_function("ConstraintProgram::eval", |b| {
+        let mut registers = Registers::default();
+        b.iter(|| {
+            rows.chunks_exact(ROWS_PER_EVAL)
+                .flat_map(|rows| {
+                    let inputs: [_; ROWS_PER_EVAL] = array::from_fn(|j| ConstraintInputs {
+                        main: RowMajorMatrixView::new(&rows[j], width),
+                        preprocessed: empty,
+                        padding: empty,
+                        permutation: empty_ext,
+                        phases: &[],
+                        public_values: &public_values,
+                        challenges: &[],
+                        phase_challenges: &[],
+                        is_first_row: selectors,
+                        is_last_row: selectors,
+                        is_transition: selectors,
+                        is_transition_windows: &[],
+                    });
+                    program.eval::<Challenge, _, PackedChallenge<MyConfig>, ROWS_PER_EVAL>(
+                        &inputs,
+                        &alpha_powers,
+                        &mut registers,
+                    )
+                })
+                .sum::<PackedChallenge<MyConfig>>()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_keccak_constraints);
+criterion_main!(benches);
@@ -1,53 +1,292 @@
 use alloc::vec::Vec;
+use core::fmt;
+use core::panic::Location;
 
-use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues};
-use p3_field::Field;
+use p3_air::{
+    Air, AirBuilder, AirBuilderWithPublicValues, ExtensionBuilder, InteractionBuilder,
+    MultiPhaseAirBuilder, PairBuilder,
+};
+use p3_field::{ExtensionField, Field};
 use p3_matrix::Matrix;
 use p3_matrix::dense::{RowMajorMatrix, RowMajorMatrixView};
-use p3_matrix::stack::{VerticalPair, ViewPair};
+use p3_maybe_rayon::prelude::*;
 use tracing::instrument;
 
+use crate::ProvingError;
+use crate::folder::transition_window;
+
 /// Runs constraint checks using a given AIR definition and trace matrix.
 ///
-/// Iterates over every row in `main`, providing both the current and next row
+/// Iterates over every row in `main`, providing the window of rows starting at it
 /// (with wraparound) to the AIR logic. Also injects public values into the builder
 /// for first/last row assertions.
 ///
+/// Returns [`ProvingError::UnsatisfiedConstraint`] for the first constraint which does not
+/// vanish, on the first row on which any constraint does not vanish.
+///
 /// # Arguments
 /// - `air`: The AIR logic to run
 /// - `main`: The trace matrix (rows of witness values)
+/// - `preprocessed`: The preprocessed trace matrix, if the AIR has one
 /// - `public_values`: Public values provided to the builder
 #[instrument(name = "check constraints", skip_all)]
-pub(crate) fn check_constraints<F, A>(air: &A, main: &RowMajorMatrix<F>, public_values: &Vec<F>)
+pub(crate) fn check_constraints<F, A>(
+    air: &A,
+    main: &RowMajorMatrix<F>,
+    preprocessed: Option<&RowMajorMatrix<F>>,
+    public_values: &Vec<F>,
+) -> Result<(), ProvingError>
+where
+    F: Field,
+    A: for<'a> Air<DebugConstraintBuilder<'a, F>>,
+{
+    check_constraints_with_phases::<F, F, A>(air, main, preprocessed, None, &[], &[], public_values)
+}
+
+/// Like [`check_constraints`], for an AIR which may also pad its trace and read
+/// challenge-dependent trace phases.
+///
+/// `padding` holds the padding columns of a padded trace, `phases` holds the trace of each phase,
+/// and `phase_challenges` the challenges sampled before each phase.
+#[instrument(name = "check constraints", skip_all)]
+pub(crate) fn check_constraints_with_phases<F, EF, A>(
+    air: &A,
+    main: &RowMajorMatrix<F>,
+    preprocessed: Option<&RowMajorMatrix<F>>,
+    padding: Option<&RowMajorMatrix<F>>,
+    phases: &[RowMajorMatrix<EF>],
+    phase_challenges: &[Vec<EF>],
+    public_values: &Vec<F>,
+) -> Result<(), ProvingError>
+where
+    F: Field,
+    EF: ExtensionField<F>,
+    A: for<'a> Air<DebugConstraintBuilder<'a, F, EF>>,
+{
+    let failures = find_failures(
+        air,
+        main,
+        preprocessed,
+        padding,
+        phases,
+        phase_challenges,
+        public_values,
+        true,
+    );
+    failures.first().map_or(Ok(()), |failure| {
+        Err(ProvingError::UnsatisfiedConstraint {
+            row: failure.row,
+            constraint: failure.constraint,
+        })
+    })
+}
+
+/// Evaluate the constraints of `air` on every row of `main` and report every constraint which
+/// does not vanish.
+///
+/// Unlike the check run by the prover, this does not stop at the first failure, which makes it
+/// suitable for debugging an AIR or its trace generation in tests and CI. Constraints are indexed
+/// in the order in which the AIR asserts them, and carry the label most recently set through
+/// [`AirBuilder::label_constraints`], if any.
+#[instrument(name = "report constraint failures", skip_all)]
+pub fn constraint_report<F, A>(
+    air: &A,
+    main: &RowMajorMatrix<F>,
+    preprocessed: Option<&RowMajorMatrix<F>>,
+    public_values: &Vec<F>,
+) -> ConstraintReport<F>
 where
     F: Field,
     A: for<'a> Air<DebugConstraintBuilder<'a, F>>,
+{
+    ConstraintReport {
+        height: main.height(),
+        failures: find_failures(
+            air,
+            main,
+            preprocessed,
+            None,
+            &[],
+            &[],
+            public_values,
+            false,
+        ),
+    }
+}
+
+/// Evaluate the constraints of `air` row by row, collecting the constraints which do not vanish.
+///
+/// If `stop_at_first` is set, only the failures of the first row with any failure are returned.
+#[allow(clippy::too_many_arguments)]
+fn find_failures<F, EF, A>(
+    air: &A,
+    main: &RowMajorMatrix<F>,
+    preprocessed: Option<&RowMajorMatrix<F>>,
+    padding: Option<&RowMajorMatrix<F>>,
+    phases: &[RowMajorMatrix<EF>],
+    phase_challenges: &[Vec<EF>],
+    public_values: &Vec<F>,
+    stop_at_first: bool,
+) -> Vec<ConstraintFailure<EF>>
+where
+    F: Field,
+    EF: ExtensionField<F>,
+    A: for<'a> Air<DebugConstraintBuilder<'a, F, EF>>,
 {
     let height = main.height();
+    if let Some(preprocessed) = preprocessed {
+        assert_eq!(
+            preprocessed.height(),
+            height,
+            "preprocessed trace height must equal the trace height"
+        );
+    }
+    for phase in phases {
+        assert_eq!(
+            phase.height(),
+            height,
+            "phase trace height must equal the trace height"
+        );
+    }
 
-    (0..height).for_each(|row_index| {
-        let row_index_next = (row_index + 1) % height;
+    let window_size = air.window_size();
+    // Each row is checked on its own, so the rows are checked in parallel.
+    let eval_row = |row_index: usize| {
+        let main_window = window(main, row_index, window_size);
+        let main = RowMajorMatrixView::new(&main_window, main.width());
 
-        // row_index < height so we can used unchecked indexing.
-        let local = unsafe { main.row_slice_unchecked(row_index) };
-        // row_index_next < height so we can used unchecked indexing.
-        let next = unsafe { main.row_slice_unchecked(row_index_next) };
-        let main = VerticalPair::new(
-            RowMajorMatrixView::new_row(&*local),
-            RowMajorMatrixView::new_row(&*next),
+        let preprocessed_window =
+            preprocessed.map_or_else(Vec::new, |prep| window(prep, row_index, window_size));
+        let preprocessed = RowMajorMatrixView::new(
+            &preprocessed_window,
+            preprocessed.map_or(0, |prep| prep.width()),
         );
 
+        let phase_windows = phases
+            .iter()
+            .map(|phase| window(phase, row_index, window_size))
+            .collect::<Vec<_>>();
+        let phases = phases
+            .iter()
+            .zip(&phase_windows)
+            .map(|(phase, values)| RowMajorMatrixView::new(values, phase.width()))
+            .collect();
+
+        let is_transition_windows = (3..=window_size)
+            .map(|size| F::from_bool(row_index + size <= height))
+            .collect();
+
         let mut builder = DebugConstraintBuilder {
             row_index,
             main,
+            preprocessed,
+            phases,
+            phase_challenges,
             public_values,
             is_first_row: F::from_bool(row_index == 0),
             is_last_row: F::from_bool(row_index == height - 1),
             is_transition: F::from_bool(row_index != height - 1),
+            is_transition_windows,
+            is_padding: padding.map_or(F::ZERO, |padding| padding.get(row_index, 0).unwrap()),
+            constraint_index: 0,
+            label: None,
+            failures: Vec::new(),
         };
 
         air.eval(&mut builder);
-    });
+        builder.failures
+    };
+    let mut failures: Vec<_> = (0..height)
+        .into_par_iter()
+        .flat_map_iter(eval_row)
+        .collect();
+    let first_row = failures.first().map(|failure| failure.row);
+    if let Some(first_row) = first_row.filter(|_| stop_at_first) {
+        failures.retain(|failure| failure.row == first_row);
+    }
+    failures
+}
+
+/// The rows `row..row + size` of `mat`, wrapping around at the end.
+fn window<T: Clone + Send + Sync>(mat: &RowMajorMatrix<T>, row: usize, size: usize) -> Vec<T> {
+    (0..size)
+        .flat_map(|offset| {
+            mat.row_slice((row + offset) % mat.height())
+                .unwrap()
+                .to_vec()
+        })
+        .collect()
+}
+
+/// The label of a group of constraints, set through [`AirBuilder::label_constraints`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConstraintLabel {
+    /// The name given by the AIR.
+    pub name: &'static str,
+    /// The source location at which the label was set.
+    pub location: &'static Location<'static>,
+}
+
+impl fmt::Display for ConstraintLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.name, self.location)
+    }
+}
+
+/// A constraint which does not vanish on some row of the trace.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConstraintFailure<EF> {
+    /// The row on which the constraint does not vanish.
+    pub row: usize,
+    /// The index of the constraint, in the order in which the AIR asserts its constraints.
+    pub constraint: usize,
+    /// The value of the constraint on this row.
+    pub value: EF,
+    /// The label of the constraint, if the AIR set one.
+    pub label: Option<ConstraintLabel>,
+    /// The source location at which the constraint was asserted.
+    pub location: &'static Location<'static>,
+}
+
+impl<EF: fmt::Display> fmt::Display for ConstraintFailure<EF> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "row {}: constraint {}", self.row, self.constraint)?;
+        if let Some(label) = &self.label {
+            write!(f, " [{label}]")?;
+        }
+        write!(f, " has value {}", self.value)
+    }
+}
+
+/// Every constraint failure of a trace, as returned by [`constraint_report`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConstraintReport<EF> {
+    /// The number of rows which were checked.
+    pub height: usize,
+    /// The failing constraints, ordered by row and then by constraint index.
+    pub failures: Vec<ConstraintFailure<EF>>,
+}
+
+impl<EF> ConstraintReport<EF> {
+    /// Returns `true` if every constraint vanishes on every row.
+    pub fn is_satisfied(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl<EF: fmt::Display> fmt::Display for ConstraintReport<EF> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} constraint failures in {} rows",
+            self.failures.len(),
+            self.height
+        )?;
+        for failure in &self.failures {
+            write!(f, "\n  {failure}")?;
+        }
+        Ok(())
+    }
 }
 
 /// A builder that runs constraint assertions during testing.
@@ -55,11 +294,17 @@ where
 /// Used in conjunction with [`check_constraints`] to simulate
 /// an execution trace and verify that the AIR logic enforces all constraints.
 #[derive(Debug)]
-pub struct DebugConstraintBuilder<'a, F: Field> {
+pub struct DebugConstraintBuilder<'a, F: Field, EF: ExtensionField<F> = F> {
     /// The index of the row currently being evaluated.
     row_index: usize,
-    /// A view of the current and next row as a vertical pair.
-    main: ViewPair<'a, F>,
+    /// A view of the window of rows starting at the current row.
+    main: RowMajorMatrixView<'a, F>,
+    /// A view of the window of rows of the preprocessed trace.
+    preprocessed: RowMajorMatrixView<'a, F>,
+    /// A view of the window of rows of each trace phase.
+    phases: Vec<RowMajorMatrixView<'a, EF>>,
+    /// The challenges sampled before each trace phase.
+    phase_challenges: &'a [Vec<EF>],
     /// The public values provided for constraint validation (e.g. inputs or outputs).
     public_values: &'a [F],
     /// A flag indicating whether this is the first row.
@@ -68,16 +313,44 @@ pub struct DebugConstraintBuilder<'a, F: Field> {
     is_last_row: F,
     /// A flag indicating whether this is a transition row (not the last row).
     is_transition: F,
+    /// Flags indicating whether a window of `3, 4, ...` rows fits before the end of the trace.
+    is_transition_windows: Vec<F>,
+    /// A flag indicating whether this row was added by padding the trace.
+    is_padding: F,
+    /// The index of the next constraint to be asserted on this row.
+    constraint_index: usize,
+    /// The label of the constraints currently being asserted, if any.
+    label: Option<ConstraintLabel>,
+    /// The constraints which did not vanish on this row.
+    failures: Vec<ConstraintFailure<EF>>,
 }
 
-impl<'a, F> AirBuilder for DebugConstraintBuilder<'a, F>
+impl<F: Field, EF: ExtensionField<F>> DebugConstraintBuilder<'_, F, EF> {
+    /// Record the value of the next constraint, asserted by the caller.
+    #[track_caller]
+    fn record(&mut self, value: EF) {
+        if !value.is_zero() {
+            self.failures.push(ConstraintFailure {
+                row: self.row_index,
+                constraint: self.constraint_index,
+                value,
+                label: self.label,
+                location: Location::caller(),
+            });
+        }
+        self.constraint_index += 1;
+    }
+}
+
+impl<'a, F, EF> AirBuilder for DebugConstraintBuilder<'a, F, EF>
 where
     F: Field,
+    EF: ExtensionField<F>,
 {
     type F = F;
     type Expr = F;
     type Var = F;
-    type M = ViewPair<'a, F>;
+    type M = RowMajorMatrixView<'a, F>;
 
     fn main(&self) -> Self::M {
         self.main
@@ -92,36 +365,30 @@ where
     }
 
     /// # Panics
-    /// This function panics if `size` is not `2`.
+    /// This function panics if `size` exceeds the window size of the AIR.
     fn is_transition_window(&self, size: usize) -> Self::Expr {
-        if size == 2 {
-            self.is_transition
-        } else {
-            panic!("only supports a window size of 2")
-        }
+        transition_window(self.is_transition, &self.is_transition_windows, size)
     }
 
     fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
-        assert_eq!(
-            x.into(),
-            F::ZERO,
-            "constraints had nonzero value on row {}",
-            self.row_index
-        );
+        self.record(x.into().into());
     }
 
-    fn assert_eq<I1: Into<Self::Expr>, I2: Into<Self::Expr>>(&mut self, x: I1, y: I2) {
-        let x = x.into();
-        let y = y.into();
-        assert_eq!(
-            x, y,
-            "values didn't match on row {}: {} != {}",
-            self.row_index, x, y
-        );
+    fn is_padding(&self) -> Self::Expr {
+        self.is_padding
+    }
+
+    fn label_constraints(&mut self, label: &'static str) {
+        self.label = Some(ConstraintLabel {
+            name: label,
+            location: Location::caller(),
+        });
     }
 }
 
-impl<F: Field> AirBuilderWithPublicValues for DebugConstraintBuilder<'_, F> {
+impl<F: Field, EF: ExtensionField<F>> AirBuilderWithPublicValues
+    for DebugConstraintBuilder<'_, F, EF>
+{
     type PublicVar = Self::F;
 
     fn public_values(&self) -> &[Self::F] {
@@ -129,6 +396,52 @@ impl<F: Field> AirBuilderWithPublicValues for DebugConstraintBuilder<'_, F> {
     }
 }
 
+impl<F: Field, EF: ExtensionField<F>> PairBuilder for DebugConstraintBuilder<'_, F, EF> {
+    fn preprocessed(&self) -> Self::M {
+        self.preprocessed
+    }
+}
+
+impl<F: Field, EF: ExtensionField<F>> ExtensionBuilder for DebugConstraintBuilder<'_, F, EF> {
+    type EF = EF;
+    type ExprEF = EF;
+    type VarEF = EF;
+
+    fn assert_zero_ext<I>(&mut self, x: I)
+    where
+        I: Into<Self::ExprEF>,
+    {
+        self.record(x.into());
+    }
+}
+
+impl<'a, F: Field, EF: ExtensionField<F>> MultiPhaseAirBuilder
+    for DebugConstraintBuilder<'a, F, EF>
+{
+    type MPhase = RowMajorMatrixView<'a, EF>;
+    type PhaseChallengeVar = EF;
+
+    fn phase(&self, phase: usize) -> Self::MPhase {
+        self.phases[phase]
+    }
+
+    fn phase_challenges(&self, phase: usize) -> &[Self::PhaseChallengeVar] {
+        &self.phase_challenges[phase]
+    }
+}
+
+impl<F: Field, EF: ExtensionField<F>> InteractionBuilder for DebugConstraintBuilder<'_, F, EF> {
+    /// Interactions are only balanced across all AIRs of a proof, so there is nothing to check
+    /// one row at a time.
+    fn push_interaction<E: Into<Self::Expr>>(
+        &mut self,
+        _bus: usize,
+        _fields: impl IntoIterator<Item = E>,
+        _multiplicity: impl Into<Self::Expr>,
+    ) {
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use alloc::vec;
@@ -160,19 +473,21 @@ mod tests {
         fn eval(&self, builder: &mut DebugConstraintBuilder<'_, F>) {
             let main = builder.main();
 
+            builder.label_constraints("increment");
             for col in 0..W {
-                let a = main.top.get(0, col).unwrap();
-                let b = main.bottom.get(0, col).unwrap();
+                let a = main.get(0, col).unwrap();
+                let b = main.get(1, col).unwrap();
 
                 // New logic: enforce row[i+1] = row[i] + 1, only on transitions
                 builder.when_transition().assert_eq(b, a + F::ONE);
             }
 
             // Add public value equality on last row for extra coverage
+            builder.label_constraints("public values");
             let public_values = builder.public_values;
             let mut when_last = builder.when(builder.is_last_row);
             for (i, &pv) in public_values.iter().enumerate().take(W) {
-                when_last.assert_eq(main.top.get(0, i).unwrap(), pv);
+                when_last.assert_eq(main.get(0, i).unwrap(), pv);
             }
         }
     }
@@ -193,11 +508,13 @@ mod tests {
             BabyBear::new(4), // Row 3 (last)
         ];
         let main = RowMajorMatrix::new(values, 2);
-        check_constraints(&air, &main, &vec![BabyBear::new(4); 2]);
+        assert_eq!(
+            check_constraints(&air, &main, None, &vec![BabyBear::new(4); 2]),
+            Ok(())
+        );
     }
 
     #[test]
-    #[should_panic]
     fn test_incorrect_increment_logic() {
         // Row 2 does not equal row 1 + 1 → should fail on transition from row 1 to 2.
         let air = RowLogicAir::<2>;
@@ -212,11 +529,16 @@ mod tests {
             BabyBear::new(6), // Row 3
         ];
         let main = RowMajorMatrix::new(values, 2);
-        check_constraints(&air, &main, &vec![BabyBear::new(6); 2]);
+        assert_eq!(
+            check_constraints(&air, &main, None, &vec![BabyBear::new(6); 2]),
+            Err(ProvingError::UnsatisfiedConstraint {
+                row: 1,
+                constraint: 0
+            })
+        );
     }
 
     #[test]
-    #[should_panic]
     fn test_wrong_last_row_public_value() {
         // The transition logic is fine, but public value check fails at the last row.
         let air = RowLogicAir::<2>;
@@ -231,8 +553,14 @@ mod tests {
             BabyBear::new(4), // Row 3
         ];
         let main = RowMajorMatrix::new(values, 2);
-        // Wrong public value on column 1
-        check_constraints(&air, &main, &vec![BabyBear::new(4), BabyBear::new(5)]);
+        // Wrong public value on column 1, checked by the fourth constraint.
+        assert_eq!(
+            check_constraints(&air, &main, None, &vec![BabyBear::new(4), BabyBear::new(5)]),
+            Err(ProvingError::UnsatisfiedConstraint {
+                row: 3,
+                constraint: 3
+            })
+        );
     }
 
     #[test]
@@ -246,6 +574,14 @@ mod tests {
             BabyBear::new(77), // Row 0
         ];
         let main = RowMajorMatrix::new(values, 2);
-        check_constraints(&air, &main, &vec![BabyBear::new(99), BabyBear::new(77)]);
+        assert_eq!(
+            check_constraints(
+                &air,
+                &main,
+                None,
+                &vec![BabyBear::new(99), BabyBear::new(77)],
+            ),
+            Ok(())
+        );
     }
 }
@@ -14,6 +14,16 @@ pub type Domain<SC> = <<SC as StarkGenericConfig>::Pcs as Pcs<
     <SC as StarkGenericConfig>::Challenger,
 >>::Domain;
 
+pub type Com<SC> = <<SC as StarkGenericConfig>::Pcs as Pcs<
+    <SC as StarkGenericConfig>::Challenge,
+    <SC as StarkGenericConfig>::Challenger,
+>>::Commitment;
+
+pub type PcsProverData<SC> = <<SC as StarkGenericConfig>::Pcs as Pcs<
+    <SC as StarkGenericConfig>::Challenge,
+    <SC as StarkGenericConfig>::Challenger,
+>>::ProverData;
+
 pub type Val<SC> = <Domain<SC> as PolynomialSpace>::Val;
 
 pub type PackedVal<SC> = <Val<SC> as Field>::Packing;
@@ -43,6 +53,29 @@ pub trait StarkGenericConfig {
     fn is_zk(&self) -> usize {
         Self::Pcs::ZK as usize
     }
+
+    /// The number of rows of the quotient domain on which the prover evaluates the quotient in one
+    /// batch, or `None` to evaluate them all at once.
+    ///
+    /// With a batch size, the prover writes each batch straight into the base field chunks it
+    /// commits to, instead of first building the quotient over the whole domain, flattening it and
+    /// splitting it. This only saves these intermediate copies of the quotient. It does not bound
+    /// the memory of the prover: the chunks are committed to, and their LDEs kept for the openings,
+    /// in full, as are the trace evaluations read over the quotient domain. The proof is the same
+    /// either way.
+    fn quotient_batch_rows(&self) -> Option<usize> {
+        None
+    }
+
+    /// Whether the prover checks that the trace satisfies the constraints of the AIR before
+    /// proving it, returning [`ProvingError::UnsatisfiedConstraint`](crate::ProvingError) if not.
+    ///
+    /// Checking costs an evaluation of the constraints on every row of the trace, spread over all
+    /// threads with the `parallel` feature. Without it, an unsatisfied constraint results in a
+    /// proof which the verifier rejects.
+    fn check_constraints(&self) -> bool {
+        true
+    }
 }
 
 #[derive(Debug)]
@@ -51,6 +84,10 @@ pub struct StarkConfig<Pcs, Challenge, Challenger> {
     pcs: Pcs,
     /// An initialised instance of the challenger.
     challenger: Challenger,
+    /// See [`StarkGenericConfig::quotient_batch_rows`].
+    quotient_batch_rows: Option<usize>,
+    /// See [`StarkGenericConfig::check_constraints`].
+    check_constraints: bool,
     _phantom: PhantomData<Challenge>,
 }
 
@@ -59,9 +96,25 @@ impl<Pcs, Challenge, Challenger> StarkConfig<Pcs, Challenge, Challenger> {
         Self {
             pcs,
             challenger,
+            quotient_batch_rows: None,
+            check_constraints: true,
             _phantom: PhantomData,
         }
     }
+
+    /// Evaluate the quotient in batches of `rows` rows, see
+    /// [`StarkGenericConfig::quotient_batch_rows`].
+    pub const fn with_quotient_batch_rows(mut self, rows: usize) -> Self {
+        self.quotient_batch_rows = Some(rows);
+        self
+    }
+
+    /// Skip checking the trace against the constraints of the AIR before proving it, see
+    /// [`StarkGenericConfig::check_constraints`].
+    pub const fn without_constraint_check(mut self) -> Self {
+        self.check_constraints = false;
+        self
+    }
 }
 
 impl<Pcs, Challenge, Challenger> StarkGenericConfig for StarkConfig<Pcs, Challenge, Challenger>
@@ -84,4 +137,12 @@ where
     fn initialise_challenger(&self) -> Self::Challenger {
         self.challenger.clone()
     }
+
+    fn quotient_batch_rows(&self) -> Option<usize> {
+        self.quotient_batch_rows
+    }
+
+    fn check_constraints(&self) -> bool {
+        self.check_constraints
+    }
 }
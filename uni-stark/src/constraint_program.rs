@@ -0,0 +1,825 @@
+//! Compiling symbolic constraints into a straight-line program.
+//!
+//! Evaluating the constraints of an AIR through [`Air::eval`](p3_air::Air::eval) on every point of
+//! the quotient domain repeats whatever redundant arithmetic the AIR performs. A
+//! [`ConstraintProgram`] instead evaluates the constraints collected by
+//! [`get_symbolic_constraints`](crate::get_symbolic_constraints) after deduplicating common
+//! subexpressions and folding trivial arithmetic.
+
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::array;
+
+use p3_field::{Algebra, BasedVectorSpace, ExtensionField, Field, PackedField};
+use p3_matrix::dense::RowMajorMatrixView;
+
+use crate::flatten::{FlatNode, flatten};
+use crate::{Entry, SymbolicExpression, SymbolicVariable};
+
+/// The number of base field constraints [`ConstraintProgram::eval`] combines at once.
+const COMBINATION_WIDTH: usize = 8;
+
+/// A value read by a [`ConstraintProgram`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Input<F> {
+    Main {
+        offset: usize,
+        index: usize,
+    },
+    Preprocessed {
+        offset: usize,
+        index: usize,
+    },
+    Padding {
+        offset: usize,
+        index: usize,
+    },
+    Public(usize),
+    IsFirstRow,
+    IsLastRow,
+    IsTransition,
+    IsTransitionWindow(usize),
+    Constant(F),
+    Permutation {
+        offset: usize,
+        index: usize,
+    },
+    Phase {
+        phase: usize,
+        offset: usize,
+        index: usize,
+    },
+    Challenge(usize),
+    PhaseChallenge {
+        phase: usize,
+        index: usize,
+    },
+}
+
+impl<F> Input<F> {
+    /// Whether this input lies in the extension field.
+    pub const fn is_ext(&self) -> bool {
+        matches!(
+            self,
+            Self::Permutation { .. }
+                | Self::Phase { .. }
+                | Self::Challenge(_)
+                | Self::PhaseChallenge { .. }
+        )
+    }
+}
+
+impl<F> From<SymbolicVariable<F>> for Input<F> {
+    fn from(v: SymbolicVariable<F>) -> Self {
+        let index = v.index;
+        match v.entry {
+            Entry::Main { offset } => Self::Main { offset, index },
+            Entry::Preprocessed { offset } => Self::Preprocessed { offset, index },
+            Entry::Padding { offset } => Self::Padding { offset, index },
+            Entry::Public => Self::Public(index),
+            Entry::Permutation { offset } => Self::Permutation { offset, index },
+            Entry::Phase { phase, offset } => Self::Phase {
+                phase,
+                offset,
+                index,
+            },
+            Entry::Challenge => Self::Challenge(index),
+            Entry::PhaseChallenge { phase } => Self::PhaseChallenge { phase, index },
+        }
+    }
+}
+
+/// An instruction of a [`ConstraintProgram`], operating on its base field registers and its
+/// extension field registers.
+///
+/// The instructions without a prefix operate on base field registers, those prefixed with `Ext`
+/// operate on extension field registers, and the remaining ones mix both as indicated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Instruction {
+    Add {
+        x: usize,
+        y: usize,
+        dst: usize,
+    },
+    Sub {
+        x: usize,
+        y: usize,
+        dst: usize,
+    },
+    Neg {
+        x: usize,
+        dst: usize,
+    },
+    Mul {
+        x: usize,
+        y: usize,
+        dst: usize,
+    },
+    ExtAdd {
+        x: usize,
+        y: usize,
+        dst: usize,
+    },
+    ExtSub {
+        x: usize,
+        y: usize,
+        dst: usize,
+    },
+    ExtNeg {
+        x: usize,
+        dst: usize,
+    },
+    ExtMul {
+        x: usize,
+        y: usize,
+        dst: usize,
+    },
+    /// `ext[dst] = ext[x] + base[y]`
+    ExtAddBase {
+        x: usize,
+        y: usize,
+        dst: usize,
+    },
+    /// `ext[dst] = ext[x] - base[y]`
+    ExtSubBase {
+        x: usize,
+        y: usize,
+        dst: usize,
+    },
+    /// `ext[dst] = base[x] - ext[y]`
+    BaseSubExt {
+        x: usize,
+        y: usize,
+        dst: usize,
+    },
+    /// `ext[dst] = ext[x] * base[y]`
+    ExtMulBase {
+        x: usize,
+        y: usize,
+        dst: usize,
+    },
+    /// Assert that a base field register is zero, i.e. add it to the random linear combination of
+    /// the constraints, as the constraint with the given index.
+    AssertZero {
+        x: usize,
+        index: usize,
+    },
+    /// Assert that an extension field register is zero.
+    AssertZeroExt {
+        x: usize,
+        index: usize,
+    },
+}
+
+/// The values a [`ConstraintProgram`] is evaluated on: a window of consecutive rows of each trace,
+/// the public values, the challenges and the selectors.
+///
+/// Base field values are of type `P` and extension field values of type `PE`, which are typically
+/// packed. Matrices of traces the AIR does not have may be empty.
+#[derive(Clone, Copy, Debug)]
+pub struct ConstraintInputs<'a, F, EF, P, PE> {
+    pub main: RowMajorMatrixView<'a, P>,
+    pub preprocessed: RowMajorMatrixView<'a, P>,
+    pub padding: RowMajorMatrixView<'a, P>,
+    pub permutation: RowMajorMatrixView<'a, PE>,
+    pub phases: &'a [RowMajorMatrixView<'a, PE>],
+    /// The public values, followed by the real height of the trace if the AIR pads its trace.
+    pub public_values: &'a [F],
+    /// The LogUp challenges followed by the cumulative sum, if the AIR has interactions.
+    pub challenges: &'a [EF],
+    pub phase_challenges: &'a [Vec<EF>],
+    pub is_first_row: P,
+    pub is_last_row: P,
+    pub is_transition: P,
+    /// The selectors of `is_transition_window(size)` for `size = 3, 4, ...` up to the window size
+    /// of the AIR.
+    pub is_transition_windows: &'a [P],
+}
+
+/// The constraints of an AIR, compiled to a list of instructions in which every distinct
+/// subexpression is computed once.
+///
+/// The first registers of a program hold its inputs, loaded before the instructions run; the
+/// remaining ones hold intermediate values and are reused once a value is no longer needed.
+#[derive(Clone, Debug)]
+pub struct ConstraintProgram<F> {
+    base_inputs: Vec<Input<F>>,
+    ext_inputs: Vec<Input<F>>,
+    instructions: Vec<Instruction>,
+    num_base_registers: usize,
+    num_ext_registers: usize,
+    num_constraints: usize,
+}
+
+impl<F: Field> ConstraintProgram<F> {
+    /// Compile `constraints`, as returned by
+    /// [`get_symbolic_constraints`](crate::get_symbolic_constraints).
+    pub fn new(constraints: &[SymbolicExpression<F>]) -> Self {
+        let (flat_nodes, roots) = flatten(constraints);
+        let mut compiler = Compiler {
+            ids: BTreeMap::new(),
+            nodes: Vec::new(),
+            constants: Vec::new(),
+            steps: Vec::new(),
+        };
+        // The node compiling each flattened node.
+        let mut compiled = Vec::with_capacity(flat_nodes.len());
+        let mut roots = roots.into_iter().peekable();
+        for (i, node) in flat_nodes.iter().enumerate() {
+            let id = compiler.compile(node, &compiled);
+            compiled.push(id);
+            while roots.next_if_eq(&i).is_some() {
+                compiler.steps.push(Step::AssertZero(id));
+            }
+        }
+        compiler.into_program()
+    }
+
+    /// The values loaded into the first base field registers.
+    pub fn base_inputs(&self) -> &[Input<F>] {
+        &self.base_inputs
+    }
+
+    /// The values loaded into the first extension field registers.
+    pub fn ext_inputs(&self) -> &[Input<F>] {
+        &self.ext_inputs
+    }
+
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    pub const fn num_constraints(&self) -> usize {
+        self.num_constraints
+    }
+
+    /// Evaluate the random linear combination `sum_i alpha_powers[i] C_i` of the constraints `C_i`
+    /// on `N` rows at once.
+    ///
+    /// Each instruction is applied to all `N` rows before moving on to the next one, which
+    /// amortizes the cost of interpreting it.
+    pub fn eval<EF, P, PE, const N: usize>(
+        &self,
+        inputs: &[ConstraintInputs<'_, F, EF, P, PE>; N],
+        alpha_powers: &[EF],
+        registers: &mut Registers<P, PE, N>,
+    ) -> [PE; N]
+    where
+        EF: ExtensionField<F>,
+        P: PackedField<Scalar = F>,
+        PE: Algebra<EF> + Algebra<P> + BasedVectorSpace<P> + Copy,
+    {
+        debug_assert_eq!(alpha_powers.len(), self.num_constraints);
+        let Registers { base, ext } = registers;
+        // Registers past the inputs are always written before being read, so whatever a previous
+        // evaluation left in them can stay.
+        base.resize(self.num_base_registers, [P::ZERO; N]);
+        ext.resize(self.num_ext_registers, [PE::ZERO; N]);
+        for (register, &input) in base.iter_mut().zip(&self.base_inputs) {
+            *register = load_base(inputs, input);
+        }
+        for (register, &input) in ext.iter_mut().zip(&self.ext_inputs) {
+            *register = load_ext(inputs, input);
+        }
+
+        // Like `ProverConstraintFolder::assert_zeros`, base field constraints are combined a few at
+        // a time, using the decomposed powers of alpha as coefficients.
+        let mut pending = [[P::ZERO; N]; COMBINATION_WIDTH];
+        let mut pending_alpha_powers = [EF::ZERO; COMBINATION_WIDTH];
+        let mut num_pending = 0;
+        let mut base_accumulators = vec![[P::ZERO; N]; EF::DIMENSION];
+        let mut accumulators = [PE::ZERO; N];
+        for instruction in &self.instructions {
+            match *instruction {
+                Instruction::Add { x, y, dst } => base[dst] = zip(&base[x], &base[y], |a, b| a + b),
+                Instruction::Sub { x, y, dst } => base[dst] = zip(&base[x], &base[y], |a, b| a - b),
+                Instruction::Neg { x, dst } => base[dst] = base[x].map(|a| -a),
+                Instruction::Mul { x, y, dst } => base[dst] = zip(&base[x], &base[y], |a, b| a * b),
+                Instruction::ExtAdd { x, y, dst } => ext[dst] = zip(&ext[x], &ext[y], |a, b| a + b),
+                Instruction::ExtSub { x, y, dst } => ext[dst] = zip(&ext[x], &ext[y], |a, b| a - b),
+                Instruction::ExtNeg { x, dst } => ext[dst] = ext[x].map(|a| -a),
+                Instruction::ExtMul { x, y, dst } => ext[dst] = zip(&ext[x], &ext[y], |a, b| a * b),
+                Instruction::ExtAddBase { x, y, dst } => {
+                    ext[dst] = zip(&ext[x], &base[y], |a, b| a + b);
+                }
+                Instruction::ExtSubBase { x, y, dst } => {
+                    ext[dst] = zip(&ext[x], &base[y], |a, b| a - b);
+                }
+                Instruction::BaseSubExt { x, y, dst } => {
+                    ext[dst] = zip(&base[x], &ext[y], |a, b| PE::from(a) - b);
+                }
+                Instruction::ExtMulBase { x, y, dst } => {
+                    ext[dst] = zip(&ext[x], &base[y], |a, b| a * b);
+                }
+                Instruction::AssertZero { x, index } => {
+                    pending[num_pending] = base[x];
+                    pending_alpha_powers[num_pending] = alpha_powers[index];
+                    num_pending += 1;
+                    if num_pending == COMBINATION_WIDTH {
+                        combine(&mut base_accumulators, &pending, &pending_alpha_powers);
+                        num_pending = 0;
+                    }
+                }
+                Instruction::AssertZeroExt { x, index } => {
+                    let alpha_power = PE::from(alpha_powers[index]);
+                    for (accumulator, &value) in accumulators.iter_mut().zip(&ext[x]) {
+                        *accumulator += alpha_power * value;
+                    }
+                }
+            }
+        }
+        // Stale pending values are cancelled by a zero coefficient.
+        pending_alpha_powers[num_pending..].fill(EF::ZERO);
+        combine(&mut base_accumulators, &pending, &pending_alpha_powers);
+
+        array::from_fn(|j| {
+            accumulators[j] + PE::from_basis_coefficients_fn(|i| base_accumulators[i][j])
+        })
+    }
+}
+
+/// The registers of a [`ConstraintProgram`] evaluated on `N` rows at once.
+///
+/// Reusing them across evaluations saves allocating and initializing them every time.
+#[derive(Clone, Debug)]
+pub struct Registers<P, PE, const N: usize> {
+    base: Vec<[P; N]>,
+    ext: Vec<[PE; N]>,
+}
+
+impl<P, PE, const N: usize> Default for Registers<P, PE, N> {
+    fn default() -> Self {
+        Self {
+            base: Vec::new(),
+            ext: Vec::new(),
+        }
+    }
+}
+
+#[inline(always)]
+fn zip<A: Copy, B: Copy, C, const N: usize>(
+    a: &[A; N],
+    b: &[B; N],
+    f: impl Fn(A, B) -> C,
+) -> [C; N] {
+    array::from_fn(|j| f(a[j], b[j]))
+}
+
+/// Add `sum_k alpha_powers[k] values[k]` to the accumulators of the basis coefficients.
+fn combine<F, EF, P, const N: usize>(
+    accumulators: &mut [[P; N]],
+    values: &[[P; N]; COMBINATION_WIDTH],
+    alpha_powers: &[EF; COMBINATION_WIDTH],
+) where
+    F: Field,
+    EF: ExtensionField<F>,
+    P: PackedField<Scalar = F>,
+{
+    for (i, accumulator) in accumulators.iter_mut().enumerate() {
+        let coeffs = alpha_powers.map(|alpha_power| alpha_power.as_basis_coefficients_slice()[i]);
+        for (j, accumulator) in accumulator.iter_mut().enumerate() {
+            let values: [P; COMBINATION_WIDTH] = array::from_fn(|k| values[k][j]);
+            *accumulator += P::packed_linear_combination::<COMBINATION_WIDTH>(&coeffs, &values);
+        }
+    }
+}
+
+/// Read a base field input on each of the `N` rows.
+fn load_base<F, EF, P, PE, const N: usize>(
+    inputs: &[ConstraintInputs<'_, F, EF, P, PE>; N],
+    input: Input<F>,
+) -> [P; N]
+where
+    F: Field,
+    P: Algebra<F> + Copy,
+{
+    match input {
+        Input::Main { offset, index } => read(inputs.each_ref().map(|i| &i.main), offset, index),
+        Input::Preprocessed { offset, index } => {
+            read(inputs.each_ref().map(|i| &i.preprocessed), offset, index)
+        }
+        Input::Padding { offset, index } => {
+            read(inputs.each_ref().map(|i| &i.padding), offset, index)
+        }
+        Input::Public(index) => array::from_fn(|j| P::from(inputs[j].public_values[index])),
+        Input::IsFirstRow => array::from_fn(|j| inputs[j].is_first_row),
+        Input::IsLastRow => array::from_fn(|j| inputs[j].is_last_row),
+        Input::IsTransition => array::from_fn(|j| inputs[j].is_transition),
+        Input::IsTransitionWindow(size) => {
+            array::from_fn(|j| inputs[j].is_transition_windows[size - 3])
+        }
+        Input::Constant(c) => [P::from(c); N],
+        _ => unreachable!("an extension field input"),
+    }
+}
+
+/// Read an extension field input on each of the `N` rows.
+fn load_ext<F, EF, P, PE, const N: usize>(
+    inputs: &[ConstraintInputs<'_, F, EF, P, PE>; N],
+    input: Input<F>,
+) -> [PE; N]
+where
+    EF: Copy,
+    PE: Algebra<EF> + Copy,
+{
+    match input {
+        Input::Permutation { offset, index } => {
+            read(inputs.each_ref().map(|i| &i.permutation), offset, index)
+        }
+        Input::Phase {
+            phase,
+            offset,
+            index,
+        } => read(inputs.each_ref().map(|i| &i.phases[phase]), offset, index),
+        Input::Challenge(index) => array::from_fn(|j| PE::from(inputs[j].challenges[index])),
+        Input::PhaseChallenge { phase, index } => {
+            array::from_fn(|j| PE::from(inputs[j].phase_challenges[phase][index]))
+        }
+        _ => unreachable!("a base field input"),
+    }
+}
+
+/// Read the entry at `offset` rows and column `index` of each matrix.
+fn read<T: Copy, const N: usize>(
+    mats: [&RowMajorMatrixView<'_, T>; N],
+    offset: usize,
+    index: usize,
+) -> [T; N] {
+    mats.map(|mat| mat.values[offset * mat.width + index])
+}
+
+/// A node of the deduplicated expression DAG, referring to constants and operands by index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Node {
+    Variable(Entry, usize),
+    IsFirstRow,
+    IsLastRow,
+    IsTransition,
+    IsTransitionWindow(usize),
+    Constant(usize),
+    Add(usize, usize),
+    Sub(usize, usize),
+    Neg(usize),
+    Mul(usize, usize),
+}
+
+impl Node {
+    const fn is_input(&self) -> bool {
+        !matches!(
+            self,
+            Self::Add(..) | Self::Sub(..) | Self::Neg(_) | Self::Mul(..)
+        )
+    }
+
+    fn operands(self) -> Vec<usize> {
+        match self {
+            Self::Add(x, y) | Self::Sub(x, y) | Self::Mul(x, y) => vec![x, y],
+            Self::Neg(x) => vec![x],
+            _ => vec![],
+        }
+    }
+}
+
+/// Either the computation of a new node or an assertion that a node is zero, in program order.
+#[derive(Clone, Copy, Debug)]
+enum Step {
+    Node(usize),
+    AssertZero(usize),
+}
+
+struct Compiler<F> {
+    /// The id of each distinct node.
+    ids: BTreeMap<Node, usize>,
+    /// Each node, along with whether its value lies in the extension field.
+    nodes: Vec<(Node, bool)>,
+    constants: Vec<F>,
+    steps: Vec<Step>,
+}
+
+impl<F: Field> Compiler<F> {
+    /// Compile a flattened node, given the compiled node of each earlier flattened node, and
+    /// return its id.
+    fn compile(&mut self, node: &FlatNode<F>, compiled: &[usize]) -> usize {
+        match *node {
+            FlatNode::Variable(v) => self.node(Node::Variable(v.entry, v.index)),
+            FlatNode::IsFirstRow => self.node(Node::IsFirstRow),
+            FlatNode::IsLastRow => self.node(Node::IsLastRow),
+            FlatNode::IsTransition => self.node(Node::IsTransition),
+            FlatNode::IsTransitionWindow(size) => self.node(Node::IsTransitionWindow(size)),
+            FlatNode::Constant(c) => self.constant(c),
+            FlatNode::Add(x, y) => {
+                let (x, y) = (compiled[x], compiled[y]);
+                match (self.constant_value(x), self.constant_value(y)) {
+                    (Some(a), Some(b)) => self.constant(a + b),
+                    (Some(a), _) if a.is_zero() => y,
+                    (_, Some(b)) if b.is_zero() => x,
+                    _ => self.node(Node::Add(x.min(y), x.max(y))),
+                }
+            }
+            FlatNode::Sub(x, y) => {
+                let (x, y) = (compiled[x], compiled[y]);
+                match (self.constant_value(x), self.constant_value(y)) {
+                    (Some(a), Some(b)) => self.constant(a - b),
+                    (Some(a), _) if a.is_zero() => self.node(Node::Neg(y)),
+                    (_, Some(b)) if b.is_zero() => x,
+                    _ if x == y => self.constant(F::ZERO),
+                    _ => self.node(Node::Sub(x, y)),
+                }
+            }
+            FlatNode::Neg(x) => {
+                let x = compiled[x];
+                match self.constant_value(x) {
+                    Some(a) => self.constant(-a),
+                    None => self.node(Node::Neg(x)),
+                }
+            }
+            FlatNode::Mul(x, y) => {
+                let (x, y) = (compiled[x], compiled[y]);
+                match (self.constant_value(x), self.constant_value(y)) {
+                    (Some(a), Some(b)) => self.constant(a * b),
+                    (Some(c), _) | (_, Some(c)) if c.is_zero() => self.constant(F::ZERO),
+                    (Some(a), _) if a.is_one() => y,
+                    (_, Some(b)) if b.is_one() => x,
+                    _ => self.node(Node::Mul(x.min(y), x.max(y))),
+                }
+            }
+        }
+    }
+
+    fn constant(&mut self, c: F) -> usize {
+        let index = self
+            .constants
+            .iter()
+            .position(|&d| d == c)
+            .unwrap_or_else(|| {
+                self.constants.push(c);
+                self.constants.len() - 1
+            });
+        self.node(Node::Constant(index))
+    }
+
+    /// The value of a node if it is a constant.
+    fn constant_value(&self, id: usize) -> Option<F> {
+        match self.nodes[id].0 {
+            Node::Constant(index) => Some(self.constants[index]),
+            _ => None,
+        }
+    }
+
+    /// Return the id of `node`, adding it to the program if it is new.
+    fn node(&mut self, node: Node) -> usize {
+        if let Some(&id) = self.ids.get(&node) {
+            return id;
+        }
+        let is_ext = match node {
+            Node::Variable(entry, index) => {
+                Input::from(SymbolicVariable::<F>::new(entry, index)).is_ext()
+            }
+            _ => node.operands().into_iter().any(|x| self.nodes[x].1),
+        };
+        let id = self.nodes.len();
+        self.ids.insert(node, id);
+        self.nodes.push((node, is_ext));
+        self.steps.push(Step::Node(id));
+        id
+    }
+
+    fn input(&self, node: Node) -> Input<F> {
+        match node {
+            Node::Variable(entry, index) => SymbolicVariable::new(entry, index).into(),
+            Node::IsFirstRow => Input::IsFirstRow,
+            Node::IsLastRow => Input::IsLastRow,
+            Node::IsTransition => Input::IsTransition,
+            Node::IsTransitionWindow(size) => Input::IsTransitionWindow(size),
+            Node::Constant(index) => Input::Constant(self.constants[index]),
+            Node::Add(..) | Node::Sub(..) | Node::Neg(_) | Node::Mul(..) => {
+                unreachable!("not an input")
+            }
+        }
+    }
+
+    /// Assign a register to every node and turn the operations and assertions into instructions.
+    ///
+    /// Inputs get their own registers, while the register of an intermediate value is reused once
+    /// the value has been used for the last time.
+    fn into_program(self) -> ConstraintProgram<F> {
+        // The index of the last step using each node.
+        let mut last_use = vec![0; self.nodes.len()];
+        for (i, &step) in self.steps.iter().enumerate() {
+            match step {
+                Step::Node(id) => {
+                    last_use[id] = i;
+                    for x in self.nodes[id].0.operands() {
+                        last_use[x] = i;
+                    }
+                }
+                Step::AssertZero(id) => last_use[id] = i,
+            }
+        }
+
+        let (mut base_inputs, mut ext_inputs) = (Vec::new(), Vec::new());
+        let mut registers = vec![0; self.nodes.len()];
+        for (id, &(node, is_ext)) in self.nodes.iter().enumerate() {
+            if node.is_input() {
+                let inputs = if is_ext {
+                    &mut ext_inputs
+                } else {
+                    &mut base_inputs
+                };
+                registers[id] = inputs.len();
+                inputs.push(self.input(node));
+            }
+        }
+
+        let (mut free_base, mut free_ext) = (Vec::new(), Vec::new());
+        let (mut num_base, mut num_ext) = (base_inputs.len(), ext_inputs.len());
+        let mut num_constraints = 0;
+        let mut instructions = Vec::with_capacity(self.steps.len());
+        for (i, &step) in self.steps.iter().enumerate() {
+            let id = match step {
+                Step::Node(id) => id,
+                Step::AssertZero(x) => {
+                    let (node, is_ext) = self.nodes[x];
+                    instructions.push(if is_ext {
+                        Instruction::AssertZeroExt {
+                            x: registers[x],
+                            index: num_constraints,
+                        }
+                    } else {
+                        Instruction::AssertZero {
+                            x: registers[x],
+                            index: num_constraints,
+                        }
+                    });
+                    num_constraints += 1;
+                    if last_use[x] == i && !node.is_input() {
+                        if is_ext {
+                            &mut free_ext
+                        } else {
+                            &mut free_base
+                        }
+                        .push(registers[x]);
+                    }
+                    continue;
+                }
+            };
+            let (node, is_ext) = self.nodes[id];
+            if node.is_input() {
+                continue;
+            }
+
+            // Operands are read before the result is written, so the result may reuse the
+            // register of an operand used for the last time.
+            let mut operands = node.operands();
+            operands.dedup();
+            for x in operands {
+                let (operand, operand_is_ext) = self.nodes[x];
+                if last_use[x] == i && !operand.is_input() {
+                    if operand_is_ext {
+                        &mut free_ext
+                    } else {
+                        &mut free_base
+                    }
+                    .push(registers[x]);
+                }
+            }
+            let (free, num) = if is_ext {
+                (&mut free_ext, &mut num_ext)
+            } else {
+                (&mut free_base, &mut num_base)
+            };
+            let dst = free.pop().unwrap_or_else(|| {
+                *num += 1;
+                *num - 1
+            });
+            registers[id] = dst;
+
+            let operand = |x: usize| (registers[x], self.nodes[x].1);
+            instructions.push(match node {
+                Node::Add(x, y) => match (operand(x), operand(y)) {
+                    ((x, false), (y, false)) => Instruction::Add { x, y, dst },
+                    ((x, true), (y, true)) => Instruction::ExtAdd { x, y, dst },
+                    ((x, true), (y, false)) | ((y, false), (x, true)) => {
+                        Instruction::ExtAddBase { x, y, dst }
+                    }
+                },
+                Node::Sub(x, y) => match (operand(x), operand(y)) {
+                    ((x, false), (y, false)) => Instruction::Sub { x, y, dst },
+                    ((x, true), (y, true)) => Instruction::ExtSub { x, y, dst },
+                    ((x, true), (y, false)) => Instruction::ExtSubBase { x, y, dst },
+                    ((x, false), (y, true)) => Instruction::BaseSubExt { x, y, dst },
+                },
+                Node::Mul(x, y) => match (operand(x), operand(y)) {
+                    ((x, false), (y, false)) => Instruction::Mul { x, y, dst },
+                    ((x, true), (y, true)) => Instruction::ExtMul { x, y, dst },
+                    ((x, true), (y, false)) | ((y, false), (x, true)) => {
+                        Instruction::ExtMulBase { x, y, dst }
+                    }
+                },
+                Node::Neg(x) => match operand(x) {
+                    (x, false) => Instruction::Neg { x, dst },
+                    (x, true) => Instruction::ExtNeg { x, dst },
+                },
+                _ => unreachable!("inputs are loaded before the instructions run"),
+            });
+            // A node which is never used, e.g. one simplified away, does not hold on to its
+            // register.
+            if last_use[id] == i {
+                free.push(dst);
+            }
+        }
+
+        ConstraintProgram {
+            base_inputs,
+            ext_inputs,
+            instructions,
+            num_base_registers: num_base,
+            num_ext_registers: num_ext,
+            num_constraints,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p3_air::{Air, AirBuilder, BaseAir};
+    use p3_baby_bear::BabyBear;
+    use p3_field::PrimeCharacteristicRing;
+    use p3_field::extension::BinomialExtensionField;
+    use p3_matrix::Matrix;
+
+    use super::*;
+    use crate::get_symbolic_constraints;
+
+    type EF = BinomialExtensionField<BabyBear, 4>;
+
+    /// Checks `(a + b)^2 = b` and `a' = a + b` on transitions, without sharing `a + b`.
+    struct RepeatedAir;
+
+    impl<F> BaseAir<F> for RepeatedAir {
+        fn width(&self) -> usize {
+            2
+        }
+    }
+
+    impl<AB: AirBuilder> Air<AB> for RepeatedAir {
+        fn eval(&self, builder: &mut AB) {
+            let main = builder.main();
+            let local = main.row_slice(0).expect("Matrix is empty?");
+            let next = main.row_slice(1).expect("Matrix only has 1 row?");
+            let (a, b) = (local[0].clone(), local[1].clone());
+            let sum = || a.clone().into() + b.clone().into();
+            builder.assert_eq(sum() * sum(), b.clone());
+            builder
+                .when_transition()
+                .assert_eq(next[0].clone(), sum() + AB::Expr::ZERO);
+        }
+    }
+
+    #[test]
+    fn test_constraint_program() {
+        let constraints = get_symbolic_constraints::<BabyBear, _>(&RepeatedAir, 0, 0);
+        let program = ConstraintProgram::new(&constraints);
+        // `a + b`, its square and `a' - (a + b)` are each computed once.
+        assert_eq!(program.num_constraints(), 2);
+        assert_eq!(program.instructions().len(), 7);
+
+        let rows = [
+            [2, 3, 5, 1].map(BabyBear::new),
+            [7, 11, 13, 17].map(BabyBear::new),
+        ];
+        let is_transition = [BabyBear::ONE, BabyBear::new(4)];
+        let inputs: [_; 2] = array::from_fn(|j| ConstraintInputs {
+            main: RowMajorMatrixView::new(&rows[j], 2),
+            preprocessed: RowMajorMatrixView::new(&[], 0),
+            padding: RowMajorMatrixView::new(&[], 0),
+            permutation: RowMajorMatrixView::new(&[], 0),
+            phases: &[],
+            public_values: &[],
+            challenges: &[],
+            phase_challenges: &[],
+            is_first_row: BabyBear::ZERO,
+            is_last_row: BabyBear::ZERO,
+            is_transition: is_transition[j],
+            is_transition_windows: &[],
+        });
+        let alpha_powers = [
+            EF::from_u8(3),
+            EF::from_basis_coefficients_fn(BabyBear::from_usize),
+        ];
+        let mut registers = Registers::default();
+        let values = program.eval::<EF, BabyBear, EF, 2>(&inputs, &alpha_powers, &mut registers);
+
+        for (j, [a, b, next_a, _]) in rows.into_iter().enumerate() {
+            let expected = alpha_powers[0] * ((a + b).square() - b)
+                + alpha_powers[1] * (is_transition[j] * (next_a - (a + b)));
+            assert_eq!(values[j], expected);
+        }
+        // Evaluating again with the same registers gives the same result.
+        assert_eq!(
+            program.eval::<EF, BabyBear, EF, 2>(&inputs, &alpha_powers, &mut registers),
+            values
+        );
+    }
+}
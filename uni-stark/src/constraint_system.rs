@@ -0,0 +1,350 @@
+//! A portable description of the constraints of an AIR.
+//!
+//! A [`ConstraintSystem`] holds the constraints collected by
+//! [`get_symbolic_constraints`](crate::get_symbolic_constraints) as a list of nodes in
+//! topological order, along with the shape of the trace they apply to. It implements `Serialize`
+//! and `Deserialize`, so that it can be stored in any serde format, e.g. JSON for humans or
+//! postcard for a compact binary encoding, and read back by tools outside of Rust.
+//!
+//! A deserialized constraint system can be turned back into an AIR, see
+//! [`DynamicAir`](crate::DynamicAir).
+
+use alloc::vec::Vec;
+
+use p3_air::{Air, BaseAirWithPublicValues};
+use p3_field::{Algebra, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use serde::{Deserialize, Serialize};
+
+use crate::flatten::{FlatNode, flatten};
+use crate::{
+    Entry, SymbolicAirBuilder, SymbolicExpression, SymbolicVariable, get_symbolic_constraints,
+    get_symbolic_interactions,
+};
+
+/// The version of the [`ConstraintSystem`] format, increased on every incompatible change.
+pub const CONSTRAINT_SYSTEM_VERSION: u32 = 2;
+
+/// A node of a [`ConstraintSystem`].
+///
+/// Operations refer to their operands by index, and operands always come before the operations
+/// using them. Variables are read at row `offset` of the evaluation window, i.e. `0` for the
+/// current row and `1` for the next one. `IsTransitionWindow(size)` selects the rows whose window
+/// of `size` rows does not wrap around, see `AirBuilder::is_transition_window`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConstraintNode<F> {
+    Main { offset: usize, index: usize },
+    Preprocessed { offset: usize, index: usize },
+    Public(usize),
+    IsFirstRow,
+    IsLastRow,
+    IsTransition,
+    IsTransitionWindow(usize),
+    Constant(F),
+    Add(usize, usize),
+    Sub(usize, usize),
+    Neg(usize),
+    Mul(usize, usize),
+}
+
+/// Which row selectors the constraints of a [`ConstraintSystem`] use.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SelectorUsage {
+    pub is_first_row: bool,
+    pub is_last_row: bool,
+    pub is_transition: bool,
+    pub is_transition_window: bool,
+}
+
+/// The constraints of an AIR together with the shape of its trace, see the
+/// [module documentation](self).
+///
+/// Only AIRs without interactions, trace phases or trace padding can be described.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConstraintSystem<F> {
+    /// The version of the format, see [`CONSTRAINT_SYSTEM_VERSION`].
+    pub version: u32,
+    /// The width of the main trace.
+    pub width: usize,
+    /// The number of rows the constraints are evaluated over, see `BaseAir::window_size`.
+    pub window_size: usize,
+    pub preprocessed: Option<RowMajorMatrix<F>>,
+    pub num_public_values: usize,
+    pub selectors: SelectorUsage,
+    /// The constraints, as a DAG in topological order.
+    pub nodes: Vec<ConstraintNode<F>>,
+    /// The node of each constraint, in the order in which the AIR asserts them.
+    pub constraints: Vec<usize>,
+}
+
+/// The reason a [`ConstraintSystem`] is invalid.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConstraintSystemError {
+    /// The constraint system is in a format this library cannot read.
+    UnsupportedVersion { version: u32 },
+    /// The evaluation window has fewer than two rows.
+    InvalidWindowSize { window_size: usize },
+    /// The node reads a column or public value which does not exist, or a row outside of the
+    /// evaluation window, or selects a window larger than the evaluation window.
+    InvalidVariable { node: usize },
+    /// The node has an operand which does not come before it.
+    InvalidOperand { node: usize },
+    /// The node uses a selector missing from [`ConstraintSystem::selectors`].
+    UndeclaredSelector { node: usize },
+    /// The constraint refers to a node which does not exist.
+    InvalidConstraint { constraint: usize },
+    /// The symbolic constraint reads a variable other than a main or preprocessed column or a
+    /// public value, e.g. a permutation column.
+    UnsupportedVariable { constraint: usize },
+}
+
+impl<F: Field> ConstraintSystem<F> {
+    /// Describe the constraints of `air`.
+    ///
+    /// # Panics
+    /// This function panics if `air` has interactions, trace phases or trace padding.
+    pub fn from_air<A>(air: &A) -> Self
+    where
+        A: BaseAirWithPublicValues<F> + Air<SymbolicAirBuilder<F>>,
+    {
+        assert!(
+            air.trace_phases().is_empty() && air.trace_padding().is_none(),
+            "the constraints of AIRs with trace phases or trace padding cannot be exported"
+        );
+        let preprocessed = air.preprocessed_trace();
+        let preprocessed_width = preprocessed.as_ref().map_or(0, |prep| prep.width());
+        let num_public_values = air.num_public_values();
+        assert!(
+            get_symbolic_interactions(air, preprocessed_width, num_public_values).is_empty(),
+            "the constraints of AIRs with interactions cannot be exported"
+        );
+
+        let constraints = get_symbolic_constraints(air, preprocessed_width, num_public_values);
+        let mut system = Self::from_symbolic_constraints(
+            air.width(),
+            preprocessed,
+            num_public_values,
+            &constraints,
+        )
+        .expect("the symbolic constraints of an AIR are valid");
+        // The constraints may not read every row of the window of the AIR.
+        system.window_size = air.window_size();
+        system
+    }
+
+    /// Describe symbolic constraints over a main trace of the given width, an optional
+    /// preprocessed trace and `num_public_values` public values.
+    ///
+    /// The constraints may only read main and preprocessed columns, public values and selectors.
+    /// The window is the smallest one covering every row the constraints read or select.
+    pub fn from_symbolic_constraints(
+        width: usize,
+        preprocessed: Option<RowMajorMatrix<F>>,
+        num_public_values: usize,
+        constraints: &[SymbolicExpression<F>],
+    ) -> Result<Self, ConstraintSystemError> {
+        let (nodes, roots) = flatten(constraints);
+        Self::from_flat(width, preprocessed, num_public_values, &nodes, roots)
+    }
+
+    /// Describe flattened constraints, given the nodes of the DAG and the node of each
+    /// constraint in increasing order, see [`flatten`].
+    pub(crate) fn from_flat(
+        width: usize,
+        preprocessed: Option<RowMajorMatrix<F>>,
+        num_public_values: usize,
+        nodes: &[FlatNode<F>],
+        constraints: Vec<usize>,
+    ) -> Result<Self, ConstraintSystemError> {
+        let nodes = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| {
+                // The constraint which first reads a node is the first one whose node is not
+                // before it.
+                ConstraintNode::from_flat(node).ok_or(ConstraintSystemError::UnsupportedVariable {
+                    constraint: constraints.partition_point(|&root| root < i),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut selectors = SelectorUsage::default();
+        for node in &nodes {
+            match node {
+                ConstraintNode::IsFirstRow => selectors.is_first_row = true,
+                ConstraintNode::IsLastRow => selectors.is_last_row = true,
+                ConstraintNode::IsTransition => selectors.is_transition = true,
+                ConstraintNode::IsTransitionWindow(_) => selectors.is_transition_window = true,
+                _ => {}
+            }
+        }
+        let window_size = nodes
+            .iter()
+            .map(|node| match *node {
+                ConstraintNode::Main { offset, .. }
+                | ConstraintNode::Preprocessed { offset, .. } => offset + 1,
+                ConstraintNode::IsTransitionWindow(size) => size,
+                _ => 0,
+            })
+            .fold(2, usize::max);
+
+        let system = Self {
+            version: CONSTRAINT_SYSTEM_VERSION,
+            width,
+            window_size,
+            preprocessed,
+            num_public_values,
+            selectors,
+            nodes,
+            constraints,
+        };
+        system.validate()?;
+        Ok(system)
+    }
+
+    /// Check that the constraint system is well formed.
+    pub fn validate(&self) -> Result<(), ConstraintSystemError> {
+        if self.version != CONSTRAINT_SYSTEM_VERSION {
+            return Err(ConstraintSystemError::UnsupportedVersion {
+                version: self.version,
+            });
+        }
+        if self.window_size < 2 {
+            return Err(ConstraintSystemError::InvalidWindowSize {
+                window_size: self.window_size,
+            });
+        }
+        let preprocessed_width = self.preprocessed.as_ref().map_or(0, |prep| prep.width());
+        for (i, node) in self.nodes.iter().enumerate() {
+            let valid_variable = match *node {
+                ConstraintNode::Main { offset, index } => {
+                    offset < self.window_size && index < self.width
+                }
+                ConstraintNode::Preprocessed { offset, index } => {
+                    offset < self.window_size && index < preprocessed_width
+                }
+                ConstraintNode::Public(index) => index < self.num_public_values,
+                ConstraintNode::IsTransitionWindow(size) => size <= self.window_size,
+                _ => true,
+            };
+            if !valid_variable {
+                return Err(ConstraintSystemError::InvalidVariable { node: i });
+            }
+            let declared_selector = match node {
+                ConstraintNode::IsFirstRow => self.selectors.is_first_row,
+                ConstraintNode::IsLastRow => self.selectors.is_last_row,
+                ConstraintNode::IsTransition => self.selectors.is_transition,
+                ConstraintNode::IsTransitionWindow(_) => self.selectors.is_transition_window,
+                _ => true,
+            };
+            if !declared_selector {
+                return Err(ConstraintSystemError::UndeclaredSelector { node: i });
+            }
+            if node.operands().iter().any(|&x| x >= i) {
+                return Err(ConstraintSystemError::InvalidOperand { node: i });
+            }
+        }
+        if let Some(constraint) = self
+            .constraints
+            .iter()
+            .position(|&node| node >= self.nodes.len())
+        {
+            return Err(ConstraintSystemError::InvalidConstraint { constraint });
+        }
+        Ok(())
+    }
+
+    /// Rebuild the symbolic constraints, as returned by
+    /// [`get_symbolic_constraints`](crate::get_symbolic_constraints).
+    pub fn symbolic_constraints(&self) -> Vec<SymbolicExpression<F>> {
+        let variable = |entry, index| SymbolicExpression::from(SymbolicVariable::new(entry, index));
+        let values = self.eval_nodes(|node| match *node {
+            ConstraintNode::Main { offset, index } => variable(Entry::Main { offset }, index),
+            ConstraintNode::Preprocessed { offset, index } => {
+                variable(Entry::Preprocessed { offset }, index)
+            }
+            ConstraintNode::Public(index) => variable(Entry::Public, index),
+            ConstraintNode::IsFirstRow => SymbolicExpression::IsFirstRow,
+            ConstraintNode::IsLastRow => SymbolicExpression::IsLastRow,
+            ConstraintNode::IsTransition => SymbolicExpression::IsTransition,
+            ConstraintNode::IsTransitionWindow(size) => match size {
+                0 | 1 => SymbolicExpression::Constant(F::ONE),
+                2 => SymbolicExpression::IsTransition,
+                _ => SymbolicExpression::IsTransitionWindow(size),
+            },
+            _ => unreachable!("operations are evaluated by eval_nodes"),
+        });
+        self.constraints
+            .iter()
+            .map(|&constraint| values[constraint].clone())
+            .collect()
+    }
+
+    /// Evaluate every node, reading leaves other than constants through `leaf`.
+    pub(crate) fn eval_nodes<E: Algebra<F>>(
+        &self,
+        mut leaf: impl FnMut(&ConstraintNode<F>) -> E,
+    ) -> Vec<E> {
+        let mut values: Vec<E> = Vec::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            let value = node.eval_operation(&values).unwrap_or_else(|| leaf(node));
+            values.push(value);
+        }
+        values
+    }
+}
+
+impl<F: Field> ConstraintNode<F> {
+    /// Evaluate a constant or an operation given the values of every earlier node, or return
+    /// `None` for a variable or a selector.
+    pub(crate) fn eval_operation<E: Algebra<F>>(&self, values: &[E]) -> Option<E> {
+        Some(match *self {
+            Self::Constant(c) => c.into(),
+            Self::Add(x, y) => values[x].clone() + values[y].clone(),
+            Self::Sub(x, y) => values[x].clone() - values[y].clone(),
+            Self::Neg(x) => -values[x].clone(),
+            Self::Mul(x, y) => values[x].clone() * values[y].clone(),
+            _ => return None,
+        })
+    }
+}
+
+impl<F> ConstraintNode<F> {
+    fn operands(&self) -> Vec<usize> {
+        match *self {
+            Self::Add(x, y) | Self::Sub(x, y) | Self::Mul(x, y) => alloc::vec![x, y],
+            Self::Neg(x) => alloc::vec![x],
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl<F: Copy> ConstraintNode<F> {
+    /// Convert a flattened node, or return `None` if it reads an unsupported variable.
+    fn from_flat(node: &FlatNode<F>) -> Option<Self> {
+        Some(match *node {
+            FlatNode::Variable(v) => match v.entry {
+                Entry::Main { offset } => Self::Main {
+                    offset,
+                    index: v.index,
+                },
+                Entry::Preprocessed { offset } => Self::Preprocessed {
+                    offset,
+                    index: v.index,
+                },
+                Entry::Public => Self::Public(v.index),
+                _ => return None,
+            },
+            FlatNode::IsFirstRow => Self::IsFirstRow,
+            FlatNode::IsLastRow => Self::IsLastRow,
+            FlatNode::IsTransition => Self::IsTransition,
+            FlatNode::IsTransitionWindow(size) => Self::IsTransitionWindow(size),
+            FlatNode::Constant(c) => Self::Constant(c),
+            FlatNode::Add(x, y) => Self::Add(x, y),
+            FlatNode::Sub(x, y) => Self::Sub(x, y),
+            FlatNode::Neg(x) => Self::Neg(x),
+            FlatNode::Mul(x, y) => Self::Mul(x, y),
+        })
+    }
+}
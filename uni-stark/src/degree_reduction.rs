@@ -0,0 +1,407 @@
+//! Lowering the degree of the constraints of an AIR by introducing auxiliary columns.
+//!
+//! The quotient domain of an AIR grows with the degree of its highest degree constraint, so a
+//! single high degree constraint makes the whole proof more expensive. [`DegreeReducedAir`]
+//! rewrites the constraints of an AIR so that no constraint exceeds a given degree: whenever a
+//! product is of too high a degree, one of its factors is replaced by a new column `aux`, together
+//! with the constraint `aux = factor`.
+
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+
+use p3_air::{Air, AirBuilderWithPublicValues, BaseAir, BaseAirWithPublicValues, PairBuilder};
+use p3_field::Field;
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_maybe_rayon::prelude::*;
+use tracing::instrument;
+
+use crate::flatten::{FlatNode, Flattener};
+use crate::{
+    ConstraintNode, ConstraintSystem, DynamicAir, Entry, SymbolicAirBuilder, SymbolicExpression,
+    SymbolicVariable, get_symbolic_constraints, get_symbolic_interactions,
+};
+
+/// An AIR whose constraints are those of another AIR, rewritten to have degree at most
+/// `max_degree` by appending auxiliary columns to its main trace.
+///
+/// The rewritten constraints, including those defining the auxiliary columns, form a
+/// [`ConstraintSystem`] evaluated through a [`DynamicAir`]. Use
+/// [`generate_trace`](Self::generate_trace) to fill in the auxiliary columns of a trace of the
+/// original AIR. The preprocessed trace and public values of the original AIR are kept as they
+/// are.
+///
+/// The original AIR may not have interactions, trace phases or trace padding.
+#[derive(Clone, Debug)]
+pub struct DegreeReducedAir<F> {
+    /// The width of the original AIR.
+    width: usize,
+    air: DynamicAir<F>,
+    /// The node of the constraint system defining each auxiliary column.
+    aux_columns: Vec<usize>,
+}
+
+impl<F: Field> DegreeReducedAir<F> {
+    /// Rewrite the constraints of `air` to have degree at most `max_degree`.
+    ///
+    /// # Panics
+    /// This function panics if `max_degree < 2`, or if `air` has interactions, trace phases or
+    /// trace padding.
+    #[instrument(name = "reduce constraint degree", skip_all)]
+    pub fn new<A>(air: &A, max_degree: usize) -> Self
+    where
+        A: BaseAirWithPublicValues<F> + Air<SymbolicAirBuilder<F>>,
+    {
+        assert!(
+            max_degree >= 2,
+            "constraints cannot be reduced below degree 2"
+        );
+        assert!(
+            air.trace_phases().is_empty() && air.trace_padding().is_none(),
+            "the degree of AIRs with trace phases or trace padding cannot be reduced"
+        );
+        let width = air.width();
+        let preprocessed = air.preprocessed_trace();
+        let preprocessed_width = preprocessed.as_ref().map_or(0, |prep| prep.width());
+        let num_public_values = air.num_public_values();
+        assert!(
+            get_symbolic_interactions(air, preprocessed_width, num_public_values).is_empty(),
+            "the degree of AIRs with interactions cannot be reduced"
+        );
+
+        let constraints = get_symbolic_constraints(air, preprocessed_width, num_public_values);
+        let mut reducer = DegreeReducer {
+            max_degree,
+            width,
+            reduced: BTreeMap::new(),
+            aux: BTreeMap::new(),
+            aux_columns: Vec::new(),
+        };
+        let constraints = constraints
+            .iter()
+            .map(|constraint| reducer.reduce(constraint))
+            .collect::<Vec<_>>();
+
+        // Flatten the auxiliary column definitions first, so that every auxiliary column is
+        // defined by an earlier node than any node reading it.
+        let mut flattener = Flattener::default();
+        let aux_columns = reducer
+            .aux_columns
+            .iter()
+            .map(|def| flattener.flatten(def))
+            .collect::<Vec<_>>();
+        let mut constraints = constraints
+            .iter()
+            .map(|constraint| flattener.flatten(constraint))
+            .collect::<Vec<_>>();
+        for (i, &def) in aux_columns.iter().enumerate() {
+            let aux = flattener.push(FlatNode::Variable(SymbolicVariable::new(
+                Entry::Main { offset: 0 },
+                width + i,
+            )));
+            constraints.push(flattener.push(FlatNode::Sub(aux, def)));
+        }
+
+        let mut system = ConstraintSystem::from_flat(
+            width + aux_columns.len(),
+            preprocessed,
+            num_public_values,
+            &flattener.into_nodes(),
+            constraints,
+        )
+        .expect("the reduced constraints only read supported variables");
+        // The constraints may not read every row of the window of the AIR.
+        system.window_size = air.window_size();
+        let air = DynamicAir::try_from(system).expect("the reduced constraints are valid");
+
+        Self {
+            width,
+            air,
+            aux_columns,
+        }
+    }
+
+    /// The number of auxiliary columns appended to the main trace of the original AIR.
+    pub const fn num_aux_columns(&self) -> usize {
+        self.aux_columns.len()
+    }
+
+    /// The rewritten constraints, e.g. to store them.
+    pub const fn constraint_system(&self) -> &ConstraintSystem<F> {
+        self.air.constraint_system()
+    }
+
+    /// Append the auxiliary columns to `main`, a trace of the original AIR.
+    #[instrument(name = "generate auxiliary columns", skip_all)]
+    pub fn generate_trace(
+        &self,
+        main: &RowMajorMatrix<F>,
+        public_values: &[F],
+    ) -> RowMajorMatrix<F> {
+        assert_eq!(main.width(), self.width, "the trace has the wrong width");
+        let system = self.air.constraint_system();
+        let height = main.height();
+        let full_width = system.width;
+        // Only the nodes up to the last auxiliary column definition are needed.
+        let num_nodes = self.aux_columns.last().map_or(0, |&def| def + 1);
+
+        let mut values = F::zero_vec(height * full_width);
+        values
+            .par_chunks_exact_mut(full_width)
+            .enumerate()
+            .for_each(|(row, values)| {
+                values[..self.width].copy_from_slice(&main.row_slice(row).unwrap());
+                let mut node_values = Vec::with_capacity(num_nodes);
+                let mut aux_columns = self.aux_columns.iter().enumerate().peekable();
+                for (i, node) in system.nodes[..num_nodes].iter().enumerate() {
+                    let value = node
+                        .eval_operation(&node_values)
+                        .unwrap_or_else(|| match *node {
+                            // Auxiliary columns are only read in the current row, after being
+                            // defined.
+                            ConstraintNode::Main { offset: 0, index } => values[index],
+                            ConstraintNode::Main { offset, index } => {
+                                main.get((row + offset) % height, index).unwrap()
+                            }
+                            ConstraintNode::Preprocessed { offset, index } => system
+                                .preprocessed
+                                .as_ref()
+                                .and_then(|prep| prep.get((row + offset) % height, index))
+                                .expect("missing preprocessed trace"),
+                            ConstraintNode::Public(index) => public_values[index],
+                            ConstraintNode::IsFirstRow => F::from_bool(row == 0),
+                            ConstraintNode::IsLastRow => F::from_bool(row == height - 1),
+                            ConstraintNode::IsTransition => F::from_bool(row != height - 1),
+                            ConstraintNode::IsTransitionWindow(size) => {
+                                F::from_bool(row + size <= height)
+                            }
+                            _ => unreachable!("operations are evaluated by eval_operation"),
+                        });
+                    node_values.push(value);
+                    if let Some((aux, _)) = aux_columns.next_if(|&(_, &def)| def == i) {
+                        values[self.width + aux] = value;
+                    }
+                }
+            });
+        RowMajorMatrix::new(values, full_width)
+    }
+}
+
+impl<F: Field> BaseAir<F> for DegreeReducedAir<F> {
+    fn width(&self) -> usize {
+        self.air.width()
+    }
+
+    fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
+        self.air.preprocessed_trace()
+    }
+
+    fn window_size(&self) -> usize {
+        self.air.window_size()
+    }
+}
+
+impl<F: Field> BaseAirWithPublicValues<F> for DegreeReducedAir<F> {
+    fn num_public_values(&self) -> usize {
+        self.air.num_public_values()
+    }
+}
+
+impl<AB> Air<AB> for DegreeReducedAir<AB::F>
+where
+    AB: PairBuilder + AirBuilderWithPublicValues,
+    AB::F: Field,
+{
+    fn eval(&self, builder: &mut AB) {
+        self.air.eval(builder);
+    }
+}
+
+/// Rewrites symbolic expressions to have degree at most `max_degree`, collecting the definitions
+/// of the auxiliary columns it introduces.
+struct DegreeReducer<F> {
+    max_degree: usize,
+    /// The width of the original AIR, i.e. the index of the first auxiliary column.
+    width: usize,
+    /// The reduced form of each shared subexpression reduced so far.
+    reduced: BTreeMap<*const SymbolicExpression<F>, Rc<SymbolicExpression<F>>>,
+    /// The auxiliary column replacing each shared subexpression replaced so far.
+    aux: BTreeMap<*const SymbolicExpression<F>, Rc<SymbolicExpression<F>>>,
+    /// The reduced definition of each auxiliary column.
+    aux_columns: Vec<SymbolicExpression<F>>,
+}
+
+impl<F: Field> DegreeReducer<F> {
+    /// Reduce a shared subexpression, unless it has already been reduced.
+    fn reduce_shared(&mut self, expr: &Rc<SymbolicExpression<F>>) -> Rc<SymbolicExpression<F>> {
+        if let Some(reduced) = self.reduced.get(&Rc::as_ptr(expr)) {
+            return reduced.clone();
+        }
+        let reduced = Rc::new(self.reduce(expr));
+        self.reduced.insert(Rc::as_ptr(expr), reduced.clone());
+        reduced
+    }
+
+    /// Rewrite `expr` to have degree at most `max_degree`.
+    fn reduce(&mut self, expr: &SymbolicExpression<F>) -> SymbolicExpression<F> {
+        match expr {
+            SymbolicExpression::Add { x, y, .. } => {
+                let (x, y) = (self.reduce_shared(x), self.reduce_shared(y));
+                SymbolicExpression::Add {
+                    degree_multiple: x.degree_multiple().max(y.degree_multiple()),
+                    x,
+                    y,
+                }
+            }
+            SymbolicExpression::Sub { x, y, .. } => {
+                let (x, y) = (self.reduce_shared(x), self.reduce_shared(y));
+                SymbolicExpression::Sub {
+                    degree_multiple: x.degree_multiple().max(y.degree_multiple()),
+                    x,
+                    y,
+                }
+            }
+            SymbolicExpression::Neg { x, .. } => {
+                let x = self.reduce_shared(x);
+                SymbolicExpression::Neg {
+                    degree_multiple: x.degree_multiple(),
+                    x,
+                }
+            }
+            SymbolicExpression::Mul { x, y, .. } => {
+                let (mut reduced_x, mut reduced_y) = (self.reduce_shared(x), self.reduce_shared(y));
+                // Both factors have degree at most `max_degree >= 2`, so this replaces at most
+                // two factors, each by a degree 1 column.
+                while reduced_x.degree_multiple() + reduced_y.degree_multiple() > self.max_degree {
+                    if reduced_x.degree_multiple() >= reduced_y.degree_multiple() {
+                        reduced_x = self.aux_column(x, reduced_x);
+                    } else {
+                        reduced_y = self.aux_column(y, reduced_y);
+                    }
+                }
+                SymbolicExpression::Mul {
+                    degree_multiple: reduced_x.degree_multiple() + reduced_y.degree_multiple(),
+                    x: reduced_x,
+                    y: reduced_y,
+                }
+            }
+            SymbolicExpression::Variable(v) => {
+                assert!(
+                    matches!(
+                        v.entry,
+                        Entry::Main { .. } | Entry::Preprocessed { .. } | Entry::Public
+                    ),
+                    "only constraints over the main and preprocessed traces can be reduced"
+                );
+                expr.clone()
+            }
+            SymbolicExpression::IsFirstRow
+            | SymbolicExpression::IsLastRow
+            | SymbolicExpression::IsTransition
+            | SymbolicExpression::IsTransitionWindow(_)
+            | SymbolicExpression::Constant(_) => expr.clone(),
+        }
+    }
+
+    /// Replace the subexpression `expr`, whose reduced form is `reduced`, by an auxiliary column.
+    fn aux_column(
+        &mut self,
+        expr: &Rc<SymbolicExpression<F>>,
+        reduced: Rc<SymbolicExpression<F>>,
+    ) -> Rc<SymbolicExpression<F>> {
+        self.aux
+            .entry(Rc::as_ptr(expr))
+            .or_insert_with(|| {
+                let index = self.width + self.aux_columns.len();
+                self.aux_columns.push((*reduced).clone());
+                Rc::new(SymbolicVariable::new(Entry::Main { offset: 0 }, index).into())
+            })
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use p3_air::AirBuilder;
+    use p3_baby_bear::BabyBear;
+    use p3_field::PrimeCharacteristicRing;
+
+    use super::*;
+    use crate::{constraint_report, get_max_constraint_degree};
+
+    /// Checks `x_{i+1} = x_i^9 + y_i` on transitions and `x_0 = 1`, where `y` is a constant column
+    /// given by the first public value.
+    struct PowerAir;
+
+    impl<F> BaseAir<F> for PowerAir {
+        fn width(&self) -> usize {
+            2
+        }
+    }
+
+    impl<F> BaseAirWithPublicValues<F> for PowerAir {
+        fn num_public_values(&self) -> usize {
+            1
+        }
+    }
+
+    impl<AB: AirBuilderWithPublicValues> Air<AB> for PowerAir {
+        fn eval(&self, builder: &mut AB) {
+            let main = builder.main();
+            let local = main.row_slice(0).expect("Matrix is empty?");
+            let next = main.row_slice(1).expect("Matrix only has 1 row?");
+            let y: AB::Expr = builder.public_values()[0].into();
+            let x: AB::Expr = local[0].clone().into();
+            builder.when_first_row().assert_one(x.clone());
+            builder.assert_eq(local[1].clone(), y);
+            builder
+                .when_transition()
+                .assert_eq(next[0].clone(), x.exp_const_u64::<9>() + local[1].clone());
+        }
+    }
+
+    fn power_trace(height: usize, y: BabyBear) -> RowMajorMatrix<BabyBear> {
+        let mut x = BabyBear::ONE;
+        let mut values = Vec::with_capacity(2 * height);
+        for _ in 0..height {
+            values.extend([x, y]);
+            x = x.exp_const_u64::<9>() + y;
+        }
+        RowMajorMatrix::new(values, 2)
+    }
+
+    #[test]
+    fn test_reduce_degree() {
+        assert_eq!(get_max_constraint_degree::<BabyBear, _>(&PowerAir, 0, 1), 9);
+        let y = BabyBear::new(5);
+        let trace = power_trace(8, y);
+
+        for max_degree in [2, 3, 4, 9] {
+            let air = DegreeReducedAir::new(&PowerAir, max_degree);
+            assert!(get_max_constraint_degree::<BabyBear, _>(&air, 0, 1) <= max_degree);
+            assert_eq!(air.num_aux_columns() == 0, max_degree == 9);
+
+            let trace = air.generate_trace(&trace, &[y]);
+            assert_eq!(trace.width(), 2 + air.num_aux_columns());
+            assert!(constraint_report(&air, &trace, None, &vec![y]).is_satisfied());
+        }
+    }
+
+    #[test]
+    fn test_reduce_degree_invalid_trace() {
+        let y = BabyBear::new(5);
+        let mut trace = power_trace(8, y);
+        trace.values[6] += BabyBear::ONE;
+
+        let air = DegreeReducedAir::new(&PowerAir, 3);
+        let trace = air.generate_trace(&trace, &[y]);
+        let report = constraint_report(&air, &trace, None, &vec![y]);
+        // The auxiliary columns are consistent with the altered trace, but the transitions into
+        // and out of row 3 are not.
+        let rows = report.failures.iter().map(|f| f.row).collect::<Vec<_>>();
+        assert_eq!(rows, vec![2, 3]);
+    }
+}
@@ -0,0 +1,121 @@
+//! An AIR whose constraints are only known at runtime.
+
+use alloc::vec::Vec;
+
+use p3_air::{Air, AirBuilderWithPublicValues, BaseAir, BaseAirWithPublicValues, PairBuilder};
+use p3_field::Field;
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+
+use crate::{ConstraintNode, ConstraintSystem, ConstraintSystemError, SymbolicExpression};
+
+/// An AIR defined by a list of symbolic constraints instead of a Rust type, e.g. one loaded from
+/// a file as a [`ConstraintSystem`].
+///
+/// The constraints are evaluated node by node for every builder, so a `DynamicAir` can be used
+/// wherever an AIR can, including proving, verifying and checking constraints.
+#[derive(Clone, Debug)]
+pub struct DynamicAir<F> {
+    system: ConstraintSystem<F>,
+}
+
+impl<F: Field> DynamicAir<F> {
+    /// Build an AIR asserting that each of `constraints` is zero.
+    ///
+    /// The constraints may read any row of the window of the main trace, of width `width`, and of
+    /// the preprocessed trace, as well as `num_public_values` public values and the row selectors.
+    /// The window is the smallest one covering every row the constraints read.
+    pub fn new(
+        width: usize,
+        preprocessed: Option<RowMajorMatrix<F>>,
+        num_public_values: usize,
+        constraints: &[SymbolicExpression<F>],
+    ) -> Result<Self, ConstraintSystemError> {
+        ConstraintSystem::from_symbolic_constraints(
+            width,
+            preprocessed,
+            num_public_values,
+            constraints,
+        )
+        .map(|system| Self { system })
+    }
+
+    /// The constraints of this AIR, e.g. to store them.
+    pub const fn constraint_system(&self) -> &ConstraintSystem<F> {
+        &self.system
+    }
+}
+
+impl<F: Field> TryFrom<ConstraintSystem<F>> for DynamicAir<F> {
+    type Error = ConstraintSystemError;
+
+    fn try_from(system: ConstraintSystem<F>) -> Result<Self, Self::Error> {
+        system.validate()?;
+        Ok(Self { system })
+    }
+}
+
+impl<F: Field> BaseAir<F> for DynamicAir<F> {
+    fn width(&self) -> usize {
+        self.system.width
+    }
+
+    fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
+        self.system.preprocessed.clone()
+    }
+
+    fn window_size(&self) -> usize {
+        self.system.window_size
+    }
+}
+
+impl<F: Field> BaseAirWithPublicValues<F> for DynamicAir<F> {
+    fn num_public_values(&self) -> usize {
+        self.system.num_public_values
+    }
+}
+
+impl<AB> Air<AB> for DynamicAir<AB::F>
+where
+    AB: PairBuilder + AirBuilderWithPublicValues,
+    AB::F: Field,
+{
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let main_rows = (0..self.system.window_size)
+            .map(|offset| {
+                main.row_slice(offset)
+                    .expect("Matrix is smaller than the window?")
+            })
+            .collect::<Vec<_>>();
+        let preprocessed = builder.preprocessed();
+        let preprocessed_rows = self.system.preprocessed.as_ref().map(|_| {
+            (0..self.system.window_size)
+                .map(|offset| {
+                    preprocessed
+                        .row_slice(offset)
+                        .expect("Matrix is smaller than the window?")
+                })
+                .collect::<Vec<_>>()
+        });
+        let public_values = builder.public_values();
+
+        let values = self.system.eval_nodes::<AB::Expr>(|node| match *node {
+            ConstraintNode::Main { offset, index } => main_rows[offset][index].clone().into(),
+            ConstraintNode::Preprocessed { offset, index } => {
+                preprocessed_rows.as_ref().expect("no preprocessed trace")[offset][index]
+                    .clone()
+                    .into()
+            }
+            ConstraintNode::Public(index) => public_values[index].into(),
+            ConstraintNode::IsFirstRow => builder.is_first_row(),
+            ConstraintNode::IsLastRow => builder.is_last_row(),
+            ConstraintNode::IsTransition => builder.is_transition(),
+            ConstraintNode::IsTransitionWindow(size) => builder.is_transition_window(size),
+            _ => unreachable!("operations are evaluated by eval_nodes"),
+        });
+        for &constraint in &self.system.constraints {
+            builder.assert_zero(values[constraint].clone());
+        }
+    }
+}
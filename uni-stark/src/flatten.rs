@@ -0,0 +1,135 @@
+//! Flattening symbolic expressions into a list of nodes.
+//!
+//! Symbolic expressions are trees whose subexpressions may be shared through an `Rc`. Everything
+//! which walks the constraints of an AIR node by node, e.g. to fingerprint, compile, export or
+//! rewrite them, starts from the DAG built here, in which every shared subexpression appears
+//! once and every node comes after its operands.
+
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+
+use p3_field::{Algebra, Field};
+
+use crate::{SymbolicExpression, SymbolicVariable};
+
+/// A node of a flattened symbolic expression, referring to its operands by index.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum FlatNode<F> {
+    Variable(SymbolicVariable<F>),
+    IsFirstRow,
+    IsLastRow,
+    IsTransition,
+    IsTransitionWindow(usize),
+    Constant(F),
+    Add(usize, usize),
+    Sub(usize, usize),
+    Neg(usize),
+    Mul(usize, usize),
+}
+
+impl<F: Field> FlatNode<F> {
+    /// Evaluate a constant or an operation given the values of every earlier node, or return
+    /// `None` for a variable or a selector.
+    pub(crate) fn eval_operation<E: Algebra<F>>(&self, values: &[E]) -> Option<E> {
+        Some(match *self {
+            Self::Constant(c) => c.into(),
+            Self::Add(x, y) => values[x].clone() + values[y].clone(),
+            Self::Sub(x, y) => values[x].clone() - values[y].clone(),
+            Self::Neg(x) => -values[x].clone(),
+            Self::Mul(x, y) => values[x].clone() * values[y].clone(),
+            Self::Variable(_)
+            | Self::IsFirstRow
+            | Self::IsLastRow
+            | Self::IsTransition
+            | Self::IsTransitionWindow(_) => return None,
+        })
+    }
+}
+
+/// Flattens symbolic expressions into nodes in topological order.
+///
+/// A subexpression shared through an `Rc` is flattened once, however many expressions use it.
+/// The expressions passed to [`flatten`](Self::flatten) themselves are not deduplicated, so each
+/// of them gets a node of its own, after the nodes of all earlier expressions.
+pub(crate) struct Flattener<F> {
+    ids: BTreeMap<*const SymbolicExpression<F>, usize>,
+    nodes: Vec<FlatNode<F>>,
+}
+
+impl<F> Default for Flattener<F> {
+    fn default() -> Self {
+        Self {
+            ids: BTreeMap::new(),
+            nodes: Vec::new(),
+        }
+    }
+}
+
+impl<F: Field> Flattener<F> {
+    /// Append a node whose operands have already been flattened and return its index.
+    pub(crate) fn push(&mut self, node: FlatNode<F>) -> usize {
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    /// Flatten a shared subexpression, unless it has already been flattened, and return its index.
+    fn flatten_shared(&mut self, expr: &Rc<SymbolicExpression<F>>) -> usize {
+        if let Some(&id) = self.ids.get(&Rc::as_ptr(expr)) {
+            return id;
+        }
+        let id = self.flatten(expr);
+        self.ids.insert(Rc::as_ptr(expr), id);
+        id
+    }
+
+    /// Flatten `expr` after its operands and return its index.
+    pub(crate) fn flatten(&mut self, expr: &SymbolicExpression<F>) -> usize {
+        let node = match expr {
+            SymbolicExpression::Variable(v) => FlatNode::Variable(*v),
+            SymbolicExpression::IsFirstRow => FlatNode::IsFirstRow,
+            SymbolicExpression::IsLastRow => FlatNode::IsLastRow,
+            SymbolicExpression::IsTransition => FlatNode::IsTransition,
+            SymbolicExpression::IsTransitionWindow(size) => FlatNode::IsTransitionWindow(*size),
+            SymbolicExpression::Constant(c) => FlatNode::Constant(*c),
+            SymbolicExpression::Add { x, y, .. } => {
+                FlatNode::Add(self.flatten_shared(x), self.flatten_shared(y))
+            }
+            SymbolicExpression::Sub { x, y, .. } => {
+                FlatNode::Sub(self.flatten_shared(x), self.flatten_shared(y))
+            }
+            SymbolicExpression::Neg { x, .. } => FlatNode::Neg(self.flatten_shared(x)),
+            SymbolicExpression::Mul { x, y, .. } => {
+                FlatNode::Mul(self.flatten_shared(x), self.flatten_shared(y))
+            }
+        };
+        self.push(node)
+    }
+
+    pub(crate) fn into_nodes(self) -> Vec<FlatNode<F>> {
+        self.nodes
+    }
+}
+
+/// Flatten `exprs`, returning the nodes along with the node of each expression.
+///
+/// The node of each expression is the last of the nodes added while flattening it, so the
+/// returned indices are increasing.
+pub(crate) fn flatten<F: Field>(exprs: &[SymbolicExpression<F>]) -> (Vec<FlatNode<F>>, Vec<usize>) {
+    let mut flattener = Flattener::default();
+    let roots = exprs.iter().map(|expr| flattener.flatten(expr)).collect();
+    (flattener.into_nodes(), roots)
+}
+
+/// Evaluate every node, reading variables and selectors through `leaf`.
+pub(crate) fn eval_flat<F: Field, E: Algebra<F>>(
+    nodes: &[FlatNode<F>],
+    mut leaf: impl FnMut(&FlatNode<F>) -> E,
+) -> Vec<E> {
+    let mut values = Vec::with_capacity(nodes.len());
+    for node in nodes {
+        let value = node.eval_operation(&values).unwrap_or_else(|| leaf(node));
+        values.push(value);
+    }
+    values
+}
@@ -1,7 +1,10 @@
 use alloc::vec::Vec;
 
-use p3_air::{AirBuilder, AirBuilderWithPublicValues};
-use p3_field::{BasedVectorSpace, PackedField};
+use p3_air::{
+    AirBuilder, AirBuilderWithPublicValues, ExtensionBuilder, Interaction, InteractionBuilder,
+    MultiPhaseAirBuilder, PairBuilder, PermutationAirBuilder,
+};
+use p3_field::{BasedVectorSpace, PackedField, PrimeCharacteristicRing};
 use p3_matrix::dense::RowMajorMatrixView;
 use p3_matrix::stack::ViewPair;
 
@@ -12,10 +15,17 @@ use crate::{PackedChallenge, PackedVal, StarkGenericConfig, Val};
 /// This struct is responsible for evaluating constraints corresponding to a given row in the trace matrix.
 /// It accumulates them into a single value using a randomized challenge.
 /// `C_0 + alpha C_1 + alpha^2 C_2 + ...`
+///
+/// The prover computes the quotient through a [`ConstraintProgram`](crate::ConstraintProgram)
+/// instead, so this folder only supports AIRs with a window of two rows and without interactions,
+/// trace phases or padding.
 #[derive(Debug)]
 pub struct ProverConstraintFolder<'a, SC: StarkGenericConfig> {
     /// The matrix containing rows on which the constraint polynomial is to be evaluated
     pub main: RowMajorMatrixView<'a, PackedVal<SC>>,
+    /// The matrix containing the corresponding rows of the preprocessed trace.
+    /// This has width `0` if the AIR has no preprocessed columns.
+    pub preprocessed: RowMajorMatrixView<'a, PackedVal<SC>>,
     /// Public inputs to the AIR
     pub public_values: &'a Vec<Val<SC>>,
     /// Evaluations of the Selector polynomial for the first row of the trace
@@ -41,8 +51,25 @@ pub struct ProverConstraintFolder<'a, SC: StarkGenericConfig> {
 /// using a more efficient accumulation method for verification.
 #[derive(Debug)]
 pub struct VerifierConstraintFolder<'a, SC: StarkGenericConfig> {
-    /// Pair of consecutive rows from the committed polynomial evaluations
-    pub main: ViewPair<'a, SC::Challenge>,
+    /// The window of consecutive rows from the committed polynomial evaluations
+    pub main: RowMajorMatrixView<'a, SC::Challenge>,
+    /// The window of consecutive rows from the committed preprocessed polynomial evaluations.
+    /// These rows are empty if the AIR has no preprocessed columns.
+    pub preprocessed: RowMajorMatrixView<'a, SC::Challenge>,
+    /// Pair of consecutive rows from the committed LogUp permutation trace.
+    /// These rows are empty if the AIR has no interactions.
+    pub permutation: ViewPair<'a, SC::Challenge>,
+    /// The LogUp challenges, empty if no AIR being verified has interactions.
+    pub permutation_challenges: &'a [SC::Challenge],
+    /// The interactions pushed by the AIR so far.
+    pub interactions: Vec<Interaction<SC::Challenge>>,
+    /// The windows of consecutive rows from each committed challenge-dependent trace phase.
+    pub phases: Vec<RowMajorMatrixView<'a, SC::Challenge>>,
+    /// The challenges sampled before each trace phase.
+    pub phase_challenges: &'a [Vec<SC::Challenge>],
+    /// Pair of consecutive rows from the committed padding columns.
+    /// These rows are empty if the AIR does not pad its trace.
+    pub padding: ViewPair<'a, SC::Challenge>,
     /// Public values that are inputs to the computation
     pub public_values: &'a Vec<Val<SC>>,
     /// Evaluations of the Selector polynomial for the first row of the trace
@@ -51,12 +78,32 @@ pub struct VerifierConstraintFolder<'a, SC: StarkGenericConfig> {
     pub is_last_row: SC::Challenge,
     /// Evaluations of the Selector polynomial for rows where transition constraints should be applied
     pub is_transition: SC::Challenge,
+    /// Evaluations of the selectors of `is_transition_window(size)` for `size = 3, 4, ...` up to
+    /// the window size of the AIR.
+    pub is_transition_windows: &'a [SC::Challenge],
     /// Single challenge value used for constraint combination
     pub alpha: SC::Challenge,
     /// Running accumulator for all constraints
     pub accumulator: SC::Challenge,
 }
 
+/// Select the selector of `is_transition_window(size)` from the selector of `is_transition` and
+/// those of larger windows.
+#[inline]
+pub(crate) fn transition_window<T: PrimeCharacteristicRing + Copy>(
+    is_transition: T,
+    is_transition_windows: &[T],
+    size: usize,
+) -> T {
+    match size {
+        0 | 1 => T::ONE,
+        2 => is_transition,
+        _ => *is_transition_windows
+            .get(size - 3)
+            .unwrap_or_else(|| panic!("the window of the AIR has fewer than {size} rows")),
+    }
+}
+
 impl<'a, SC: StarkGenericConfig> AirBuilder for ProverConstraintFolder<'a, SC> {
     type F = Val<SC>;
     type Expr = PackedVal<SC>;
@@ -81,14 +128,10 @@ impl<'a, SC: StarkGenericConfig> AirBuilder for ProverConstraintFolder<'a, SC> {
     /// Returns an expression indicating rows where transition constraints should be checked.
     ///
     /// # Panics
-    /// This function panics if `size` is not `2`.
+    /// This function panics if `size` exceeds `2`.
     #[inline]
     fn is_transition_window(&self, size: usize) -> Self::Expr {
-        if size == 2 {
-            self.is_transition
-        } else {
-            panic!("uni-stark only supports a window size of 2")
-        }
+        transition_window(self.is_transition, &[], size)
     }
 
     #[inline]
@@ -119,11 +162,34 @@ impl<SC: StarkGenericConfig> AirBuilderWithPublicValues for ProverConstraintFold
     }
 }
 
+impl<SC: StarkGenericConfig> PairBuilder for ProverConstraintFolder<'_, SC> {
+    #[inline]
+    fn preprocessed(&self) -> Self::M {
+        self.preprocessed
+    }
+}
+
+impl<SC: StarkGenericConfig> ExtensionBuilder for ProverConstraintFolder<'_, SC> {
+    type EF = SC::Challenge;
+    type ExprEF = PackedChallenge<SC>;
+    type VarEF = PackedChallenge<SC>;
+
+    #[inline]
+    fn assert_zero_ext<I>(&mut self, x: I)
+    where
+        I: Into<Self::ExprEF>,
+    {
+        let alpha_power = self.alpha_powers[self.constraint_index];
+        self.accumulator += Into::<PackedChallenge<SC>>::into(alpha_power) * x.into();
+        self.constraint_index += 1;
+    }
+}
+
 impl<'a, SC: StarkGenericConfig> AirBuilder for VerifierConstraintFolder<'a, SC> {
     type F = Val<SC>;
     type Expr = SC::Challenge;
     type Var = SC::Challenge;
-    type M = ViewPair<'a, SC::Challenge>;
+    type M = RowMajorMatrixView<'a, SC::Challenge>;
 
     fn main(&self) -> Self::M {
         self.main
@@ -140,19 +206,19 @@ impl<'a, SC: StarkGenericConfig> AirBuilder for VerifierConstraintFolder<'a, SC>
     /// Returns an expression indicating rows where transition constraints should be checked.
     ///
     /// # Panics
-    /// This function panics if `size` is not `2`.
+    /// This function panics if `size` exceeds the window size of the AIR.
     fn is_transition_window(&self, size: usize) -> Self::Expr {
-        if size == 2 {
-            self.is_transition
-        } else {
-            panic!("uni-stark only supports a window size of 2")
-        }
+        transition_window(self.is_transition, self.is_transition_windows, size)
     }
 
     fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
         self.accumulator *= self.alpha;
         self.accumulator += x.into();
     }
+
+    fn is_padding(&self) -> Self::Expr {
+        self.padding.top.values.first().copied().unwrap_or_default()
+    }
 }
 
 impl<SC: StarkGenericConfig> AirBuilderWithPublicValues for VerifierConstraintFolder<'_, SC> {
@@ -162,3 +228,64 @@ impl<SC: StarkGenericConfig> AirBuilderWithPublicValues for VerifierConstraintFo
         self.public_values
     }
 }
+
+impl<SC: StarkGenericConfig> PairBuilder for VerifierConstraintFolder<'_, SC> {
+    fn preprocessed(&self) -> Self::M {
+        self.preprocessed
+    }
+}
+
+impl<SC: StarkGenericConfig> ExtensionBuilder for VerifierConstraintFolder<'_, SC> {
+    type EF = SC::Challenge;
+    type ExprEF = SC::Challenge;
+    type VarEF = SC::Challenge;
+
+    fn assert_zero_ext<I>(&mut self, x: I)
+    where
+        I: Into<Self::ExprEF>,
+    {
+        self.accumulator *= self.alpha;
+        self.accumulator += x.into();
+    }
+}
+
+impl<'a, SC: StarkGenericConfig> PermutationAirBuilder for VerifierConstraintFolder<'a, SC> {
+    type MP = ViewPair<'a, SC::Challenge>;
+    type RandomVar = SC::Challenge;
+
+    fn permutation(&self) -> Self::MP {
+        self.permutation
+    }
+
+    fn permutation_randomness(&self) -> &[Self::RandomVar] {
+        self.permutation_challenges
+    }
+}
+
+impl<'a, SC: StarkGenericConfig> MultiPhaseAirBuilder for VerifierConstraintFolder<'a, SC> {
+    type MPhase = RowMajorMatrixView<'a, SC::Challenge>;
+    type PhaseChallengeVar = SC::Challenge;
+
+    fn phase(&self, phase: usize) -> Self::MPhase {
+        self.phases[phase]
+    }
+
+    fn phase_challenges(&self, phase: usize) -> &[Self::PhaseChallengeVar] {
+        &self.phase_challenges[phase]
+    }
+}
+
+impl<SC: StarkGenericConfig> InteractionBuilder for VerifierConstraintFolder<'_, SC> {
+    fn push_interaction<E: Into<Self::Expr>>(
+        &mut self,
+        bus: usize,
+        fields: impl IntoIterator<Item = E>,
+        multiplicity: impl Into<Self::Expr>,
+    ) {
+        self.interactions.push(Interaction {
+            fields: fields.into_iter().map(Into::into).collect(),
+            multiplicity: multiplicity.into(),
+            bus,
+        });
+    }
+}
@@ -5,22 +5,43 @@
 extern crate alloc;
 
 mod config;
+mod constraint_program;
+mod constraint_system;
+mod degree_reduction;
+mod dynamic_air;
+mod flatten;
 mod folder;
+mod logup;
+mod multi_prover;
+mod multi_verifier;
+mod padding;
+mod preprocessed;
 mod proof;
 mod prover;
 mod symbolic_builder;
 mod symbolic_expression;
 mod symbolic_variable;
 mod verifier;
+mod verifier_key;
 
 mod check_constraints;
 
 pub use check_constraints::*;
 pub use config::*;
+pub use constraint_program::*;
+pub use constraint_system::*;
+pub use degree_reduction::*;
+pub use dynamic_air::*;
 pub use folder::*;
+pub use logup::*;
+pub use multi_prover::*;
+pub use multi_verifier::*;
+pub use padding::*;
+pub use preprocessed::*;
 pub use proof::*;
 pub use prover::*;
 pub use symbolic_builder::*;
 pub use symbolic_expression::*;
 pub use symbolic_variable::*;
 pub use verifier::*;
+pub use verifier_key::*;
@@ -0,0 +1,192 @@
+//! A LogUp argument proving that the interactions of a set of AIRs balance.
+//!
+//! Given challenges `alpha` and `beta`, each message `(bus, f_0, ..., f_{k-1})` is fingerprinted as
+//! `d = beta + bus + alpha f_0 + ... + alpha^k f_{k-1}`. The permutation trace of an AIR with `n`
+//! interactions has `n + 1` extension field columns:
+//! - `h_i = m_i / d_i`, one for each interaction with multiplicity `m_i` and fingerprint `d_i`.
+//! - A running sum `phi` of the `h_i` over all rows so far.
+//!
+//! The value of `phi` in the last row is the cumulative sum of the AIR. If every message is sent as
+//! many times as it is received, the cumulative sums of all AIRs add up to zero.
+
+use alloc::vec::Vec;
+use core::iter;
+
+use itertools::{Itertools, izip};
+use p3_air::{ExtensionBuilder, Interaction, PermutationAirBuilder};
+use p3_field::{ExtensionField, Field, PrimeCharacteristicRing, batch_multiplicative_inverse};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_maybe_rayon::prelude::*;
+use tracing::instrument;
+
+use crate::flatten::{FlatNode, eval_flat, flatten};
+use crate::{Entry, SymbolicExpression};
+
+/// The number of challenges used by the LogUp argument, namely `alpha` and `beta`.
+pub const LOGUP_NUM_CHALLENGES: usize = 2;
+
+/// Fingerprint the message of an interaction, `beta + bus + alpha f_0 + ... + alpha^k f_{k-1}`.
+fn fingerprint<Expr, ExprEF>(
+    interaction: &Interaction<Expr>,
+    alpha: &ExprEF,
+    beta: &ExprEF,
+) -> ExprEF
+where
+    Expr: PrimeCharacteristicRing,
+    ExprEF: PrimeCharacteristicRing + From<Expr> + core::ops::Mul<Expr, Output = ExprEF>,
+{
+    let mut alpha_power = alpha.clone();
+    let mut fingerprint = beta.clone() + ExprEF::from(Expr::from_usize(interaction.bus));
+    for field in &interaction.fields {
+        fingerprint += alpha_power.clone() * field.clone();
+        alpha_power *= alpha.clone();
+    }
+    fingerprint
+}
+
+/// Enforce that the permutation trace of `builder` is the LogUp trace of `interactions`, and that
+/// its running sum ends at `cumulative_sum`.
+///
+/// The permutation trace must have `interactions.len() + 1` columns, and the permutation
+/// randomness must consist of the `LOGUP_NUM_CHALLENGES` challenges `[alpha, beta]`.
+pub fn eval_logup<AB: PermutationAirBuilder>(
+    builder: &mut AB,
+    interactions: &[Interaction<AB::Expr>],
+    cumulative_sum: AB::ExprEF,
+) {
+    let permutation = builder.permutation();
+    let local = permutation.row_slice(0).expect("Matrix is empty?");
+    let next = permutation.row_slice(1).expect("Matrix only has 1 row?");
+    assert_eq!(
+        local.len(),
+        interactions.len() + 1,
+        "the permutation trace must have one column per interaction plus a running sum"
+    );
+
+    let randomness = builder.permutation_randomness();
+    let alpha: AB::ExprEF = randomness[0].into();
+    let beta: AB::ExprEF = randomness[1].into();
+
+    // h_i * d_i = m_i
+    for (interaction, &h) in interactions.iter().zip(local.iter()) {
+        let fingerprint = fingerprint(interaction, &alpha, &beta);
+        builder.assert_eq_ext(h.into() * fingerprint, interaction.multiplicity.clone());
+    }
+
+    let n = interactions.len();
+    let phi_local: AB::ExprEF = local[n].into();
+    let phi_next: AB::ExprEF = next[n].into();
+    let sum_local: AB::ExprEF = local[..n].iter().map(|&h| h.into()).sum();
+    let sum_next: AB::ExprEF = next[..n].iter().map(|&h| h.into()).sum();
+
+    builder
+        .when_first_row()
+        .assert_eq_ext(phi_local.clone(), sum_local);
+    builder
+        .when_transition()
+        .assert_eq_ext(phi_next, phi_local.clone() + sum_next);
+    builder
+        .when_last_row()
+        .assert_eq_ext(phi_local, cumulative_sum);
+}
+
+/// Generate the LogUp permutation trace of `interactions`, returning it along with its cumulative sum.
+///
+/// `challenges` must contain the `LOGUP_NUM_CHALLENGES` challenges `[alpha, beta]`.
+///
+/// # Panics
+/// This function panics if an interaction depends on anything other than the main trace, the
+/// preprocessed trace and the public values, or if a fingerprint happens to be zero.
+#[instrument(name = "generate LogUp trace", skip_all)]
+pub fn generate_logup_trace<F: Field, EF: ExtensionField<F>>(
+    interactions: &[Interaction<SymbolicExpression<F>>],
+    main: &RowMajorMatrix<F>,
+    preprocessed: Option<&RowMajorMatrix<F>>,
+    public_values: &[F],
+    challenges: &[EF],
+) -> (RowMajorMatrix<EF>, EF) {
+    assert_eq!(challenges.len(), LOGUP_NUM_CHALLENGES);
+    let (alpha, beta) = (challenges[0], challenges[1]);
+    let height = main.height();
+    let num_interactions = interactions.len();
+
+    // Symbolic expressions cannot be shared across threads, so flatten the fields and
+    // multiplicity of every interaction, in this order, before evaluating them on every row.
+    let exprs = interactions
+        .iter()
+        .flat_map(|interaction| {
+            interaction
+                .fields
+                .iter()
+                .chain(iter::once(&interaction.multiplicity))
+                .cloned()
+        })
+        .collect_vec();
+    let (nodes, roots) = flatten(&exprs);
+    let buses_and_num_fields = interactions
+        .iter()
+        .map(|interaction| (interaction.bus, interaction.fields.len()))
+        .collect_vec();
+
+    // Evaluate the fingerprint and multiplicity of every interaction on every row.
+    let (fingerprints, multiplicities): (Vec<EF>, Vec<F>) = (0..height)
+        .into_par_iter()
+        .flat_map_iter(|row| {
+            let values = eval_flat(&nodes, |node| match *node {
+                FlatNode::Variable(v) => match v.entry {
+                    Entry::Main { offset } => main.get((row + offset) % height, v.index).unwrap(),
+                    Entry::Preprocessed { offset } => preprocessed
+                        .and_then(|prep| prep.get((row + offset) % height, v.index))
+                        .expect("missing preprocessed trace"),
+                    Entry::Public => public_values[v.index],
+                    Entry::Permutation { .. }
+                    | Entry::Phase { .. }
+                    | Entry::Padding { .. }
+                    | Entry::Challenge
+                    | Entry::PhaseChallenge { .. } => {
+                        panic!("interactions may only depend on the main and preprocessed traces")
+                    }
+                },
+                FlatNode::IsFirstRow => F::from_bool(row == 0),
+                FlatNode::IsLastRow => F::from_bool(row == height - 1),
+                FlatNode::IsTransition => F::from_bool(row != height - 1),
+                FlatNode::IsTransitionWindow(size) => F::from_bool(row + size <= height),
+                _ => unreachable!("operations are evaluated by eval_flat"),
+            });
+            let mut roots = roots.iter().map(|&root| values[root]);
+            buses_and_num_fields
+                .iter()
+                .map(|&(bus, num_fields)| {
+                    let evaluated = Interaction {
+                        fields: roots.by_ref().take(num_fields).collect(),
+                        multiplicity: roots.next().unwrap(),
+                        bus,
+                    };
+                    (
+                        fingerprint(&evaluated, &alpha, &beta),
+                        evaluated.multiplicity,
+                    )
+                })
+                .collect_vec()
+        })
+        .unzip();
+
+    let inverses = batch_multiplicative_inverse(&fingerprints);
+
+    let mut values = EF::zero_vec(height * (num_interactions + 1));
+    let mut phi = EF::ZERO;
+    for (row, inverses, multiplicities) in izip!(
+        values.chunks_exact_mut(num_interactions + 1),
+        inverses.chunks_exact(num_interactions),
+        multiplicities.chunks_exact(num_interactions)
+    ) {
+        for (h, &inverse, &multiplicity) in izip!(row.iter_mut(), inverses, multiplicities) {
+            *h = inverse * multiplicity;
+            phi += *h;
+        }
+        row[num_interactions] = phi;
+    }
+
+    (RowMajorMatrix::new(values, num_interactions + 1), phi)
+}
@@ -0,0 +1,360 @@
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use itertools::{Itertools, izip};
+use p3_air::{Air, BaseAirWithPublicValues};
+use p3_challenger::{CanObserve, FieldChallenger};
+use p3_commit::{Pcs, PolynomialSpace};
+use p3_field::PrimeCharacteristicRing;
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_util::log2_strict_usize;
+use tracing::{info_span, instrument};
+
+use crate::check_constraints::check_constraints;
+use crate::prover::{check_instance, window_points};
+use crate::{
+    Commitments, ConstraintProgram, DebugConstraintBuilder, LOGUP_NUM_CHALLENGES, MultiProof,
+    OpenedValues, ProvingError, StarkGenericConfig, SymbolicAirBuilder, Val, air_fingerprint,
+    generate_logup_trace, get_log_quotient_degree, get_symbolic_constraints,
+    get_symbolic_interactions, quotient_chunks,
+};
+
+/// A single AIR instance to be proven as part of a [`prove_multi`] call.
+#[derive(Debug)]
+pub struct StarkInstance<'a, SC: StarkGenericConfig, A> {
+    /// The AIR the trace should satisfy.
+    pub air: &'a A,
+    /// The main trace. Its height must be a power of two but may differ between instances.
+    pub trace: RowMajorMatrix<Val<SC>>,
+    /// The public values of this instance.
+    pub public_values: Vec<Val<SC>>,
+}
+
+/// Prove that several traces, possibly of differing heights, satisfy their respective AIRs, such as
+/// the chips of a VM.
+///
+/// Every instance has the same AIR type `A`. To prove AIRs of different types together, wrap them
+/// in an enum with a variant per AIR, which implements the AIR traits by matching on itself and
+/// forwarding to the AIR of the variant, as `ProofObjective` in `p3-examples` does for its hash
+/// AIRs. The verifier is then given the same enum values, in the same order.
+///
+/// This does not yet support every kind of AIR [`prove`](crate::prove) does, and returns an error
+/// instead of a proof if given:
+/// - a hiding PCS, with [`ProvingError::HidingPcsUnsupported`],
+/// - an AIR with preprocessed columns, with [`ProvingError::PreprocessedTraceUnsupported`],
+/// - an AIR with trace phases, with [`ProvingError::TracePhasesUnsupported`],
+/// - an AIR which pads its trace, with [`ProvingError::TracePaddingUnsupported`].
+///
+/// The errors about a single AIR are wrapped in [`ProvingError::InvalidInstance`].
+///
+/// Otherwise, this works like [`prove`](crate::prove), except that:
+/// - All traces are committed to in a single PCS commitment round.
+/// - A single `alpha` is used to fold the constraints of every instance, and the quotient chunks
+///   of every instance are committed to in a single PCS commitment round.
+/// - Every instance is opened at the same out-of-domain point `zeta` (and its own `zeta_next`),
+///   giving a single PCS opening proof for the whole batch.
+///
+/// Each AIR must have its own quotient degree, so instances with low degree constraints do not
+/// pay for the high degree constraints of other instances.
+///
+/// If any AIR pushes interactions, the prover runs a LogUp argument after committing to the traces:
+/// it samples the LogUp challenges, commits to the permutation trace of every instance with
+/// interactions in a single round, and sends the cumulative sum of each such instance. The verifier
+/// checks that these cumulative sums add up to zero.
+#[instrument(skip_all)]
+pub fn prove_multi<SC, A>(
+    config: &SC,
+    instances: Vec<StarkInstance<'_, SC, A>>,
+) -> Result<MultiProof<SC>, ProvingError>
+where
+    SC: StarkGenericConfig,
+    A: BaseAirWithPublicValues<Val<SC>>
+        + Air<SymbolicAirBuilder<Val<SC>>>
+        + for<'a> Air<DebugConstraintBuilder<'a, Val<SC>>>,
+{
+    if SC::Pcs::ZK {
+        return Err(ProvingError::HidingPcsUnsupported);
+    }
+    if instances.is_empty() {
+        return Err(ProvingError::NoInstances);
+    }
+    for (i, instance) in instances.iter().enumerate() {
+        check_multi_instance(config, instance).map_err(|error| ProvingError::InvalidInstance {
+            instance: i,
+            error: Box::new(error),
+        })?;
+    }
+
+    let pcs = config.pcs();
+    let mut challenger = config.initialise_challenger();
+
+    let (airs, traces, public_values): (Vec<_>, Vec<_>, Vec<_>) = instances
+        .into_iter()
+        .map(|instance| (instance.air, instance.trace, instance.public_values))
+        .multiunzip();
+
+    let degree_bits = traces
+        .iter()
+        .map(|trace| log2_strict_usize(trace.height()))
+        .collect_vec();
+    let trace_domains = degree_bits
+        .iter()
+        .map(|&bits| pcs.natural_domain_for_degree(1 << bits))
+        .collect_vec();
+
+    // Every instance has its own quotient degree and constraints.
+    let log_quotient_degrees = izip!(&airs, &public_values)
+        .map(|(air, pvs)| get_log_quotient_degree::<Val<SC>, A>(*air, 0, pvs.len(), 0))
+        .collect_vec();
+    let programs = info_span!("compile constraints").in_scope(|| {
+        izip!(&airs, &public_values)
+            .map(|(air, pvs)| {
+                ConstraintProgram::new(&get_symbolic_constraints::<Val<SC>, A>(*air, 0, pvs.len()))
+            })
+            .collect_vec()
+    });
+
+    let interactions = izip!(&airs, &public_values)
+        .map(|(air, pvs)| get_symbolic_interactions::<Val<SC>, A>(*air, 0, pvs.len()))
+        .collect_vec();
+
+    // Commit to every trace at once. The PCS supports matrices of differing heights in a single round.
+    // The traces are kept around, as they are needed to generate the permutation traces.
+    let (trace_commit, trace_data) = info_span!("commit to trace data")
+        .in_scope(|| pcs.commit(izip!(trace_domains.iter().copied(), traces.iter().cloned())));
+
+    // Observe the instances.
+    challenger.observe(Val::<SC>::from_usize(airs.len()));
+    for &bits in &degree_bits {
+        // degree < 2^255 so we can safely cast log_degree to a u8.
+        challenger.observe(Val::<SC>::from_u8(bits as u8));
+    }
+    for (air, pvs) in izip!(&airs, &public_values) {
+        challenger.observe_slice(&air_fingerprint(config, *air, None, pvs.len()));
+    }
+    challenger.observe(trace_commit.clone());
+    for pvs in &public_values {
+        challenger.observe_slice(pvs);
+    }
+
+    // Run the LogUp argument if any instance has interactions.
+    let has_interactions = interactions.iter().any(|i| !i.is_empty());
+    let permutation_challenges: Vec<SC::Challenge> = if has_interactions {
+        (0..LOGUP_NUM_CHALLENGES)
+            .map(|_| challenger.sample_algebra_element())
+            .collect()
+    } else {
+        vec![]
+    };
+    let (permutation_traces, cumulative_sums): (Vec<_>, Vec<_>) =
+        info_span!("generate permutation traces").in_scope(|| {
+            izip!(&interactions, &traces, &public_values)
+                .map(|(interactions, trace, pvs)| {
+                    if interactions.is_empty() {
+                        (None, None)
+                    } else {
+                        let (perm, cumulative_sum) = generate_logup_trace(
+                            interactions,
+                            trace,
+                            None,
+                            pvs,
+                            &permutation_challenges,
+                        );
+                        (Some(perm), Some(cumulative_sum))
+                    }
+                })
+                .unzip()
+        });
+    drop(traces);
+
+    // The index of each permutation trace within the permutation commitment.
+    let permutation_indices = permutation_traces
+        .iter()
+        .scan(0, |next, perm| {
+            Some(perm.as_ref().map(|_| {
+                *next += 1;
+                *next - 1
+            }))
+        })
+        .collect_vec();
+    let permutation_commit_and_data =
+        has_interactions.then(|| {
+            info_span!("commit to permutation traces").in_scope(|| {
+                pcs.commit(izip!(&trace_domains, permutation_traces).filter_map(
+                    |(&domain, perm)| perm.map(|perm| (domain, perm.flatten_to_base())),
+                ))
+            })
+        });
+    if let Some((permutation_commit, _)) = &permutation_commit_and_data {
+        challenger.observe(permutation_commit.clone());
+    }
+    for cumulative_sum in cumulative_sums.iter().flatten() {
+        challenger.observe_algebra_element(*cumulative_sum);
+    }
+
+    // A single challenge is used to fold the constraints of every instance.
+    let alpha: SC::Challenge = challenger.sample_algebra_element();
+
+    let quotient_domains = izip!(&trace_domains, &degree_bits, &log_quotient_degrees)
+        .map(|(trace_domain, &bits, &log_quotient_degree)| {
+            trace_domain.create_disjoint_domain(1 << (bits + log_quotient_degree))
+        })
+        .collect_vec();
+
+    // Compute the quotient of every instance, split it into chunks and gather all chunks so they
+    // can be committed to at once.
+    let quotient_chunks = info_span!("compute quotient chunks").in_scope(|| {
+        izip!(
+            0..airs.len(),
+            &public_values,
+            &trace_domains,
+            &quotient_domains,
+            &log_quotient_degrees,
+            &programs,
+            &permutation_indices,
+            &cumulative_sums
+        )
+        .flat_map(
+            |(
+                i,
+                pvs,
+                &trace_domain,
+                &quotient_domain,
+                &log_quotient_degree,
+                program,
+                &permutation_index,
+                &cumulative_sum,
+            )| {
+                let trace_on_quotient_domain =
+                    pcs.get_evaluations_on_domain(&trace_data, i, quotient_domain);
+                let permutation_on_quotient_domain = permutation_index.map(|j| {
+                    let (_, permutation_data) = permutation_commit_and_data.as_ref().unwrap();
+                    pcs.get_evaluations_on_domain(permutation_data, j, quotient_domain)
+                });
+                let num_chunks = 1 << log_quotient_degree;
+                let chunks = quotient_chunks::<SC, _>(
+                    program,
+                    pvs,
+                    airs[i].window_size(),
+                    trace_domain,
+                    quotient_domain,
+                    trace_on_quotient_domain,
+                    None,
+                    permutation_on_quotient_domain,
+                    &permutation_challenges,
+                    cumulative_sum,
+                    vec![],
+                    &[],
+                    None,
+                    alpha,
+                    num_chunks,
+                    config.quotient_batch_rows(),
+                );
+                izip!(quotient_domain.split_domains(num_chunks), chunks)
+            },
+        )
+        .collect_vec()
+    });
+
+    let (quotient_commit, quotient_data) =
+        info_span!("commit to quotient poly chunks").in_scope(|| pcs.commit(quotient_chunks));
+    challenger.observe(quotient_commit.clone());
+
+    // Get an out-of-domain point shared by every instance.
+    let zeta: SC::Challenge = challenger.sample_algebra_element();
+
+    let (opened_values, opening_proof) = info_span!("open").in_scope(|| {
+        let trace_points = izip!(&airs, &trace_domains)
+            .map(|(air, &domain)| window_points(domain, zeta, air.window_size()))
+            .collect_vec();
+        let quotient_points = log_quotient_degrees
+            .iter()
+            .flat_map(|&log_quotient_degree| vec![vec![zeta]; 1 << log_quotient_degree])
+            .collect_vec();
+        // The permutation traces are opened at the first two points of the corresponding traces.
+        let permutation_round = permutation_commit_and_data.as_ref().map(|(_, data)| {
+            let points = izip!(&trace_points, &permutation_indices)
+                .filter(|(_, index)| index.is_some())
+                .map(|(points, _)| points[..2].to_vec())
+                .collect_vec();
+            (data, points)
+        });
+        let rounds = [
+            (&trace_data, trace_points),
+            (&quotient_data, quotient_points),
+        ]
+        .into_iter()
+        .chain(permutation_round)
+        .collect();
+        pcs.open(rounds, &mut challenger)
+    });
+
+    let trace_openings = &opened_values[SC::Pcs::TRACE_IDX];
+    let mut quotient_openings = opened_values[SC::Pcs::QUOTIENT_IDX].iter();
+    // The permutation round, if present, is opened after the quotient round.
+    let permutation_openings = opened_values.get(SC::Pcs::QUOTIENT_IDX + 1);
+    let opened_values = izip!(trace_openings, &log_quotient_degrees, &permutation_indices)
+        .map(
+            |(trace_opening, &log_quotient_degree, &permutation_index)| OpenedValues {
+                trace_window: trace_opening.clone(),
+                preprocessed_window: None,
+                permutation_local: permutation_index
+                    .map(|j| permutation_openings.unwrap()[j][0].clone()),
+                permutation_next: permutation_index
+                    .map(|j| permutation_openings.unwrap()[j][1].clone()),
+                phases_window: vec![],
+                padding_local: None,
+                padding_next: None,
+                quotient_chunks: quotient_openings
+                    .by_ref()
+                    .take(1 << log_quotient_degree)
+                    .map(|chunk| chunk[0].clone())
+                    .collect(),
+                random: None,
+            },
+        )
+        .collect();
+
+    Ok(MultiProof {
+        commitments: Commitments {
+            trace: trace_commit,
+            quotient_chunks: quotient_commit,
+            random: None,
+            permutation: permutation_commit_and_data.map(|(commit, _)| commit),
+            phases: vec![],
+        },
+        opened_values,
+        opening_proof,
+        degree_bits,
+        cumulative_sums,
+    })
+}
+
+/// Check that `instance` is supported by [`prove_multi`] and fits its AIR, and, unless the config
+/// opts out, that its trace satisfies the constraints of its AIR.
+fn check_multi_instance<SC, A>(
+    config: &SC,
+    instance: &StarkInstance<'_, SC, A>,
+) -> Result<(), ProvingError>
+where
+    SC: StarkGenericConfig,
+    A: BaseAirWithPublicValues<Val<SC>> + for<'a> Air<DebugConstraintBuilder<'a, Val<SC>>>,
+{
+    let air = instance.air;
+    if air.preprocessed_trace().is_some() {
+        return Err(ProvingError::PreprocessedTraceUnsupported);
+    }
+    if !air.trace_phases().is_empty() {
+        return Err(ProvingError::TracePhasesUnsupported);
+    }
+    if air.trace_padding().is_some() {
+        return Err(ProvingError::TracePaddingUnsupported);
+    }
+    check_instance(air, &instance.trace, &instance.public_values)?;
+    if config.check_constraints() {
+        check_constraints(air, &instance.trace, None, &instance.public_values)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,246 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use itertools::{Itertools, izip};
+use p3_air::Air;
+use p3_challenger::{CanObserve, FieldChallenger};
+use p3_commit::{Pcs, PolynomialSpace};
+use p3_field::{BasedVectorSpace, PrimeCharacteristicRing};
+use tracing::instrument;
+
+use crate::prover::window_points;
+use crate::symbolic_builder::{
+    SymbolicAirBuilder, get_log_quotient_degree, get_symbolic_interactions,
+};
+use crate::verifier::check_window_size;
+use crate::{
+    LOGUP_NUM_CHALLENGES, MultiProof, PcsError, StarkGenericConfig, Val, VerificationError,
+    VerifierConstraintFolder, air_fingerprint, verify_constraints,
+};
+
+/// Verify a proof produced by [`prove_multi`](crate::prove_multi).
+///
+/// `airs` and `public_values` must be given in the same order as the instances passed to the prover.
+#[instrument(skip_all)]
+pub fn verify_multi<SC, A>(
+    config: &SC,
+    airs: &[&A],
+    proof: &MultiProof<SC>,
+    public_values: &[Vec<Val<SC>>],
+) -> Result<(), VerificationError<PcsError<SC>>>
+where
+    SC: StarkGenericConfig,
+    A: Air<SymbolicAirBuilder<Val<SC>>> + for<'a> Air<VerifierConstraintFolder<'a, SC>>,
+{
+    let MultiProof {
+        commitments,
+        opened_values,
+        opening_proof,
+        degree_bits,
+        cumulative_sums,
+    } = proof;
+
+    // Multi-instance proofs are never randomized.
+    if SC::Pcs::ZK || commitments.random.is_some() {
+        return Err(VerificationError::RandomizationError);
+    }
+
+    if airs.len() != public_values.len()
+        || airs.len() != opened_values.len()
+        || airs.len() != degree_bits.len()
+        || airs.len() != cumulative_sums.len()
+    {
+        return Err(VerificationError::InvalidProofShape);
+    }
+    for air in airs {
+        check_window_size(*air)?;
+    }
+
+    let pcs = config.pcs();
+
+    let num_interactions = izip!(airs, public_values)
+        .map(|(air, pvs)| get_symbolic_interactions::<Val<SC>, A>(*air, 0, pvs.len()).len())
+        .collect_vec();
+    let has_interactions = num_interactions.iter().any(|&n| n > 0);
+
+    let log_quotient_degrees = izip!(airs, public_values)
+        .map(|(air, pvs)| get_log_quotient_degree::<Val<SC>, A>(*air, 0, pvs.len(), 0))
+        .collect_vec();
+
+    let valid_shape = commitments.permutation.is_some() == has_interactions
+        && commitments.phases.is_empty()
+        && izip!(
+            airs,
+            opened_values,
+            &log_quotient_degrees,
+            &num_interactions,
+            cumulative_sums
+        )
+        .all(
+            |(air, values, &log_quotient_degree, &num_interactions, cumulative_sum)| {
+                let air_width = A::width(air);
+                let window_size = air.window_size();
+                // The permutation trace has a column per interaction plus a running sum, each of
+                // which is committed to as `DIMENSION` base field columns.
+                let permutation_width = (num_interactions + 1) * SC::Challenge::DIMENSION;
+                let valid_permutation = if num_interactions == 0 {
+                    values.permutation_local.is_none()
+                        && values.permutation_next.is_none()
+                        && cumulative_sum.is_none()
+                } else {
+                    values
+                        .permutation_local
+                        .as_ref()
+                        .is_some_and(|v| v.len() == permutation_width)
+                        && values
+                            .permutation_next
+                            .as_ref()
+                            .is_some_and(|v| v.len() == permutation_width)
+                        && cumulative_sum.is_some()
+                };
+                values.trace_window.len() == window_size
+                    && values.trace_window.iter().all(|row| row.len() == air_width)
+                    && values.preprocessed_window.is_none()
+                    && air.trace_phases().is_empty()
+                    && values.phases_window.is_empty()
+                    && air.trace_padding().is_none()
+                    && values.padding_local.is_none()
+                    && values.padding_next.is_none()
+                    && valid_permutation
+                    && values.random.is_none()
+                    && values.quotient_chunks.len() == 1 << log_quotient_degree
+                    && values
+                        .quotient_chunks
+                        .iter()
+                        .all(|qc| qc.len() == SC::Challenge::DIMENSION)
+            },
+        );
+    if !valid_shape {
+        return Err(VerificationError::InvalidProofShape);
+    }
+
+    let trace_domains = degree_bits
+        .iter()
+        .map(|&bits| pcs.natural_domain_for_degree(1 << bits))
+        .collect_vec();
+    let quotient_chunks_domains = izip!(&trace_domains, degree_bits, &log_quotient_degrees)
+        .map(|(trace_domain, &bits, &log_quotient_degree)| {
+            trace_domain
+                .create_disjoint_domain(1 << (bits + log_quotient_degree))
+                .split_domains(1 << log_quotient_degree)
+        })
+        .collect_vec();
+
+    let mut challenger = config.initialise_challenger();
+
+    // Observe the instances, in the same order as the prover.
+    challenger.observe(Val::<SC>::from_usize(airs.len()));
+    for &bits in degree_bits {
+        challenger.observe(Val::<SC>::from_usize(bits));
+    }
+    for (air, pvs) in izip!(airs, public_values) {
+        challenger.observe_slice(&air_fingerprint(config, *air, None, pvs.len()));
+    }
+    challenger.observe(commitments.trace.clone());
+    for pvs in public_values {
+        challenger.observe_slice(pvs);
+    }
+
+    let permutation_challenges: Vec<SC::Challenge> = if has_interactions {
+        (0..LOGUP_NUM_CHALLENGES)
+            .map(|_| challenger.sample_algebra_element())
+            .collect()
+    } else {
+        vec![]
+    };
+    // We've already checked that the permutation commitment is present if and only if some
+    // instance has interactions.
+    if let Some(permutation_commit) = &commitments.permutation {
+        challenger.observe(permutation_commit.clone());
+    }
+    for &cumulative_sum in cumulative_sums.iter().flatten() {
+        challenger.observe_algebra_element(cumulative_sum);
+    }
+
+    let alpha = challenger.sample_algebra_element();
+    challenger.observe(commitments.quotient_chunks.clone());
+
+    let zeta = challenger.sample_algebra_element();
+
+    let trace_round = izip!(airs, &trace_domains, opened_values)
+        .map(|(air, &domain, values)| {
+            let points = window_points(domain, zeta, air.window_size());
+            (
+                domain,
+                izip!(points, values.trace_window.clone()).collect_vec(),
+            )
+        })
+        .collect_vec();
+    let quotient_round = izip!(&quotient_chunks_domains, opened_values)
+        .flat_map(|(domains, values)| {
+            izip!(domains, &values.quotient_chunks).map(|(domain, chunk)| {
+                (
+                    pcs.natural_domain_for_degree(domain.size()),
+                    vec![(zeta, chunk.clone())],
+                )
+            })
+        })
+        .collect_vec();
+
+    let mut coms_to_verify = vec![
+        (commitments.trace.clone(), trace_round.clone()),
+        (commitments.quotient_chunks.clone(), quotient_round),
+    ];
+    // The permutation traces are opened at the first two points of the corresponding traces.
+    if let Some(permutation_commit) = &commitments.permutation {
+        let permutation_round = izip!(&trace_round, opened_values)
+            .filter_map(|((domain, points), values)| {
+                let (local, next) = (
+                    values.permutation_local.clone()?,
+                    values.permutation_next.clone()?,
+                );
+                Some((*domain, vec![(points[0].0, local), (points[1].0, next)]))
+            })
+            .collect_vec();
+        coms_to_verify.push((permutation_commit.clone(), permutation_round));
+    }
+
+    pcs.verify(coms_to_verify, opening_proof, &mut challenger)
+        .map_err(VerificationError::InvalidOpeningArgument)?;
+
+    for (air, values, pvs, &trace_domain, chunks_domains, &cumulative_sum) in izip!(
+        airs,
+        opened_values,
+        public_values,
+        &trace_domains,
+        &quotient_chunks_domains,
+        cumulative_sums
+    ) {
+        verify_constraints::<SC, A, PcsError<SC>>(
+            *air,
+            values,
+            pvs,
+            trace_domain,
+            chunks_domains,
+            &permutation_challenges,
+            cumulative_sum,
+            &[],
+            None,
+            zeta,
+            alpha,
+        )?;
+    }
+
+    // Every message sent on a bus must be received, so the cumulative sums must cancel out.
+    if cumulative_sums
+        .iter()
+        .flatten()
+        .copied()
+        .sum::<SC::Challenge>()
+        != SC::Challenge::ZERO
+    {
+        return Err(VerificationError::UnbalancedInteractions);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,83 @@
+//! Support for AIRs whose trace height is not a power of two, see
+//! [`BaseAir::trace_padding`](p3_air::BaseAir::trace_padding).
+//!
+//! When an AIR pads its trace, the prover commits to two more columns next to the main trace:
+//! - `is_padding`, which is `0` on the rows of the original trace and `1` on the padding rows.
+//! - `count`, the number of rows of the original trace up to and including the current row.
+//!
+//! Constraints force `is_padding` to be a run of zeros followed by a run of ones, and `count` to
+//! end at the real height of the trace, so the verifier knows exactly which rows are padding.
+
+use alloc::vec::Vec;
+
+use p3_air::{AirBuilder, BaseAir, TracePaddingError};
+use p3_field::{Field, PrimeCharacteristicRing};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+
+use crate::ProvingError;
+
+/// The number of columns committed to next to the main trace of an AIR which pads its trace.
+pub const PADDING_WIDTH: usize = 2;
+
+/// The padding columns of a trace, along with the height of the trace before padding.
+#[derive(Clone, Debug)]
+pub(crate) struct PaddingTrace<F> {
+    pub(crate) real_height: usize,
+    pub(crate) columns: RowMajorMatrix<F>,
+}
+
+/// Pad `trace` according to the padding policy of `air`, returning its padding columns.
+///
+/// Returns `None` if the AIR does not pad its trace, in which case the trace is left untouched.
+pub(crate) fn pad_trace<F: Field, A: BaseAir<F>>(
+    air: &A,
+    trace: &mut RowMajorMatrix<F>,
+) -> Result<Option<PaddingTrace<F>>, ProvingError> {
+    let Some(padding) = air.trace_padding() else {
+        return Ok(None);
+    };
+    let real_height = trace.height();
+    padding.pad(trace).map_err(|err| match err {
+        TracePaddingError::EmptyTrace => ProvingError::InvalidTraceHeight { height: 0 },
+        TracePaddingError::FillerRowWidthMismatch { expected, actual } => {
+            ProvingError::FillerRowWidthMismatch { expected, actual }
+        }
+    })?;
+
+    let mut values = Vec::with_capacity(PADDING_WIDTH * trace.height());
+    for row in 0..trace.height() {
+        values.push(F::from_bool(row >= real_height));
+        values.push(F::from_usize(real_height.min(row + 1)));
+    }
+    Ok(Some(PaddingTrace {
+        real_height,
+        columns: RowMajorMatrix::new(values, PADDING_WIDTH),
+    }))
+}
+
+/// Enforce that `padding` holds the padding columns of a trace of height `real_height`.
+pub(crate) fn eval_padding<AB: AirBuilder>(
+    builder: &mut AB,
+    padding: &impl Matrix<AB::Var>,
+    real_height: AB::Expr,
+) {
+    let local = padding.row_slice(0).expect("Matrix is empty?");
+    let next = padding.row_slice(1).expect("Matrix only has 1 row?");
+    let (is_padding, count) = (local[0].clone(), local[1].clone());
+    let (is_padding_next, count_next) = (next[0].clone(), next[1].clone());
+    let is_real_next = AB::Expr::ONE - is_padding_next.clone();
+
+    builder.assert_bool(is_padding.clone());
+    // Once a row is padding, every later row is padding.
+    builder
+        .when_transition()
+        .assert_zero(is_padding.clone() * is_real_next.clone());
+    builder
+        .when_first_row()
+        .assert_eq(count.clone(), AB::Expr::ONE - is_padding);
+    builder
+        .when_transition()
+        .assert_eq(count_next, count.clone() + is_real_next);
+    builder.when_last_row().assert_eq(count, real_height);
+}
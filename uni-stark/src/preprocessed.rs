@@ -0,0 +1,104 @@
+use p3_air::BaseAir;
+use p3_commit::Pcs;
+use p3_matrix::Matrix;
+use serde::{Deserialize, Serialize};
+use tracing::{info_span, instrument};
+
+use crate::{Com, PcsProverData, StarkGenericConfig, Val};
+
+/// Prover side of the preprocessed (fixed) trace of an AIR.
+///
+/// This is produced once by [`setup_preprocessed`] and can then be reused across every proof of
+/// the same AIR at the same trace height.
+pub struct PreprocessedProverData<SC: StarkGenericConfig> {
+    /// The number of preprocessed columns.
+    pub width: usize,
+    /// `log_2` of the height of the preprocessed trace. Must match the height of the main trace.
+    pub degree_bits: usize,
+    /// The commitment to the preprocessed trace.
+    pub commitment: Com<SC>,
+    /// The PCS prover data for the preprocessed trace, used when opening it.
+    pub prover_data: PcsProverData<SC>,
+}
+
+/// Verifier side of the preprocessed (fixed) trace of an AIR.
+///
+/// This only holds the commitment produced by [`setup_preprocessed`] along with the shape of the
+/// committed trace.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct PreprocessedVerifierKey<SC: StarkGenericConfig> {
+    /// The number of preprocessed columns.
+    pub width: usize,
+    /// `log_2` of the height of the preprocessed trace. Must match the height of the main trace.
+    pub degree_bits: usize,
+    /// The commitment to the preprocessed trace.
+    pub commitment: Com<SC>,
+}
+
+impl<SC: StarkGenericConfig> Clone for PreprocessedVerifierKey<SC> {
+    fn clone(&self) -> Self {
+        Self {
+            width: self.width,
+            degree_bits: self.degree_bits,
+            commitment: self.commitment.clone(),
+        }
+    }
+}
+
+impl<SC: StarkGenericConfig> PreprocessedProverData<SC> {
+    /// Extract the verifier key corresponding to this prover data.
+    pub fn verifier_key(&self) -> PreprocessedVerifierKey<SC> {
+        PreprocessedVerifierKey {
+            width: self.width,
+            degree_bits: self.degree_bits,
+            commitment: self.commitment.clone(),
+        }
+    }
+}
+
+/// Commit to the preprocessed trace of `air`, if it has one.
+///
+/// Returns `None` if `air.preprocessed_trace()` is `None`. Otherwise returns the prover data which
+/// should be passed to [`prove_with_preprocessed`](crate::prove_with_preprocessed) and the
+/// verifier key which should be passed to [`verify_with_preprocessed`](crate::verify_with_preprocessed).
+///
+/// When the PCS is hiding, the commitment is randomized, so the verifier key must be taken from
+/// this function rather than recomputed by the verifier.
+///
+/// # Panics
+/// Panics if the height of the preprocessed trace is not `2^degree_bits`.
+#[instrument(skip_all)]
+pub fn setup_preprocessed<SC, A>(
+    config: &SC,
+    air: &A,
+    degree_bits: usize,
+) -> Option<(PreprocessedProverData<SC>, PreprocessedVerifierKey<SC>)>
+where
+    SC: StarkGenericConfig,
+    A: BaseAir<Val<SC>>,
+{
+    let preprocessed = air.preprocessed_trace()?;
+    let width = preprocessed.width();
+    let degree = 1 << degree_bits;
+    assert_eq!(
+        preprocessed.height(),
+        degree,
+        "preprocessed trace height must equal the trace height"
+    );
+
+    let pcs = config.pcs();
+    // The preprocessed trace lives on the same (possibly extended) domain as the main trace.
+    let ext_domain = pcs.natural_domain_for_degree(degree << config.is_zk());
+    let (commitment, prover_data) = info_span!("commit to preprocessed trace")
+        .in_scope(|| pcs.commit([(ext_domain, preprocessed)]));
+
+    let prover_data = PreprocessedProverData {
+        width,
+        degree_bits,
+        commitment,
+        prover_data,
+    };
+    let verifier_key = prover_data.verifier_key();
+    Some((prover_data, verifier_key))
+}
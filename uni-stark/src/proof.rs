@@ -1,14 +1,11 @@
 use alloc::vec::Vec;
+use core::fmt;
 
-use p3_commit::Pcs;
+use p3_commit::{OpeningProofSize, Pcs, SerializedSize, SizeBreakdown};
 use serde::{Deserialize, Serialize};
 
-use crate::StarkGenericConfig;
+use crate::{Com, StarkGenericConfig};
 
-type Com<SC> = <<SC as StarkGenericConfig>::Pcs as Pcs<
-    <SC as StarkGenericConfig>::Challenge,
-    <SC as StarkGenericConfig>::Challenger,
->>::Commitment;
 type PcsProof<SC> = <<SC as StarkGenericConfig>::Pcs as Pcs<
     <SC as StarkGenericConfig>::Challenge,
     <SC as StarkGenericConfig>::Challenger,
@@ -21,6 +18,109 @@ pub struct Proof<SC: StarkGenericConfig> {
     pub(crate) opened_values: OpenedValues<SC::Challenge>,
     pub(crate) opening_proof: PcsProof<SC>,
     pub(crate) degree_bits: usize,
+    /// The height of the trace before it was padded, or `None` if the AIR does not pad its trace.
+    pub(crate) real_height: Option<usize>,
+}
+
+impl<SC: StarkGenericConfig> Proof<SC> {
+    /// The height of the trace before it was padded to a power of two, or `None` if the AIR does
+    /// not pad its trace, see [`BaseAir::trace_padding`](p3_air::BaseAir::trace_padding).
+    pub const fn real_height(&self) -> Option<usize> {
+        self.real_height
+    }
+
+    /// The serialized size of each part of this proof, as measured by `sizer`.
+    pub fn size_report(&self, sizer: &impl SerializedSize) -> ProofSizeReport {
+        ProofSizeReport {
+            commitments: sizer.serialized_size(&self.commitments),
+            opened_values: sizer.serialized_size(&self.opened_values),
+            opening_proof: SC::Pcs::opening_proof_size(&self.opening_proof, sizer),
+            other: 0,
+        }
+        .with_total(sizer.serialized_size(self))
+    }
+}
+
+/// A proof that several AIR instances are satisfied, produced by [`prove_multi`](crate::prove_multi).
+///
+/// All traces share one commitment, all quotient chunks share another, and every opening is
+/// proven by a single PCS opening proof.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct MultiProof<SC: StarkGenericConfig> {
+    pub(crate) commitments: Commitments<Com<SC>>,
+    /// The opened values of each instance, in the order the instances were given to the prover.
+    pub(crate) opened_values: Vec<OpenedValues<SC::Challenge>>,
+    pub(crate) opening_proof: PcsProof<SC>,
+    /// `log_2` of the trace height of each instance.
+    pub(crate) degree_bits: Vec<usize>,
+    /// The LogUp cumulative sum of each instance, or `None` for instances without interactions.
+    pub(crate) cumulative_sums: Vec<Option<SC::Challenge>>,
+}
+
+impl<SC: StarkGenericConfig> MultiProof<SC> {
+    /// The serialized size of each part of this proof, as measured by `sizer`.
+    pub fn size_report(&self, sizer: &impl SerializedSize) -> ProofSizeReport {
+        ProofSizeReport {
+            commitments: sizer.serialized_size(&self.commitments),
+            opened_values: sizer.serialized_size(&self.opened_values),
+            opening_proof: SC::Pcs::opening_proof_size(&self.opening_proof, sizer),
+            other: 0,
+        }
+        .with_total(sizer.serialized_size(self))
+    }
+}
+
+/// The serialized size, in bytes, of each part of a proof, see [`Proof::size_report`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofSizeReport {
+    /// The commitments to the traces, the quotient and the random polynomial.
+    pub commitments: usize,
+    /// The values of the committed polynomials opened at the out-of-domain point.
+    pub opened_values: usize,
+    /// The PCS proof that the opened values are correct.
+    pub opening_proof: OpeningProofSize,
+    /// Everything not counted above, e.g. the trace heights.
+    pub other: usize,
+}
+
+impl SizeBreakdown for ProofSizeReport {
+    fn total(&self) -> usize {
+        self.commitments + self.opened_values + self.opening_proof.total() + self.other
+    }
+
+    fn other_mut(&mut self) -> &mut usize {
+        &mut self.other
+    }
+}
+
+impl fmt::Display for ProofSizeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let opening_proof = &self.opening_proof;
+        writeln!(f, "Proof size: {} bytes", self.total())?;
+        writeln!(f, "  commitments: {} bytes", self.commitments)?;
+        writeln!(f, "  opened values: {} bytes", self.opened_values)?;
+        writeln!(f, "  opening proof: {} bytes", opening_proof.total())?;
+        writeln!(
+            f,
+            "    commit phase commitments: {} bytes",
+            opening_proof.commit_phase_commitments
+        )?;
+        writeln!(
+            f,
+            "    commit phase openings: {} bytes",
+            opening_proof.commit_phase_openings
+        )?;
+        writeln!(
+            f,
+            "    query openings: {} bytes",
+            opening_proof.query_openings
+        )?;
+        writeln!(f, "    final poly: {} bytes", opening_proof.final_poly)?;
+        writeln!(f, "    PoW witness: {} bytes", opening_proof.pow_witness)?;
+        writeln!(f, "    other: {} bytes", opening_proof.other)?;
+        write!(f, "  other: {} bytes", self.other)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -28,12 +128,23 @@ pub struct Commitments<Com> {
     pub(crate) trace: Com,
     pub(crate) quotient_chunks: Com,
     pub(crate) random: Option<Com>,
+    /// The commitment to the LogUp permutation traces, if any instance has interactions.
+    pub(crate) permutation: Option<Com>,
+    /// The commitments to each challenge-dependent trace phase, in order.
+    pub(crate) phases: Vec<Com>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct OpenedValues<Challenge> {
-    pub(crate) trace_local: Vec<Challenge>,
-    pub(crate) trace_next: Vec<Challenge>,
+    /// The main trace opened at each row of the window of the AIR, starting at `zeta`.
+    pub(crate) trace_window: Vec<Vec<Challenge>>,
+    pub(crate) preprocessed_window: Option<Vec<Vec<Challenge>>>,
+    pub(crate) permutation_local: Option<Vec<Challenge>>,
+    pub(crate) permutation_next: Option<Vec<Challenge>>,
+    /// The window of each trace phase, in order.
+    pub(crate) phases_window: Vec<Vec<Vec<Challenge>>>,
+    pub(crate) padding_local: Option<Vec<Challenge>>,
+    pub(crate) padding_next: Option<Vec<Challenge>>,
     pub(crate) quotient_chunks: Vec<Vec<Challenge>>,
     pub(crate) random: Option<Vec<Challenge>>,
 }
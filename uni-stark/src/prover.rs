@@ -1,51 +1,290 @@
+use alloc::boxed::Box;
 use alloc::vec;
 use alloc::vec::Vec;
+use core::ops::Range;
+use core::{array, iter};
 
 use itertools::Itertools;
-use p3_air::Air;
+use p3_air::{Air, BaseAirWithPublicValues};
 use p3_challenger::{CanObserve, FieldChallenger};
 use p3_commit::{Pcs, PolynomialSpace};
-use p3_field::{BasedVectorSpace, PackedValue, PrimeCharacteristicRing};
+use p3_field::{BasedVectorSpace, ExtensionField, PackedValue, PrimeCharacteristicRing};
 use p3_matrix::Matrix;
 use p3_matrix::dense::RowMajorMatrix;
 use p3_maybe_rayon::prelude::*;
 use p3_util::log2_strict_usize;
 use tracing::{debug_span, info_span, instrument};
 
+use crate::check_constraints::check_constraints_with_phases;
+use crate::padding::{PaddingTrace, pad_trace};
 use crate::{
-    Commitments, Domain, OpenedValues, PackedChallenge, PackedVal, Proof, ProverConstraintFolder,
-    StarkGenericConfig, SymbolicAirBuilder, Val, get_log_quotient_degree, get_symbolic_constraints,
+    Commitments, ConstraintInputs, ConstraintProgram, DebugConstraintBuilder, Domain, OpenedValues,
+    PADDING_WIDTH, PackedChallenge, PackedVal, PreprocessedProverData, Proof, Registers,
+    StarkGenericConfig, SymbolicAirBuilder, Val, air_fingerprint, get_log_quotient_degree,
+    get_symbolic_constraints, get_symbolic_interactions,
 };
 
+/// Prove that `trace` satisfies `air`.
+///
+/// Returns a [`ProvingError`] if the trace or the public values do not fit the AIR, or if the trace
+/// does not satisfy the constraints of the AIR, unless the config opts out of checking them through
+/// [`StarkGenericConfig::check_constraints`].
 #[instrument(skip_all)]
-#[allow(clippy::multiple_bound_locations)] // cfg not supported in where clauses?
-pub fn prove<
-    SC,
-    #[cfg(debug_assertions)] A: for<'a> Air<crate::check_constraints::DebugConstraintBuilder<'a, Val<SC>>>,
-    #[cfg(not(debug_assertions))] A,
->(
+pub fn prove<SC, A>(
     config: &SC,
     air: &A,
     trace: RowMajorMatrix<Val<SC>>,
     public_values: &Vec<Val<SC>>,
-) -> Proof<SC>
+) -> Result<Proof<SC>, ProvingError>
 where
     SC: StarkGenericConfig,
-    A: Air<SymbolicAirBuilder<Val<SC>>> + for<'a> Air<ProverConstraintFolder<'a, SC>>,
+    A: BaseAirWithPublicValues<Val<SC>>
+        + Air<SymbolicAirBuilder<Val<SC>>>
+        + for<'a> Air<DebugConstraintBuilder<'a, Val<SC>>>,
 {
-    #[cfg(debug_assertions)]
-    crate::check_constraints::check_constraints(air, &trace, public_values);
+    prove_with_preprocessed(config, air, trace, public_values, None)
+}
+
+/// Prove that `trace` satisfies `air`, where `air` may make use of a preprocessed trace.
+///
+/// `preprocessed` should be the prover data returned by [`setup_preprocessed`](crate::setup_preprocessed)
+/// for this AIR, or `None` if the AIR has no preprocessed columns.
+#[instrument(skip_all)]
+pub fn prove_with_preprocessed<SC, A>(
+    config: &SC,
+    air: &A,
+    mut trace: RowMajorMatrix<Val<SC>>,
+    public_values: &Vec<Val<SC>>,
+    preprocessed: Option<&PreprocessedProverData<SC>>,
+) -> Result<Proof<SC>, ProvingError>
+where
+    SC: StarkGenericConfig,
+    A: BaseAirWithPublicValues<Val<SC>>
+        + Air<SymbolicAirBuilder<Val<SC>>>
+        + for<'a> Air<DebugConstraintBuilder<'a, Val<SC>>>,
+{
+    if !air.trace_phases().is_empty() {
+        return Err(ProvingError::TracePhasesUnsupported);
+    }
+    check_instance(air, &trace, public_values)?;
+    let padding = pad_trace(air, &mut trace)?;
+
+    if config.check_constraints() {
+        check_constraints_with_phases::<_, Val<SC>, _>(
+            air,
+            &trace,
+            preprocessed.and_then(|_| air.preprocessed_trace()).as_ref(),
+            padding.as_ref().map(|padding| &padding.columns),
+            &[],
+            &[],
+            public_values,
+        )?;
+    }
+
+    prove_impl(
+        config,
+        air,
+        trace,
+        padding,
+        public_values,
+        preprocessed,
+        |_| unreachable!("the AIR has no trace phases"),
+    )
+}
+
+/// The data available when generating a challenge-dependent trace phase, see [`prove_with_phases`].
+#[derive(Debug)]
+pub struct PhaseInputs<'a, SC: StarkGenericConfig> {
+    /// The index of the phase to generate, where phase `0` is the first phase after the main trace.
+    pub phase: usize,
+    /// The main trace.
+    pub main: &'a RowMajorMatrix<Val<SC>>,
+    /// The public values of the instance.
+    pub public_values: &'a [Val<SC>],
+    /// The traces of every previous phase.
+    pub phases: &'a [RowMajorMatrix<SC::Challenge>],
+    /// The challenges sampled before every phase up to and including this one.
+    pub challenges: &'a [Vec<SC::Challenge>],
+}
+
+/// Prove that `trace` satisfies `air`, where `air` declares challenge-dependent trace phases
+/// through [`BaseAir::trace_phases`](p3_air::BaseAir::trace_phases).
+///
+/// After the main trace is committed to, for each phase the prover samples the phase's
+/// challenges, calls `generate_phase` to build the phase trace, and commits to it. Only once
+/// every phase has been committed to is `alpha` sampled and the quotient computed.
+///
+/// Each phase trace must have the width declared by the AIR and the height of the main trace.
+#[instrument(skip_all)]
+pub fn prove_with_phases<SC, A, G>(
+    config: &SC,
+    air: &A,
+    mut trace: RowMajorMatrix<Val<SC>>,
+    public_values: &Vec<Val<SC>>,
+    preprocessed: Option<&PreprocessedProverData<SC>>,
+    mut generate_phase: G,
+) -> Result<Proof<SC>, ProvingError>
+where
+    SC: StarkGenericConfig,
+    A: BaseAirWithPublicValues<Val<SC>>
+        + Air<SymbolicAirBuilder<Val<SC>>>
+        + for<'a> Air<DebugConstraintBuilder<'a, Val<SC>, SC::Challenge>>,
+    G: FnMut(&PhaseInputs<'_, SC>) -> RowMajorMatrix<SC::Challenge>,
+{
+    check_instance(air, &trace, public_values)?;
+    let padding = pad_trace(air, &mut trace)?;
+
+    let check = config.check_constraints();
+    let trace_phases = air.trace_phases();
+    let num_phases = trace_phases.len();
+    let preprocessed_trace = preprocessed
+        .filter(|_| check)
+        .and_then(|_| air.preprocessed_trace());
+    let padding_columns = padding
+        .as_ref()
+        .filter(|_| check)
+        .map(|padding| padding.columns.clone());
+    if check && num_phases == 0 {
+        check_constraints_with_phases(
+            air,
+            &trace,
+            preprocessed_trace.as_ref(),
+            padding_columns.as_ref(),
+            &[],
+            &[],
+            public_values,
+        )?;
+    }
+
+    prove_impl(
+        config,
+        air,
+        trace,
+        padding,
+        public_values,
+        preprocessed,
+        |inputs| {
+            let phase_trace = generate_phase(inputs);
+            let expected_width = trace_phases[inputs.phase].width;
+            if phase_trace.width() != expected_width {
+                return Err(ProvingError::PhaseTraceWidthMismatch {
+                    phase: inputs.phase,
+                    expected: expected_width,
+                    actual: phase_trace.width(),
+                });
+            }
+            if phase_trace.height() != inputs.main.height() {
+                return Err(ProvingError::PhaseTraceHeightMismatch {
+                    phase: inputs.phase,
+                    expected: inputs.main.height(),
+                    actual: phase_trace.height(),
+                });
+            }
+
+            // Once the last phase is generated, every column is known and the constraints can be checked.
+            if check && inputs.phase + 1 == num_phases {
+                let mut phases = inputs.phases.to_vec();
+                phases.push(phase_trace.clone());
+                check_constraints_with_phases(
+                    air,
+                    inputs.main,
+                    preprocessed_trace.as_ref(),
+                    padding_columns.as_ref(),
+                    &phases,
+                    inputs.challenges,
+                    public_values,
+                )?;
+            }
+
+            Ok(phase_trace)
+        },
+    )
+}
 
+/// Check that the shape of the trace and the number of public values match the AIR.
+///
+/// The height of the trace need not be a power of two if the AIR pads its trace.
+pub(crate) fn check_instance<F, A: BaseAirWithPublicValues<F>>(
+    air: &A,
+    trace: &RowMajorMatrix<F>,
+    public_values: &[F],
+) -> Result<(), ProvingError>
+where
+    F: Clone + Send + Sync,
+{
+    if air.window_size() < 2 {
+        return Err(ProvingError::InvalidWindowSize {
+            window_size: air.window_size(),
+        });
+    }
+    if air.trace_padding().is_none() && !trace.height().is_power_of_two() {
+        return Err(ProvingError::InvalidTraceHeight {
+            height: trace.height(),
+        });
+    }
+    if trace.width() != air.width() {
+        return Err(ProvingError::TraceWidthMismatch {
+            expected: air.width(),
+            actual: trace.width(),
+        });
+    }
+    if public_values.len() != air.num_public_values() {
+        return Err(ProvingError::PublicValuesCountMismatch {
+            expected: air.num_public_values(),
+            actual: public_values.len(),
+        });
+    }
+    Ok(())
+}
+
+/// The main proving routine, shared by every `prove` variant once the instance has been checked
+/// and the trace padded.
+///
+/// `generate_phase` may fail, for instance because the constraints are checked once the last
+/// phase has been generated.
+fn prove_impl<SC, A, G>(
+    config: &SC,
+    air: &A,
+    trace: RowMajorMatrix<Val<SC>>,
+    padding: Option<PaddingTrace<Val<SC>>>,
+    public_values: &Vec<Val<SC>>,
+    preprocessed: Option<&PreprocessedProverData<SC>>,
+    mut generate_phase: G,
+) -> Result<Proof<SC>, ProvingError>
+where
+    SC: StarkGenericConfig,
+    A: Air<SymbolicAirBuilder<Val<SC>>>,
+    G: FnMut(&PhaseInputs<'_, SC>) -> Result<RowMajorMatrix<SC::Challenge>, ProvingError>,
+{
     // Compute the height `N = 2^n` and `log_2(height)`, `n`, of the trace.
     let degree = trace.height();
+    let real_height = padding.as_ref().map(|padding| padding.real_height);
     let log_degree = log2_strict_usize(degree);
     let log_ext_degree = log_degree + config.is_zk();
 
+    let preprocessed_width = match preprocessed {
+        Some(prep) if prep.degree_bits != log_degree => {
+            return Err(ProvingError::PreprocessedHeightMismatch {
+                expected: degree,
+                actual: 1 << prep.degree_bits,
+            });
+        }
+        Some(prep) => prep.width,
+        None => 0,
+    };
+
+    if !get_symbolic_interactions(air, preprocessed_width, public_values.len()).is_empty() {
+        return Err(ProvingError::InteractionsUnsupported);
+    }
+
     // Compute the constraint polynomials as vectors of symbolic expressions.
-    let symbolic_constraints = get_symbolic_constraints(air, 0, public_values.len());
+    let symbolic_constraints =
+        get_symbolic_constraints(air, preprocessed_width, public_values.len());
 
-    // Count the number of constraints that we have.
-    let constraint_count = symbolic_constraints.len();
+    // Compile them once, so that the quotient can be computed without re-running `air.eval` on
+    // every point of the quotient domain.
+    let program = info_span!("compile constraints")
+        .in_scope(|| ConstraintProgram::new(&symbolic_constraints));
 
     // Each constraint polynomial looks like `C_j(X_1, ..., X_w, Y_1, ..., Y_w, Z_1, ..., Z_j)`.
     // When evaluated on a given row, the X_i's will be the `i`'th element of the that row, the
@@ -80,8 +319,12 @@ where
     // From the degree of the constraint polynomial, compute the number
     // of quotient polynomials we will split Q(x) into. This is chosen to
     // always be a power of 2.
-    let log_quotient_degree =
-        get_log_quotient_degree::<Val<SC>, A>(air, 0, public_values.len(), config.is_zk());
+    let log_quotient_degree = get_log_quotient_degree::<Val<SC>, A>(
+        air,
+        preprocessed_width,
+        public_values.len(),
+        config.is_zk(),
+    );
     let quotient_degree = 1 << (log_quotient_degree + config.is_zk());
 
     // Initialize the PCS and the Challenger.
@@ -106,14 +349,36 @@ where
     //      trace_commit contains the root of the tree
     //      trace_data contains the entire tree.
     //          - trace_data.leaves is the matrix containing `ET`.
-    let (trace_commit, trace_data) =
-        info_span!("commit to trace data").in_scope(|| pcs.commit([(ext_trace_domain, trace)]));
+    //
+    // If the trace is padded, its padding columns are committed to in the same tree.
+    let trace_phases = air.trace_phases();
+    // The main trace is needed to generate the trace phases, if there are any.
+    let main_trace = (!trace_phases.is_empty()).then(|| trace.clone());
+    let (trace_commit, trace_data) = info_span!("commit to trace data").in_scope(|| {
+        pcs.commit(
+            iter::once((ext_trace_domain, trace))
+                .chain(padding.map(|padding| (ext_trace_domain, padding.columns))),
+        )
+    });
 
     // Observe the instance.
     // degree < 2^255 so we can safely cast log_degree to a u8.
     challenger.observe(Val::<SC>::from_u8(log_ext_degree as u8));
     challenger.observe(Val::<SC>::from_u8(log_degree as u8));
-    // TODO: Might be best practice to include other instance data here; see verifier comment.
+
+    // Observe the fingerprint of the AIR, binding the proof to this particular AIR.
+    let preprocessed_vk = preprocessed.map(PreprocessedProverData::verifier_key);
+    challenger.observe_slice(&air_fingerprint(
+        config,
+        air,
+        preprocessed_vk.as_ref(),
+        public_values.len(),
+    ));
+
+    // Observe the commitment to the preprocessed trace, if there is one.
+    if let Some(prep) = preprocessed {
+        challenger.observe(prep.commitment.clone());
+    }
 
     // Observe the Merkle root of the trace commitment.
     challenger.observe(trace_commit.clone());
@@ -121,6 +386,40 @@ where
     // Observe the public input values.
     challenger.observe_slice(public_values);
 
+    // Observe the real height of the trace, which the padding constraints treat as a public value.
+    if let Some(real_height) = real_height {
+        challenger.observe(Val::<SC>::from_usize(real_height));
+    }
+
+    // Commit to each trace phase in turn. The challenges of a phase are sampled after every
+    // previous phase has been committed to, so the phase trace may depend on them.
+    let mut phase_challenges: Vec<Vec<SC::Challenge>> = Vec::with_capacity(trace_phases.len());
+    let mut phase_traces = Vec::with_capacity(trace_phases.len());
+    let mut phase_commits = Vec::with_capacity(trace_phases.len());
+    let mut phase_data = Vec::with_capacity(trace_phases.len());
+    for (phase, trace_phase) in trace_phases.iter().enumerate() {
+        phase_challenges.push(
+            (0..trace_phase.num_challenges)
+                .map(|_| challenger.sample_algebra_element())
+                .collect(),
+        );
+        let phase_trace = generate_phase(&PhaseInputs {
+            phase,
+            main: main_trace.as_ref().unwrap(),
+            public_values,
+            phases: &phase_traces,
+            challenges: &phase_challenges,
+        })?;
+
+        let (phase_commit, data) = info_span!("commit to trace phase", phase)
+            .in_scope(|| pcs.commit([(ext_trace_domain, phase_trace.clone().flatten_to_base())]));
+        challenger.observe(phase_commit.clone());
+        phase_traces.push(phase_trace);
+        phase_commits.push(phase_commit);
+        phase_data.push(data);
+    }
+    drop(phase_traces);
+
     // Get the first Fiat Shamir challenge which will be used to combine all constraint polynomials
     // into a single polynomial.
     //
@@ -132,15 +431,9 @@ where
     // This is a polynomial of degree n, so it has at most n roots. Thus the probability of this
     // occurring for a given trace and set of constraints is n/|EF|.
     //
-    // Currently, we do not observe data about the constraint polynomials directly. In particular
-    // a prover could take a trace and fiddle around with the AIR it claims to satisfy without
-    // changing this sample alpha.
-    //
-    // In particular this means that a malicious prover could create a custom AIR for a given trace
-    // such that equation (1) holds. However, such AIRs would need to be very specific and
-    // so such tampering should be obvious to spot. The verifier needs to check the AIR anyway to
-    // confirm that satisfying it indeed proves what the prover claims. Hence this should not be
-    // a soundness issue.
+    // The fingerprint of the AIR was observed above, so a prover cannot fiddle around with the AIR
+    // it claims to satisfy without changing this sample alpha. In particular it cannot choose a
+    // custom AIR for a given trace such that equation (1) holds.
     let alpha: SC::Challenge = challenger.sample_algebra_element();
 
     // A domain large enough to uniquely identify the quotient polynomial.
@@ -155,21 +448,24 @@ where
     // This only works if the trace domain is `gH'` and the quotient domain is `gK` for some subgroup `K` contained in `H'`.
     // TODO: Make this explicit in `get_evaluations_on_domain` or otherwise fix this.
     let trace_on_quotient_domain = pcs.get_evaluations_on_domain(&trace_data, 0, quotient_domain);
+    let preprocessed_on_quotient_domain = preprocessed
+        .map(|prep| pcs.get_evaluations_on_domain(&prep.prover_data, 0, quotient_domain));
+    let phases_on_quotient_domain = phase_data
+        .iter()
+        .map(|data| pcs.get_evaluations_on_domain(data, 0, quotient_domain))
+        .collect();
+    let padding_on_quotient_domain = real_height.map(|real_height| {
+        (
+            pcs.get_evaluations_on_domain(&trace_data, 1, quotient_domain),
+            real_height,
+        )
+    });
 
     // Compute the quotient polynomial `Q(x)` by evaluating
     //          `C(T_1(x), ..., T_w(x), T_1(hx), ..., T_w(hx), selectors(x)) / Z_H(x)`
     // at every point in the quotient domain. The degree of `Q(x)` is `<= deg(C(x)) - N = 2N - 2` in the case
     // where `deg(C) = 3`. (See the discussion above constraint_degree for more details.)
-    let quotient_values = quotient_values(
-        air,
-        public_values,
-        trace_domain,
-        quotient_domain,
-        trace_on_quotient_domain,
-        alpha,
-        constraint_count,
-    );
-
+    //
     // Due to `alpha`, evaluations of `Q` all lie in the extension field `E`.
     // We flatten this into a matrix of `F` values by treating `E` as an `F`
     // vector space and so separating each element of `E` into `e + 1 = [E: F]` elements of `F`.
@@ -177,7 +473,26 @@ where
     // This is valid to do because our domain lies in the base field `F`. Hence we can split
     // `Q(x)` into `e + 1` polynomials `Q_0(x), ... , Q_e(x)` each contained in `F`.
     // such that `Q(x) = [Q_0(x), ... ,Q_e(x)]` holds for all `x` in `F`.
-    let quotient_flat = RowMajorMatrix::new_col(quotient_values).flatten_to_base();
+    //
+    // The flattened evaluations are returned already split into the chunks described below.
+    let quotient_chunks = quotient_chunks::<SC, _>(
+        &program,
+        public_values,
+        air.window_size(),
+        trace_domain,
+        quotient_domain,
+        trace_on_quotient_domain,
+        preprocessed_on_quotient_domain,
+        None,
+        &[],
+        None,
+        phases_on_quotient_domain,
+        &phase_challenges,
+        padding_on_quotient_domain,
+        alpha,
+        quotient_degree,
+        config.quotient_batch_rows(),
+    );
 
     // Currently each polynomial `Q_i(x)` is of degree `<= 2(N - 1)` and
     // we have it's evaluations over a the coset `gK of size `2N`. Let `k` be the chosen
@@ -198,7 +513,7 @@ where
     //      quotient_data contains the entire tree.
     //          - quotient_data.leaves is a pair of matrices containing the `q_i0(x)` and `q_i1(x)`.
     let (quotient_commit, quotient_data) = info_span!("commit to quotient poly chunks")
-        .in_scope(|| pcs.commit_quotient(quotient_domain, quotient_flat, quotient_degree));
+        .in_scope(|| pcs.commit_quotient_chunks(quotient_domain, quotient_chunks));
     challenger.observe(quotient_commit.clone());
 
     // If zk is enabled, we generate random extension field values of the size of the randomized trace. If `n` is the degree of the initial trace,
@@ -224,6 +539,8 @@ where
         trace: trace_commit,
         quotient_chunks: quotient_commit,
         random: opt_r_commit.clone(),
+        permutation: None,
+        phases: phase_commits,
     };
 
     if let Some(r_commit) = opt_r_commit {
@@ -242,22 +559,41 @@ where
     // by zero errors. This doesn't lead to a soundness issue as the verifier will just reject in those
     // cases but it is a completeness issue and contributes a completeness error of |gK| = 2N/|EF|.
     let zeta: SC::Challenge = challenger.sample_algebra_element();
-    let zeta_next = trace_domain.next_point(zeta).unwrap();
+    // The traces evaluated over the window of the AIR are opened at `zeta` and at its successors
+    // `g * zeta, g^2 * zeta, ...` while the padding columns are only read at `zeta` and `g * zeta`.
+    let window_points = window_points(trace_domain, zeta, air.window_size());
 
     let is_random = opt_r_data.is_some();
     let (opened_values, opening_proof) = info_span!("open").in_scope(|| {
         let round0 = opt_r_data.as_ref().map(|r_data| (r_data, vec![vec![zeta]]));
-        let round1 = (&trace_data, vec![vec![zeta, zeta_next]]);
+        let round1 = (
+            &trace_data,
+            iter::once(window_points.clone())
+                .chain(real_height.map(|_| window_points[..2].to_vec()))
+                .collect(),
+        );
         let round2 = (&quotient_data, vec![vec![zeta]; quotient_degree]); // open every chunk at zeta
-
-        let rounds = round0.into_iter().chain([round1, round2]).collect();
+        let round3 = preprocessed.map(|prep| (&prep.prover_data, vec![window_points.clone()]));
+        let phase_rounds = phase_data
+            .iter()
+            .map(|data| (data, vec![window_points.clone()]));
+
+        let rounds = round0
+            .into_iter()
+            .chain([round1, round2])
+            .chain(round3)
+            .chain(phase_rounds)
+            .collect();
 
         pcs.open(rounds, &mut challenger)
     });
     let trace_idx = SC::Pcs::TRACE_IDX;
     let quotient_idx = SC::Pcs::QUOTIENT_IDX;
-    let trace_local = opened_values[trace_idx][0][0].clone();
-    let trace_next = opened_values[trace_idx][0][1].clone();
+    let trace_window = opened_values[trace_idx][0].clone();
+    let (padding_local, padding_next) = opened_values[trace_idx]
+        .get(1)
+        .map(|padding| (padding[0].clone(), padding[1].clone()))
+        .unzip();
     let quotient_chunks = opened_values[quotient_idx]
         .iter()
         .map(|v| v[0].clone())
@@ -267,45 +603,102 @@ where
     } else {
         None
     };
+    // The preprocessed round, if present, is opened after the quotient round.
+    let preprocessed_window = preprocessed.map(|_| opened_values[quotient_idx + 1][0].clone());
+    // The trace phases, if any, are opened last.
+    let first_phase_idx = quotient_idx + 1 + preprocessed.is_some() as usize;
+    let phases_window = opened_values[first_phase_idx..]
+        .iter()
+        .map(|phase| phase[0].clone())
+        .collect();
     let opened_values = OpenedValues {
-        trace_local,
-        trace_next,
+        trace_window,
+        preprocessed_window,
+        permutation_local: None,
+        permutation_next: None,
+        phases_window,
+        padding_local,
+        padding_next,
         quotient_chunks,
         random,
     };
-    Proof {
+    Ok(Proof {
         commitments,
         opened_values,
         opening_proof,
         degree_bits: log_ext_degree,
-    }
+        real_height,
+    })
+}
+
+/// The points at which a trace evaluated over a window of `window_size` rows is opened: `zeta`
+/// and its `window_size - 1` successors in `trace_domain`.
+pub(crate) fn window_points<D, EF>(trace_domain: D, zeta: EF, window_size: usize) -> Vec<EF>
+where
+    D: PolynomialSpace,
+    EF: ExtensionField<D::Val>,
+{
+    iter::successors(Some(zeta), |&point| trace_domain.next_point(point))
+        .take(window_size)
+        .collect()
 }
 
+/// The number of packed rows on which the quotient computation evaluates the constraints at once.
+const ROWS_PER_EVAL: usize = 8;
+
+/// Evaluate the quotient polynomial over the quotient domain, flattened to the base field and split
+/// into `num_chunks` chunks as by [`PolynomialSpace::split_evals`].
+///
+/// With `batch_rows`, see [`StarkGenericConfig::quotient_batch_rows`], the quotient is evaluated
+/// in batches of rows written straight into the chunks.
 #[instrument(name = "compute quotient polynomial", skip_all)]
 // TODO: Group some arguments to remove the `allow`?
 #[allow(clippy::too_many_arguments)]
-fn quotient_values<SC, A, Mat>(
-    air: &A,
-    public_values: &Vec<Val<SC>>,
+pub(crate) fn quotient_chunks<SC, Mat>(
+    program: &ConstraintProgram<Val<SC>>,
+    public_values: &[Val<SC>],
+    window_size: usize,
     trace_domain: Domain<SC>,
     quotient_domain: Domain<SC>,
     trace_on_quotient_domain: Mat,
+    preprocessed_on_quotient_domain: Option<Mat>,
+    permutation_on_quotient_domain: Option<Mat>,
+    permutation_challenges: &[SC::Challenge],
+    cumulative_sum: Option<SC::Challenge>,
+    phases_on_quotient_domain: Vec<Mat>,
+    phase_challenges: &[Vec<SC::Challenge>],
+    padding_on_quotient_domain: Option<(Mat, usize)>,
     alpha: SC::Challenge,
-    constraint_count: usize,
-) -> Vec<SC::Challenge>
+    num_chunks: usize,
+    batch_rows: Option<usize>,
+) -> Vec<RowMajorMatrix<Val<SC>>>
 where
     SC: StarkGenericConfig,
-    A: for<'a> Air<ProverConstraintFolder<'a, SC>>,
     Mat: Matrix<Val<SC>> + Sync,
 {
     let quotient_size = quotient_domain.size();
     let width = trace_on_quotient_domain.width();
+    let preprocessed_width = preprocessed_on_quotient_domain
+        .as_ref()
+        .map_or(0, |prep| prep.width());
     let mut sels = debug_span!("Compute Selectors")
         .in_scope(|| trace_domain.selectors_on_coset(quotient_domain));
 
     let qdb = log2_strict_usize(quotient_domain.size()) - log2_strict_usize(trace_domain.size());
     let next_step = 1 << qdb;
 
+    // The selector of the rows whose window of `size` rows does not wrap around, for each `size`
+    // from 3 to the window of the AIR, is the product of `is_transition` over the first `size - 1`
+    // rows of the window.
+    let mut transition_windows: Vec<Vec<Val<SC>>> = Vec::new();
+    for size in 3..=window_size {
+        let previous = transition_windows.last().unwrap_or(&sels.is_transition);
+        let window = (0..quotient_size)
+            .map(|i| previous[i] * sels.is_transition[(i + (size - 2) * next_step) % quotient_size])
+            .collect();
+        transition_windows.push(window);
+    }
+
     // We take PackedVal::<SC>::WIDTH worth of values at a time from a quotient_size slice, so we need to
     // pad with default values in the case where quotient_size is smaller than PackedVal::<SC>::WIDTH.
     for _ in quotient_size..PackedVal::<SC>::WIDTH {
@@ -313,59 +706,258 @@ where
         sels.is_last_row.push(Val::<SC>::default());
         sels.is_transition.push(Val::<SC>::default());
         sels.inv_vanishing.push(Val::<SC>::default());
+        for window in &mut transition_windows {
+            window.push(Val::<SC>::default());
+        }
     }
 
-    let mut alpha_powers = alpha.powers().collect_n(constraint_count);
+    let mut alpha_powers = alpha.powers().collect_n(program.num_constraints());
     alpha_powers.reverse();
-    // alpha powers looks like Vec<EF> ~ Vec<[F; D]>
-    // It's useful to also have access to the transpose of this of form [Vec<F>; D].
-    let decomposed_alpha_powers: Vec<_> = (0..SC::Challenge::DIMENSION)
-        .map(|i| {
-            alpha_powers
-                .iter()
-                .map(|x| x.as_basis_coefficients_slice()[i])
-                .collect()
-        })
-        .collect();
-    (0..quotient_size)
-        .into_par_iter()
-        .step_by(PackedVal::<SC>::WIDTH)
-        .flat_map_iter(|i_start| {
-            let i_range = i_start..i_start + PackedVal::<SC>::WIDTH;
-
-            let is_first_row = *PackedVal::<SC>::from_slice(&sels.is_first_row[i_range.clone()]);
-            let is_last_row = *PackedVal::<SC>::from_slice(&sels.is_last_row[i_range.clone()]);
-            let is_transition = *PackedVal::<SC>::from_slice(&sels.is_transition[i_range.clone()]);
-            let inv_vanishing = *PackedVal::<SC>::from_slice(&sels.inv_vanishing[i_range]);
-
-            let main = RowMajorMatrix::new(
-                trace_on_quotient_domain.vertically_packed_row_pair(i_start, next_step),
-                width,
-            );
-
-            let accumulator = PackedChallenge::<SC>::ZERO;
-            let mut folder = ProverConstraintFolder {
-                main: main.as_view(),
-                public_values,
-                is_first_row,
-                is_last_row,
-                is_transition,
-                alpha_powers: &alpha_powers,
-                decomposed_alpha_powers: &decomposed_alpha_powers,
-                accumulator,
-                constraint_index: 0,
-            };
-            air.eval(&mut folder);
-
-            // quotient(x) = constraints(x) / Z_H(x)
-            let quotient = folder.accumulator * inv_vanishing;
-
-            // "Transpose" D packed base coefficients into WIDTH scalar extension coefficients.
-            (0..core::cmp::min(quotient_size, PackedVal::<SC>::WIDTH)).map(move |idx_in_packing| {
-                SC::Challenge::from_basis_coefficients_fn(|coeff_idx| {
-                    quotient.as_basis_coefficients_slice()[coeff_idx].as_slice()[idx_in_packing]
-                })
+    // The symbolic constraints refer to the real height of a padded trace as an extra public
+    // value, and to the cumulative sum of the LogUp argument as an extra challenge.
+    let public_values = public_values
+        .iter()
+        .copied()
+        .chain(
+            padding_on_quotient_domain
+                .as_ref()
+                .map(|&(_, real_height)| Val::<SC>::from_usize(real_height)),
+        )
+        .collect_vec();
+    let challenges = permutation_challenges
+        .iter()
+        .copied()
+        .chain(cumulative_sum)
+        .collect_vec();
+
+    // Every task evaluates the constraints on `ROWS_PER_EVAL` packed rows at a time, reusing the
+    // same registers.
+    let packed_width = PackedVal::<SC>::WIDTH;
+    let rows_per_eval = packed_width * ROWS_PER_EVAL;
+    let packed = |values: &[Val<SC>], i_start: usize| {
+        *PackedVal::<SC>::from_slice(&values[i_start..i_start + packed_width])
+    };
+    // Evaluate the quotient on the given rows of the quotient domain.
+    let eval_rows = |rows: Range<usize>| -> Vec<SC::Challenge> {
+        let rows_per_task = (rows.len() / current_num_threads())
+            .max(1)
+            .next_multiple_of(rows_per_eval);
+        rows.clone()
+            .into_par_iter()
+            .step_by(rows_per_task)
+            .flat_map_iter(|task_start| {
+                let task_end = core::cmp::min(task_start + rows_per_task, rows.end);
+                let mut registers = Registers::default();
+                let mut values = Vec::with_capacity(task_end - task_start);
+                for eval_start in (task_start..task_end).step_by(rows_per_eval) {
+                    // If the quotient domain is smaller than `rows_per_eval`, rows wrap around and the
+                    // extra results are dropped.
+                    let i_starts: [usize; ROWS_PER_EVAL] =
+                        array::from_fn(|j| (eval_start + j * packed_width) % quotient_size);
+
+                    let main = i_starts.map(|i_start| {
+                        RowMajorMatrix::new(
+                            trace_on_quotient_domain.vertically_packed_row_window(
+                                i_start,
+                                next_step,
+                                window_size,
+                            ),
+                            width,
+                        )
+                    });
+                    let preprocessed = i_starts.map(|i_start| {
+                        RowMajorMatrix::new(
+                            preprocessed_on_quotient_domain.as_ref().map_or_else(
+                                Vec::new,
+                                |prep| {
+                                    prep.vertically_packed_row_window(
+                                        i_start,
+                                        next_step,
+                                        window_size,
+                                    )
+                                },
+                            ),
+                            preprocessed_width,
+                        )
+                    });
+                    let permutation = i_starts.map(|i_start| {
+                        permutation_on_quotient_domain.as_ref().map_or_else(
+                            || RowMajorMatrix::new(vec![], 0),
+                            |perm| packed_ext_row_window::<SC, _>(perm, i_start, next_step, 2),
+                        )
+                    });
+                    let phases = i_starts.map(|i_start| {
+                        phases_on_quotient_domain
+                            .iter()
+                            .map(|phase| {
+                                packed_ext_row_window::<SC, _>(
+                                    phase,
+                                    i_start,
+                                    next_step,
+                                    window_size,
+                                )
+                            })
+                            .collect_vec()
+                    });
+                    let phases = phases
+                        .each_ref()
+                        .map(|phases| phases.iter().map(RowMajorMatrix::as_view).collect_vec());
+                    let padding = i_starts.map(|i_start| {
+                        padding_on_quotient_domain.as_ref().map_or_else(
+                            || RowMajorMatrix::new(vec![], 0),
+                            |(padding, _)| {
+                                RowMajorMatrix::new(
+                                    padding.vertically_packed_row_pair(i_start, next_step),
+                                    PADDING_WIDTH,
+                                )
+                            },
+                        )
+                    });
+
+                    let is_transition_windows = i_starts.map(|i_start| {
+                        transition_windows
+                            .iter()
+                            .map(|window| packed(window, i_start))
+                            .collect_vec()
+                    });
+
+                    let inputs: [_; ROWS_PER_EVAL] = array::from_fn(|j| ConstraintInputs {
+                        main: main[j].as_view(),
+                        preprocessed: preprocessed[j].as_view(),
+                        padding: padding[j].as_view(),
+                        permutation: permutation[j].as_view(),
+                        phases: &phases[j],
+                        public_values: &public_values,
+                        challenges: &challenges,
+                        phase_challenges,
+                        is_first_row: packed(&sels.is_first_row, i_starts[j]),
+                        is_last_row: packed(&sels.is_last_row, i_starts[j]),
+                        is_transition: packed(&sels.is_transition, i_starts[j]),
+                        is_transition_windows: &is_transition_windows[j],
+                    });
+                    let accumulators = program.eval(&inputs, &alpha_powers, &mut registers);
+
+                    // quotient(x) = constraints(x) / Z_H(x)
+                    let quotients: [PackedChallenge<SC>; ROWS_PER_EVAL] = array::from_fn(|j| {
+                        accumulators[j] * packed(&sels.inv_vanishing, i_starts[j])
+                    });
+
+                    // "Transpose" D packed base coefficients into WIDTH scalar extension coefficients.
+                    let num_values = core::cmp::min(rows_per_eval, task_end - eval_start);
+                    values.extend((0..num_values).map(|i| {
+                        let quotient = quotients[i / packed_width];
+                        SC::Challenge::from_basis_coefficients_fn(|coeff_idx| {
+                            quotient.as_basis_coefficients_slice()[coeff_idx].as_slice()
+                                [i % packed_width]
+                        })
+                    }));
+                }
+                values
             })
-        })
+            .collect()
+    };
+
+    let Some(batch_rows) = batch_rows else {
+        let quotient_flat = RowMajorMatrix::new_col(eval_rows(0..quotient_size)).flatten_to_base();
+        return quotient_domain.split_evals(num_chunks, quotient_flat);
+    };
+
+    // Each batch covers a whole number of evaluations, so that its rows do not wrap around.
+    let batch_size = batch_rows.max(1).next_multiple_of(rows_per_eval);
+    let chunk_height = quotient_size / num_chunks;
+    let dimension = SC::Challenge::DIMENSION;
+    let mut chunks = vec![Val::<SC>::zero_vec(chunk_height * dimension); num_chunks];
+    for batch_start in (0..quotient_size).step_by(batch_size) {
+        let batch_end = core::cmp::min(batch_start + batch_size, quotient_size);
+        let _span = debug_span!("quotient batch", batch_start).entered();
+        for (i, value) in (batch_start..batch_end).zip(eval_rows(batch_start..batch_end)) {
+            let (chunk, row) = quotient_domain.split_index(num_chunks, i);
+            chunks[chunk][row * dimension..(row + 1) * dimension]
+                .copy_from_slice(value.as_basis_coefficients_slice());
+        }
+    }
+    chunks
+        .into_iter()
+        .map(|values| RowMajorMatrix::new(values, dimension))
         .collect()
 }
+
+/// Read the rows `i_start..i_start + WIDTH` and the `size - 1` windows of rows following them,
+/// each `next_step` rows apart, from an extension field matrix committed to as a flattened base
+/// field matrix, packing them as extension field values.
+fn packed_ext_row_window<SC, Mat>(
+    mat: &Mat,
+    i_start: usize,
+    next_step: usize,
+    size: usize,
+) -> RowMajorMatrix<PackedChallenge<SC>>
+where
+    SC: StarkGenericConfig,
+    Mat: Matrix<Val<SC>>,
+{
+    let values = mat
+        .vertically_packed_row_window(i_start, next_step, size)
+        .chunks_exact(SC::Challenge::DIMENSION)
+        .map(|coeffs| PackedChallenge::<SC>::from_basis_coefficients_fn(|i| coeffs[i]))
+        .collect();
+    RowMajorMatrix::new(values, mat.width() / SC::Challenge::DIMENSION)
+}
+
+/// An error returned by the prover when it is given an invalid instance.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProvingError {
+    /// The height of the trace is not a power of two, or the trace of an AIR which pads its trace
+    /// is empty.
+    InvalidTraceHeight { height: usize },
+    /// The width of the trace does not match [`BaseAir::width`](p3_air::BaseAir::width).
+    TraceWidthMismatch { expected: usize, actual: usize },
+    /// The number of public values does not match
+    /// [`BaseAirWithPublicValues::num_public_values`].
+    PublicValuesCountMismatch { expected: usize, actual: usize },
+    /// The window of the AIR has fewer than two rows, see
+    /// [`BaseAir::window_size`](p3_air::BaseAir::window_size).
+    InvalidWindowSize { window_size: usize },
+    /// The constraint with the given index, in the order in which the AIR asserts its constraints,
+    /// does not vanish on the given row.
+    ///
+    /// Constraints are not checked if [`StarkGenericConfig::check_constraints`] returns `false`, in
+    /// which case an unsatisfied constraint results in a proof which the verifier rejects.
+    UnsatisfiedConstraint { row: usize, constraint: usize },
+    /// The filler row of an AIR padding its trace with [`TracePadding::FillerRow`] does not have
+    /// the width of the trace.
+    ///
+    /// [`TracePadding::FillerRow`]: p3_air::TracePadding::FillerRow
+    FillerRowWidthMismatch { expected: usize, actual: usize },
+    /// The height of the preprocessed trace does not match the height of the trace.
+    PreprocessedHeightMismatch { expected: usize, actual: usize },
+    /// The trace generated for the given phase does not have the width declared by the AIR.
+    PhaseTraceWidthMismatch {
+        phase: usize,
+        expected: usize,
+        actual: usize,
+    },
+    /// The trace generated for the given phase does not have the height of the trace.
+    PhaseTraceHeightMismatch {
+        phase: usize,
+        expected: usize,
+        actual: usize,
+    },
+    /// The AIR declares trace phases, which only [`prove_with_phases`] supports.
+    TracePhasesUnsupported,
+    /// The AIR has interactions, which only [`prove_multi`](crate::prove_multi) supports.
+    InteractionsUnsupported,
+    /// [`prove_multi`](crate::prove_multi) was given no instances.
+    NoInstances,
+    /// [`prove_multi`](crate::prove_multi) does not support hiding PCSs.
+    HidingPcsUnsupported,
+    /// [`prove_multi`](crate::prove_multi) does not support AIRs with preprocessed columns.
+    PreprocessedTraceUnsupported,
+    /// [`prove_multi`](crate::prove_multi) does not support AIRs which pad their trace.
+    TracePaddingUnsupported,
+    /// The instance with the given index, in the order passed to
+    /// [`prove_multi`](crate::prove_multi), is invalid.
+    InvalidInstance {
+        instance: usize,
+        error: Box<ProvingError>,
+    },
+}
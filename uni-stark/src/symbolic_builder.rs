@@ -1,15 +1,21 @@
 use alloc::vec;
 use alloc::vec::Vec;
-
-use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, PairBuilder};
-use p3_field::Field;
+use core::mem;
+
+use p3_air::{
+    Air, AirBuilder, AirBuilderWithPublicValues, ExtensionBuilder, Interaction, InteractionBuilder,
+    MultiPhaseAirBuilder, PairBuilder, PermutationAirBuilder, TracePhase,
+};
+use p3_field::{Field, PrimeCharacteristicRing};
+use p3_matrix::Matrix;
 use p3_matrix::dense::RowMajorMatrix;
 use p3_util::log2_ceil_usize;
 use tracing::instrument;
 
-use crate::Entry;
+use crate::padding::eval_padding;
 use crate::symbolic_expression::SymbolicExpression;
 use crate::symbolic_variable::SymbolicVariable;
+use crate::{Entry, LOGUP_NUM_CHALLENGES, PADDING_WIDTH, eval_logup};
 
 #[instrument(name = "infer log of constraint degree", skip_all)]
 pub fn get_log_quotient_degree<F, A>(
@@ -60,46 +66,167 @@ where
     F: Field,
     A: Air<SymbolicAirBuilder<F>>,
 {
-    let mut builder = SymbolicAirBuilder::new(preprocessed_width, air.width(), num_public_values);
+    let mut builder = SymbolicAirBuilder::new(preprocessed_width, air.width(), num_public_values)
+        .with_window_size(air.window_size())
+        .with_trace_phases(&air.trace_phases())
+        .with_padding(air.trace_padding().is_some());
     air.eval(&mut builder);
+    builder.eval_logup();
+    builder.eval_padding();
     builder.constraints()
 }
 
+/// Collect the interactions of an AIR symbolically, in the order in which they are pushed.
+#[instrument(name = "evaluate interactions symbolically", skip_all, level = "debug")]
+pub fn get_symbolic_interactions<F, A>(
+    air: &A,
+    preprocessed_width: usize,
+    num_public_values: usize,
+) -> Vec<Interaction<SymbolicExpression<F>>>
+where
+    F: Field,
+    A: Air<SymbolicAirBuilder<F>>,
+{
+    let mut builder = SymbolicAirBuilder::new(preprocessed_width, air.width(), num_public_values)
+        .with_window_size(air.window_size())
+        .with_trace_phases(&air.trace_phases())
+        .with_padding(air.trace_padding().is_some());
+    air.eval(&mut builder);
+    builder.interactions
+}
+
+/// The variables of a window of `size` rows of a trace of the given width.
+fn window<F: Field>(
+    size: usize,
+    width: usize,
+    entry: impl Fn(usize) -> Entry,
+) -> RowMajorMatrix<SymbolicVariable<F>> {
+    let values = (0..size)
+        .flat_map(|offset| {
+            let entry = entry(offset);
+            (0..width).map(move |index| SymbolicVariable::new(entry, index))
+        })
+        .collect();
+    RowMajorMatrix::new(values, width)
+}
+
 /// An `AirBuilder` for evaluating constraints symbolically, and recording them for later use.
 #[derive(Debug)]
 pub struct SymbolicAirBuilder<F: Field> {
+    window_size: usize,
     preprocessed: RowMajorMatrix<SymbolicVariable<F>>,
     main: RowMajorMatrix<SymbolicVariable<F>>,
+    permutation: RowMajorMatrix<SymbolicVariable<F>>,
+    permutation_challenges: Vec<SymbolicVariable<F>>,
+    phases: Vec<RowMajorMatrix<SymbolicVariable<F>>>,
+    phase_challenges: Vec<Vec<SymbolicVariable<F>>>,
+    padding: RowMajorMatrix<SymbolicVariable<F>>,
     public_values: Vec<SymbolicVariable<F>>,
+    interactions: Vec<Interaction<SymbolicExpression<F>>>,
     constraints: Vec<SymbolicExpression<F>>,
 }
 
 impl<F: Field> SymbolicAirBuilder<F> {
     pub(crate) fn new(preprocessed_width: usize, width: usize, num_public_values: usize) -> Self {
-        let prep_values = [0, 1]
-            .into_iter()
-            .flat_map(|offset| {
-                (0..preprocessed_width)
-                    .map(move |index| SymbolicVariable::new(Entry::Preprocessed { offset }, index))
-            })
-            .collect();
-        let main_values = [0, 1]
-            .into_iter()
-            .flat_map(|offset| {
-                (0..width).map(move |index| SymbolicVariable::new(Entry::Main { offset }, index))
-            })
-            .collect();
         let public_values = (0..num_public_values)
             .map(move |index| SymbolicVariable::new(Entry::Public, index))
             .collect();
+        let permutation_challenges = (0..LOGUP_NUM_CHALLENGES)
+            .map(|index| SymbolicVariable::new(Entry::Challenge, index))
+            .collect();
         Self {
-            preprocessed: RowMajorMatrix::new(prep_values, preprocessed_width),
-            main: RowMajorMatrix::new(main_values, width),
+            window_size: 2,
+            preprocessed: window(2, preprocessed_width, |offset| Entry::Preprocessed {
+                offset,
+            }),
+            main: window(2, width, |offset| Entry::Main { offset }),
+            permutation: RowMajorMatrix::new(vec![], 0),
+            permutation_challenges,
+            phases: vec![],
+            phase_challenges: vec![],
+            padding: RowMajorMatrix::new(vec![], 0),
             public_values,
+            interactions: vec![],
             constraints: vec![],
         }
     }
 
+    /// Give the main and preprocessed traces, and the trace phases added afterwards, a window of
+    /// `window_size` rows, see [`BaseAir::window_size`](p3_air::BaseAir::window_size).
+    pub(crate) fn with_window_size(mut self, window_size: usize) -> Self {
+        assert!(
+            window_size >= 2,
+            "the window must contain at least two rows"
+        );
+        self.window_size = window_size;
+        self.preprocessed = window(window_size, self.preprocessed.width(), |offset| {
+            Entry::Preprocessed { offset }
+        });
+        self.main = window(window_size, self.main.width(), |offset| Entry::Main {
+            offset,
+        });
+        self
+    }
+
+    /// Add variables for the given challenge-dependent trace phases.
+    pub(crate) fn with_trace_phases(mut self, trace_phases: &[TracePhase]) -> Self {
+        self.phases = trace_phases
+            .iter()
+            .enumerate()
+            .map(|(phase, trace_phase)| {
+                window(self.window_size, trace_phase.width, |offset| Entry::Phase {
+                    phase,
+                    offset,
+                })
+            })
+            .collect();
+        self.phase_challenges = trace_phases
+            .iter()
+            .enumerate()
+            .map(|(phase, trace_phase)| {
+                (0..trace_phase.num_challenges)
+                    .map(|index| SymbolicVariable::new(Entry::PhaseChallenge { phase }, index))
+                    .collect()
+            })
+            .collect();
+        self
+    }
+
+    /// Add variables for the padding columns, if the AIR pads its trace.
+    pub(crate) fn with_padding(mut self, padded: bool) -> Self {
+        if padded {
+            self.padding = window(2, PADDING_WIDTH, |offset| Entry::Padding { offset });
+        }
+        self
+    }
+
+    /// Add the constraints on the padding columns, if the AIR pads its trace.
+    ///
+    /// The real height of the trace is represented by the public value following those of the AIR.
+    fn eval_padding(&mut self) {
+        if self.padding.width() == 0 {
+            return;
+        }
+        let padding = self.padding.clone();
+        let real_height = SymbolicVariable::new(Entry::Public, self.public_values.len());
+        eval_padding(self, &padding, real_height.into());
+    }
+
+    /// Add the LogUp constraints enforcing the interactions recorded so far, if there are any.
+    ///
+    /// The cumulative sum is represented by the challenge following the LogUp challenges.
+    fn eval_logup(&mut self) {
+        let interactions = mem::take(&mut self.interactions);
+        if interactions.is_empty() {
+            return;
+        }
+        self.permutation = window(2, interactions.len() + 1, |offset| Entry::Permutation {
+            offset,
+        });
+        let cumulative_sum = SymbolicVariable::new(Entry::Challenge, LOGUP_NUM_CHALLENGES);
+        eval_logup(self, &interactions, cumulative_sum.into());
+    }
+
     pub(crate) fn constraints(self) -> Vec<SymbolicExpression<F>> {
         self.constraints
     }
@@ -124,18 +251,29 @@ impl<F: Field> AirBuilder for SymbolicAirBuilder<F> {
     }
 
     /// # Panics
-    /// This function panics if `size` is not `2`.
+    /// This function panics if `size` exceeds the window size of the AIR.
     fn is_transition_window(&self, size: usize) -> Self::Expr {
-        if size == 2 {
-            SymbolicExpression::IsTransition
-        } else {
-            panic!("uni-stark only supports a window size of 2")
+        assert!(
+            size <= self.window_size,
+            "the window of the AIR has fewer than {size} rows"
+        );
+        match size {
+            0 | 1 => SymbolicExpression::ONE,
+            2 => SymbolicExpression::IsTransition,
+            _ => SymbolicExpression::IsTransitionWindow(size),
         }
     }
 
     fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
         self.constraints.push(x.into());
     }
+
+    fn is_padding(&self) -> Self::Expr {
+        self.padding
+            .values
+            .first()
+            .map_or(Self::Expr::ZERO, |&v| v.into())
+    }
 }
 
 impl<F: Field> AirBuilderWithPublicValues for SymbolicAirBuilder<F> {
@@ -151,6 +289,60 @@ impl<F: Field> PairBuilder for SymbolicAirBuilder<F> {
     }
 }
 
+impl<F: Field> ExtensionBuilder for SymbolicAirBuilder<F> {
+    type EF = F;
+    type ExprEF = SymbolicExpression<F>;
+    type VarEF = SymbolicVariable<F>;
+
+    fn assert_zero_ext<I>(&mut self, x: I)
+    where
+        I: Into<Self::ExprEF>,
+    {
+        self.constraints.push(x.into());
+    }
+}
+
+impl<F: Field> PermutationAirBuilder for SymbolicAirBuilder<F> {
+    type MP = RowMajorMatrix<Self::VarEF>;
+    type RandomVar = SymbolicVariable<F>;
+
+    fn permutation(&self) -> Self::MP {
+        self.permutation.clone()
+    }
+
+    fn permutation_randomness(&self) -> &[Self::RandomVar] {
+        &self.permutation_challenges
+    }
+}
+
+impl<F: Field> MultiPhaseAirBuilder for SymbolicAirBuilder<F> {
+    type MPhase = RowMajorMatrix<Self::VarEF>;
+    type PhaseChallengeVar = SymbolicVariable<F>;
+
+    fn phase(&self, phase: usize) -> Self::MPhase {
+        self.phases[phase].clone()
+    }
+
+    fn phase_challenges(&self, phase: usize) -> &[Self::PhaseChallengeVar] {
+        &self.phase_challenges[phase]
+    }
+}
+
+impl<F: Field> InteractionBuilder for SymbolicAirBuilder<F> {
+    fn push_interaction<E: Into<Self::Expr>>(
+        &mut self,
+        bus: usize,
+        fields: impl IntoIterator<Item = E>,
+        multiplicity: impl Into<Self::Expr>,
+    ) {
+        self.interactions.push(Interaction {
+            fields: fields.into_iter().map(Into::into).collect(),
+            multiplicity: multiplicity.into(),
+            bus,
+        });
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use alloc::vec;
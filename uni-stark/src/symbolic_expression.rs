@@ -14,6 +14,9 @@ pub enum SymbolicExpression<F> {
     IsFirstRow,
     IsLastRow,
     IsTransition,
+    /// The selector of [`is_transition_window`](p3_air::AirBuilder::is_transition_window) for
+    /// a window of more than two rows.
+    IsTransitionWindow(usize),
     Constant(F),
     Add {
         x: Rc<Self>,
@@ -42,6 +45,10 @@ impl<F> SymbolicExpression<F> {
         match self {
             Self::Variable(v) => v.degree_multiple(),
             Self::IsFirstRow | Self::IsLastRow => 1,
+            // The selector of a window of `size` rows has degree `size - 1`. Unlike the degree 1
+            // selector `IsTransition`, it could exceed the slack in the quotient degree, so it is
+            // accounted for as a full multiple of `n`.
+            Self::IsTransitionWindow(_) => 1,
             Self::IsTransition | Self::Constant(_) => 0,
             Self::Add {
                 degree_multiple, ..
@@ -59,6 +66,36 @@ impl<F> SymbolicExpression<F> {
     }
 }
 
+impl<F: Field> SymbolicExpression<F> {
+    /// Evaluate this expression at a single point.
+    ///
+    /// `variable` resolves each `SymbolicVariable` to its value, while `is_first_row`,
+    /// `is_last_row` and `is_transition_window` give the values of the row selectors at that
+    /// point, the latter for a given window size.
+    pub fn evaluate<E: Algebra<F>>(
+        &self,
+        variable: &impl Fn(&SymbolicVariable<F>) -> E,
+        is_first_row: &E,
+        is_last_row: &E,
+        is_transition_window: &impl Fn(usize) -> E,
+    ) -> E {
+        let eval =
+            |expr: &Self| expr.evaluate(variable, is_first_row, is_last_row, is_transition_window);
+        match self {
+            Self::Variable(v) => variable(v),
+            Self::IsFirstRow => is_first_row.clone(),
+            Self::IsLastRow => is_last_row.clone(),
+            Self::IsTransition => is_transition_window(2),
+            Self::IsTransitionWindow(size) => is_transition_window(*size),
+            Self::Constant(c) => E::from(*c),
+            Self::Add { x, y, .. } => eval(x) + eval(y),
+            Self::Sub { x, y, .. } => eval(x) - eval(y),
+            Self::Neg { x, .. } => -eval(x),
+            Self::Mul { x, y, .. } => eval(x) * eval(y),
+        }
+    }
+}
+
 impl<F: Field> Default for SymbolicExpression<F> {
     fn default() -> Self {
         Self::Constant(F::ZERO)
@@ -284,6 +321,13 @@ mod tests {
             "IsTransition should have degree 0"
         );
 
+        let is_transition_window = SymbolicExpression::<BabyBear>::IsTransitionWindow(3);
+        assert_eq!(
+            is_transition_window.degree_multiple(),
+            1,
+            "IsTransitionWindow should have degree 1"
+        );
+
         let add_expr = SymbolicExpression::<BabyBear>::Add {
             x: Rc::new(variable_expr.clone()),
             y: Rc::new(preprocessed_var.clone()),
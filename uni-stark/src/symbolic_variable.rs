@@ -5,13 +5,32 @@ use p3_field::Field;
 
 use crate::symbolic_expression::SymbolicExpression;
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Entry {
-    Preprocessed { offset: usize },
-    Main { offset: usize },
-    Permutation { offset: usize },
+    Preprocessed {
+        offset: usize,
+    },
+    Main {
+        offset: usize,
+    },
+    Permutation {
+        offset: usize,
+    },
+    /// A column of a challenge-dependent trace phase, see [`p3_air::BaseAir::trace_phases`].
+    Phase {
+        phase: usize,
+        offset: usize,
+    },
     Public,
     Challenge,
+    /// A challenge sampled right before the given trace phase was committed to.
+    PhaseChallenge {
+        phase: usize,
+    },
+    /// A padding column, see [`PADDING_WIDTH`](crate::PADDING_WIDTH).
+    Padding {
+        offset: usize,
+    },
 }
 
 /// A variable within the evaluation window, i.e. a column in either the local or next row.
@@ -33,8 +52,12 @@ impl<F> SymbolicVariable<F> {
 
     pub const fn degree_multiple(&self) -> usize {
         match self.entry {
-            Entry::Preprocessed { .. } | Entry::Main { .. } | Entry::Permutation { .. } => 1,
-            Entry::Public | Entry::Challenge => 0,
+            Entry::Preprocessed { .. }
+            | Entry::Main { .. }
+            | Entry::Permutation { .. }
+            | Entry::Phase { .. }
+            | Entry::Padding { .. } => 1,
+            Entry::Public | Entry::Challenge | Entry::PhaseChallenge { .. } => 0,
         }
     }
 }
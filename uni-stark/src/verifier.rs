@@ -2,9 +2,10 @@
 
 use alloc::vec;
 use alloc::vec::Vec;
+use core::mem;
 
-use itertools::Itertools;
-use p3_air::Air;
+use itertools::{Itertools, izip};
+use p3_air::{Air, BaseAir};
 use p3_challenger::{CanObserve, FieldChallenger};
 use p3_commit::{Pcs, PolynomialSpace};
 use p3_field::{BasedVectorSpace, Field, PrimeCharacteristicRing};
@@ -13,8 +14,13 @@ use p3_matrix::stack::VerticalPair;
 use p3_util::zip_eq::zip_eq;
 use tracing::instrument;
 
+use crate::padding::eval_padding;
+use crate::prover::window_points;
 use crate::symbolic_builder::{SymbolicAirBuilder, get_log_quotient_degree};
-use crate::{PcsError, Proof, StarkGenericConfig, Val, VerifierConstraintFolder};
+use crate::{
+    Domain, OpenedValues, PADDING_WIDTH, PcsError, PreprocessedVerifierKey, Proof,
+    StarkGenericConfig, Val, VerifierConstraintFolder, VerifierKey, eval_logup, setup_verifier_key,
+};
 
 #[instrument(skip_all)]
 pub fn verify<SC, A>(
@@ -27,18 +33,101 @@ where
     SC: StarkGenericConfig,
     A: Air<SymbolicAirBuilder<Val<SC>>> + for<'a> Air<VerifierConstraintFolder<'a, SC>>,
 {
+    verify_with_preprocessed(config, air, proof, public_values, None)
+}
+
+/// Verify a proof of an AIR which may make use of a preprocessed trace.
+///
+/// `preprocessed_vk` should be the verifier key returned by [`setup_preprocessed`](crate::setup_preprocessed)
+/// for this AIR, or `None` if the AIR has no preprocessed columns.
+#[instrument(skip_all)]
+pub fn verify_with_preprocessed<SC, A>(
+    config: &SC,
+    air: &A,
+    proof: &Proof<SC>,
+    public_values: &Vec<Val<SC>>,
+    preprocessed_vk: Option<&PreprocessedVerifierKey<SC>>,
+) -> Result<(), VerificationError<PcsError<SC>>>
+where
+    SC: StarkGenericConfig,
+    A: Air<SymbolicAirBuilder<Val<SC>>> + for<'a> Air<VerifierConstraintFolder<'a, SC>>,
+{
+    // An AIR cannot be evaluated over a window which is too small, or without the preprocessed
+    // columns it reads, so these must be checked before the fingerprint of the AIR is computed.
+    check_window_size(air)?;
+    if proof.opened_values.preprocessed_window.is_some() != preprocessed_vk.is_some() {
+        return Err(VerificationError::InvalidProofShape);
+    }
+    let vk = setup_verifier_key(config, air, preprocessed_vk.cloned(), public_values.len());
+    verify_with_key(config, air, &vk, proof, public_values)
+}
+
+/// Check that the window of `air` has at least two rows, as the verifier opens the traces at
+/// `zeta` and at least its successor.
+pub(crate) fn check_window_size<F, A: BaseAir<F>, PcsErr>(
+    air: &A,
+) -> Result<(), VerificationError<PcsErr>> {
+    if air.window_size() < 2 {
+        return Err(VerificationError::InvalidWindowSize {
+            window_size: air.window_size(),
+        });
+    }
+    Ok(())
+}
+
+/// Verify a proof of an AIR against a verifier key built by [`setup_verifier_key`].
+///
+/// This avoids recomputing the fingerprint of the AIR when verifying many proofs of the same AIR.
+#[instrument(skip_all)]
+pub fn verify_with_key<SC, A>(
+    config: &SC,
+    air: &A,
+    vk: &VerifierKey<SC>,
+    proof: &Proof<SC>,
+    public_values: &Vec<Val<SC>>,
+) -> Result<(), VerificationError<PcsError<SC>>>
+where
+    SC: StarkGenericConfig,
+    A: Air<SymbolicAirBuilder<Val<SC>>> + for<'a> Air<VerifierConstraintFolder<'a, SC>>,
+{
+    let preprocessed_vk = vk.preprocessed.as_ref();
     let Proof {
         commitments,
         opened_values,
         opening_proof,
         degree_bits,
+        real_height,
     } = proof;
 
     let pcs = config.pcs();
 
+    check_window_size(air)?;
+
+    // Check that the preprocessed openings are present if and only if we were given a preprocessed
+    // verifier key, and that the public values match the key. This must happen before the AIR is
+    // evaluated, as an AIR reading preprocessed columns cannot be evaluated without them.
+    if opened_values.preprocessed_window.is_some() != preprocessed_vk.is_some()
+        || public_values.len() != vk.num_public_values
+    {
+        return Err(VerificationError::InvalidProofShape);
+    }
+
+    // Single-instance proofs never contain a LogUp argument.
+    if commitments.permutation.is_some()
+        || opened_values.permutation_local.is_some()
+        || opened_values.permutation_next.is_some()
+    {
+        return Err(VerificationError::InvalidProofShape);
+    }
+
     let degree = 1 << degree_bits;
-    let log_quotient_degree =
-        get_log_quotient_degree::<Val<SC>, A>(air, 0, public_values.len(), config.is_zk());
+    let preprocessed_width = preprocessed_vk.map_or(0, |vk| vk.width);
+    let log_quotient_degree = get_log_quotient_degree::<Val<SC>, A>(
+        air,
+        preprocessed_width,
+        public_values.len(),
+        config.is_zk(),
+    );
     let quotient_degree = 1 << (log_quotient_degree + config.is_zk());
 
     let mut challenger = config.initialise_challenger();
@@ -64,8 +153,13 @@ where
     }
 
     let air_width = A::width(air);
-    let valid_shape = opened_values.trace_local.len() == air_width
-        && opened_values.trace_next.len() == air_width
+    let window_size = air.window_size();
+    let trace_phases = air.trace_phases();
+    // Each trace evaluated over the window of the AIR is opened at every row of the window.
+    let valid_window = |window: &Vec<Vec<SC::Challenge>>, width: usize| {
+        window.len() == window_size && window.iter().all(|row| row.len() == width)
+    };
+    let valid_shape = valid_window(&opened_values.trace_window, air_width)
         && opened_values.quotient_chunks.len() == quotient_degree
         && opened_values
             .quotient_chunks
@@ -76,6 +170,34 @@ where
             r_comm.len() == SC::Challenge::DIMENSION
         } else {
             true
+        }
+        // We've already checked that the preprocessed openings are present if and only if the key is.
+        && if let (Some(vk), Some(window)) = (preprocessed_vk, &opened_values.preprocessed_window) {
+            vk.degree_bits + config.is_zk() == *degree_bits && valid_window(window, vk.width)
+        } else {
+            true
+        }
+        // Each trace phase is committed to as `DIMENSION` base field columns per extension field column.
+        && commitments.phases.len() == trace_phases.len()
+        && opened_values.phases_window.len() == trace_phases.len()
+        && izip!(&trace_phases, &opened_values.phases_window).all(|(trace_phase, window)| {
+            valid_window(window, trace_phase.width * SC::Challenge::DIMENSION)
+        })
+        // The real height and the padding openings are present if and only if the AIR pads its
+        // trace, and the real height must fit in the trace.
+        && match (
+            real_height,
+            &opened_values.padding_local,
+            &opened_values.padding_next,
+        ) {
+            (Some(real_height), Some(local), Some(next)) => {
+                air.trace_padding().is_some()
+                    && (1..=degree >> config.is_zk()).contains(real_height)
+                    && local.len() == PADDING_WIDTH
+                    && next.len() == PADDING_WIDTH
+            }
+            (None, None, None) => air.trace_padding().is_none(),
+            _ => false,
         };
     if !valid_shape {
         return Err(VerificationError::InvalidProofShape);
@@ -84,14 +206,27 @@ where
     // Observe the instance.
     challenger.observe(Val::<SC>::from_usize(proof.degree_bits));
     challenger.observe(Val::<SC>::from_usize(proof.degree_bits - config.is_zk()));
-    // TODO: Might be best practice to include other instance data here in the transcript, like some
-    // encoding of the AIR. This protects against transcript collisions between distinct instances.
-    // Practically speaking though, the only related known attack is from failing to include public
-    // values. It's not clear if failing to include other instance data could enable a transcript
-    // collision, since most such changes would completely change the set of satisfying witnesses.
+    challenger.observe_slice(&vk.air_fingerprint);
 
+    if let Some(vk) = preprocessed_vk {
+        challenger.observe(vk.commitment.clone());
+    }
     challenger.observe(commitments.trace.clone());
     challenger.observe_slice(public_values);
+    if let Some(real_height) = real_height {
+        challenger.observe(Val::<SC>::from_usize(*real_height));
+    }
+
+    // Sample the challenges of each trace phase before observing its commitment.
+    let phase_challenges = izip!(&trace_phases, &commitments.phases)
+        .map(|(trace_phase, phase_commit)| {
+            let challenges = (0..trace_phase.num_challenges)
+                .map(|_| challenger.sample_algebra_element())
+                .collect_vec();
+            challenger.observe(phase_commit.clone());
+            challenges
+        })
+        .collect_vec();
 
     // Get the first Fiat Shamir challenge which will be used to combine all constraint polynomials
     // into a single polynomial.
@@ -110,7 +245,14 @@ where
     //
     // Soundness Error: dN/|EF| where `N` is the trace length and our constraint polynomial has degree `d`.
     let zeta = challenger.sample_algebra_element();
-    let zeta_next = init_trace_domain.next_point(zeta).unwrap();
+    let window_points = window_points(init_trace_domain, zeta, window_size);
+    let zeta_next = window_points[1];
+    // Pair each row of a window with the point it was opened at.
+    let window_openings = |window: &Vec<Vec<SC::Challenge>>| {
+        izip!(&window_points, window)
+            .map(|(&point, row)| (point, row.clone()))
+            .collect_vec()
+    };
 
     // We've already checked that commitments.random and opened_values.random are present if and only if ZK is enabled.
     let mut coms_to_verify = if let Some(random_commit) = &commitments.random {
@@ -125,17 +267,16 @@ where
     } else {
         vec![]
     };
+    // The padding columns, if any, are committed to in the same tree as the trace.
+    let mut trace_openings = vec![(trace_domain, window_openings(&opened_values.trace_window))];
+    if let (Some(local), Some(next)) = (&opened_values.padding_local, &opened_values.padding_next) {
+        trace_openings.push((
+            trace_domain,
+            vec![(zeta, local.clone()), (zeta_next, next.clone())],
+        ));
+    }
     coms_to_verify.extend(vec![
-        (
-            commitments.trace.clone(),
-            vec![(
-                trace_domain,
-                vec![
-                    (zeta, opened_values.trace_local.clone()),
-                    (zeta_next, opened_values.trace_next.clone()),
-                ],
-            )],
-        ),
+        (commitments.trace.clone(), trace_openings),
         (
             commitments.quotient_chunks.clone(),
             // Check the commitment on the randomized domains.
@@ -148,10 +289,63 @@ where
             .collect_vec(),
         ),
     ]);
+    // We've already checked that the preprocessed openings are present if and only if the key is.
+    if let (Some(vk), Some(window)) = (preprocessed_vk, &opened_values.preprocessed_window) {
+        coms_to_verify.push((
+            vk.commitment.clone(),
+            vec![(trace_domain, window_openings(window))],
+        ));
+    }
+    // The trace phases are opened last.
+    coms_to_verify.extend(
+        izip!(&commitments.phases, &opened_values.phases_window).map(|(phase_commit, window)| {
+            (
+                phase_commit.clone(),
+                vec![(trace_domain, window_openings(window))],
+            )
+        }),
+    );
 
     pcs.verify(coms_to_verify, opening_proof, &mut challenger)
         .map_err(VerificationError::InvalidOpeningArgument)?;
 
+    verify_constraints::<SC, A, PcsError<SC>>(
+        air,
+        opened_values,
+        public_values,
+        init_trace_domain,
+        &quotient_chunks_domains,
+        &[],
+        None,
+        &phase_challenges,
+        *real_height,
+        zeta,
+        alpha,
+    )
+}
+
+/// Check that the opened trace values satisfy the AIR constraints at `zeta`, i.e. that
+/// `folded_constraints(zeta) / Z_H(zeta)` matches the quotient recomposed from its opened chunks.
+///
+/// This assumes the shape of `opened_values` has already been checked.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn verify_constraints<SC, A, PcsErr>(
+    air: &A,
+    opened_values: &OpenedValues<SC::Challenge>,
+    public_values: &Vec<Val<SC>>,
+    init_trace_domain: Domain<SC>,
+    quotient_chunks_domains: &[Domain<SC>],
+    permutation_challenges: &[SC::Challenge],
+    cumulative_sum: Option<SC::Challenge>,
+    phase_challenges: &[Vec<SC::Challenge>],
+    real_height: Option<usize>,
+    zeta: SC::Challenge,
+    alpha: SC::Challenge,
+) -> Result<(), VerificationError<PcsErr>>
+where
+    SC: StarkGenericConfig,
+    A: for<'a> Air<VerifierConstraintFolder<'a, SC>>,
+{
     let zps = quotient_chunks_domains
         .iter()
         .enumerate()
@@ -188,21 +382,98 @@ where
 
     let sels = init_trace_domain.selectors_at_point(zeta);
 
-    let main = VerticalPair::new(
-        RowMajorMatrixView::new_row(&opened_values.trace_local),
-        RowMajorMatrixView::new_row(&opened_values.trace_next),
+    // The selector of the rows whose window of `size` rows does not wrap around is the product of
+    // `is_transition` over the first `size - 1` rows of the window.
+    let window_size = air.window_size();
+    let window_points = window_points(init_trace_domain, zeta, window_size);
+    let is_transition_windows = (3..=window_size)
+        .scan(sels.is_transition, |is_transition_window, size| {
+            *is_transition_window *= init_trace_domain
+                .selectors_at_point(window_points[size - 2])
+                .is_transition;
+            Some(*is_transition_window)
+        })
+        .collect_vec();
+
+    // The rows of a window are concatenated into a single matrix with one row per window row.
+    let flatten = |window: Option<&Vec<Vec<SC::Challenge>>>| {
+        window.map_or_else(Vec::new, |window| window.concat())
+    };
+    let main_values = flatten(Some(&opened_values.trace_window));
+    let main = RowMajorMatrixView::new(&main_values, A::width(air));
+    let preprocessed_values = flatten(opened_values.preprocessed_window.as_ref());
+    let preprocessed = RowMajorMatrixView::new(
+        &preprocessed_values,
+        preprocessed_values.len() / window_size,
+    );
+
+    // The permutation trace and the trace phases are committed to as flattened base field matrices,
+    // so we recombine the openings of their base field columns into extension field values.
+    let recombine = |values: Option<&Vec<SC::Challenge>>| {
+        values.map_or_else(Vec::new, |values| {
+            values
+                .chunks_exact(SC::Challenge::DIMENSION)
+                .map(|coeffs| {
+                    coeffs
+                        .iter()
+                        .enumerate()
+                        .map(|(e_i, &c)| SC::Challenge::ith_basis_element(e_i).unwrap() * c)
+                        .sum::<SC::Challenge>()
+                })
+                .collect_vec()
+        })
+    };
+    let phases_values = opened_values
+        .phases_window
+        .iter()
+        .map(|window| recombine(Some(&window.concat())))
+        .collect_vec();
+    let phases = phases_values
+        .iter()
+        .map(|values| RowMajorMatrixView::new(values, values.len() / window_size))
+        .collect();
+    let permutation_local = recombine(opened_values.permutation_local.as_ref());
+    let permutation_next = recombine(opened_values.permutation_next.as_ref());
+    let permutation = VerticalPair::new(
+        RowMajorMatrixView::new_row(&permutation_local),
+        RowMajorMatrixView::new_row(&permutation_next),
+    );
+    let padding = VerticalPair::new(
+        RowMajorMatrixView::new_row(opened_values.padding_local.as_deref().unwrap_or(&[])),
+        RowMajorMatrixView::new_row(opened_values.padding_next.as_deref().unwrap_or(&[])),
     );
 
     let mut folder = VerifierConstraintFolder {
         main,
+        preprocessed,
+        permutation,
+        permutation_challenges,
+        interactions: Vec::new(),
+        phases,
+        phase_challenges,
+        padding,
         public_values,
         is_first_row: sels.is_first_row,
         is_last_row: sels.is_last_row,
         is_transition: sels.is_transition,
+        is_transition_windows: &is_transition_windows,
         alpha,
         accumulator: SC::Challenge::ZERO,
     };
     air.eval(&mut folder);
+    let interactions = mem::take(&mut folder.interactions);
+    match (interactions.is_empty(), cumulative_sum) {
+        (true, None) => {}
+        (false, Some(cumulative_sum)) => eval_logup(&mut folder, &interactions, cumulative_sum),
+        _ => return Err(VerificationError::InvalidProofShape),
+    }
+    if let Some(real_height) = real_height {
+        eval_padding(
+            &mut folder,
+            &padding,
+            SC::Challenge::from_usize(real_height),
+        );
+    }
     let folded_constraints = folder.accumulator;
 
     // Finally, check that
@@ -224,4 +495,11 @@ pub enum VerificationError<PcsErr> {
     OodEvaluationMismatch,
     /// The FRI batch randomization does not correspond to the ZK setting.
     RandomizationError,
+    /// The LogUp cumulative sums do not add up to zero, i.e. some interaction is unbalanced.
+    UnbalancedInteractions,
+    /// The window of the AIR has fewer than two rows, see
+    /// [`BaseAir::window_size`](p3_air::BaseAir::window_size).
+    InvalidWindowSize {
+        window_size: usize,
+    },
 }
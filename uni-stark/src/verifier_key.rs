@@ -0,0 +1,148 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use itertools::Itertools;
+use p3_air::Air;
+use p3_challenger::{CanObserve, CanSample};
+use p3_field::{Field, PrimeCharacteristicRing};
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+
+use crate::flatten::{FlatNode, flatten};
+use crate::{
+    Entry, PreprocessedVerifierKey, StarkGenericConfig, SymbolicAirBuilder, Val,
+    get_symbolic_constraints,
+};
+
+/// The number of field elements in an AIR fingerprint.
+pub const AIR_FINGERPRINT_LEN: usize = 8;
+
+/// Everything the verifier needs to know about an AIR besides the AIR itself.
+///
+/// Both the prover and the verifier observe `air_fingerprint` before sampling `alpha`, so a proof
+/// for one AIR cannot be replayed as a proof for another. Computing the fingerprint requires
+/// evaluating the AIR symbolically, so it is worth building this key once with
+/// [`setup_verifier_key`] and reusing it across proofs.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct VerifierKey<SC: StarkGenericConfig> {
+    /// The fingerprint of the AIR, see [`air_fingerprint`].
+    pub air_fingerprint: [Val<SC>; AIR_FINGERPRINT_LEN],
+    /// The number of public values of the AIR.
+    pub num_public_values: usize,
+    /// The verifier key of the preprocessed trace, if the AIR has one.
+    pub preprocessed: Option<PreprocessedVerifierKey<SC>>,
+}
+
+impl<SC: StarkGenericConfig> Clone for VerifierKey<SC> {
+    fn clone(&self) -> Self {
+        Self {
+            air_fingerprint: self.air_fingerprint,
+            num_public_values: self.num_public_values,
+            preprocessed: self.preprocessed.clone(),
+        }
+    }
+}
+
+/// Build the verifier key of `air`.
+///
+/// `preprocessed` should be the verifier key returned by [`setup_preprocessed`](crate::setup_preprocessed)
+/// for this AIR, or `None` if the AIR has no preprocessed columns.
+pub fn setup_verifier_key<SC, A>(
+    config: &SC,
+    air: &A,
+    preprocessed: Option<PreprocessedVerifierKey<SC>>,
+    num_public_values: usize,
+) -> VerifierKey<SC>
+where
+    SC: StarkGenericConfig,
+    A: Air<SymbolicAirBuilder<Val<SC>>>,
+{
+    VerifierKey {
+        air_fingerprint: air_fingerprint(config, air, preprocessed.as_ref(), num_public_values),
+        num_public_values,
+        preprocessed,
+    }
+}
+
+/// Compute a deterministic fingerprint of `air`.
+///
+/// The fingerprint binds the width and window size of the AIR, its trace phases, whether it pads
+/// its trace, the number of public values, the preprocessed trace commitment and every symbolic
+/// constraint, including those of the LogUp argument and of the padding columns. The config has
+/// no hash function of its own, so these are absorbed by a freshly initialised challenger from
+/// which the fingerprint is then sampled.
+///
+/// Constraints are encoded as a DAG, in which subexpressions shared through an `Rc` are encoded
+/// only once. The fingerprint therefore depends on how the AIR builds its constraints and not
+/// just on the polynomials they describe, which is fine as long as `eval` is deterministic.
+#[instrument(name = "compute AIR fingerprint", skip_all)]
+pub fn air_fingerprint<SC, A>(
+    config: &SC,
+    air: &A,
+    preprocessed: Option<&PreprocessedVerifierKey<SC>>,
+    num_public_values: usize,
+) -> [Val<SC>; AIR_FINGERPRINT_LEN]
+where
+    SC: StarkGenericConfig,
+    A: Air<SymbolicAirBuilder<Val<SC>>>,
+{
+    let preprocessed_width = preprocessed.map_or(0, |prep| prep.width);
+    let constraints = get_symbolic_constraints(air, preprocessed_width, num_public_values);
+    let (nodes, roots) = flatten(&constraints);
+
+    let mut hasher = config.initialise_challenger();
+    let trace_phases = air.trace_phases();
+    hasher.observe_slice(&[
+        Val::<SC>::from_usize(air.width()),
+        Val::<SC>::from_usize(air.window_size()),
+        Val::<SC>::from_usize(preprocessed_width),
+        Val::<SC>::from_usize(num_public_values),
+        Val::<SC>::from_usize(trace_phases.len()),
+        Val::<SC>::from_bool(air.trace_padding().is_some()),
+    ]);
+    for phase in &trace_phases {
+        hasher.observe(Val::<SC>::from_usize(phase.width));
+        hasher.observe(Val::<SC>::from_usize(phase.num_challenges));
+    }
+    if let Some(prep) = preprocessed {
+        hasher.observe(Val::<SC>::from_usize(prep.degree_bits));
+        hasher.observe(prep.commitment.clone());
+    }
+    hasher.observe(Val::<SC>::from_usize(nodes.len()));
+    hasher.observe_slice(&nodes.iter().flat_map(encode_node).collect_vec());
+    hasher.observe(Val::<SC>::from_usize(roots.len()));
+    for root in roots {
+        hasher.observe(Val::<SC>::from_usize(root));
+    }
+    core::array::from_fn(|_| hasher.sample())
+}
+
+/// Encode a node of the flattened constraints as a tag followed by a fixed number of values
+/// determined by the tag, so that the encoding of the whole DAG is unambiguous.
+fn encode_node<F: Field>(node: &FlatNode<F>) -> Vec<F> {
+    match *node {
+        FlatNode::Variable(v) => {
+            let (kind, a, b) = match v.entry {
+                Entry::Preprocessed { offset } => (0, offset, 0),
+                Entry::Main { offset } => (1, offset, 0),
+                Entry::Permutation { offset } => (2, offset, 0),
+                Entry::Phase { phase, offset } => (3, phase, offset),
+                Entry::Public => (4, 0, 0),
+                Entry::Challenge => (5, 0, 0),
+                Entry::PhaseChallenge { phase } => (6, phase, 0),
+                Entry::Padding { offset } => (7, offset, 0),
+            };
+            [0, kind, a, b, v.index].map(F::from_usize).to_vec()
+        }
+        FlatNode::IsFirstRow => vec![F::from_u8(1)],
+        FlatNode::IsLastRow => vec![F::from_u8(2)],
+        FlatNode::IsTransition => vec![F::from_u8(3)],
+        FlatNode::IsTransitionWindow(size) => [9, size].map(F::from_usize).to_vec(),
+        FlatNode::Constant(c) => vec![F::from_u8(4), c],
+        FlatNode::Add(x, y) => [5, x, y].map(F::from_usize).to_vec(),
+        FlatNode::Sub(x, y) => [6, x, y].map(F::from_usize).to_vec(),
+        FlatNode::Neg(x) => [7, x].map(F::from_usize).to_vec(),
+        FlatNode::Mul(x, y) => [8, x, y].map(F::from_usize).to_vec(),
+    }
+}
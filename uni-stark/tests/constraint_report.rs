@@ -0,0 +1,111 @@
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir, BaseAirWithPublicValues};
+use p3_baby_bear::BabyBear;
+use p3_field::PrimeCharacteristicRing;
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_uni_stark::constraint_report;
+
+/// Each column increments by one from row to row, and ends at the public values.
+struct IncrementAir;
+
+impl<F> BaseAir<F> for IncrementAir {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<F> BaseAirWithPublicValues<F> for IncrementAir {
+    fn num_public_values(&self) -> usize {
+        2
+    }
+}
+
+impl<AB: AirBuilderWithPublicValues> Air<AB> for IncrementAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let (local, next) = (
+            main.row_slice(0).unwrap().to_vec(),
+            main.row_slice(1).unwrap().to_vec(),
+        );
+        let public_values = builder.public_values().to_vec();
+
+        builder.label_constraints("increment");
+        let mut transition = builder.when_transition();
+        for (a, b) in local.iter().zip(next) {
+            transition.assert_eq(b, a.clone() + AB::Expr::ONE);
+        }
+
+        builder.label_constraints("public values");
+        let mut last_row = builder.when_last_row();
+        for (a, pv) in local.into_iter().zip(public_values) {
+            last_row.assert_eq(a, pv);
+        }
+    }
+}
+
+/// The line of the first line of this file containing `pattern`.
+fn line_of(pattern: &str) -> u32 {
+    let line = include_str!("constraint_report.rs")
+        .lines()
+        .position(|line| line.contains(pattern))
+        .unwrap();
+    line as u32 + 1
+}
+
+#[test]
+fn test_constraint_report() {
+    // Row 2 is off by one in column 0, and the public value of column 1 is wrong.
+    let values = [1, 1, 2, 2, 4, 3, 4, 4].map(BabyBear::new).to_vec();
+    let main = RowMajorMatrix::new(values, 2);
+    let public_values = vec![BabyBear::new(4), BabyBear::new(5)];
+    let report = constraint_report(&IncrementAir, &main, None, &public_values);
+    assert!(!report.is_satisfied());
+
+    let failures = report
+        .failures
+        .iter()
+        .map(|failure| {
+            (
+                failure.row,
+                failure.constraint,
+                failure.value,
+                failure.label.unwrap().name,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        failures,
+        vec![
+            (1, 0, BabyBear::ONE, "increment"),
+            (2, 0, -BabyBear::ONE, "increment"),
+            (3, 3, -BabyBear::ONE, "public values"),
+        ]
+    );
+
+    // Labels and constraints point at the lines of the AIR setting and asserting them, rather than
+    // at the builder.
+    let increment = &report.failures[0];
+    let label = increment.label.unwrap().location;
+    assert_eq!(label.file(), file!());
+    assert_eq!(label.line(), line_of("label_constraints(\"increment\")"));
+    assert_eq!(increment.location.file(), file!());
+    assert_eq!(increment.location.line(), line_of("transition.assert_eq"));
+    let public_value = &report.failures[2];
+    assert_eq!(
+        public_value.label.unwrap().location.line(),
+        line_of("label_constraints(\"public values\")")
+    );
+    assert_eq!(public_value.location.line(), line_of("last_row.assert_eq"));
+
+    let printed = report.to_string();
+    assert!(printed.starts_with("3 constraint failures in 4 rows"));
+    assert!(printed.contains("row 3: constraint 3 [public values ("));
+    assert_eq!(printed.lines().count(), 4);
+
+    // A satisfying trace has an empty report.
+    let values = [1, 1, 2, 2, 3, 3, 4, 4].map(BabyBear::new).to_vec();
+    let main = RowMajorMatrix::new(values, 2);
+    assert!(
+        constraint_report(&IncrementAir, &main, None, &vec![BabyBear::new(4); 2]).is_satisfied()
+    );
+}
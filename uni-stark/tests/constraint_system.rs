@@ -0,0 +1,229 @@
+use p3_air::{
+    Air, AirBuilder, AirBuilderWithPublicValues, BaseAir, BaseAirWithPublicValues, PairBuilder,
+};
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::DuplexChallenger;
+use p3_commit::ExtensionMmcs;
+use p3_dft::Radix2DitParallel;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{Field, PrimeCharacteristicRing};
+use p3_fri::{TwoAdicFriPcs, create_test_fri_params};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_merkle_tree::MerkleTreeMmcs;
+use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+use p3_uni_stark::{
+    CONSTRAINT_SYSTEM_VERSION, ConstraintNode, ConstraintSystem, ConstraintSystemError, DynamicAir,
+    StarkConfig, get_symbolic_constraints, prove_with_preprocessed, setup_preprocessed,
+    verify_with_preprocessed,
+};
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+
+const LOG_HEIGHT: usize = 4;
+
+/// Sums the squares of a preprocessed column `fixed[i] = i`:
+/// - `acc[0] = 0`
+/// - `acc[i + 1] = acc[i] + fixed[i + 1]^2`
+/// - `acc[n - 1]` is the public value.
+struct SumOfSquaresAir;
+
+impl<F: Field> BaseAir<F> for SumOfSquaresAir {
+    fn width(&self) -> usize {
+        1
+    }
+
+    fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
+        Some(RowMajorMatrix::new_col(
+            (0..1 << LOG_HEIGHT).map(F::from_usize).collect(),
+        ))
+    }
+}
+
+impl<F: Field> BaseAirWithPublicValues<F> for SumOfSquaresAir {
+    fn num_public_values(&self) -> usize {
+        1
+    }
+}
+
+impl<AB: PairBuilder<F: Field> + AirBuilderWithPublicValues> Air<AB> for SumOfSquaresAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let preprocessed = builder.preprocessed();
+        let sum = builder.public_values()[0];
+
+        let local = main.row_slice(0).expect("Matrix is empty?");
+        let next = main.row_slice(1).expect("Matrix only has 1 row?");
+        let fixed_next = preprocessed.row_slice(1).expect("Matrix only has 1 row?");
+
+        builder.when_first_row().assert_zero(local[0].clone());
+        builder.when_transition().assert_eq(
+            next[0].clone(),
+            local[0].clone() + fixed_next[0].clone().into().square(),
+        );
+        builder.when_last_row().assert_eq(local[0].clone(), sum);
+    }
+}
+
+fn generate_trace<F: Field>() -> RowMajorMatrix<F> {
+    let mut acc = F::ZERO;
+    let values = (0..1 << LOG_HEIGHT)
+        .map(|i| {
+            acc += F::from_usize(i * i);
+            acc
+        })
+        .collect();
+    RowMajorMatrix::new_col(values)
+}
+
+type Val = BabyBear;
+type Perm = Poseidon2BabyBear<16>;
+type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+type ValMmcs =
+    MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+type Challenge = BinomialExtensionField<Val, 4>;
+type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+type Dft = Radix2DitParallel<Val>;
+type Pcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+
+fn make_config() -> MyConfig {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let perm = Perm::new_from_rng_128(&mut rng);
+    let hash = MyHash::new(perm.clone());
+    let compress = MyCompress::new(perm.clone());
+    let val_mmcs = ValMmcs::new(hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let fri_params = create_test_fri_params(challenge_mmcs, 2);
+    let pcs = Pcs::new(Dft::default(), val_mmcs, fri_params);
+    MyConfig::new(pcs, Challenger::new(perm))
+}
+
+#[test]
+fn test_constraint_system_round_trip() {
+    let system = ConstraintSystem::<Val>::from_air(&SumOfSquaresAir);
+    assert_eq!(system.width, 1);
+    assert_eq!(system.num_public_values, 1);
+    assert!(system.selectors.is_first_row && system.selectors.is_last_row);
+    assert_eq!(system.constraints.len(), 3);
+    system.validate().expect("exported constraints are valid");
+    // The rebuilt constraints have the same degrees, and exporting them again gives back the same
+    // system.
+    let degrees = get_symbolic_constraints::<Val, _>(&SumOfSquaresAir, 1, 1)
+        .iter()
+        .map(|c| c.degree_multiple())
+        .collect::<Vec<_>>();
+    let rebuilt_degrees = system
+        .symbolic_constraints()
+        .iter()
+        .map(|c| c.degree_multiple())
+        .collect::<Vec<_>>();
+    assert_eq!(rebuilt_degrees, degrees);
+    let air = DynamicAir::try_from(system.clone()).expect("exported constraints are valid");
+    assert_eq!(ConstraintSystem::from_air(&air), system);
+
+    let json = serde_json::to_string(&system).expect("unable to serialize to JSON");
+    let from_json: ConstraintSystem<Val> =
+        serde_json::from_str(&json).expect("unable to deserialize from JSON");
+    assert_eq!(from_json, system);
+
+    let bytes = postcard::to_allocvec(&system).expect("unable to serialize to bytes");
+    let from_bytes: ConstraintSystem<Val> =
+        postcard::from_bytes(&bytes).expect("unable to deserialize from bytes");
+    assert_eq!(from_bytes, system);
+}
+
+#[test]
+fn test_constraint_system_prove_verify() {
+    let config = make_config();
+    let json = serde_json::to_string(&ConstraintSystem::<Val>::from_air(&SumOfSquaresAir))
+        .expect("unable to serialize to JSON");
+    let system: ConstraintSystem<Val> =
+        serde_json::from_str(&json).expect("unable to deserialize from JSON");
+    let air = DynamicAir::try_from(system).expect("exported constraints are valid");
+
+    let trace = generate_trace::<Val>();
+    let public_values = vec![trace.values[trace.height() - 1]];
+    let (prover_data, vk) =
+        setup_preprocessed(&config, &air, LOG_HEIGHT).expect("AIR has a preprocessed trace");
+    let proof = prove_with_preprocessed(&config, &air, trace, &public_values, Some(&prover_data))
+        .expect("proving failed");
+    verify_with_preprocessed(&config, &air, &proof, &public_values, Some(&vk))
+        .expect("verification failed");
+
+    // The loaded AIR has the same constraints as the original one.
+    verify_with_preprocessed(&config, &SumOfSquaresAir, &proof, &public_values, Some(&vk))
+        .expect("verification against the original AIR failed");
+
+    let wrong_public_values = vec![Val::ONE];
+    assert!(
+        verify_with_preprocessed(&config, &air, &proof, &wrong_public_values, Some(&vk)).is_err()
+    );
+}
+
+#[test]
+fn test_constraint_system_invalid() {
+    let system = ConstraintSystem::<Val>::from_air(&SumOfSquaresAir);
+    let invalid = |f: fn(&mut ConstraintSystem<Val>)| {
+        let mut system = system.clone();
+        f(&mut system);
+        system.validate().unwrap_err()
+    };
+
+    assert_eq!(
+        invalid(|s| s.version = CONSTRAINT_SYSTEM_VERSION + 1),
+        ConstraintSystemError::UnsupportedVersion {
+            version: CONSTRAINT_SYSTEM_VERSION + 1
+        }
+    );
+    assert_eq!(
+        invalid(|s| s.nodes.push(ConstraintNode::Main {
+            offset: 0,
+            index: 1
+        })),
+        ConstraintSystemError::InvalidVariable {
+            node: system.nodes.len()
+        }
+    );
+    assert_eq!(
+        invalid(|s| s.nodes.push(ConstraintNode::Main {
+            offset: 2,
+            index: 0
+        })),
+        ConstraintSystemError::InvalidVariable {
+            node: system.nodes.len()
+        }
+    );
+    assert_eq!(
+        invalid(|s| s.nodes.push(ConstraintNode::Public(1))),
+        ConstraintSystemError::InvalidVariable {
+            node: system.nodes.len()
+        }
+    );
+    assert_eq!(
+        invalid(|s| s.nodes.push(ConstraintNode::Neg(s.nodes.len()))),
+        ConstraintSystemError::InvalidOperand {
+            node: system.nodes.len()
+        }
+    );
+    assert_eq!(
+        invalid(|s| s.selectors.is_transition = false),
+        ConstraintSystemError::UndeclaredSelector {
+            node: system
+                .nodes
+                .iter()
+                .position(|node| *node == ConstraintNode::IsTransition)
+                .unwrap()
+        }
+    );
+    assert_eq!(
+        invalid(|s| s.constraints.push(s.nodes.len())),
+        ConstraintSystemError::InvalidConstraint { constraint: 3 }
+    );
+
+    let mut unsupported = system.clone();
+    unsupported.version = CONSTRAINT_SYSTEM_VERSION + 1;
+    assert!(DynamicAir::try_from(unsupported).is_err());
+}
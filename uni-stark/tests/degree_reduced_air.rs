@@ -0,0 +1,98 @@
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir, BaseAirWithPublicValues};
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::DuplexChallenger;
+use p3_commit::ExtensionMmcs;
+use p3_dft::Radix2DitParallel;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{Field, PrimeCharacteristicRing};
+use p3_fri::{TwoAdicFriPcs, create_test_fri_params};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_merkle_tree::MerkleTreeMmcs;
+use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+use p3_uni_stark::{DegreeReducedAir, StarkConfig, get_log_quotient_degree, prove, verify};
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+
+/// Checks `x_{i+1} = x_i^9 + y_i` on transitions and `x_0 = 1`, where `y` is a constant column
+/// given by the first public value.
+struct PowerAir;
+
+impl<F> BaseAir<F> for PowerAir {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<F> BaseAirWithPublicValues<F> for PowerAir {
+    fn num_public_values(&self) -> usize {
+        1
+    }
+}
+
+impl<AB: AirBuilderWithPublicValues> Air<AB> for PowerAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0).expect("Matrix is empty?");
+        let next = main.row_slice(1).expect("Matrix only has 1 row?");
+        let y: AB::Expr = builder.public_values()[0].into();
+        let x: AB::Expr = local[0].clone().into();
+        builder.when_first_row().assert_one(x.clone());
+        builder.assert_eq(local[1].clone(), y);
+        builder
+            .when_transition()
+            .assert_eq(next[0].clone(), x.exp_const_u64::<9>() + local[1].clone());
+    }
+}
+
+fn generate_trace<F: Field>(height: usize, y: F) -> RowMajorMatrix<F> {
+    let mut x = F::ONE;
+    let mut values = Vec::with_capacity(2 * height);
+    for _ in 0..height {
+        values.extend([x, y]);
+        x = x.exp_const_u64::<9>() + y;
+    }
+    RowMajorMatrix::new(values, 2)
+}
+
+type Val = BabyBear;
+type Perm = Poseidon2BabyBear<16>;
+type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+type ValMmcs =
+    MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+type Challenge = BinomialExtensionField<Val, 4>;
+type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+type Dft = Radix2DitParallel<Val>;
+type Pcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+
+#[test]
+fn test_prove_degree_reduced() {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let perm = Perm::new_from_rng_128(&mut rng);
+    let hash = MyHash::new(perm.clone());
+    let compress = MyCompress::new(perm.clone());
+    let val_mmcs = ValMmcs::new(hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let fri_params = create_test_fri_params(challenge_mmcs, 2);
+    let pcs = Pcs::new(Dft::default(), val_mmcs, fri_params);
+    let config = MyConfig::new(pcs, Challenger::new(perm));
+
+    let y = Val::new(7);
+    let public_values = vec![y];
+    let trace = generate_trace(1 << 6, y);
+    assert_eq!(get_log_quotient_degree::<Val, _>(&PowerAir, 0, 1, 0), 3);
+
+    for (max_degree, log_quotient_degree) in [(2, 0), (3, 1), (5, 2)] {
+        let air = DegreeReducedAir::new(&PowerAir, max_degree);
+        assert_eq!(
+            get_log_quotient_degree::<Val, _>(&air, 0, 1, 0),
+            log_quotient_degree
+        );
+        let trace = air.generate_trace(&trace, &public_values);
+        let proof = prove(&config, &air, trace, &public_values).expect("proving failed");
+        verify(&config, &air, &proof, &public_values).expect("verification failed");
+    }
+}
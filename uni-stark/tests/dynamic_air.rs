@@ -0,0 +1,129 @@
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::DuplexChallenger;
+use p3_commit::ExtensionMmcs;
+use p3_dft::Radix2DitParallel;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{Field, PrimeCharacteristicRing};
+use p3_fri::{TwoAdicFriPcs, create_test_fri_params};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_merkle_tree::MerkleTreeMmcs;
+use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+use p3_uni_stark::{
+    ConstraintSystem, ConstraintSystemError, DynamicAir, Entry, StarkConfig, SymbolicExpression,
+    SymbolicVariable, prove_with_preprocessed, setup_preprocessed, verify_with_preprocessed,
+};
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+
+const LOG_HEIGHT: usize = 3;
+
+type Val = BabyBear;
+type Perm = Poseidon2BabyBear<16>;
+type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+type ValMmcs =
+    MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+type Challenge = BinomialExtensionField<Val, 4>;
+type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+type Dft = Radix2DitParallel<Val>;
+type Pcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+
+fn variable(entry: Entry, index: usize) -> SymbolicExpression<Val> {
+    SymbolicVariable::new(entry, index).into()
+}
+
+/// A Fibonacci AIR over the columns `[left, right, step]`, built at runtime:
+/// - the first row starts from the public values `a` and `b`,
+/// - each transition computes the next pair of Fibonacci numbers,
+/// - the last `right` is the public value `x`,
+/// - `step` matches the preprocessed column `[0, 1, 2, ...]`.
+fn fibonacci_constraints() -> Vec<SymbolicExpression<Val>> {
+    let local = |index| variable(Entry::Main { offset: 0 }, index);
+    let next = |index| variable(Entry::Main { offset: 1 }, index);
+    let public = |index| variable(Entry::Public, index);
+    let preprocessed_next = variable(Entry::Preprocessed { offset: 1 }, 0);
+    vec![
+        SymbolicExpression::IsFirstRow * (local(0) - public(0)),
+        SymbolicExpression::IsFirstRow * (local(1) - public(1)),
+        SymbolicExpression::IsTransition * (next(0) - local(1)),
+        SymbolicExpression::IsTransition * (next(1) - local(0) - local(1)),
+        SymbolicExpression::IsLastRow * (local(1) - public(2)),
+        next(2) - preprocessed_next,
+    ]
+}
+
+fn fibonacci_air() -> DynamicAir<Val> {
+    let steps = RowMajorMatrix::new_col((0..1 << LOG_HEIGHT).map(Val::from_usize).collect());
+    DynamicAir::new(3, Some(steps), 3, &fibonacci_constraints())
+        .expect("the constraints are supported")
+}
+
+fn generate_trace() -> (RowMajorMatrix<Val>, Vec<Val>) {
+    let (mut left, mut right) = (Val::ZERO, Val::ONE);
+    let mut values = Vec::new();
+    for i in 0..1 << LOG_HEIGHT {
+        values.extend([left, right, Val::from_usize(i)]);
+        (left, right) = (right, left + right);
+    }
+    let x = values[values.len() - 2];
+    (RowMajorMatrix::new(values, 3), vec![Val::ZERO, Val::ONE, x])
+}
+
+fn make_config() -> MyConfig {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let perm = Perm::new_from_rng_128(&mut rng);
+    let hash = MyHash::new(perm.clone());
+    let compress = MyCompress::new(perm.clone());
+    let val_mmcs = ValMmcs::new(hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let fri_params = create_test_fri_params(challenge_mmcs, 2);
+    let pcs = Pcs::new(Dft::default(), val_mmcs, fri_params);
+    MyConfig::new(pcs, Challenger::new(perm))
+}
+
+#[test]
+fn test_dynamic_air_prove_verify() {
+    let config = make_config();
+    let air = fibonacci_air();
+    let (trace, public_values) = generate_trace();
+
+    let (prover_data, vk) =
+        setup_preprocessed(&config, &air, LOG_HEIGHT).expect("AIR has a preprocessed trace");
+    let proof = prove_with_preprocessed(&config, &air, trace, &public_values, Some(&prover_data))
+        .expect("proving failed");
+    verify_with_preprocessed(&config, &air, &proof, &public_values, Some(&vk))
+        .expect("verification failed");
+
+    let mut wrong_public_values = public_values;
+    wrong_public_values[2] += Val::ONE;
+    assert!(
+        verify_with_preprocessed(&config, &air, &proof, &wrong_public_values, Some(&vk)).is_err()
+    );
+}
+
+#[test]
+fn test_dynamic_air_round_trip() {
+    let air = fibonacci_air();
+    let json = serde_json::to_string(air.constraint_system()).expect("unable to serialize");
+    let system: ConstraintSystem<Val> = serde_json::from_str(&json).expect("unable to deserialize");
+    let loaded = DynamicAir::try_from(system).expect("exported constraints are valid");
+    assert_eq!(loaded.constraint_system(), air.constraint_system());
+}
+
+#[test]
+fn test_dynamic_air_invalid() {
+    let mut constraints = fibonacci_constraints();
+    constraints.push(variable(Entry::Main { offset: 0 }, 3));
+    assert!(matches!(
+        DynamicAir::new(3, None, 3, &constraints[..]),
+        Err(ConstraintSystemError::InvalidVariable { .. })
+    ));
+
+    let permutation = variable(Entry::Permutation { offset: 0 }, 0);
+    assert_eq!(
+        DynamicAir::new(3, None, 3, &[permutation]).unwrap_err(),
+        ConstraintSystemError::UnsupportedVariable { constraint: 0 }
+    );
+}
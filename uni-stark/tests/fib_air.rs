@@ -1,6 +1,6 @@
 use core::borrow::Borrow;
 
-use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir};
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir, BaseAirWithPublicValues};
 use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
 use p3_challenger::{DuplexChallenger, HashChallenger, SerializingChallenger32};
 use p3_commit::ExtensionMmcs;
@@ -15,7 +15,7 @@ use p3_merkle_tree::{MerkleTreeHidingMmcs, MerkleTreeMmcs};
 use p3_symmetric::{
     CompressionFunctionFromHasher, PaddingFreeSponge, SerializingHasher, TruncatedPermutation,
 };
-use p3_uni_stark::{StarkConfig, prove, verify};
+use p3_uni_stark::{ProvingError, StarkConfig, prove, setup_verifier_key, verify, verify_with_key};
 use rand::SeedableRng;
 use rand::rngs::SmallRng;
 
@@ -28,6 +28,12 @@ impl<F> BaseAir<F> for FibonacciAir {
     }
 }
 
+impl<F> BaseAirWithPublicValues<F> for FibonacciAir {
+    fn num_public_values(&self) -> usize {
+        3
+    }
+}
+
 impl<AB: AirBuilderWithPublicValues> Air<AB> for FibonacciAir {
     fn eval(&self, builder: &mut AB) {
         let main = builder.main();
@@ -136,7 +142,7 @@ fn test_public_value_impl(n: usize, x: u64, log_final_poly_len: usize) {
     let config = MyConfig::new(pcs, challenger);
     let pis = vec![BabyBear::ZERO, BabyBear::ONE, BabyBear::from_u64(x)];
 
-    let proof = prove(&config, &FibonacciAir {}, trace, &pis);
+    let proof = prove(&config, &FibonacciAir {}, trace, &pis).expect("proving failed");
     verify(&config, &FibonacciAir {}, &proof, &pis).expect("verification failed");
 }
 
@@ -184,7 +190,7 @@ fn test_zk() {
     let challenger = Challenger::from_hasher(vec![], byte_hash);
     let config = MyHidingConfig::new(pcs, challenger);
     let pis = vec![BabyBear::ZERO, BabyBear::ONE, BabyBear::from_u64(x)];
-    let proof = prove(&config, &FibonacciAir {}, trace, &pis);
+    let proof = prove(&config, &FibonacciAir {}, trace, &pis).expect("proving failed");
     verify(&config, &FibonacciAir {}, &proof, &pis).expect("verification failed");
 }
 
@@ -199,10 +205,7 @@ fn test_public_value() {
     test_public_value_impl(1 << 3, 21, 2);
 }
 
-#[cfg(debug_assertions)]
-#[test]
-#[should_panic(expected = "assertion `left == right` failed: constraints had nonzero value")]
-fn test_incorrect_public_value() {
+fn make_config() -> MyConfig {
     let mut rng = SmallRng::seed_from_u64(1);
     let perm = Perm::new_from_rng_128(&mut rng);
     let hash = MyHash::new(perm.clone());
@@ -211,14 +214,84 @@ fn test_incorrect_public_value() {
     let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
     let dft = Dft::default();
     let fri_params = create_test_fri_params(challenge_mmcs, 1);
-    let trace = generate_trace_rows::<Val>(0, 1, 1 << 3);
     let pcs = Pcs::new(dft, val_mmcs, fri_params);
     let challenger = Challenger::new(perm);
-    let config = MyConfig::new(pcs, challenger);
+    MyConfig::new(pcs, challenger)
+}
+
+#[test]
+fn test_verifier_key() {
+    let config = make_config();
+    let trace = generate_trace_rows::<Val>(0, 1, 1 << 3);
+    let pis = vec![BabyBear::ZERO, BabyBear::ONE, BabyBear::from_u64(21)];
+    let proof = prove(&config, &FibonacciAir {}, trace, &pis).expect("proving failed");
+
+    let vk = setup_verifier_key(&config, &FibonacciAir {}, None, pis.len());
+    assert_eq!(
+        vk.air_fingerprint,
+        setup_verifier_key(&config, &FibonacciAir {}, None, pis.len()).air_fingerprint,
+        "the AIR fingerprint should be deterministic"
+    );
+    verify_with_key(&config, &FibonacciAir {}, &vk, &proof, &pis).expect("verification failed");
+
+    // The proof is bound to the fingerprint of the AIR it was generated for.
+    let mut other_vk = vk.clone();
+    other_vk.air_fingerprint[0] += BabyBear::ONE;
+    assert!(verify_with_key(&config, &FibonacciAir {}, &other_vk, &proof, &pis).is_err());
+}
+
+#[test]
+fn test_invalid_instance() {
+    let config = make_config();
+    let pis = vec![BabyBear::ZERO, BabyBear::ONE, BabyBear::from_u64(21)];
+
+    let mut trace = generate_trace_rows::<Val>(0, 1, 1 << 3);
+    trace.values.truncate(6 * NUM_FIBONACCI_COLS);
+    assert_eq!(
+        prove(&config, &FibonacciAir {}, trace, &pis).err(),
+        Some(ProvingError::InvalidTraceHeight { height: 6 })
+    );
+
+    let trace = generate_trace_rows::<Val>(0, 1, 1 << 3);
+    let trace = RowMajorMatrix::new(trace.values, 2 * NUM_FIBONACCI_COLS);
+    assert_eq!(
+        prove(&config, &FibonacciAir {}, trace, &pis).err(),
+        Some(ProvingError::TraceWidthMismatch {
+            expected: NUM_FIBONACCI_COLS,
+            actual: 2 * NUM_FIBONACCI_COLS
+        })
+    );
+
+    let trace = generate_trace_rows::<Val>(0, 1, 1 << 3);
+    assert_eq!(
+        prove(&config, &FibonacciAir {}, trace, &pis[..2].to_vec()).err(),
+        Some(ProvingError::PublicValuesCountMismatch {
+            expected: 3,
+            actual: 2
+        })
+    );
+}
+
+#[test]
+fn test_incorrect_public_value() {
+    let config = make_config();
+    let trace = generate_trace_rows::<Val>(0, 1, 1 << 3);
     let pis = vec![
         BabyBear::ZERO,
         BabyBear::ONE,
         BabyBear::from_u32(123_123), // incorrect result
     ];
-    prove(&config, &FibonacciAir {}, trace, &pis);
+    // The last row constraint is the fifth constraint of the AIR.
+    assert_eq!(
+        prove(&config, &FibonacciAir {}, trace, &pis).err(),
+        Some(ProvingError::UnsatisfiedConstraint {
+            row: 7,
+            constraint: 4
+        })
+    );
+    // Without the check, the prover produces a proof which the verifier rejects.
+    let config = make_config().without_constraint_check();
+    let trace = generate_trace_rows::<Val>(0, 1, 1 << 3);
+    let proof = prove(&config, &FibonacciAir {}, trace, &pis).expect("proving failed");
+    assert!(verify(&config, &FibonacciAir {}, &proof, &pis).is_err());
 }
@@ -0,0 +1,212 @@
+use p3_air::{Air, AirBuilder, BaseAir, BaseAirWithPublicValues, InteractionBuilder};
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::DuplexChallenger;
+use p3_commit::ExtensionMmcs;
+use p3_dft::Radix2DitParallel;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{Field, PrimeCharacteristicRing};
+use p3_fri::{TwoAdicFriPcs, create_test_fri_params};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_merkle_tree::MerkleTreeMmcs;
+use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+use p3_uni_stark::{
+    MultiProof, StarkConfig, StarkInstance, VerificationError, prove_multi, verify_multi,
+};
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+
+/// The bus on which `(x, x^2)` pairs are looked up.
+const SQUARE_BUS: usize = 0;
+
+/// A lookup table of squares together with some unrelated arithmetic.
+enum ChipAir {
+    /// A table of `(i, i^2, multiplicity)` for `i` in `0..height`, receiving each pair
+    /// `multiplicity` times on `SQUARE_BUS`.
+    SquareTable,
+    /// Looks up `(x, x^2)` on `SQUARE_BUS`, but only on rows where the selector `s` is set.
+    SquareUser,
+    /// Checks `x * y = z` on every row, without any interactions.
+    Mul,
+}
+
+impl<F> BaseAir<F> for ChipAir {
+    fn width(&self) -> usize {
+        match self {
+            Self::SquareTable | Self::Mul => 3,
+            Self::SquareUser => 2,
+        }
+    }
+}
+
+impl<F> BaseAirWithPublicValues<F> for ChipAir {}
+
+impl<AB: InteractionBuilder> Air<AB> for ChipAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0).expect("Matrix is empty?");
+        let next = main.row_slice(1).expect("Matrix only has 1 row?");
+        match self {
+            Self::SquareTable => {
+                let (i, square, multiplicity) =
+                    (local[0].clone(), local[1].clone(), local[2].clone());
+                builder.when_first_row().assert_zero(i.clone());
+                builder
+                    .when_transition()
+                    .assert_eq(next[0].clone(), i.clone() + AB::Expr::ONE);
+                builder.assert_eq(square.clone(), i.clone() * i.clone());
+                builder.receive(SQUARE_BUS, [i, square], multiplicity);
+            }
+            Self::SquareUser => {
+                let (s, x) = (local[0].clone(), local[1].clone());
+                builder.assert_bool(s.clone());
+                // The looked up square is a degree 2 expression.
+                builder
+                    .when(s)
+                    .send(SQUARE_BUS, [x.clone().into(), x.clone() * x], AB::Expr::ONE);
+            }
+            Self::Mul => {
+                let (x, y, z) = (local[0].clone(), local[1].clone(), local[2].clone());
+                builder.assert_eq(x * y, z);
+            }
+        }
+    }
+}
+
+fn square_user_trace<F: Field>(values: &[(bool, u64)]) -> RowMajorMatrix<F> {
+    let values = values
+        .iter()
+        .flat_map(|&(s, x)| [F::from_bool(s), F::from_u64(x)])
+        .collect();
+    RowMajorMatrix::new(values, 2)
+}
+
+fn square_table_trace<F: Field>(log_height: usize, users: &[&[(bool, u64)]]) -> RowMajorMatrix<F> {
+    let mut multiplicities = vec![0; 1 << log_height];
+    for &(s, x) in users.iter().copied().flatten() {
+        if s {
+            multiplicities[x as usize] += 1;
+        }
+    }
+    let values = multiplicities
+        .into_iter()
+        .enumerate()
+        .flat_map(|(i, m)| [F::from_usize(i), F::from_usize(i * i), F::from_usize(m)])
+        .collect();
+    RowMajorMatrix::new(values, 3)
+}
+
+fn mul_trace<F: Field>(log_height: usize) -> RowMajorMatrix<F> {
+    let values = (0..1 << log_height)
+        .flat_map(|i| {
+            let x = F::from_usize(i);
+            let y = F::from_usize(i + 3);
+            [x, y, x * y]
+        })
+        .collect();
+    RowMajorMatrix::new(values, 3)
+}
+
+type Val = BabyBear;
+type Perm = Poseidon2BabyBear<16>;
+type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+type ValMmcs =
+    MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+type Challenge = BinomialExtensionField<Val, 4>;
+type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+type Dft = Radix2DitParallel<Val>;
+type Pcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+
+fn make_config() -> MyConfig {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let perm = Perm::new_from_rng_128(&mut rng);
+    let hash = MyHash::new(perm.clone());
+    let compress = MyCompress::new(perm.clone());
+    let val_mmcs = ValMmcs::new(hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let fri_params = create_test_fri_params(challenge_mmcs, 1);
+    let pcs = Pcs::new(Dft::default(), val_mmcs, fri_params);
+    MyConfig::new(pcs, Challenger::new(perm))
+}
+
+const AIRS: [&ChipAir; 4] = [
+    &ChipAir::Mul,
+    &ChipAir::SquareUser,
+    &ChipAir::SquareTable,
+    &ChipAir::SquareUser,
+];
+
+/// Prove a table of squares, two users of that table and an unrelated AIR.
+///
+/// The table is built from `table_users`, so passing different users than `users` unbalances the bus.
+fn prove_chips(
+    config: &MyConfig,
+    users: [&[(bool, u64)]; 2],
+    table_users: [&[(bool, u64)]; 2],
+) -> MultiProof<MyConfig> {
+    let instances = vec![
+        StarkInstance {
+            air: AIRS[0],
+            trace: mul_trace::<Val>(3),
+            public_values: vec![],
+        },
+        StarkInstance {
+            air: AIRS[1],
+            trace: square_user_trace::<Val>(users[0]),
+            public_values: vec![],
+        },
+        StarkInstance {
+            air: AIRS[2],
+            trace: square_table_trace::<Val>(4, &table_users),
+            public_values: vec![],
+        },
+        StarkInstance {
+            air: AIRS[3],
+            trace: square_user_trace::<Val>(users[1]),
+            public_values: vec![],
+        },
+    ];
+    prove_multi(config, instances).expect("proving failed")
+}
+
+const USERS_A: [(bool, u64); 4] = [(true, 3), (true, 15), (false, 100), (true, 3)];
+const USERS_B: [(bool, u64); 8] = [
+    (true, 0),
+    (true, 7),
+    (true, 7),
+    (false, 1000),
+    (true, 12),
+    (true, 1),
+    (false, 2),
+    (true, 15),
+];
+
+#[test]
+fn test_lookup_prove_verify() {
+    let config = make_config();
+    let proof = prove_chips(&config, [&USERS_A, &USERS_B], [&USERS_A, &USERS_B]);
+
+    let serialized_proof = postcard::to_allocvec(&proof).expect("unable to serialize proof");
+    let proof: MultiProof<MyConfig> =
+        postcard::from_bytes(&serialized_proof).expect("unable to deserialize proof");
+
+    verify_multi(&config, &AIRS, &proof, &[vec![], vec![], vec![], vec![]])
+        .expect("verification failed");
+}
+
+#[test]
+fn test_lookup_unbalanced() {
+    let config = make_config();
+    // The second user looks up a value which the table does not account for.
+    let mut users_b = USERS_B;
+    users_b[6] = (true, 2);
+    let proof = prove_chips(&config, [&USERS_A, &users_b], [&USERS_A, &USERS_B]);
+
+    assert!(matches!(
+        verify_multi(&config, &AIRS, &proof, &[vec![], vec![], vec![], vec![]]),
+        Err(VerificationError::UnbalancedInteractions)
+    ));
+}
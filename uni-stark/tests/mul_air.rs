@@ -2,12 +2,12 @@ use core::fmt::Debug;
 use core::marker::PhantomData;
 
 use itertools::Itertools;
-use p3_air::{Air, AirBuilder, BaseAir};
+use p3_air::{Air, AirBuilder, BaseAir, BaseAirWithPublicValues};
 use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
 use p3_challenger::{DuplexChallenger, HashChallenger, SerializingChallenger32};
 use p3_circle::CirclePcs;
-use p3_commit::ExtensionMmcs;
 use p3_commit::testing::TrivialPcs;
+use p3_commit::{ExtensionMmcs, SerializedSize, SizeBreakdown};
 use p3_dft::Radix2DitParallel;
 use p3_field::extension::BinomialExtensionField;
 use p3_field::{Field, PrimeCharacteristicRing};
@@ -24,6 +24,7 @@ use p3_uni_stark::{StarkConfig, StarkGenericConfig, Val, prove, verify};
 use rand::distr::{Distribution, StandardUniform};
 use rand::rngs::SmallRng;
 use rand::{Rng, SeedableRng};
+use serde::Serialize;
 
 /// How many `a * b = c` operations to do per row in the AIR.
 const REPETITIONS: usize = 20; // This should be < 255 so it can fit into a u8.
@@ -88,6 +89,8 @@ impl<F> BaseAir<F> for MulAir {
     }
 }
 
+impl<F> BaseAirWithPublicValues<F> for MulAir {}
+
 impl<AB: AirBuilder> Air<AB> for MulAir {
     fn eval(&self, builder: &mut AB) {
         let main = builder.main();
@@ -115,6 +118,17 @@ impl<AB: AirBuilder> Air<AB> for MulAir {
     }
 }
 
+/// Measures sizes in the encoding the tests send proofs in.
+struct PostcardSize;
+
+impl SerializedSize for PostcardSize {
+    fn serialized_size<T: Serialize>(&self, value: &T) -> usize {
+        postcard::to_allocvec(value)
+            .expect("unable to serialize value")
+            .len()
+    }
+}
+
 fn do_test<SC: StarkGenericConfig>(
     config: SC,
     air: MulAir,
@@ -126,10 +140,12 @@ where
 {
     let trace = air.random_valid_trace(log_height, true);
 
-    let proof = prove(&config, &air, trace, &vec![]);
+    let proof = prove(&config, &air, trace, &vec![]).expect("proving failed");
 
     let serialized_proof = postcard::to_allocvec(&proof).expect("unable to serialize proof");
-    tracing::debug!("serialized_proof len: {} bytes", serialized_proof.len());
+    let size_report = proof.size_report(&PostcardSize);
+    tracing::debug!("{size_report}");
+    assert_eq!(size_report.total(), serialized_proof.len());
 
     let deserialized_proof =
         postcard::from_bytes(&serialized_proof).expect("unable to deserialize proof");
@@ -184,7 +200,12 @@ fn prove_bb_trivial_deg4() -> Result<(), impl Debug> {
     do_test_bb_trivial(4, 8)
 }
 
-fn do_test_bb_twoadic(log_blowup: usize, degree: u64, log_n: usize) -> Result<(), impl Debug> {
+fn do_test_bb_twoadic(
+    log_blowup: usize,
+    degree: u64,
+    log_n: usize,
+    quotient_batch_rows: Option<usize>,
+) -> Result<(), impl Debug> {
     type Val = BabyBear;
     type Challenge = BinomialExtensionField<Val, 4>;
 
@@ -215,6 +236,10 @@ fn do_test_bb_twoadic(log_blowup: usize, degree: u64, log_n: usize) -> Result<()
         log_final_poly_len: 3,
         num_queries: 40,
         proof_of_work_bits: 8,
+        commit_proof_of_work_bits: 0,
+        batch_proof_of_work_bits: 0,
+        max_log_arity: 1,
+        log_arity_schedule: vec![],
         mmcs: challenge_mmcs,
     };
     type Pcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
@@ -222,7 +247,10 @@ fn do_test_bb_twoadic(log_blowup: usize, degree: u64, log_n: usize) -> Result<()
     let challenger = Challenger::new(perm);
 
     type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
-    let config = MyConfig::new(pcs, challenger);
+    let mut config = MyConfig::new(pcs, challenger);
+    if let Some(bytes) = quotient_batch_rows {
+        config = config.with_quotient_batch_rows(bytes);
+    }
 
     let air = MulAir {
         degree,
@@ -234,7 +262,7 @@ fn do_test_bb_twoadic(log_blowup: usize, degree: u64, log_n: usize) -> Result<()
 
 #[test]
 fn prove_bb_twoadic_deg2() -> Result<(), impl Debug> {
-    do_test_bb_twoadic(1, 2, 5)
+    do_test_bb_twoadic(1, 2, 5, None)
 }
 
 #[test]
@@ -288,20 +316,31 @@ fn prove_bb_twoadic_deg2_zk() -> Result<(), impl Debug> {
 
 #[test]
 fn prove_bb_twoadic_deg3() -> Result<(), impl Debug> {
-    do_test_bb_twoadic(1, 3, 5)
+    do_test_bb_twoadic(1, 3, 5, None)
 }
 
 #[test]
 fn prove_bb_twoadic_deg4() -> Result<(), impl Debug> {
-    do_test_bb_twoadic(2, 4, 4)
+    do_test_bb_twoadic(2, 4, 4, None)
 }
 
 #[test]
 fn prove_bb_twoadic_deg5() -> Result<(), impl Debug> {
-    do_test_bb_twoadic(2, 5, 4)
+    do_test_bb_twoadic(2, 5, 4, None)
 }
 
-fn do_test_m31_circle(log_blowup: usize, degree: u64, log_n: usize) -> Result<(), impl Debug> {
+#[test]
+fn prove_bb_twoadic_deg5_quotient_batches() -> Result<(), impl Debug> {
+    // A batch size this small evaluates the quotient a single batch of rows at a time.
+    do_test_bb_twoadic(2, 5, 6, Some(1))
+}
+
+fn do_test_m31_circle(
+    log_blowup: usize,
+    degree: u64,
+    log_n: usize,
+    quotient_batch_rows: Option<usize>,
+) -> Result<(), impl Debug> {
     type Val = Mersenne31;
     type Challenge = BinomialExtensionField<Val, 3>;
 
@@ -326,6 +365,10 @@ fn do_test_m31_circle(log_blowup: usize, degree: u64, log_n: usize) -> Result<()
         log_final_poly_len: 0,
         num_queries: 40,
         proof_of_work_bits: 8,
+        commit_proof_of_work_bits: 0,
+        batch_proof_of_work_bits: 0,
+        max_log_arity: 1,
+        log_arity_schedule: vec![],
         mmcs: challenge_mmcs,
     };
 
@@ -338,7 +381,10 @@ fn do_test_m31_circle(log_blowup: usize, degree: u64, log_n: usize) -> Result<()
     let challenger = Challenger::from_hasher(vec![], byte_hash);
 
     type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
-    let config = MyConfig::new(pcs, challenger);
+    let mut config = MyConfig::new(pcs, challenger);
+    if let Some(bytes) = quotient_batch_rows {
+        config = config.with_quotient_batch_rows(bytes);
+    }
 
     let air = MulAir {
         degree,
@@ -351,10 +397,15 @@ fn do_test_m31_circle(log_blowup: usize, degree: u64, log_n: usize) -> Result<()
 
 #[test]
 fn prove_m31_circle_deg2() -> Result<(), impl Debug> {
-    do_test_m31_circle(1, 2, 6)
+    do_test_m31_circle(1, 2, 6, None)
 }
 
 #[test]
 fn prove_m31_circle_deg3() -> Result<(), impl Debug> {
-    do_test_m31_circle(1, 3, 7)
+    do_test_m31_circle(1, 3, 7, None)
+}
+
+#[test]
+fn prove_m31_circle_deg3_quotient_batches() -> Result<(), impl Debug> {
+    do_test_m31_circle(1, 3, 7, Some(1))
 }
@@ -0,0 +1,207 @@
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir, BaseAirWithPublicValues};
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::DuplexChallenger;
+use p3_commit::ExtensionMmcs;
+use p3_dft::Radix2DitParallel;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{Field, PrimeCharacteristicRing};
+use p3_fri::{TwoAdicFriPcs, create_test_fri_params};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_merkle_tree::MerkleTreeMmcs;
+use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+use p3_uni_stark::{
+    MultiProof, ProvingError, StarkConfig, StarkInstance, prove_multi, verify_multi,
+};
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+
+/// The chips of a toy VM, all proven together.
+enum ChipAir {
+    /// A two column Fibonacci sequence `(a, b) -> (b, a + b)` with public values `[a_0, b_0, b_last]`.
+    Fibonacci,
+    /// Checks `x^2 * y = z` on every row, a degree `3` constraint.
+    Cube,
+}
+
+impl<F> BaseAir<F> for ChipAir {
+    fn width(&self) -> usize {
+        match self {
+            Self::Fibonacci => 2,
+            Self::Cube => 3,
+        }
+    }
+}
+
+impl<F> BaseAirWithPublicValues<F> for ChipAir {
+    fn num_public_values(&self) -> usize {
+        match self {
+            Self::Fibonacci => 3,
+            Self::Cube => 0,
+        }
+    }
+}
+
+impl<AB: AirBuilderWithPublicValues> Air<AB> for ChipAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0).expect("Matrix is empty?");
+        let next = main.row_slice(1).expect("Matrix only has 1 row?");
+        match self {
+            Self::Fibonacci => {
+                let pis = builder.public_values();
+                let (a, b, x) = (pis[0], pis[1], pis[2]);
+
+                let mut when_first_row = builder.when_first_row();
+                when_first_row.assert_eq(local[0].clone(), a);
+                when_first_row.assert_eq(local[1].clone(), b);
+
+                let mut when_transition = builder.when_transition();
+                when_transition.assert_eq(local[1].clone(), next[0].clone());
+                when_transition.assert_eq(local[0].clone() + local[1].clone(), next[1].clone());
+
+                builder.when_last_row().assert_eq(local[1].clone(), x);
+            }
+            Self::Cube => {
+                let (x, y, z) = (local[0].clone(), local[1].clone(), local[2].clone());
+                builder.assert_eq(x.clone() * x * y, z);
+            }
+        }
+    }
+}
+
+fn fibonacci_trace<F: Field>(log_height: usize) -> (RowMajorMatrix<F>, Vec<F>) {
+    let mut values = Vec::with_capacity(2 << log_height);
+    let (mut a, mut b) = (F::ZERO, F::ONE);
+    for _ in 0..1 << log_height {
+        values.extend([a, b]);
+        (a, b) = (b, a + b);
+    }
+    let last = values[values.len() - 1];
+    (RowMajorMatrix::new(values, 2), vec![F::ZERO, F::ONE, last])
+}
+
+fn cube_trace<F: Field>(log_height: usize) -> RowMajorMatrix<F> {
+    let values = (0..1 << log_height)
+        .flat_map(|i| {
+            let x = F::from_usize(i);
+            let y = F::from_usize(i + 7);
+            [x, y, x.square() * y]
+        })
+        .collect();
+    RowMajorMatrix::new(values, 3)
+}
+
+type Val = BabyBear;
+type Perm = Poseidon2BabyBear<16>;
+type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+type ValMmcs =
+    MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+type Challenge = BinomialExtensionField<Val, 4>;
+type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+type Dft = Radix2DitParallel<Val>;
+type Pcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+
+fn make_config() -> MyConfig {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let perm = Perm::new_from_rng_128(&mut rng);
+    let hash = MyHash::new(perm.clone());
+    let compress = MyCompress::new(perm.clone());
+    let val_mmcs = ValMmcs::new(hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let fri_params = create_test_fri_params(challenge_mmcs, 1);
+    let pcs = Pcs::new(Dft::default(), val_mmcs, fri_params);
+    MyConfig::new(pcs, Challenger::new(perm))
+}
+
+fn prove_chips(config: &MyConfig) -> (MultiProof<MyConfig>, Vec<Vec<Val>>) {
+    let (fib_trace, fib_pis) = fibonacci_trace::<Val>(3);
+    let instances = vec![
+        StarkInstance {
+            air: &ChipAir::Fibonacci,
+            trace: fib_trace,
+            public_values: fib_pis.clone(),
+        },
+        StarkInstance {
+            air: &ChipAir::Cube,
+            trace: cube_trace::<Val>(5),
+            public_values: vec![],
+        },
+        StarkInstance {
+            air: &ChipAir::Cube,
+            trace: cube_trace::<Val>(2),
+            public_values: vec![],
+        },
+    ];
+    let proof = prove_multi(config, instances).expect("proving failed");
+    (proof, vec![fib_pis, vec![], vec![]])
+}
+
+#[test]
+fn test_multi_prove_verify() {
+    let config = make_config();
+    let (proof, pis) = prove_chips(&config);
+
+    let serialized_proof = postcard::to_allocvec(&proof).expect("unable to serialize proof");
+    let proof: MultiProof<MyConfig> =
+        postcard::from_bytes(&serialized_proof).expect("unable to deserialize proof");
+
+    let airs = [&ChipAir::Fibonacci, &ChipAir::Cube, &ChipAir::Cube];
+    verify_multi(&config, &airs, &proof, &pis).expect("verification failed");
+}
+
+#[test]
+fn test_multi_wrong_public_values() {
+    let config = make_config();
+    let (proof, mut pis) = prove_chips(&config);
+    pis[0][2] += Val::ONE;
+
+    let airs = [&ChipAir::Fibonacci, &ChipAir::Cube, &ChipAir::Cube];
+    assert!(verify_multi(&config, &airs, &proof, &pis).is_err());
+}
+
+#[test]
+fn test_multi_wrong_airs() {
+    let config = make_config();
+    let (proof, pis) = prove_chips(&config);
+
+    // The verifier must know which AIR each instance was proven against.
+    let airs = [&ChipAir::Fibonacci, &ChipAir::Cube];
+    assert!(verify_multi(&config, &airs, &proof, &pis[..2]).is_err());
+}
+
+#[test]
+fn test_multi_invalid_instance() {
+    let config = make_config();
+    let instances: Vec<StarkInstance<'_, MyConfig, ChipAir>> = vec![];
+    assert_eq!(
+        prove_multi(&config, instances).err(),
+        Some(ProvingError::NoInstances)
+    );
+
+    let (fib_trace, fib_pis) = fibonacci_trace::<Val>(3);
+    let mut cube_trace = cube_trace::<Val>(2);
+    cube_trace.values.truncate(3 * 3);
+    let instances = vec![
+        StarkInstance {
+            air: &ChipAir::Fibonacci,
+            trace: fib_trace,
+            public_values: fib_pis,
+        },
+        StarkInstance {
+            air: &ChipAir::Cube,
+            trace: cube_trace,
+            public_values: vec![],
+        },
+    ];
+    assert_eq!(
+        prove_multi(&config, instances).err(),
+        Some(ProvingError::InvalidInstance {
+            instance: 1,
+            error: Box::new(ProvingError::InvalidTraceHeight { height: 3 })
+        })
+    );
+}
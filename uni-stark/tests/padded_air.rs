@@ -0,0 +1,195 @@
+use p3_air::{Air, AirBuilder, BaseAir, BaseAirWithPublicValues, TracePadding};
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::{DuplexChallenger, HashChallenger, SerializingChallenger32};
+use p3_commit::ExtensionMmcs;
+use p3_dft::Radix2DitParallel;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{Field, PrimeCharacteristicRing};
+use p3_fri::{HidingFriPcs, TwoAdicFriPcs, create_test_fri_params};
+use p3_keccak::{Keccak256Hash, KeccakF};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_merkle_tree::{MerkleTreeHidingMmcs, MerkleTreeMmcs};
+use p3_symmetric::{
+    CompressionFunctionFromHasher, PaddingFreeSponge, SerializingHasher, TruncatedPermutation,
+};
+use p3_uni_stark::{Proof, ProvingError, StarkConfig, prove, verify};
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+
+/// Checks `a * b = c` on every row of the original trace, but not on padding rows.
+struct PaddedMulAir<F> {
+    padding: TracePadding<F>,
+}
+
+impl<F: Sync + Clone> BaseAir<F> for PaddedMulAir<F> {
+    fn width(&self) -> usize {
+        3
+    }
+
+    fn trace_padding(&self) -> Option<TracePadding<F>> {
+        Some(self.padding.clone())
+    }
+}
+
+impl<F: Sync + Clone> BaseAirWithPublicValues<F> for PaddedMulAir<F> {}
+
+impl<AB: AirBuilder> Air<AB> for PaddedMulAir<AB::F>
+where
+    AB::F: Clone,
+{
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0).expect("Matrix is empty?");
+        let (a, b, c) = (local[0].clone(), local[1].clone(), local[2].clone());
+        let is_real = AB::Expr::ONE - builder.is_padding();
+        builder.when(is_real).assert_eq(a * b, c);
+    }
+}
+
+fn generate_trace<F: Field>(height: usize) -> RowMajorMatrix<F> {
+    let values = (0..height)
+        .flat_map(|i| {
+            let a = F::from_usize(i + 2);
+            let b = F::from_usize(3 * i + 1);
+            [a, b, a * b]
+        })
+        .collect();
+    RowMajorMatrix::new(values, 3)
+}
+
+type Val = BabyBear;
+type Perm = Poseidon2BabyBear<16>;
+type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+type ValMmcs =
+    MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+type Challenge = BinomialExtensionField<Val, 4>;
+type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+type Dft = Radix2DitParallel<Val>;
+type Pcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+
+fn make_config() -> MyConfig {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let perm = Perm::new_from_rng_128(&mut rng);
+    let hash = MyHash::new(perm.clone());
+    let compress = MyCompress::new(perm.clone());
+    let val_mmcs = ValMmcs::new(hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let fri_params = create_test_fri_params(challenge_mmcs, 2);
+    let pcs = Pcs::new(Dft::default(), val_mmcs, fri_params);
+    MyConfig::new(pcs, Challenger::new(perm))
+}
+
+#[test]
+fn test_padding_policies() {
+    let config = make_config();
+    let policies = [
+        TracePadding::RepeatLastRow,
+        TracePadding::ZeroRow,
+        // The filler row does not satisfy `a * b = c`, so the constraint must skip padding rows.
+        TracePadding::FillerRow(vec![Val::ONE, Val::TWO, Val::ONE]),
+    ];
+    for padding in policies {
+        let air = PaddedMulAir { padding };
+        for height in [5, 8, 13, 27] {
+            let proof =
+                prove(&config, &air, generate_trace(height), &vec![]).expect("proving failed");
+            assert_eq!(proof.real_height(), Some(height));
+            verify(&config, &air, &proof, &vec![]).expect("verification failed");
+        }
+    }
+}
+
+#[test]
+fn test_padding_empty_trace() {
+    let config = make_config();
+    let air = PaddedMulAir {
+        padding: TracePadding::ZeroRow,
+    };
+    assert_eq!(
+        prove(&config, &air, generate_trace(0), &vec![]).err(),
+        Some(ProvingError::InvalidTraceHeight { height: 0 })
+    );
+}
+
+#[test]
+fn test_padding_wrong_filler_row() {
+    let config = make_config();
+    let air = PaddedMulAir {
+        padding: TracePadding::FillerRow(vec![Val::ONE, Val::TWO]),
+    };
+    assert_eq!(
+        prove(&config, &air, generate_trace(5), &vec![]).err(),
+        Some(ProvingError::FillerRowWidthMismatch {
+            expected: 3,
+            actual: 2
+        })
+    );
+}
+
+#[test]
+fn test_padding_wrong_real_height() {
+    let config = make_config();
+    let air = PaddedMulAir {
+        padding: TracePadding::ZeroRow,
+    };
+    let proof = prove(&config, &air, generate_trace(5), &vec![]).expect("proving failed");
+
+    // The real height is serialized last, as `Some` followed by a single byte.
+    let mut serialized_proof = postcard::to_allocvec(&proof).expect("unable to serialize proof");
+    assert_eq!(serialized_proof.last(), Some(&5));
+    for real_height in [4, 6, 9] {
+        *serialized_proof.last_mut().unwrap() = real_height;
+        let proof: Proof<MyConfig> =
+            postcard::from_bytes(&serialized_proof).expect("unable to deserialize proof");
+        assert_eq!(proof.real_height(), Some(real_height as usize));
+        assert!(verify(&config, &air, &proof, &vec![]).is_err());
+    }
+}
+
+#[test]
+fn test_padding_zk() {
+    type ByteHash = Keccak256Hash;
+    type U64Hash = PaddingFreeSponge<KeccakF, 25, 17, 4>;
+    type FieldHash = SerializingHasher<U64Hash>;
+    type HashCompress = CompressionFunctionFromHasher<U64Hash, 2, 4>;
+    type ValHidingMmcs = MerkleTreeHidingMmcs<
+        [Val; p3_keccak::VECTOR_LEN],
+        [u64; p3_keccak::VECTOR_LEN],
+        FieldHash,
+        HashCompress,
+        SmallRng,
+        4,
+        4,
+    >;
+    type ChallengeHidingMmcs = ExtensionMmcs<Val, Challenge, ValHidingMmcs>;
+    type HashChallenger32 = SerializingChallenger32<Val, HashChallenger<u8, ByteHash, 32>>;
+    type HidingPcs = HidingFriPcs<Val, Dft, ValHidingMmcs, ChallengeHidingMmcs, SmallRng>;
+    type MyHidingConfig = StarkConfig<HidingPcs, Challenge, HashChallenger32>;
+
+    let u64_hash = U64Hash::new(KeccakF {});
+    let field_hash = FieldHash::new(u64_hash);
+    let compress = HashCompress::new(u64_hash);
+    let val_mmcs = ValHidingMmcs::new(field_hash, compress, SmallRng::seed_from_u64(1));
+    let challenge_mmcs = ChallengeHidingMmcs::new(val_mmcs.clone());
+    let fri_params = create_test_fri_params(challenge_mmcs, 2);
+    let pcs = HidingPcs::new(
+        Dft::default(),
+        val_mmcs,
+        fri_params,
+        4,
+        SmallRng::seed_from_u64(1),
+    );
+    let challenger = HashChallenger32::from_hasher(vec![], ByteHash {});
+    let config = MyHidingConfig::new(pcs, challenger);
+
+    let air = PaddedMulAir {
+        padding: TracePadding::RepeatLastRow,
+    };
+    let proof = prove(&config, &air, generate_trace(6), &vec![]).expect("proving failed");
+    assert_eq!(proof.real_height(), Some(6));
+    verify(&config, &air, &proof, &vec![]).expect("verification failed");
+}
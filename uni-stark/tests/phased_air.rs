@@ -0,0 +1,295 @@
+use p3_air::{
+    Air, BaseAir, BaseAirWithPublicValues, ExtensionBuilder, MultiPhaseAirBuilder, TracePhase,
+};
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::{DuplexChallenger, HashChallenger, SerializingChallenger32};
+use p3_commit::{ExtensionMmcs, PolynomialSpace};
+use p3_dft::Radix2DitParallel;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{ExtensionField, Field, PrimeCharacteristicRing};
+use p3_fri::{HidingFriPcs, TwoAdicFriPcs, create_test_fri_params};
+use p3_keccak::{Keccak256Hash, KeccakF};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_merkle_tree::{MerkleTreeHidingMmcs, MerkleTreeMmcs};
+use p3_symmetric::{
+    CompressionFunctionFromHasher, PaddingFreeSponge, SerializingHasher, TruncatedPermutation,
+};
+use p3_uni_stark::{
+    PhaseInputs, ProvingError, StarkConfig, StarkGenericConfig, prove, prove_with_phases, verify,
+};
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+
+/// Proves that the column `b` is a permutation of the column `a` with a grand product argument.
+///
+/// - Phase `0` samples `gamma` and holds the running product `z` of `(a + gamma) / (b + gamma)`,
+///   which must end at `1`.
+/// - Phase `1` samples `delta` and holds the running sum `s` of `z + delta * a`. It has no purpose
+///   besides checking that later phases can read earlier phases and challenges.
+struct PermutationCheckAir;
+
+impl<F> BaseAir<F> for PermutationCheckAir {
+    fn width(&self) -> usize {
+        2
+    }
+
+    fn trace_phases(&self) -> Vec<TracePhase> {
+        vec![
+            TracePhase {
+                width: 1,
+                num_challenges: 1,
+            },
+            TracePhase {
+                width: 1,
+                num_challenges: 1,
+            },
+        ]
+    }
+}
+
+impl<F> BaseAirWithPublicValues<F> for PermutationCheckAir {}
+
+impl<AB: MultiPhaseAirBuilder> Air<AB> for PermutationCheckAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0).expect("Matrix is empty?");
+        let next = main.row_slice(1).expect("Matrix only has 1 row?");
+        let (a, b): (AB::Expr, AB::Expr) = (local[0].clone().into(), local[1].clone().into());
+        let (a_next, b_next): (AB::Expr, AB::Expr) =
+            (next[0].clone().into(), next[1].clone().into());
+
+        let gamma: AB::ExprEF = builder.phase_challenges(0)[0].into();
+        let products = builder.phase(0);
+        let z: AB::ExprEF = products.row_slice(0).expect("Matrix is empty?")[0].into();
+        let z_next: AB::ExprEF = products.row_slice(1).expect("Matrix only has 1 row?")[0].into();
+
+        builder
+            .when_first_row()
+            .assert_eq_ext(z.clone() * (gamma.clone() + b), gamma.clone() + a.clone());
+        builder.when_transition().assert_eq_ext(
+            z_next.clone() * (gamma.clone() + b_next),
+            z.clone() * (gamma + a_next.clone()),
+        );
+        builder.when_last_row().assert_one_ext(z.clone());
+
+        let delta: AB::ExprEF = builder.phase_challenges(1)[0].into();
+        let sums = builder.phase(1);
+        let s: AB::ExprEF = sums.row_slice(0).expect("Matrix is empty?")[0].into();
+        let s_next: AB::ExprEF = sums.row_slice(1).expect("Matrix only has 1 row?")[0].into();
+
+        builder
+            .when_first_row()
+            .assert_eq_ext(s.clone(), z + delta.clone() * a);
+        builder
+            .when_transition()
+            .assert_eq_ext(s_next, s + z_next + delta * a_next);
+    }
+}
+
+/// A trace whose second column is the first column reversed, or with an extra `1` added to its
+/// last entry if `broken` is set.
+fn generate_trace<F: Field>(log_height: usize, broken: bool) -> RowMajorMatrix<F> {
+    let height = 1 << log_height;
+    let mut values = F::zero_vec(2 * height);
+    for i in 0..height {
+        values[2 * i] = F::from_usize(3 * i + 1);
+        values[2 * (height - 1 - i) + 1] = F::from_usize(3 * i + 1);
+    }
+    if broken {
+        values[2 * height - 1] += F::ONE;
+    }
+    RowMajorMatrix::new(values, 2)
+}
+
+fn generate_phase<SC: StarkGenericConfig>(
+    inputs: &PhaseInputs<'_, SC>,
+) -> RowMajorMatrix<SC::Challenge> {
+    let main = inputs.main;
+    let values = match inputs.phase {
+        0 => {
+            let gamma = inputs.challenges[0][0];
+            let mut z = SC::Challenge::ONE;
+            main.rows()
+                .map(|mut row| {
+                    let (a, b) = (row.next().unwrap(), row.next().unwrap());
+                    z *= (gamma + a) * (gamma + b).inverse();
+                    z
+                })
+                .collect()
+        }
+        1 => {
+            let delta = inputs.challenges[1][0];
+            let mut s = SC::Challenge::ZERO;
+            main.rows()
+                .zip(&inputs.phases[0].values)
+                .map(|(mut row, &z)| {
+                    s += z + delta * row.next().unwrap();
+                    s
+                })
+                .collect()
+        }
+        _ => unreachable!(),
+    };
+    RowMajorMatrix::new_col(values)
+}
+
+type Val = BabyBear;
+type Perm = Poseidon2BabyBear<16>;
+type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+type ValMmcs =
+    MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+type Challenge = BinomialExtensionField<Val, 4>;
+type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+type Dft = Radix2DitParallel<Val>;
+type Pcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+
+fn make_config() -> MyConfig {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let perm = Perm::new_from_rng_128(&mut rng);
+    let hash = MyHash::new(perm.clone());
+    let compress = MyCompress::new(perm.clone());
+    let val_mmcs = ValMmcs::new(hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let fri_params = create_test_fri_params(challenge_mmcs, 2);
+    let pcs = Pcs::new(Dft::default(), val_mmcs, fri_params);
+    MyConfig::new(pcs, Challenger::new(perm))
+}
+
+fn prove_and_verify<SC>(config: &SC, log_height: usize, broken: bool) -> Result<(), ProvingError>
+where
+    SC: StarkGenericConfig<Challenge: ExtensionField<BabyBear>>,
+    SC::Pcs: p3_commit::Pcs<SC::Challenge, SC::Challenger, Domain: PolynomialSpace<Val = Val>>,
+{
+    let trace = generate_trace::<Val>(log_height, broken);
+    let proof = prove_with_phases(
+        config,
+        &PermutationCheckAir,
+        trace,
+        &vec![],
+        None,
+        generate_phase::<SC>,
+    )?;
+    verify(config, &PermutationCheckAir, &proof, &vec![]).expect("verification failed");
+    Ok(())
+}
+
+#[test]
+fn test_phases_prove_verify() {
+    let config = make_config();
+    prove_and_verify(&config, 4, false).expect("proving failed");
+}
+
+#[test]
+fn test_phases_zk() {
+    type ByteHash = Keccak256Hash;
+    type U64Hash = PaddingFreeSponge<KeccakF, 25, 17, 4>;
+    type FieldHash = SerializingHasher<U64Hash>;
+    type HashCompress = CompressionFunctionFromHasher<U64Hash, 2, 4>;
+    type ValHidingMmcs = MerkleTreeHidingMmcs<
+        [Val; p3_keccak::VECTOR_LEN],
+        [u64; p3_keccak::VECTOR_LEN],
+        FieldHash,
+        HashCompress,
+        SmallRng,
+        4,
+        4,
+    >;
+    type ChallengeHidingMmcs = ExtensionMmcs<Val, Challenge, ValHidingMmcs>;
+    type HashChallenger32 = SerializingChallenger32<Val, HashChallenger<u8, ByteHash, 32>>;
+    type HidingPcs = HidingFriPcs<Val, Dft, ValHidingMmcs, ChallengeHidingMmcs, SmallRng>;
+    type MyHidingConfig = StarkConfig<HidingPcs, Challenge, HashChallenger32>;
+
+    let u64_hash = U64Hash::new(KeccakF {});
+    let field_hash = FieldHash::new(u64_hash);
+    let compress = HashCompress::new(u64_hash);
+    let val_mmcs = ValHidingMmcs::new(field_hash, compress, SmallRng::seed_from_u64(1));
+    let challenge_mmcs = ChallengeHidingMmcs::new(val_mmcs.clone());
+    let fri_params = create_test_fri_params(challenge_mmcs, 2);
+    let pcs = HidingPcs::new(
+        Dft::default(),
+        val_mmcs,
+        fri_params,
+        4,
+        SmallRng::seed_from_u64(1),
+    );
+    let challenger = HashChallenger32::from_hasher(vec![], ByteHash {});
+    let config = MyHidingConfig::new(pcs, challenger);
+
+    prove_and_verify(&config, 3, false).expect("proving failed");
+}
+
+#[test]
+fn test_phases_not_a_permutation() {
+    let config = make_config();
+    // The grand product does not end at `1`.
+    assert_eq!(
+        prove_and_verify(&config, 4, true),
+        Err(ProvingError::UnsatisfiedConstraint {
+            row: 15,
+            constraint: 2
+        })
+    );
+}
+
+#[test]
+fn test_phases_invalid_instance() {
+    let config = make_config();
+
+    assert_eq!(
+        prove(
+            &config,
+            &PermutationCheckAir,
+            generate_trace(4, false),
+            &vec![]
+        )
+        .err(),
+        Some(ProvingError::TracePhasesUnsupported)
+    );
+
+    let wide_phase = |inputs: &PhaseInputs<'_, MyConfig>| {
+        let phase = generate_phase(inputs);
+        RowMajorMatrix::new(phase.values[..phase.height()].to_vec(), 2)
+    };
+    assert_eq!(
+        prove_with_phases(
+            &config,
+            &PermutationCheckAir,
+            generate_trace(4, false),
+            &vec![],
+            None,
+            wide_phase
+        )
+        .err(),
+        Some(ProvingError::PhaseTraceWidthMismatch {
+            phase: 0,
+            expected: 1,
+            actual: 2
+        })
+    );
+
+    let short_phase = |inputs: &PhaseInputs<'_, MyConfig>| {
+        let mut phase = generate_phase(inputs);
+        phase.values.truncate(phase.height() / 2);
+        phase
+    };
+    assert_eq!(
+        prove_with_phases(
+            &config,
+            &PermutationCheckAir,
+            generate_trace(4, false),
+            &vec![],
+            None,
+            short_phase
+        )
+        .err(),
+        Some(ProvingError::PhaseTraceHeightMismatch {
+            phase: 0,
+            expected: 16,
+            actual: 8
+        })
+    );
+}
@@ -0,0 +1,141 @@
+use p3_air::{Air, AirBuilder, BaseAir, BaseAirWithPublicValues, PairBuilder};
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::DuplexChallenger;
+use p3_commit::ExtensionMmcs;
+use p3_dft::Radix2DitParallel;
+use p3_field::Field;
+use p3_field::extension::BinomialExtensionField;
+use p3_fri::{TwoAdicFriPcs, create_test_fri_params};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_merkle_tree::MerkleTreeMmcs;
+use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+use p3_uni_stark::{
+    StarkConfig, prove_with_preprocessed, setup_preprocessed, verify_with_preprocessed,
+};
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+
+/// An AIR whose single main column accumulates a fixed preprocessed column:
+/// - `acc[0] = fixed[0]`
+/// - `acc[i + 1] = acc[i] + fixed[i + 1]`
+///
+/// The preprocessed column is `fixed[i] = i * step`.
+struct AccumulatorAir {
+    log_height: usize,
+    step: u64,
+}
+
+impl<F: Field> BaseAir<F> for AccumulatorAir {
+    fn width(&self) -> usize {
+        1
+    }
+
+    fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
+        let values = (0..1 << self.log_height)
+            .map(|i| F::from_u64(i as u64 * self.step))
+            .collect();
+        Some(RowMajorMatrix::new_col(values))
+    }
+}
+
+impl<F: Field> BaseAirWithPublicValues<F> for AccumulatorAir {}
+
+impl<AB: PairBuilder<F: Field>> Air<AB> for AccumulatorAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let preprocessed = builder.preprocessed();
+
+        let local = main.row_slice(0).expect("Matrix is empty?");
+        let next = main.row_slice(1).expect("Matrix only has 1 row?");
+        let fixed_local = preprocessed.row_slice(0).expect("Matrix is empty?");
+        let fixed_next = preprocessed.row_slice(1).expect("Matrix only has 1 row?");
+
+        builder
+            .when_first_row()
+            .assert_eq(local[0].clone(), fixed_local[0].clone());
+        builder
+            .when_transition()
+            .assert_eq(next[0].clone(), local[0].clone() + fixed_next[0].clone());
+    }
+}
+
+fn generate_trace<F: Field>(air: &AccumulatorAir) -> RowMajorMatrix<F> {
+    let mut acc = F::ZERO;
+    let values = (0..1 << air.log_height)
+        .map(|i| {
+            acc += F::from_u64(i as u64 * air.step);
+            acc
+        })
+        .collect();
+    RowMajorMatrix::new_col(values)
+}
+
+type Val = BabyBear;
+type Perm = Poseidon2BabyBear<16>;
+type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+type ValMmcs =
+    MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+type Challenge = BinomialExtensionField<Val, 4>;
+type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+type Dft = Radix2DitParallel<Val>;
+type Pcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+
+fn make_config() -> MyConfig {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let perm = Perm::new_from_rng_128(&mut rng);
+    let hash = MyHash::new(perm.clone());
+    let compress = MyCompress::new(perm.clone());
+    let val_mmcs = ValMmcs::new(hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let fri_params = create_test_fri_params(challenge_mmcs, 2);
+    let pcs = Pcs::new(Dft::default(), val_mmcs, fri_params);
+    MyConfig::new(pcs, Challenger::new(perm))
+}
+
+#[test]
+fn test_preprocessed_prove_verify() {
+    let config = make_config();
+    let air = AccumulatorAir {
+        log_height: 4,
+        step: 3,
+    };
+    let trace = generate_trace::<Val>(&air);
+
+    let (prover_data, vk) =
+        setup_preprocessed(&config, &air, air.log_height).expect("AIR has a preprocessed trace");
+    let proof = prove_with_preprocessed(&config, &air, trace, &vec![], Some(&prover_data))
+        .expect("proving failed");
+    verify_with_preprocessed(&config, &air, &proof, &vec![], Some(&vk))
+        .expect("verification failed");
+}
+
+#[test]
+fn test_preprocessed_wrong_verifier_key() {
+    let config = make_config();
+    let air = AccumulatorAir {
+        log_height: 4,
+        step: 3,
+    };
+    let trace = generate_trace::<Val>(&air);
+
+    let (prover_data, _) =
+        setup_preprocessed(&config, &air, air.log_height).expect("AIR has a preprocessed trace");
+    let proof = prove_with_preprocessed(&config, &air, trace, &vec![], Some(&prover_data))
+        .expect("proving failed");
+
+    // A key committing to a different fixed column must be rejected.
+    let other_air = AccumulatorAir {
+        log_height: 4,
+        step: 5,
+    };
+    let (_, other_vk) = setup_preprocessed(&config, &other_air, other_air.log_height)
+        .expect("AIR has a preprocessed trace");
+    assert!(verify_with_preprocessed(&config, &air, &proof, &vec![], Some(&other_vk)).is_err());
+
+    // So must a proof verified without any key.
+    assert!(verify_with_preprocessed(&config, &air, &proof, &vec![], None).is_err());
+}
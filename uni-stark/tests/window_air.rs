@@ -0,0 +1,239 @@
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir, BaseAirWithPublicValues};
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::DuplexChallenger;
+use p3_commit::ExtensionMmcs;
+use p3_dft::Radix2DitParallel;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{Field, PrimeCharacteristicRing};
+use p3_fri::{TwoAdicFriPcs, create_test_fri_params};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_merkle_tree::MerkleTreeMmcs;
+use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+use p3_uni_stark::{
+    ConstraintSystem, DynamicAir, Entry, ProvingError, StarkConfig, StarkInstance,
+    SymbolicExpression, SymbolicVariable, VerificationError, prove, prove_multi,
+    prove_with_preprocessed, setup_preprocessed, verify, verify_multi, verify_with_preprocessed,
+};
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+
+/// The Tribonacci sequence `x_{i + 3} = x_i + x_{i + 1} + x_{i + 2}`, in a single column read
+/// through a window of four rows:
+/// - the first three rows are the public values `a`, `b` and `c`,
+/// - the last row is the public value `x`.
+pub struct TribonacciAir;
+
+impl<F> BaseAir<F> for TribonacciAir {
+    fn width(&self) -> usize {
+        1
+    }
+
+    fn window_size(&self) -> usize {
+        4
+    }
+}
+
+impl<F> BaseAirWithPublicValues<F> for TribonacciAir {
+    fn num_public_values(&self) -> usize {
+        4
+    }
+}
+
+impl<AB: AirBuilderWithPublicValues> Air<AB> for TribonacciAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let pis = builder.public_values();
+        let (a, b, c, x) = (pis[0], pis[1], pis[2], pis[3]);
+        let rows: [_; 4] =
+            core::array::from_fn(|j| main.get(j, 0).expect("Matrix is smaller than the window?"));
+
+        let mut when_first_row = builder.when_first_row();
+        when_first_row.assert_eq(rows[0].clone(), a);
+        when_first_row.assert_eq(rows[1].clone(), b);
+        when_first_row.assert_eq(rows[2].clone(), c);
+
+        builder.when_transition_window(4).assert_eq(
+            rows[0].clone() + rows[1].clone() + rows[2].clone(),
+            rows[3].clone(),
+        );
+
+        builder.when_last_row().assert_eq(rows[0].clone(), x);
+    }
+}
+
+/// An AIR with a window of a single row, which the prover and verifier reject.
+struct SingleRowAir;
+
+impl<F> BaseAir<F> for SingleRowAir {
+    fn width(&self) -> usize {
+        1
+    }
+
+    fn window_size(&self) -> usize {
+        1
+    }
+}
+
+impl<F> BaseAirWithPublicValues<F> for SingleRowAir {
+    fn num_public_values(&self) -> usize {
+        4
+    }
+}
+
+impl<AB: AirBuilderWithPublicValues> Air<AB> for SingleRowAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let a = builder.public_values()[0];
+        builder
+            .when_first_row()
+            .assert_eq(main.get(0, 0).unwrap(), a);
+    }
+}
+
+fn tribonacci_trace(log_height: usize) -> (RowMajorMatrix<Val>, Vec<Val>) {
+    let mut values = vec![Val::ZERO, Val::ONE, Val::ONE];
+    for i in 3..1 << log_height {
+        values.push(values[i - 3] + values[i - 2] + values[i - 1]);
+    }
+    let x = *values.last().unwrap();
+    let pis = vec![Val::ZERO, Val::ONE, Val::ONE, x];
+    (RowMajorMatrix::new_col(values), pis)
+}
+
+type Val = BabyBear;
+type Perm = Poseidon2BabyBear<16>;
+type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+type ValMmcs =
+    MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+type Challenge = BinomialExtensionField<Val, 4>;
+type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+type Dft = Radix2DitParallel<Val>;
+type Pcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+
+fn make_config() -> MyConfig {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let perm = Perm::new_from_rng_128(&mut rng);
+    let hash = MyHash::new(perm.clone());
+    let compress = MyCompress::new(perm.clone());
+    let val_mmcs = ValMmcs::new(hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let fri_params = create_test_fri_params(challenge_mmcs, 1);
+    let pcs = Pcs::new(Dft::default(), val_mmcs, fri_params);
+    MyConfig::new(pcs, Challenger::new(perm))
+}
+
+#[test]
+fn test_window_prove_verify() {
+    let config = make_config();
+    let (trace, pis) = tribonacci_trace(4);
+
+    let proof = prove(&config, &TribonacciAir, trace, &pis).expect("proving failed");
+    verify(&config, &TribonacciAir, &proof, &pis).expect("verification failed");
+
+    let mut wrong_pis = pis;
+    wrong_pis[3] += Val::ONE;
+    assert!(verify(&config, &TribonacciAir, &proof, &wrong_pis).is_err());
+}
+
+#[test]
+fn test_window_too_small() {
+    let config = make_config();
+    let (trace, pis) = tribonacci_trace(4);
+    let invalid = ProvingError::InvalidWindowSize { window_size: 1 };
+
+    assert_eq!(
+        prove(&config, &SingleRowAir, trace.clone(), &pis).err(),
+        Some(invalid.clone())
+    );
+    let instances = vec![StarkInstance {
+        air: &SingleRowAir,
+        trace: trace.clone(),
+        public_values: pis.clone(),
+    }];
+    assert_eq!(
+        prove_multi(&config, instances).err(),
+        Some(ProvingError::InvalidInstance {
+            instance: 0,
+            error: Box::new(invalid),
+        })
+    );
+
+    // The verifier rejects the AIR before reading the proof.
+    let proof = prove(&config, &TribonacciAir, trace.clone(), &pis).expect("proving failed");
+    assert!(matches!(
+        verify(&config, &SingleRowAir, &proof, &pis),
+        Err(VerificationError::InvalidWindowSize { window_size: 1 })
+    ));
+    let instances = vec![StarkInstance {
+        air: &TribonacciAir,
+        trace,
+        public_values: pis.clone(),
+    }];
+    let proof = prove_multi(&config, instances).expect("proving failed");
+    assert!(matches!(
+        verify_multi(&config, &[&SingleRowAir], &proof, &[pis]),
+        Err(VerificationError::InvalidWindowSize { window_size: 1 })
+    ));
+}
+
+#[test]
+fn test_window_multi() {
+    let config = make_config();
+    let (trace_3, pis_3) = tribonacci_trace(3);
+    let (trace_5, pis_5) = tribonacci_trace(5);
+    let instances = vec![
+        StarkInstance {
+            air: &TribonacciAir,
+            trace: trace_3,
+            public_values: pis_3.clone(),
+        },
+        StarkInstance {
+            air: &TribonacciAir,
+            trace: trace_5,
+            public_values: pis_5.clone(),
+        },
+    ];
+    let proof = prove_multi(&config, instances).expect("proving failed");
+
+    let airs = [&TribonacciAir, &TribonacciAir];
+    verify_multi(&config, &airs, &proof, &[pis_3, pis_5]).expect("verification failed");
+}
+
+#[test]
+fn test_window_constraint_system() {
+    let config = make_config();
+    let system = ConstraintSystem::from_air(&TribonacciAir);
+    assert_eq!(system.window_size, 4);
+
+    let air = DynamicAir::try_from(system).expect("exported constraints are valid");
+    let (trace, pis) = tribonacci_trace(3);
+    let proof = prove(&config, &air, trace, &pis).expect("proving failed");
+    verify(&config, &air, &proof, &pis).expect("verification failed");
+}
+
+#[test]
+fn test_window_preprocessed() {
+    const LOG_HEIGHT: usize = 3;
+    let config = make_config();
+    let steps = RowMajorMatrix::new_col((0..1 << LOG_HEIGHT).map(Val::from_usize).collect());
+
+    // The main column must match the preprocessed column, as read at the end of the window.
+    let variable =
+        |entry, index| SymbolicExpression::from(SymbolicVariable::<Val>::new(entry, index));
+    let constraint =
+        variable(Entry::Main { offset: 2 }, 0) - variable(Entry::Preprocessed { offset: 2 }, 0);
+    let air = DynamicAir::new(1, Some(steps.clone()), 0, &[constraint])
+        .expect("the constraint is supported");
+    assert_eq!(BaseAir::<Val>::window_size(&air), 3);
+
+    let (prover_data, vk) =
+        setup_preprocessed(&config, &air, LOG_HEIGHT).expect("AIR has a preprocessed trace");
+    let proof = prove_with_preprocessed(&config, &air, steps, &vec![], Some(&prover_data))
+        .expect("proving failed");
+    verify_with_preprocessed(&config, &air, &proof, &vec![], Some(&vk))
+        .expect("verification failed");
+}